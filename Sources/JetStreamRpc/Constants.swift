@@ -14,3 +14,7 @@ public let RJETSTREAMERROR: UInt8 = 5
 /// Version negotiation message type IDs.
 public let TVERSION: UInt8 = 100
 public let RVERSION: UInt8 = 101
+
+// r[impl jetstream.rpc.oneway.notag]
+/// Tag of frames that expect no response: Tversion and oneway requests.
+public let NOTAG: UInt16 = 0xFFFF
@@ -12,21 +12,45 @@ public actor Mux<TReq: Framer, TRes: Framer> {
     private let tagPool: TagPool
     private var pending: [UInt16: CheckedContinuation<Frame<TRes>, Error>] = [:]
     private let transport: any Transport<TReq, TRes>
+    private var closed: String?
 
     public init(transport: any Transport<TReq, TRes>, maxConcurrent: UInt16 = 256) {
-        self.tagPool = TagPool(maxConcurrent: maxConcurrent)
+        self.tagPool = TagPool(maxConcurrent: min(maxConcurrent, NOTAG - 1))
         self.transport = transport
     }
 
     /// Start the demux loop (call once).
     public func start() async {
         Task {
-            for try await frame in transport.receive() {
-                self.dispatch(frame)
+            var reason = "connection closed"
+            do {
+                for try await frame in transport.receive() {
+                    self.dispatch(frame)
+                }
+            } catch {
+                reason = "\(error)"
             }
+            await self.failPending(reason)
         }
     }
 
+    // r[impl jetstream.retry.mux]
+    /// Nothing will answer the calls still waiting, fail them so idempotent
+    /// ones can be retried.
+    private func failPending(_ reason: String) {
+        closed = reason
+        let waiting = pending
+        pending.removeAll()
+        for (tag, continuation) in waiting {
+            Task { await tagPool.release(tag) }
+            continuation.resume(throwing: connectionLost(reason))
+        }
+    }
+
+    private func connectionLost(_ reason: String) -> JetStreamError {
+        JetStreamError(inner: ErrorInner(message: reason, code: CONNECTION_LOST))
+    }
+
     private func dispatch(_ frame: Frame<TRes>) {
         if let continuation = pending.removeValue(forKey: frame.tag) {
             Task { await tagPool.release(frame.tag) }
@@ -36,6 +60,9 @@ public actor Mux<TReq: Framer, TRes: Framer> {
 
     /// Send a request and await the response.
     public func rpc(_ msg: TReq) async throws -> Frame<TRes> {
+        if let reason = closed {
+            throw connectionLost(reason)
+        }
         guard let tag = await tagPool.acquire() else {
             throw FrameError.frameTooSmall(0) // no tags available
         }
@@ -55,6 +82,16 @@ public actor Mux<TReq: Framer, TRes: Framer> {
         }
     }
 
+    // r[impl jetstream.rpc.oneway.mux]
+    /// Send a request to a oneway method. It takes no tag and nothing waits
+    /// for its response.
+    public func oneway(_ msg: TReq) async throws {
+        if let reason = closed {
+            throw connectionLost(reason)
+        }
+        try await transport.send(Frame(tag: NOTAG, msg: msg))
+    }
+
     public func close() async throws {
         try await transport.close()
     }
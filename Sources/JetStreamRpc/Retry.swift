@@ -0,0 +1,70 @@
+// JetStream RPC — Retries
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+// r[impl jetstream.rpc.swift.retry]
+
+import Foundation
+import JetStreamWireFormat
+
+/// Error code for a server that cannot take the call right now.
+public let UNAVAILABLE = "jetstream_rpc::error::unavailable"
+/// Error code for calls whose connection went away before a response arrived.
+public let CONNECTION_LOST = "jetstream_rpc::error::connection_lost"
+/// Error code for calls that did not complete in time.
+public let DEADLINE_EXCEEDED = "jetstream_rpc::error::deadline_exceeded"
+
+/// How often, and how patiently, `#[idempotent]` methods are retried.
+public struct RetryPolicy: Equatable, Sendable {
+    /// Retries allowed per call, not counting the first attempt.
+    public var maxRetries: Int
+    /// Seconds to wait before the first retry.
+    public var initialBackoff: TimeInterval
+    /// Upper bound on the wait between retries, in seconds.
+    public var maxBackoff: TimeInterval
+
+    public init(
+        maxRetries: Int = 3,
+        initialBackoff: TimeInterval = 0.05,
+        maxBackoff: TimeInterval = 2
+    ) {
+        self.maxRetries = maxRetries
+        self.initialBackoff = initialBackoff
+        self.maxBackoff = maxBackoff
+    }
+
+    public static let `default` = RetryPolicy()
+    public static let none = RetryPolicy(maxRetries: 0, initialBackoff: 0, maxBackoff: 0)
+
+    /// Delay in seconds before retry `attempt`, counting from zero.
+    public func delay(attempt: Int) -> TimeInterval {
+        min(initialBackoff * pow(2, Double(min(attempt, 30))), maxBackoff)
+    }
+}
+
+/// Whether an idempotent call that failed with `error` may be sent again.
+public func isRetriable(_ error: Error) -> Bool {
+    guard let error = error as? JetStreamError, let code = error.inner.code else {
+        return false
+    }
+    return code == UNAVAILABLE || code == CONNECTION_LOST || code == DEADLINE_EXCEEDED
+}
+
+/// Runs `call`, retrying retriable failures as allowed by `policy`.
+public func withRetry<T>(
+    _ policy: RetryPolicy,
+    _ call: () async throws -> T
+) async throws -> T {
+    var attempt = 0
+    while true {
+        do {
+            return try await call()
+        } catch {
+            guard attempt < policy.maxRetries, isRetriable(error) else {
+                throw error
+            }
+            try await Task.sleep(nanoseconds: UInt64(policy.delay(attempt: attempt) * 1_000_000_000))
+            attempt += 1
+        }
+    }
+}
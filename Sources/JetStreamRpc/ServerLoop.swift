@@ -26,7 +26,11 @@ public func serve<T: Transport>(
         for try await requestFrame in transport.receive() {
             group.addTask {
                 let responseFrame = await dispatch(ctx, requestFrame)
-                try await transport.send(responseFrame)
+                // r[impl jetstream.rpc.oneway.server]
+                // Nobody waits for the response of a oneway call.
+                if requestFrame.tag != NOTAG {
+                    try await transport.send(responseFrame)
+                }
             }
         }
         // Wait for all in-flight dispatches to complete after the stream ends.
@@ -38,6 +38,40 @@ extension Array: WireFormat where Element: WireFormat {
     }
 }
 
+// MARK: - Fixed-size arrays
+
+// r[impl jetstream.wireformat.std]
+// r[impl jetstream.wireformat.swift.std]
+// A Rust `[T; N]` is its N elements with no count prefix. Swift has no
+// fixed-size arrays, so generated code passes the count to these.
+extension Array where Element: WireFormat {
+    public func fixedByteSize() -> UInt32 {
+        var size: UInt32 = 0
+        for element in self {
+            size += element.byteSize()
+        }
+        return size
+    }
+
+    public func encodeFixed(writer: inout BinaryWriter, count: Int) throws {
+        guard self.count == count else {
+            throw WireFormatError.arrayCountMismatch(self.count)
+        }
+        for element in self {
+            try element.encode(writer: &writer)
+        }
+    }
+
+    public static func decodeFixed(reader: inout BinaryReader, count: Int) throws -> [Element] {
+        var result: [Element] = []
+        result.reserveCapacity(count)
+        for _ in 0..<count {
+            result.append(try Element.decode(reader: &reader))
+        }
+        return result
+    }
+}
+
 // MARK: - WireData (Data with u32 length prefix)
 
 // r[impl jetstream.wireformat.data]
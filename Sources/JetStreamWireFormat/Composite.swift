@@ -68,3 +68,76 @@ import Foundation
 //           }
 //       }
 //   }
+
+// MARK: - WireResult
+
+// r[impl jetstream.wireformat.std]
+// r[impl jetstream.wireformat.swift.std]
+/// A Rust `Result<T, E>`: a u8 tag, 0 for `Ok` and 1 for `Err`, followed by
+/// the value. Swift's `Result` requires its failure to be an `Error`, so
+/// this is its own type.
+public enum WireResult<Success: WireFormat, Failure: WireFormat>: WireFormat {
+    case ok(Success)
+    case err(Failure)
+
+    public func byteSize() -> UInt32 {
+        switch self {
+        case .ok(let value): return 1 + value.byteSize()
+        case .err(let error): return 1 + error.byteSize()
+        }
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        switch self {
+        case .ok(let value):
+            writer.writeU8(0)
+            try value.encode(writer: &writer)
+        case .err(let error):
+            writer.writeU8(1)
+            try error.encode(writer: &writer)
+        }
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> WireResult {
+        let tag = try reader.readU8()
+        switch tag {
+        case 0: return .ok(try Success.decode(reader: &reader))
+        case 1: return .err(try Failure.decode(reader: &reader))
+        default: throw WireFormatError.invalidResultTag(tag)
+        }
+    }
+}
+
+extension WireResult: Equatable where Success: Equatable, Failure: Equatable {}
+
+// MARK: - WireRange
+
+/// A Rust `Range<T>`: the start followed by the end. Swift's `Range` traps
+/// when the start is after the end, which Rust allows, so this is its own
+/// type.
+public struct WireRange<Bound: WireFormat>: WireFormat {
+    public var start: Bound
+    public var end: Bound
+
+    public init(start: Bound, end: Bound) {
+        self.start = start
+        self.end = end
+    }
+
+    public func byteSize() -> UInt32 {
+        start.byteSize() + end.byteSize()
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        try start.encode(writer: &writer)
+        try end.encode(writer: &writer)
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> WireRange {
+        let start = try Bound.decode(reader: &reader)
+        let end = try Bound.decode(reader: &reader)
+        return WireRange(start: start, end: end)
+    }
+}
+
+extension WireRange: Equatable where Bound: Equatable {}
@@ -0,0 +1,143 @@
+// JetStream WireFormat — Strings, Arrays and Maps with u32 Length Prefixes
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+import Foundation
+
+// Lengths above 32 MB are rejected on decode, like those of `WireData`.
+private let maxLongLength: UInt32 = 32 * 1024 * 1024
+
+private func decodeLongLength(reader: inout BinaryReader) throws -> UInt32 {
+    let length = try UInt32.decode(reader: &reader)
+    guard length <= maxLongLength else {
+        throw WireFormatError.dataTooLarge(length)
+    }
+    return length
+}
+
+// MARK: - LongString
+
+// r[impl jetstream.wireformat.long]
+// r[impl jetstream.wireformat.swift.long]
+/// A string encoded with a u32 length prefix (unlike `String` which uses u16).
+public struct LongString: Equatable, Hashable {
+    public var value: String
+
+    public init(_ value: String = "") {
+        self.value = value
+    }
+}
+
+extension LongString: WireFormat {
+    public func byteSize() -> UInt32 {
+        UInt32(4 + value.utf8.count)
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        let utf8Bytes = Array(value.utf8)
+        guard utf8Bytes.count <= Int(UInt32.max) else {
+            throw WireFormatError.stringTooLong(utf8Bytes.count)
+        }
+        try UInt32(utf8Bytes.count).encode(writer: &writer)
+        writer.writeBytes(Data(utf8Bytes))
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> LongString {
+        let length = try decodeLongLength(reader: &reader)
+        let bytes = try reader.readBytes(count: Int(length))
+        guard let string = String(data: bytes, encoding: .utf8) else {
+            throw WireFormatError.invalidUTF8
+        }
+        return LongString(string)
+    }
+}
+
+// MARK: - LongVec
+
+/// An array encoded with a u32 count prefix (unlike `[T]` which uses u16).
+public struct LongVec<Element: WireFormat> {
+    public var elements: [Element]
+
+    public init(_ elements: [Element] = []) {
+        self.elements = elements
+    }
+}
+
+extension LongVec: Equatable where Element: Equatable {}
+
+extension LongVec: WireFormat {
+    public func byteSize() -> UInt32 {
+        var size: UInt32 = 4 // u32 count prefix
+        for element in elements {
+            size += element.byteSize()
+        }
+        return size
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        guard elements.count <= Int(UInt32.max) else {
+            throw WireFormatError.tooManyElements(elements.count)
+        }
+        try UInt32(elements.count).encode(writer: &writer)
+        for element in elements {
+            try element.encode(writer: &writer)
+        }
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> LongVec<Element> {
+        let count = try decodeLongLength(reader: &reader)
+        var elements: [Element] = []
+        elements.reserveCapacity(min(Int(count), 4096))
+        for _ in 0..<count {
+            elements.append(try Element.decode(reader: &reader))
+        }
+        return LongVec(elements)
+    }
+}
+
+// MARK: - LongMap
+
+/// An `OrderedMap` encoded with a u32 count prefix.
+public struct LongMap<K: WireFormat & Comparable, V: WireFormat>: Equatable
+    where K: Equatable, V: Equatable
+{
+    public var map: OrderedMap<K, V>
+
+    public init(_ map: OrderedMap<K, V> = OrderedMap()) {
+        self.map = map
+    }
+}
+
+extension LongMap: WireFormat {
+    public func byteSize() -> UInt32 {
+        var size: UInt32 = 4
+        for (key, value) in map.entries {
+            size += key.byteSize() + value.byteSize()
+        }
+        return size
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        guard map.entries.count <= Int(UInt32.max) else {
+            throw WireFormatError.tooManyElements(map.entries.count)
+        }
+        try UInt32(map.entries.count).encode(writer: &writer)
+        let sorted = map.entries.sorted { $0.key < $1.key }
+        for (key, value) in sorted {
+            try key.encode(writer: &writer)
+            try value.encode(writer: &writer)
+        }
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> LongMap<K, V> {
+        let count = try decodeLongLength(reader: &reader)
+        var entries: [(key: K, value: V)] = []
+        entries.reserveCapacity(min(Int(count), 4096))
+        for _ in 0..<count {
+            let key = try K.decode(reader: &reader)
+            let value = try V.decode(reader: &reader)
+            entries.append((key: key, value: value))
+        }
+        return LongMap(OrderedMap(entries))
+    }
+}
@@ -31,3 +31,24 @@ extension String: WireFormat {
         return string
     }
 }
+
+// MARK: - Unicode.Scalar (char)
+
+// r[impl jetstream.wireformat.std]
+// r[impl jetstream.wireformat.swift.std]
+/// A Rust `char`: its Unicode scalar value as a u32.
+extension Unicode.Scalar: WireFormat {
+    public func byteSize() -> UInt32 { 4 }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        try value.encode(writer: &writer)
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> Unicode.Scalar {
+        let value = try UInt32.decode(reader: &reader)
+        guard let scalar = Unicode.Scalar(value) else {
+            throw WireFormatError.invalidChar(value)
+        }
+        return scalar
+    }
+}
@@ -22,3 +22,44 @@ extension Date: WireFormat {
         return Date(timeIntervalSince1970: seconds)
     }
 }
+
+// MARK: - WireDuration
+
+// r[impl jetstream.wireformat.std]
+// r[impl jetstream.wireformat.swift.std]
+/// A Rust `Duration`: u64 whole seconds followed by the u32 nanoseconds
+/// within the second.
+public struct WireDuration: Equatable, Hashable {
+    public var secs: UInt64
+    public var nanos: UInt32
+
+    public init(secs: UInt64 = 0, nanos: UInt32 = 0) {
+        self.secs = secs
+        self.nanos = nanos
+    }
+
+    public var timeInterval: TimeInterval {
+        Double(secs) + Double(nanos) / 1_000_000_000
+    }
+}
+
+extension WireDuration: WireFormat {
+    public func byteSize() -> UInt32 { 12 }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        guard nanos < 1_000_000_000 else {
+            throw WireFormatError.invalidDurationNanos(nanos)
+        }
+        try secs.encode(writer: &writer)
+        try nanos.encode(writer: &writer)
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> WireDuration {
+        let secs = try UInt64.decode(reader: &reader)
+        let nanos = try UInt32.decode(reader: &reader)
+        guard nanos < 1_000_000_000 else {
+            throw WireFormatError.invalidDurationNanos(nanos)
+        }
+        return WireDuration(secs: secs, nanos: nanos)
+    }
+}
@@ -0,0 +1,23 @@
+// JetStream WireFormat — UUIDs
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+import Foundation
+
+// r[impl jetstream.wireformat.ecosystem]
+// r[impl jetstream.wireformat.swift.std]
+/// A Rust `uuid::Uuid`: its 16 bytes.
+extension UUID: WireFormat {
+    public func byteSize() -> UInt32 { 16 }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        withUnsafeBytes(of: uuid) { writer.writeBytes(Data($0)) }
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> UUID {
+        let data = try reader.readBytes(count: 16)
+        var uuid: uuid_t = (0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0)
+        withUnsafeMutableBytes(of: &uuid) { $0.copyBytes(from: data) }
+        return UUID(uuid: uuid)
+    }
+}
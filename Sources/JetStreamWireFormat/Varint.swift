@@ -0,0 +1,215 @@
+// JetStream WireFormat — Variable-Length Integers and Length Prefixes
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+import Foundation
+
+// Lengths above 32 MB are rejected on decode, like those of `WireData`.
+private let maxVarintLength: UInt64 = 32 * 1024 * 1024
+
+private func varintByteSize(_ value: UInt64) -> UInt32 {
+    var size: UInt32 = 1
+    var value = value
+    while value >= 0x80 {
+        value >>= 7
+        size += 1
+    }
+    return size
+}
+
+private func writeVarint(_ value: UInt64, writer: inout BinaryWriter) {
+    var value = value
+    while value >= 0x80 {
+        writer.writeU8(UInt8(value & 0x7f) | 0x80)
+        value >>= 7
+    }
+    writer.writeU8(UInt8(value))
+}
+
+private func readVarint(reader: inout BinaryReader) throws -> UInt64 {
+    var value: UInt64 = 0
+    var shift: UInt64 = 0
+    while true {
+        let byte = try reader.readU8()
+        let group = UInt64(byte & 0x7f)
+        if shift >= 64 || (shift > 57 && group >> (64 - shift) != 0) {
+            throw WireFormatError.varintOverflow
+        }
+        value |= group << shift
+        if byte & 0x80 == 0 {
+            return value
+        }
+        shift += 7
+    }
+}
+
+private func decodeVarintLength(reader: inout BinaryReader) throws -> Int {
+    let length = try readVarint(reader: &reader)
+    guard length <= maxVarintLength else {
+        throw WireFormatError.dataTooLarge(UInt32(clamping: length))
+    }
+    return Int(length)
+}
+
+// MARK: - Varint
+
+// r[impl jetstream.wireformat.varint]
+// r[impl jetstream.wireformat.swift.varint]
+/// An integer of up to 64 bits encoded as unsigned LEB128, zigzag encoded
+/// first if it is signed.
+public struct Varint<Value: FixedWidthInteger>: Equatable, Hashable {
+    public var value: Value
+
+    public init(_ value: Value = 0) {
+        self.value = value
+    }
+
+    var unsigned: UInt64 {
+        if Value.isSigned {
+            let value = Int64(self.value)
+            return UInt64(bitPattern: (value << 1) ^ (value >> 63))
+        }
+        return UInt64(value)
+    }
+}
+
+extension Varint: WireFormat {
+    public func byteSize() -> UInt32 {
+        varintByteSize(unsigned)
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        writeVarint(unsigned, writer: &writer)
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> Varint<Value> {
+        let raw = try readVarint(reader: &reader)
+        let value: Value?
+        if Value.isSigned {
+            value = Value(exactly: Int64(bitPattern: raw >> 1) ^ -Int64(bitPattern: raw & 1))
+        } else {
+            value = Value(exactly: raw)
+        }
+        guard let value else {
+            throw WireFormatError.varintOverflow
+        }
+        return Varint(value)
+    }
+}
+
+// MARK: - VarintString
+
+/// A string encoded with a varint length prefix.
+public struct VarintString: Equatable, Hashable {
+    public var value: String
+
+    public init(_ value: String = "") {
+        self.value = value
+    }
+}
+
+extension VarintString: WireFormat {
+    public func byteSize() -> UInt32 {
+        let count = value.utf8.count
+        return varintByteSize(UInt64(count)) + UInt32(count)
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        let utf8Bytes = Array(value.utf8)
+        writeVarint(UInt64(utf8Bytes.count), writer: &writer)
+        writer.writeBytes(Data(utf8Bytes))
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> VarintString {
+        let length = try decodeVarintLength(reader: &reader)
+        let bytes = try reader.readBytes(count: length)
+        guard let string = String(data: bytes, encoding: .utf8) else {
+            throw WireFormatError.invalidUTF8
+        }
+        return VarintString(string)
+    }
+}
+
+// MARK: - VarintVec
+
+/// An array encoded with a varint count prefix.
+public struct VarintVec<Element: WireFormat> {
+    public var elements: [Element]
+
+    public init(_ elements: [Element] = []) {
+        self.elements = elements
+    }
+}
+
+extension VarintVec: Equatable where Element: Equatable {}
+
+extension VarintVec: WireFormat {
+    public func byteSize() -> UInt32 {
+        var size = varintByteSize(UInt64(elements.count))
+        for element in elements {
+            size += element.byteSize()
+        }
+        return size
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        writeVarint(UInt64(elements.count), writer: &writer)
+        for element in elements {
+            try element.encode(writer: &writer)
+        }
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> VarintVec<Element> {
+        let count = try decodeVarintLength(reader: &reader)
+        var elements: [Element] = []
+        elements.reserveCapacity(min(count, 4096))
+        for _ in 0..<count {
+            elements.append(try Element.decode(reader: &reader))
+        }
+        return VarintVec(elements)
+    }
+}
+
+// MARK: - VarintMap
+
+/// An `OrderedMap` encoded with a varint count prefix.
+public struct VarintMap<K: WireFormat & Comparable, V: WireFormat>: Equatable
+    where K: Equatable, V: Equatable
+{
+    public var map: OrderedMap<K, V>
+
+    public init(_ map: OrderedMap<K, V> = OrderedMap()) {
+        self.map = map
+    }
+}
+
+extension VarintMap: WireFormat {
+    public func byteSize() -> UInt32 {
+        var size = varintByteSize(UInt64(map.entries.count))
+        for (key, value) in map.entries {
+            size += key.byteSize() + value.byteSize()
+        }
+        return size
+    }
+
+    public func encode(writer: inout BinaryWriter) throws {
+        writeVarint(UInt64(map.entries.count), writer: &writer)
+        let sorted = map.entries.sorted { $0.key < $1.key }
+        for (key, value) in sorted {
+            try key.encode(writer: &writer)
+            try value.encode(writer: &writer)
+        }
+    }
+
+    public static func decode(reader: inout BinaryReader) throws -> VarintMap<K, V> {
+        let count = try decodeVarintLength(reader: &reader)
+        var entries: [(key: K, value: V)] = []
+        entries.reserveCapacity(min(count, 4096))
+        for _ in 0..<count {
+            let key = try K.decode(reader: &reader)
+            let value = try V.decode(reader: &reader)
+            entries.append((key: key, value: value))
+        }
+        return VarintMap(OrderedMap(entries))
+    }
+}
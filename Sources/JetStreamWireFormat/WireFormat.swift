@@ -32,4 +32,9 @@ public enum WireFormatError: Error, Equatable {
     case invalidUTF8
     case timestampOverflow
     case tooManyElements(Int)
+    case varintOverflow
+    case invalidChar(UInt32)
+    case invalidResultTag(UInt8)
+    case invalidDurationNanos(UInt32)
+    case arrayCountMismatch(Int)
 }
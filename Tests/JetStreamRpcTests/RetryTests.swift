@@ -0,0 +1,62 @@
+// JetStream RPC — Retry Tests
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+// r[verify jetstream.rpc.swift.retry]
+
+import XCTest
+import JetStreamWireFormat
+@testable import JetStreamRpc
+
+final class RetryTests: XCTestCase {
+
+    private let fast = RetryPolicy(maxRetries: 2, initialBackoff: 0.001, maxBackoff: 0.001)
+
+    private func error(_ code: String?) -> JetStreamError {
+        JetStreamError(inner: ErrorInner(message: "failed", code: code))
+    }
+
+    func testRetriesRetriableErrors() async throws {
+        var calls = 0
+        let result = try await withRetry(fast) { () async throws -> String in
+            calls += 1
+            if calls < 3 { throw error(CONNECTION_LOST) }
+            return "ok"
+        }
+        XCTAssertEqual(result, "ok")
+        XCTAssertEqual(calls, 3)
+    }
+
+    func testStopsAtRetryBudget() async {
+        var calls = 0
+        do {
+            _ = try await withRetry(fast) { () async throws -> String in
+                calls += 1
+                throw error(UNAVAILABLE)
+            }
+            XCTFail("expected an error")
+        } catch {
+            XCTAssertEqual(calls, 3)
+        }
+    }
+
+    func testDoesNotRetryOtherErrors() async {
+        var calls = 0
+        do {
+            _ = try await withRetry(fast) { () async throws -> String in
+                calls += 1
+                throw error(nil)
+            }
+            XCTFail("expected an error")
+        } catch {
+            XCTAssertEqual(calls, 1)
+        }
+    }
+
+    func testBackoffDoublesUpToCap() {
+        let policy = RetryPolicy(maxRetries: 5, initialBackoff: 0.1, maxBackoff: 0.5)
+        XCTAssertEqual(policy.delay(attempt: 0), 0.1, accuracy: 1e-9)
+        XCTAssertEqual(policy.delay(attempt: 1), 0.2, accuracy: 1e-9)
+        XCTAssertEqual(policy.delay(attempt: 3), 0.5, accuracy: 1e-9)
+    }
+}
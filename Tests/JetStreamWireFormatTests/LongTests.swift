@@ -0,0 +1,51 @@
+// JetStream WireFormat — Long Length Prefix Tests
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+import XCTest
+@testable import JetStreamWireFormat
+
+final class LongTests: XCTestCase {
+
+    func roundTrip<T: WireFormat>(_ value: T) throws -> T {
+        var writer = BinaryWriter()
+        try value.encode(writer: &writer)
+        XCTAssertEqual(UInt32(writer.data.count), value.byteSize())
+        var reader = BinaryReader(data: writer.data)
+        return try T.decode(reader: &reader)
+    }
+
+    // r[verify jetstream.wireformat.swift.long]
+    // r[verify jetstream.wireformat.long]
+    func testLongStringRoundTrip() throws {
+        let value = LongString(String(repeating: "x", count: 0x10000))
+        XCTAssertEqual(try roundTrip(value), value)
+    }
+
+    func testLongStringEncoding() throws {
+        var writer = BinaryWriter()
+        try LongString("hi").encode(writer: &writer)
+        // u32 length (2, LE) + "hi" bytes
+        XCTAssertEqual(writer.data, Data([0x02, 0x00, 0x00, 0x00, 0x68, 0x69]))
+    }
+
+    func testLongVecRoundTrip() throws {
+        let value = LongVec((0..<70_000).map { UInt16($0 % 0x10000) })
+        XCTAssertEqual(try roundTrip(value), value)
+    }
+
+    func testLongMapRoundTrip() throws {
+        let value = LongMap(OrderedMap<String, UInt32>([(key: "b", value: 2), (key: "a", value: 1)]))
+        XCTAssertEqual(try roundTrip(value), value)
+    }
+
+    // r[verify jetstream.wireformat.swift.test-error]
+    func testLongLengthTooLarge() throws {
+        var writer = BinaryWriter()
+        writer.writeU32(33_554_433) // 32MB + 1
+        var reader = BinaryReader(data: writer.data)
+        XCTAssertThrowsError(try LongVec<UInt8>.decode(reader: &reader)) { error in
+            XCTAssertEqual(error as? WireFormatError, WireFormatError.dataTooLarge(33_554_433))
+        }
+    }
+}
@@ -0,0 +1,77 @@
+// JetStream WireFormat — Standard Library Type Tests
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+import XCTest
+@testable import JetStreamWireFormat
+
+final class StdTests: XCTestCase {
+
+    func encode<T: WireFormat>(_ value: T) throws -> Data {
+        var writer = BinaryWriter()
+        try value.encode(writer: &writer)
+        XCTAssertEqual(UInt32(writer.data.count), value.byteSize())
+        return writer.data
+    }
+
+    func roundTrip<T: WireFormat>(_ value: T) throws -> T {
+        var reader = BinaryReader(data: try encode(value))
+        return try T.decode(reader: &reader)
+    }
+
+    // r[verify jetstream.wireformat.swift.std]
+    // r[verify jetstream.wireformat.std]
+    func testChar() throws {
+        XCTAssertEqual(try encode(Unicode.Scalar(0x41)!), Data([0x41, 0, 0, 0]))
+        XCTAssertEqual(try roundTrip(Unicode.Scalar(0x1F600)!), Unicode.Scalar(0x1F600)!)
+        var reader = BinaryReader(data: Data([0x00, 0xd8, 0, 0]))
+        XCTAssertThrowsError(try Unicode.Scalar.decode(reader: &reader)) { error in
+            XCTAssertEqual(error as? WireFormatError, WireFormatError.invalidChar(0xd800))
+        }
+    }
+
+    func testFixedArray() throws {
+        var writer = BinaryWriter()
+        try [UInt16(1), 2].encodeFixed(writer: &writer, count: 2)
+        XCTAssertEqual(writer.data, Data([1, 0, 2, 0]))
+        XCTAssertEqual([UInt16(1), 2].fixedByteSize(), 4)
+        var reader = BinaryReader(data: writer.data)
+        XCTAssertEqual(try [UInt16].decodeFixed(reader: &reader, count: 2), [1, 2])
+        XCTAssertThrowsError(try [UInt16(1)].encodeFixed(writer: &writer, count: 2))
+    }
+
+    func testResult() throws {
+        XCTAssertEqual(try encode(WireResult<UInt8, String>.ok(5)), Data([0, 5]))
+        XCTAssertEqual(
+            try roundTrip(WireResult<UInt8, String>.err("nope")),
+            WireResult<UInt8, String>.err("nope"))
+        var reader = BinaryReader(data: Data([2]))
+        XCTAssertThrowsError(try WireResult<UInt8, UInt8>.decode(reader: &reader)) { error in
+            XCTAssertEqual(error as? WireFormatError, WireFormatError.invalidResultTag(2))
+        }
+    }
+
+    func testRange() throws {
+        XCTAssertEqual(try encode(WireRange<UInt8>(start: 3, end: 9)), Data([3, 9]))
+        XCTAssertEqual(
+            try roundTrip(WireRange<UInt32>(start: 9, end: 3)),
+            WireRange<UInt32>(start: 9, end: 3))
+    }
+
+    func testDuration() throws {
+        XCTAssertEqual(
+            try encode(WireDuration(secs: 1, nanos: 2)),
+            Data([1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0]))
+        XCTAssertEqual(try roundTrip(WireDuration(secs: 30, nanos: 500)), WireDuration(secs: 30, nanos: 500))
+        XCTAssertThrowsError(try encode(WireDuration(secs: 0, nanos: 1_000_000_000)))
+    }
+
+    // r[verify jetstream.wireformat.ecosystem]
+    func testUUID() throws {
+        let id = UUID(uuidString: "67E55044-10B1-426F-9247-BB680E5FE0C8")!
+        let data = try encode(id)
+        XCTAssertEqual(data.first, 0x67)
+        XCTAssertEqual(data.last, 0xc8)
+        XCTAssertEqual(try roundTrip(id), id)
+    }
+}
@@ -0,0 +1,54 @@
+// JetStream WireFormat — Varint Tests
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// SPDX-License-Identifier: BSD-3-Clause
+
+import XCTest
+@testable import JetStreamWireFormat
+
+final class VarintTests: XCTestCase {
+
+    func encode<T: WireFormat>(_ value: T) throws -> Data {
+        var writer = BinaryWriter()
+        try value.encode(writer: &writer)
+        XCTAssertEqual(UInt32(writer.data.count), value.byteSize())
+        return writer.data
+    }
+
+    func roundTrip<T: WireFormat>(_ value: T) throws -> T {
+        var reader = BinaryReader(data: try encode(value))
+        return try T.decode(reader: &reader)
+    }
+
+    // r[verify jetstream.wireformat.swift.varint]
+    // r[verify jetstream.wireformat.varint]
+    func testUnsignedEncoding() throws {
+        XCTAssertEqual(try encode(Varint<UInt32>(0)), Data([0x00]))
+        XCTAssertEqual(try encode(Varint<UInt32>(127)), Data([0x7f]))
+        XCTAssertEqual(try encode(Varint<UInt32>(300)), Data([0xac, 0x02]))
+        XCTAssertEqual(try encode(Varint<UInt64>(.max)).count, 10)
+    }
+
+    func testZigzagEncoding() throws {
+        XCTAssertEqual(try encode(Varint<Int32>(0)), Data([0x00]))
+        XCTAssertEqual(try encode(Varint<Int32>(-1)), Data([0x01]))
+        XCTAssertEqual(try encode(Varint<Int32>(1)), Data([0x02]))
+        XCTAssertEqual(try encode(Varint<Int32>(-2)), Data([0x03]))
+        XCTAssertEqual(try roundTrip(Varint<Int32>(.min)), Varint<Int32>(.min))
+        XCTAssertEqual(try roundTrip(Varint<Int64>(.min)), Varint<Int64>(.min))
+    }
+
+    func testOverflow() throws {
+        var reader = BinaryReader(data: Data([0x80, 0x80, 0x80, 0x80, 0x10]))
+        XCTAssertThrowsError(try Varint<UInt32>.decode(reader: &reader)) { error in
+            XCTAssertEqual(error as? WireFormatError, WireFormatError.varintOverflow)
+        }
+    }
+
+    func testLengthPrefixes() throws {
+        XCTAssertEqual(try encode(VarintString("abc")), Data([0x03, 0x61, 0x62, 0x63]))
+        let vec = VarintVec((0..<200).map { UInt8($0) })
+        XCTAssertEqual(try roundTrip(vec), vec)
+        let map = VarintMap(OrderedMap<String, UInt32>([(key: "b", value: 2), (key: "a", value: 1)]))
+        XCTAssertEqual(try roundTrip(map), map)
+    }
+}
@@ -16,6 +16,28 @@ pub struct Options {
     pub into: Option<syn::Path>,
     /// #[jetstream(as(impl As<WireFormat>))]
     pub as_: Option<syn::Path>,
+    /// #[jetstream(since = N)]
+    pub since: Option<u32>,
+    /// #[jetstream(default)]
+    pub default: bool,
+    /// #[jetstream(len = u32)] or #[jetstream(len = varint)]
+    pub len: Option<Ident>,
+    /// #[jetstream(varint)]
+    pub varint: bool,
+}
+
+impl Options {
+    // r[impl jetstream.wireformat.long]
+    /// Whether the field's length is prefixed with a `u32`.
+    pub fn is_long(&self) -> bool {
+        self.len.as_ref().is_some_and(|width| width == "u32")
+    }
+
+    // r[impl jetstream.wireformat.varint]
+    /// Whether the field's length is prefixed with a varint.
+    pub fn is_varint_len(&self) -> bool {
+        self.len.as_ref().is_some_and(|width| width == "varint")
+    }
 }
 
 /// r[jetstream.codegen.skip]
@@ -36,6 +58,24 @@ pub fn has_skip_attr(field: &syn::Field) -> bool {
     })
 }
 
+/// r[jetstream.wireformat.evolvable]
+pub fn is_evolvable(input: &DeriveInput) -> bool {
+    input.attrs.iter().any(|attr| {
+        if attr.path().is_ident("jetstream") {
+            if let Ok(()) = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("evolvable") {
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `evolvable`"))
+                }
+            }) {
+                return true;
+            }
+        }
+        false
+    })
+}
+
 pub fn extract_jetstream_type(input: &DeriveInput) -> Option<Ident> {
     for attr in &input.attrs {
         if attr.path().is_ident("jetstream_type") {
@@ -104,6 +144,23 @@ pub fn extract_field_options(field: &syn::Field) -> Options {
                     options.as_ = Some(path);
                     return Ok(());
                 }
+                if meta.path.is_ident("since") {
+                    let version: syn::LitInt = meta.value()?.parse()?;
+                    options.since = Some(version.base10_parse()?);
+                    return Ok(());
+                }
+                if meta.path.is_ident("default") {
+                    options.default = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("varint") {
+                    options.varint = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("len") {
+                    options.len = Some(meta.value()?.parse()?);
+                    return Ok(());
+                }
                 if meta.path.is_ident("skip") {
                     return Ok(());
                 }
@@ -63,10 +63,14 @@ fn main() {
         eprintln!("error resolving {}: {e}", args.input.display());
         std::process::exit(1);
     });
-    let pkg_version = find_package_version(&input_abs);
+    let package = find_package(&input_abs);
 
     let items = parse_file(&source);
-    let services = parse_services_from_file(&source, &pkg_version);
+    let services = parse_services_from_file(
+        &source,
+        &package.module_path,
+        &package.version,
+    );
 
     let stem = args
         .input
@@ -132,9 +136,17 @@ fn main() {
     }
 }
 
-/// Walk up from a source file path to find the owning Cargo package and return
-/// its semver version string (e.g. "15.0.0").
-fn find_package_version(input_path: &std::path::Path) -> String {
+/// The Cargo package owning a source file.
+struct Package {
+    /// Its semver version string (e.g. "15.0.0").
+    version: String,
+    /// The path of the module the file is (e.g. "my_crate::storage"), which
+    /// services in it are named after.
+    module_path: String,
+}
+
+/// Walk up from a source file path to find the owning Cargo package.
+fn find_package(input_path: &std::path::Path) -> Package {
     // Find the manifest directory by walking up from the input file
     let mut manifest_dir = input_path.parent();
     let manifest_path = loop {
@@ -163,15 +175,20 @@ fn find_package_version(input_path: &std::path::Path) -> String {
         });
 
     // Find the package whose manifest_path matches
-    for pkg in &metadata.packages {
-        if pkg.manifest_path == manifest_path {
-            return pkg.version.to_string();
-        }
-    }
-
-    // Fallback: if there's exactly one package, use it
-    if metadata.packages.len() == 1 {
-        return metadata.packages[0].version.to_string();
+    let pkg = metadata
+        .packages
+        .iter()
+        .find(|pkg| pkg.manifest_path == manifest_path)
+        // Fallback: if there's exactly one package, use it
+        .or(match metadata.packages.as_slice() {
+            [pkg] => Some(pkg),
+            _ => None,
+        });
+    if let Some(pkg) = pkg {
+        return Package {
+            version: pkg.version.to_string(),
+            module_path: module_path(pkg, input_path),
+        };
     }
 
     eprintln!(
@@ -180,3 +197,46 @@ fn find_package_version(input_path: &std::path::Path) -> String {
     );
     std::process::exit(1);
 }
+
+/// The path of the module `input_path` is in the target of `pkg` whose
+/// sources it is in: `src/lib.rs` is `my_crate`, and `src/storage/mod.rs`
+/// and `src/storage.rs` are `my_crate::storage`.
+fn module_path(
+    pkg: &cargo_metadata::Package,
+    input_path: &std::path::Path,
+) -> String {
+    // The target whose root the file is, or else the innermost one whose
+    // directory it is in, preferring the library.
+    let target = pkg
+        .targets
+        .iter()
+        .find(|target| target.src_path.as_std_path() == input_path)
+        .or_else(|| {
+            pkg.targets
+                .iter()
+                .filter(|target| {
+                    target
+                        .src_path
+                        .parent()
+                        .is_some_and(|dir| input_path.starts_with(dir))
+                })
+                .max_by_key(|target| {
+                    (target.src_path.components().count(), target.is_lib())
+                })
+        });
+    let Some(target) = target else {
+        return pkg.name.replace('-', "_");
+    };
+    let mut path = vec![target.name.replace('-', "_")];
+    if input_path != target.src_path.as_std_path() {
+        let dir = target.src_path.parent().unwrap().as_std_path();
+        let relative = input_path.strip_prefix(dir).unwrap().with_extension("");
+        path.extend(
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .filter(|c| c != "mod"),
+        );
+    }
+    path.join("::")
+}
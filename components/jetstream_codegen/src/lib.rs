@@ -1,7 +1,9 @@
 // r[impl jetstream.codegen.ir]
 
 pub mod attributes;
+pub mod message_ids;
 pub mod parser;
+pub mod service_attr;
 pub mod service_parser;
 pub mod swift_backend;
 pub mod swift_rpc_backend;
@@ -0,0 +1,270 @@
+// r[impl jetstream.service.message-ids]
+//! Message types of `#[service]` methods.
+//!
+//! By default a method's request message type is derived from its position
+//! in the trait, `MESSAGE_ID_START + 2 * index`, and its response is the
+//! next one. Methods can pin their request message type with `#[id = N]`,
+//! services can derive the message types of unpinned methods from their
+//! names with `#[service(hashed_ids)]`, and services that need more than
+//! the `u8` space carry a `u16` message id in a [`TEXTENDED`] or
+//! [`REXTENDED`] envelope with `#[service(extended_ids)]`.
+
+use std::collections::BTreeMap;
+
+/// First message type of service methods.
+pub const MESSAGE_ID_START: u8 = 102;
+/// Message type of requests carrying a `u16` message id.
+pub const TEXTENDED: u8 = 96;
+/// Message type of responses carrying a `u16` message id.
+pub const REXTENDED: u8 = TEXTENDED + 1;
+
+/// How message types are assigned to methods without `#[id = N]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum IdScheme {
+    /// From the method's position in the trait.
+    #[default]
+    Positional,
+    /// From a hash of the method's name.
+    Hashed,
+}
+
+/// The message types of a service's methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct IdOptions {
+    pub scheme: IdScheme,
+    /// Carry `u16` message ids in [`TEXTENDED`] and [`REXTENDED`] frames.
+    pub extended: bool,
+}
+
+impl IdOptions {
+    /// The largest request message id a method can have.
+    pub fn max_request(&self) -> u16 {
+        if self.extended {
+            u16::MAX - 1
+        } else {
+            u8::MAX as u16 - 1
+        }
+    }
+
+    /// The smallest request message id a method can have.
+    pub fn min_request(&self) -> u16 {
+        if self.extended {
+            0
+        } else {
+            MESSAGE_ID_START as u16
+        }
+    }
+
+    fn slots(&self) -> u32 {
+        (self.max_request() - self.min_request()) as u32 / 2 + 1
+    }
+}
+
+/// The request and response message ids of one method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageIds {
+    pub request: u16,
+    pub response: u16,
+}
+
+/// A method as far as message ids are concerned.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodIds {
+    pub name: String,
+    /// Position of the method among the items of the trait.
+    pub position: usize,
+    /// The method's `#[id = N]`, if any.
+    pub explicit: Option<u16>,
+}
+
+/// Whether an attribute is `#[id = N]`.
+pub fn is_id_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("id")
+}
+
+/// Parses a method's `#[id = N]`, failing if it has more than one.
+pub fn explicit_id(attrs: &[syn::Attribute]) -> syn::Result<Option<u16>> {
+    let mut ids = attrs.iter().filter(|attr| is_id_attr(attr));
+    let Some(attr) = ids.next() else {
+        return Ok(None);
+    };
+    if let Some(duplicate) = ids.next() {
+        return Err(syn::Error::new_spanned(
+            duplicate,
+            "duplicate #[id] attribute",
+        ));
+    }
+    let value = &attr.meta.require_name_value()?.value;
+    match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => int.base10_parse().map(Some),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            "expected an integer message id, like `#[id = 120]`",
+        )),
+    }
+}
+
+/// Assigns message ids to `methods`, in trait order. Fails with the index of
+/// the offending method and a message if an id is out of range or two
+/// methods share an id.
+pub fn assign(
+    methods: &[MethodIds],
+    options: IdOptions,
+) -> Result<Vec<MessageIds>, (usize, String)> {
+    let min = options.min_request();
+    let max = options.max_request();
+    let mut taken: BTreeMap<u16, usize> = BTreeMap::new();
+    let mut ids = Vec::with_capacity(methods.len());
+
+    for (index, method) in methods.iter().enumerate() {
+        let request = match method.explicit {
+            Some(id) if id < min || id > max => {
+                return Err((
+                    index,
+                    format!(
+                        "message id {} of `{}` is outside {}..={}",
+                        id, method.name, min, max
+                    ),
+                ));
+            }
+            Some(id) => id,
+            None => {
+                let slot = match options.scheme {
+                    IdScheme::Positional => method.position as u32,
+                    IdScheme::Hashed => fnv1a(&method.name) % options.slots(),
+                };
+                if slot >= options.slots() {
+                    return Err((
+                        index,
+                        format!(
+                            "`{}` does not fit in the u8 message types; pin \
+                             its id with `#[id = N]` or use \
+                             `#[service(extended_ids)]`",
+                            method.name
+                        ),
+                    ));
+                }
+                min + 2 * slot as u16
+            }
+        };
+        let response = request + 1;
+        for id in [request, response] {
+            if let Some(other) = taken.insert(id, index) {
+                return Err((
+                    index,
+                    format!(
+                        "message id {} of `{}` collides with `{}`; pin one \
+                         of them with `#[id = N]`",
+                        id, method.name, methods[other].name
+                    ),
+                ));
+            }
+        }
+        ids.push(MessageIds { request, response });
+    }
+    Ok(ids)
+}
+
+// 32-bit FNV-1a, stable across compiler versions and platforms.
+fn fnv1a(name: &str) -> u32 {
+    name.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn methods(names: &[(&str, Option<u16>)]) -> Vec<MethodIds> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(position, (name, explicit))| MethodIds {
+                name: name.to_string(),
+                position,
+                explicit: *explicit,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn positional_ids() {
+        let ids = assign(
+            &methods(&[("ping", None), ("echo", Some(200)), ("add", None)]),
+            IdOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            ids,
+            vec![
+                MessageIds {
+                    request: 102,
+                    response: 103
+                },
+                MessageIds {
+                    request: 200,
+                    response: 201
+                },
+                MessageIds {
+                    request: 106,
+                    response: 107
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn hashed_ids_do_not_depend_on_order() {
+        let options = IdOptions {
+            scheme: IdScheme::Hashed,
+            extended: true,
+        };
+        let a = assign(&methods(&[("ping", None), ("echo", None)]), options)
+            .unwrap();
+        let b = assign(&methods(&[("echo", None), ("ping", None)]), options)
+            .unwrap();
+        assert_eq!(a[0], b[1]);
+        assert_eq!(a[1], b[0]);
+    }
+
+    #[test]
+    fn rejects_collisions_and_out_of_range_ids() {
+        let err = assign(
+            &methods(&[("ping", None), ("echo", Some(103))]),
+            IdOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            (
+                1,
+                "message id 103 of `echo` collides with `ping`; pin one of \
+                 them with `#[id = N]`"
+                    .to_string()
+            )
+        );
+
+        let err =
+            assign(&methods(&[("ping", Some(255))]), IdOptions::default())
+                .unwrap_err();
+        assert_eq!(err.0, 0);
+
+        let many = (0..78)
+            .map(|i| MethodIds {
+                name: format!("m{i}"),
+                position: i,
+                explicit: None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(assign(&many, IdOptions::default()).unwrap_err().0, 77);
+        let extended = IdOptions {
+            extended: true,
+            ..Default::default()
+        };
+        assert_eq!(assign(&many, extended).unwrap()[77].request, 154);
+    }
+}
@@ -4,7 +4,35 @@ use std::collections::HashMap;
 
 use typeshare_core::rust_types::*;
 
-use crate::attributes::has_skip_attr;
+use crate::attributes::{extract_field_options, has_skip_attr, is_evolvable};
+
+/// Recorded in the comments of structs derived with `#[jetstream(evolvable)]`,
+/// since typeshare's `RustStruct` has no place for it.
+pub const EVOLVABLE: &str = "jetstream(evolvable)";
+
+/// Whether a parsed struct was derived with `#[jetstream(evolvable)]`.
+pub fn is_evolvable_struct(s: &RustStruct) -> bool {
+    s.comments.iter().any(|c| c == EVOLVABLE)
+}
+
+/// Recorded, followed by `N)`, in the comments of fields marked
+/// `#[jetstream(since = N)]`.
+const SINCE: &str = "jetstream(since = ";
+
+/// The `N` of a parsed field's `#[jetstream(since = N)]`.
+pub fn field_since(f: &RustField) -> Option<u32> {
+    f.comments
+        .iter()
+        .find_map(|c| c.strip_prefix(SINCE)?.strip_suffix(')')?.parse().ok())
+}
+
+fn field_comments(f: &syn::Field) -> Vec<String> {
+    extract_field_options(f)
+        .since
+        .map(|version| format!("{SINCE}{version})"))
+        .into_iter()
+        .collect()
+}
 
 /// Parse a syn::Type into a typeshare-core RustType.
 ///
@@ -52,7 +80,48 @@ pub fn parse_rust_type(ty: &syn::Type) -> RustType {
                 "f32" => RustType::Special(SpecialRustType::F32),
                 "f64" => RustType::Special(SpecialRustType::F64),
                 "bool" => RustType::Special(SpecialRustType::Bool),
-                "String" | "str" => RustType::Special(SpecialRustType::String),
+                "String" | "str" | "PathBuf" => {
+                    RustType::Special(SpecialRustType::String)
+                }
+                "char" => RustType::Special(SpecialRustType::Char),
+                "NonZeroU8" => RustType::Special(SpecialRustType::U8),
+                "NonZeroU16" => RustType::Special(SpecialRustType::U16),
+                "NonZeroU32" => RustType::Special(SpecialRustType::U32),
+                "NonZeroU64" => RustType::Special(SpecialRustType::U64),
+                "NonZeroUsize" => RustType::Special(SpecialRustType::USize),
+                "NonZeroI16" => RustType::Special(SpecialRustType::I16),
+                "NonZeroI32" => RustType::Special(SpecialRustType::I32),
+                "NonZeroI64" => RustType::Special(SpecialRustType::I64),
+                "NonZeroIsize" => RustType::Special(SpecialRustType::ISize),
+                "NonZeroU128" => RustType::Simple { id: "u128".into() },
+                "NonZeroI128" => RustType::Simple { id: "i128".into() },
+                // Timestamps all encode as milliseconds since the epoch.
+                // Only chrono's `DateTime<Utc>` has a wire format.
+                "SystemTime" | "OffsetDateTime" => {
+                    RustType::Special(SpecialRustType::DateTime)
+                }
+                "DateTime"
+                    if matches!(
+                        generic_args.as_slice(),
+                        [RustType::Simple { id }] if id == "Utc"
+                    ) =>
+                {
+                    RustType::Special(SpecialRustType::DateTime)
+                }
+                "Bytes" => RustType::Special(SpecialRustType::Vec(Box::new(
+                    RustType::Special(SpecialRustType::U8),
+                ))),
+                // `Version` is too common a name to assume it is semver's
+                // unless the path says so.
+                "Version"
+                    if type_path
+                        .path
+                        .segments
+                        .iter()
+                        .any(|s| s.ident == "semver") =>
+                {
+                    RustType::Special(SpecialRustType::String)
+                }
                 "Vec" => {
                     let inner = generic_args
                         .into_iter()
@@ -80,10 +149,13 @@ pub fn parse_rust_type(ty: &syn::Type) -> RustType {
                         Box::new(val),
                     ))
                 }
-                "Box" | "Arc" | "Rc" => generic_args
-                    .into_iter()
-                    .next()
-                    .unwrap_or(RustType::Special(SpecialRustType::Unit)),
+                // `Serde<T>` encodes exactly like the derived encoding of `T`.
+                "Box" | "Arc" | "Rc" | "Cow" | "NonZero" | "Serde" => {
+                    generic_args
+                        .into_iter()
+                        .next()
+                        .unwrap_or(RustType::Special(SpecialRustType::Unit))
+                }
                 _ => {
                     if generic_args.is_empty() {
                         RustType::Simple { id: ident }
@@ -97,6 +169,26 @@ pub fn parse_rust_type(ty: &syn::Type) -> RustType {
             }
         }
         syn::Type::Reference(reference) => parse_rust_type(&reference.elem),
+        syn::Type::Slice(slice) => RustType::Special(SpecialRustType::Vec(
+            Box::new(parse_rust_type(&slice.elem)),
+        )),
+        syn::Type::Array(array) => match &array.len {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(len),
+                ..
+            }) => match len.base10_parse() {
+                Ok(len) => RustType::Special(SpecialRustType::Array(
+                    Box::new(parse_rust_type(&array.elem)),
+                    len,
+                )),
+                Err(_) => RustType::Simple {
+                    id: "Unknown".into(),
+                },
+            },
+            _ => RustType::Simple {
+                id: "Unknown".into(),
+            },
+        },
         syn::Type::Tuple(tuple) if tuple.elems.is_empty() => {
             RustType::Special(SpecialRustType::Unit)
         }
@@ -126,7 +218,11 @@ pub fn parse_derive_input(input: &syn::DeriveInput) -> Option<RustItem> {
                 },
                 generic_types,
                 fields,
-                comments: vec![],
+                comments: if is_evolvable(input) {
+                    vec![EVOLVABLE.to_string()]
+                } else {
+                    vec![]
+                },
                 decorators: Default::default(),
                 is_redacted: false,
             }))
@@ -150,7 +246,7 @@ pub fn parse_derive_input(input: &syn::DeriveInput) -> Option<RustItem> {
                             if fields.unnamed.len() == 1 {
                                 let f = fields.unnamed.first().unwrap();
                                 RustEnumVariant::Tuple {
-                                    ty: parse_rust_type(&f.ty),
+                                    ty: field_type(f),
                                     shared,
                                 }
                             } else {
@@ -167,7 +263,7 @@ pub fn parse_derive_input(input: &syn::DeriveInput) -> Option<RustItem> {
                                             renamed: format!("field_{i}"),
                                             serde_rename: false,
                                         },
-                                        ty: parse_rust_type(&f.ty),
+                                        ty: field_type(f),
                                         comments: vec![],
                                         has_default: false,
                                         decorators: HashMap::new(),
@@ -196,7 +292,7 @@ pub fn parse_derive_input(input: &syn::DeriveInput) -> Option<RustItem> {
                                             renamed: field_name,
                                             serde_rename: false,
                                         },
-                                        ty: parse_rust_type(&f.ty),
+                                        ty: field_type(f),
                                         comments: vec![],
                                         has_default: false,
                                         decorators: HashMap::new(),
@@ -259,9 +355,9 @@ fn parse_fields(fields: &syn::Fields) -> Vec<RustField> {
                         renamed: field_name,
                         serde_rename: false,
                     },
-                    ty: parse_rust_type(&f.ty),
-                    comments: vec![],
-                    has_default: false,
+                    ty: field_type(f),
+                    comments: field_comments(f),
+                    has_default: extract_field_options(f).default,
                     decorators: HashMap::new(),
                 }
             })
@@ -277,9 +373,9 @@ fn parse_fields(fields: &syn::Fields) -> Vec<RustField> {
                     renamed: format!("field_{i}"),
                     serde_rename: false,
                 },
-                ty: parse_rust_type(&f.ty),
-                comments: vec![],
-                has_default: false,
+                ty: field_type(f),
+                comments: field_comments(f),
+                has_default: extract_field_options(f).default,
                 decorators: HashMap::new(),
             })
             .collect(),
@@ -287,6 +383,47 @@ fn parse_fields(fields: &syn::Fields) -> Vec<RustField> {
     }
 }
 
+// r[impl jetstream.codegen.long]
+// r[impl jetstream.codegen.varint]
+/// The type of a field, with integers marked `#[jetstream(varint)]` as the
+/// `Varint<T>` they encode like, and strings, vectors and maps marked
+/// `#[jetstream(len = u32)]` or `#[jetstream(len = varint)]` as the
+/// `LongString`, `LongVec` and `LongMap` or `VarintString`, `VarintVec` and
+/// `VarintMap` they encode like.
+fn field_type(field: &syn::Field) -> RustType {
+    let ty = parse_rust_type(&field.ty);
+    let options = extract_field_options(field);
+    if options.varint {
+        return RustType::Generic {
+            id: "Varint".into(),
+            parameters: vec![ty],
+        };
+    }
+    let prefix = if options.is_long() {
+        "Long"
+    } else if options.is_varint_len() {
+        "Varint"
+    } else {
+        return ty;
+    };
+    match ty {
+        RustType::Special(SpecialRustType::String) => RustType::Simple {
+            id: format!("{prefix}String"),
+        },
+        RustType::Special(SpecialRustType::Vec(inner)) => RustType::Generic {
+            id: format!("{prefix}Vec"),
+            parameters: vec![*inner],
+        },
+        RustType::Special(SpecialRustType::HashMap(k, v)) => {
+            RustType::Generic {
+                id: format!("{prefix}Map"),
+                parameters: vec![*k, *v],
+            }
+        }
+        ty => ty,
+    }
+}
+
 /// Check if a syn::DeriveInput has `#[derive(JetStreamWireFormat)]`.
 fn has_wireformat_derive(item: &syn::Item) -> bool {
     let attrs = match item {
@@ -455,4 +592,94 @@ mod tests {
             RustType::Special(SpecialRustType::U32)
         );
     }
+
+    /// r[verify jetstream.codegen.type-map]
+    #[test]
+    fn test_parse_rust_type_references() {
+        let ty: syn::Type = syn::parse_str("&str").unwrap();
+        assert_eq!(
+            parse_rust_type(&ty),
+            RustType::Special(SpecialRustType::String)
+        );
+
+        let ty: syn::Type = syn::parse_str("&[u8]").unwrap();
+        assert_eq!(
+            parse_rust_type(&ty),
+            RustType::Special(SpecialRustType::Vec(Box::new(
+                RustType::Special(SpecialRustType::U8)
+            )))
+        );
+    }
+
+    /// r[verify jetstream.codegen.std-types]
+    #[test]
+    fn test_parse_rust_type_std_and_ecosystem() {
+        let parse = |src: &str| parse_rust_type(&syn::parse_str(src).unwrap());
+        let u8 = || RustType::Special(SpecialRustType::U8);
+        let string = || RustType::Special(SpecialRustType::String);
+
+        assert_eq!(parse("char"), RustType::Special(SpecialRustType::Char));
+        assert_eq!(
+            parse("NonZeroU32"),
+            RustType::Special(SpecialRustType::U32)
+        );
+        assert_eq!(
+            parse("NonZero<i64>"),
+            RustType::Special(SpecialRustType::I64)
+        );
+        assert_eq!(parse("PathBuf"), string());
+        assert_eq!(parse("Cow<'a, str>"), string());
+        assert_eq!(parse("Serde<String>"), string());
+        assert_eq!(
+            parse("jetstream_wireformat::Serde<Vec<u8>>"),
+            RustType::Special(SpecialRustType::Vec(Box::new(u8())))
+        );
+        assert_eq!(parse("semver::Version"), string());
+        assert_eq!(
+            parse("Version"),
+            RustType::Simple {
+                id: "Version".into()
+            }
+        );
+        assert_eq!(
+            parse("Bytes"),
+            RustType::Special(SpecialRustType::Vec(Box::new(u8())))
+        );
+        assert_eq!(
+            parse("[u8; 16]"),
+            RustType::Special(SpecialRustType::Array(Box::new(u8()), 16))
+        );
+        for time in [
+            "SystemTime",
+            "DateTime<Utc>",
+            "chrono::DateTime<chrono::Utc>",
+            "time::OffsetDateTime",
+        ] {
+            assert_eq!(
+                parse(time),
+                RustType::Special(SpecialRustType::DateTime)
+            );
+        }
+        assert_eq!(
+            parse("DateTime<Local>"),
+            RustType::Generic {
+                id: "DateTime".into(),
+                parameters: vec![RustType::Simple { id: "Local".into() }],
+            }
+        );
+        assert_eq!(
+            parse("Duration"),
+            RustType::Simple {
+                id: "Duration".into()
+            }
+        );
+        assert_eq!(parse("uuid::Uuid"), RustType::Simple { id: "Uuid".into() });
+        assert_eq!(
+            parse("Result<u8, String>"),
+            RustType::Generic {
+                id: "Result".into(),
+                parameters: vec![u8(), string()],
+            }
+        );
+    }
 }
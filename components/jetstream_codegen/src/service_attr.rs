@@ -0,0 +1,93 @@
+//! The arguments of `#[service(...)]`, shared by the macro and by code
+//! generation from source files, so both read a trait the same way.
+
+use proc_macro2::TokenStream;
+
+use crate::message_ids::{IdOptions, IdScheme};
+
+mod kw {
+    syn::custom_keyword!(uses);
+    syn::custom_keyword!(tracing);
+    syn::custom_keyword!(async_trait);
+    syn::custom_keyword!(hashed_ids);
+    syn::custom_keyword!(extended_ids);
+    syn::custom_keyword!(mock);
+    syn::custom_keyword!(blocking);
+    syn::custom_keyword!(name);
+}
+
+/// Parsed service attribute arguments
+#[derive(Default)]
+pub struct ServiceAttr {
+    pub use_paths: Vec<syn::UseTree>,
+    pub enable_tracing: bool,
+    pub is_async_trait: bool,
+    pub ids: IdOptions,
+    pub mock: bool,
+    pub blocking: bool,
+    /// The protocol name given with `name = "..."`.
+    pub name: Option<syn::LitStr>,
+    /// The arguments as written, for handing the trait to a supertrait.
+    pub tokens: TokenStream,
+}
+
+impl syn::parse::Parse for ServiceAttr {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut attr = ServiceAttr::default();
+
+        while !input.is_empty() {
+            let lookahead = input.lookahead1();
+            if lookahead.peek(kw::uses) {
+                input.parse::<kw::uses>()?;
+                let content;
+                syn::parenthesized!(content in input);
+                let trees = content
+                    .parse_terminated(syn::UseTree::parse, syn::Token![,])?;
+                attr.use_paths.extend(trees);
+            } else if lookahead.peek(kw::tracing) {
+                input.parse::<kw::tracing>()?;
+                attr.enable_tracing = true;
+            } else if lookahead.peek(kw::async_trait) {
+                input.parse::<kw::async_trait>()?;
+                attr.is_async_trait = true;
+            } else if lookahead.peek(kw::hashed_ids) {
+                input.parse::<kw::hashed_ids>()?;
+                attr.ids.scheme = IdScheme::Hashed;
+            } else if lookahead.peek(kw::extended_ids) {
+                input.parse::<kw::extended_ids>()?;
+                attr.ids.extended = true;
+            } else if lookahead.peek(kw::mock) {
+                input.parse::<kw::mock>()?;
+                attr.mock = true;
+            } else if lookahead.peek(kw::blocking) {
+                input.parse::<kw::blocking>()?;
+                attr.blocking = true;
+            } else if lookahead.peek(kw::name) {
+                input.parse::<kw::name>()?;
+                input.parse::<syn::Token![=]>()?;
+                attr.name = Some(input.parse()?);
+            } else {
+                return Err(lookahead.error());
+            }
+            // Trailing comma is optional
+            if input.peek(syn::Token![,]) {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+
+        Ok(attr)
+    }
+}
+
+impl ServiceAttr {
+    /// Parses the arguments of a `#[service]` attribute found on a trait,
+    /// or the defaults if it has none or they don't parse.
+    pub fn from_attribute(attr: &syn::Attribute) -> Self {
+        match &attr.meta {
+            syn::Meta::List(list) => {
+                syn::parse2(list.tokens.clone()).unwrap_or_default()
+            }
+            _ => Self::default(),
+        }
+    }
+}
@@ -3,15 +3,23 @@
 use quote::ToTokens;
 use typeshare_core::rust_types::RustType;
 
+use crate::message_ids::{self, MethodIds};
 use crate::parser::parse_rust_type;
+use crate::service_attr::ServiceAttr;
 
 /// Parsed service definition from a `#[service]` trait.
 #[derive(Debug, Clone)]
 pub struct ServiceDef {
     pub name: String,
+    /// The name it is served under: `#[service(name = "...")]`, or else the
+    /// module it is declared in and its lowercase name, joined with `.`.
+    pub protocol_name: String,
     pub methods: Vec<MethodDef>,
     pub version: String,
     pub digest: String,
+    /// Declared with `#[service(extended_ids)]`: message ids are `u16`s in
+    /// `TEXTENDED`/`REXTENDED` frames.
+    pub extended_ids: bool,
 }
 
 /// A single RPC method in a service.
@@ -20,8 +28,13 @@ pub struct MethodDef {
     pub name: String,
     pub params: Vec<ParamDef>,
     pub return_type: Option<RustType>,
-    pub request_id: u8,
-    pub response_id: u8,
+    pub request_id: u16,
+    pub response_id: u16,
+    /// Marked `#[idempotent]`: clients may retry it on retriable failures.
+    pub idempotent: bool,
+    /// Marked `#[oneway]`: requests are sent with `NOTAG` and get no
+    /// response.
+    pub oneway: bool,
 }
 
 /// A single parameter of an RPC method.
@@ -31,18 +44,101 @@ pub struct ParamDef {
     pub ty: RustType,
 }
 
-const MESSAGE_ID_START: u8 = 102;
+/// Supertraits that are not services.
+const MARKERS: &[&str] = &[
+    "Send", "Sync", "Sized", "Unpin", "Clone", "Copy", "Debug", "Default",
+];
+
+/// The supertraits of `item` that are services.
+pub fn service_bounds(item: &syn::ItemTrait) -> syn::Result<Vec<syn::Path>> {
+    let mut paths = Vec::new();
+    for bound in &item.supertraits {
+        let syn::TypeParamBound::Trait(bound) = bound else {
+            continue;
+        };
+        let Some(last) = bound.path.segments.last() else {
+            continue;
+        };
+        if MARKERS.iter().any(|marker| last.ident == marker) {
+            continue;
+        }
+        if !last.arguments.is_empty() {
+            return Err(syn::Error::new_spanned(
+                bound,
+                "services cannot be composed of generic services",
+            ));
+        }
+        paths.push(bound.path.clone());
+    }
+    Ok(paths)
+}
 
 /// Parse a `syn::ItemTrait` into a `ServiceDef`.
 ///
 /// The trait should be annotated with `#[service]` and contain async methods.
 /// Context parameters are filtered out (they are not part of the wire protocol).
-pub fn parse_service_trait(item: &syn::ItemTrait, digest: &str) -> ServiceDef {
+/// `module_path` is the path of the module declaring it, e.g. `my_crate::a`.
+pub fn parse_service_trait(
+    item: &syn::ItemTrait,
+    module_path: &str,
+    digest: &str,
+) -> ServiceDef {
     let name = item.ident.to_string();
+    // r[impl jetstream.codegen.service.compose]
+    // A composed service carries its supertraits' methods, which are
+    // declared elsewhere and cannot be seen from here.
+    let bounds = service_bounds(item)
+        .unwrap_or_else(|err| panic!("invalid supertraits of `{name}`: {err}"));
+    if !bounds.is_empty() {
+        let bounds = bounds
+            .iter()
+            .map(|path| path.to_token_stream().to_string().replace(' ', ""))
+            .collect::<Vec<_>>()
+            .join(" + ");
+        panic!(
+            "cannot generate `{name}`: it is composed of the services \
+             `{bounds}`, which codegen does not support"
+        );
+    }
+    let service_attr = item
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("service"))
+        .map(ServiceAttr::from_attribute)
+        .unwrap_or_default();
+    let id_options = service_attr.ids;
+    // r[impl jetstream.service.name]
+    let protocol_name = match service_attr.name {
+        Some(name) => name.value(),
+        None => format!(
+            "{}.{}",
+            module_path.replace("::", "."),
+            name.to_lowercase()
+        ),
+    };
+    let method_ids = item
+        .items
+        .iter()
+        .enumerate()
+        .filter_map(|(position, trait_item)| match trait_item {
+            syn::TraitItem::Fn(method) => Some(MethodIds {
+                name: method.sig.ident.to_string(),
+                position,
+                explicit: message_ids::explicit_id(&method.attrs)
+                    .ok()
+                    .flatten(),
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // The `#[service]` macro rejects the same ids at compile time.
+    let ids = message_ids::assign(&method_ids, id_options).unwrap_or_else(
+        |(_, message)| panic!("invalid message ids in `{name}`: {message}"),
+    );
 
     let mut methods = Vec::new();
 
-    for (index, trait_item) in item.items.iter().enumerate() {
+    for trait_item in &item.items {
         if let syn::TraitItem::Fn(method) = trait_item {
             let method_name = method.sig.ident.to_string();
 
@@ -110,22 +206,32 @@ pub fn parse_service_trait(item: &syn::ItemTrait, digest: &str) -> ServiceDef {
                 syn::ReturnType::Default => None,
             };
 
-            let offset = 2 * index as u8;
+            let id = ids[methods.len()];
             methods.push(MethodDef {
                 name: method_name,
                 params,
                 return_type,
-                request_id: MESSAGE_ID_START + offset,
-                response_id: MESSAGE_ID_START + offset + 1,
+                request_id: id.request,
+                response_id: id.response,
+                idempotent: method
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("idempotent")),
+                oneway: method
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("oneway")),
             });
         }
     }
 
     ServiceDef {
         name,
+        protocol_name,
         methods,
         version: String::new(),
         digest: digest.to_string(),
+        extended_ids: id_options.extended,
     }
 }
 
@@ -139,9 +245,12 @@ fn return_type_from_syn(ty: &syn::Type) -> Option<RustType> {
     Some(parse_rust_type(ty))
 }
 
-/// Parse service traits from a whole .rs file.
+/// Parse service traits from a whole .rs file, including its inline modules.
+/// `module_path` is the path of the module the file is, e.g. `my_crate` for
+/// `src/lib.rs` or `my_crate::a` for `src/a.rs`.
 pub fn parse_services_from_file(
     source: &str,
+    module_path: &str,
     version: &str,
 ) -> Vec<ServiceDef> {
     let file = match syn::parse_file(source) {
@@ -150,24 +259,50 @@ pub fn parse_services_from_file(
     };
 
     let mut services = Vec::new();
-    for item in &file.items {
-        if let syn::Item::Trait(trait_item) = item {
-            // Check if it has a #[service] attribute
-            let has_service = trait_item
-                .attrs
-                .iter()
-                .any(|attr| attr.path().is_ident("service"));
-            if has_service {
-                let digest_prefix =
-                    &sha256::digest(trait_item.to_token_stream().to_string())
-                        [0..8];
-                let mut svc = parse_service_trait(trait_item, digest_prefix);
-                svc.version = version.to_string();
-                services.push(svc);
+    parse_services_from_items(&file.items, module_path, version, &mut services);
+    services
+}
+
+fn parse_services_from_items(
+    items: &[syn::Item],
+    module_path: &str,
+    version: &str,
+    services: &mut Vec<ServiceDef>,
+) {
+    for item in items {
+        match item {
+            syn::Item::Trait(trait_item) => {
+                // Check if it has a #[service] attribute
+                let has_service = trait_item
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("service"));
+                if has_service {
+                    let digest_prefix = &sha256::digest(
+                        trait_item.to_token_stream().to_string(),
+                    )[0..8];
+                    let mut svc = parse_service_trait(
+                        trait_item,
+                        module_path,
+                        digest_prefix,
+                    );
+                    svc.version = version.to_string();
+                    services.push(svc);
+                }
             }
+            syn::Item::Mod(syn::ItemMod {
+                ident,
+                content: Some((_, items)),
+                ..
+            }) => parse_services_from_items(
+                items,
+                &format!("{module_path}::{ident}"),
+                version,
+                services,
+            ),
+            _ => {}
         }
     }
-    services
 }
 
 #[cfg(test)]
@@ -179,25 +314,101 @@ mod tests {
         let source = r#"
             #[service]
             trait Echo {
+                #[idempotent]
                 async fn echo(&self, msg: String) -> Result<String, Error>;
                 async fn add(&self, a: u32, b: u32) -> Result<u32, Error>;
+                #[oneway]
+                async fn log(&self, line: String) -> Result<(), Error>;
             }
         "#;
-        let services = parse_services_from_file(source, "1.0.0");
+        let services = parse_services_from_file(source, "my_crate", "1.0.0");
         assert_eq!(services.len(), 1);
         let svc = &services[0];
         assert_eq!(svc.name, "Echo");
-        assert_eq!(svc.methods.len(), 2);
+        assert_eq!(svc.protocol_name, "my_crate.echo");
+        assert_eq!(svc.methods.len(), 3);
 
         assert_eq!(svc.methods[0].name, "echo");
         assert_eq!(svc.methods[0].params.len(), 1);
         assert_eq!(svc.methods[0].params[0].name, "msg");
         assert_eq!(svc.methods[0].request_id, 102);
         assert_eq!(svc.methods[0].response_id, 103);
+        assert!(svc.methods[0].idempotent);
 
         assert_eq!(svc.methods[1].name, "add");
         assert_eq!(svc.methods[1].params.len(), 2);
         assert_eq!(svc.methods[1].request_id, 104);
         assert_eq!(svc.methods[1].response_id, 105);
+        assert!(!svc.methods[1].idempotent);
+        assert!(!svc.methods[1].oneway);
+        assert!(svc.methods[2].oneway);
+    }
+
+    /// r[verify jetstream.codegen.service.compose]
+    #[test]
+    #[should_panic(expected = "cannot generate `Admin`: it is composed of \
+                               the services `Health + ops::Echo`")]
+    fn test_parse_service_refuses_composed_services() {
+        let source = r#"
+            #[service]
+            trait Admin: Health + ops::Echo + Send + Sync {
+                async fn restart(&mut self) -> Result<(), Error>;
+            }
+        "#;
+        parse_services_from_file(source, "my_crate", "1.0.0");
+    }
+
+    /// r[verify jetstream.codegen.service.compose]
+    #[test]
+    fn test_parse_service_ignores_marker_supertraits() {
+        let source = r#"
+            #[service]
+            trait Health: Send + Sync {
+                async fn status(&mut self) -> Result<String, Error>;
+            }
+        "#;
+        let services = parse_services_from_file(source, "my_crate", "1.0.0");
+        assert_eq!(services[0].methods[0].request_id, 102);
+    }
+
+    /// r[verify jetstream.service.message-ids]
+    #[test]
+    fn test_parse_service_message_ids() {
+        let source = r#"
+            #[service(extended_ids)]
+            trait Store {
+                async fn get(&self, key: String) -> Result<String, Error>;
+                #[id = 1000]
+                async fn put(&self, key: String) -> Result<(), Error>;
+            }
+        "#;
+        let services = parse_services_from_file(source, "my_crate", "1.0.0");
+        let svc = &services[0];
+        assert!(svc.extended_ids);
+        assert_eq!(svc.methods[0].request_id, 0);
+        assert_eq!(svc.methods[1].request_id, 1000);
+        assert_eq!(svc.methods[1].response_id, 1001);
+    }
+
+    /// r[verify jetstream.service.name]
+    #[test]
+    fn test_parse_service_names() {
+        let source = r#"
+            #[service(uses(crate::types::*), name = "com.acme.storage", hashed_ids)]
+            trait Storage {
+                async fn get(&self, key: String) -> Result<String, Error>;
+            }
+
+            mod v2 {
+                #[service]
+                trait Storage {
+                    async fn get(&self, key: String) -> Result<String, Error>;
+                }
+            }
+        "#;
+        let services = parse_services_from_file(source, "my_crate::a", "1.0.0");
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].protocol_name, "com.acme.storage");
+        assert_eq!(services[1].protocol_name, "my_crate.a.v2.storage");
     }
 }
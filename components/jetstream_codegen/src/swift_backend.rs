@@ -5,6 +5,8 @@ use std::fmt::Write;
 use convert_case::{Case, Casing};
 use typeshare_core::rust_types::*;
 
+use crate::parser::{field_since, is_evolvable_struct};
+
 /// Configuration for Swift code generation.
 pub struct SwiftConfig {
     /// Module name for the WireFormat import.
@@ -31,10 +33,19 @@ pub fn generate_swift_struct(s: &RustStruct, _config: &SwiftConfig) -> String {
     for field in &s.fields {
         let field_name = field.id.renamed.to_case(Case::Camel);
         let swift_type = rust_type_to_swift(&field.ty);
+        if let Some(version) = field_since(field) {
+            writeln!(out, "    /// Added in version {version}.").unwrap();
+        }
         writeln!(out, "    public var {field_name}: {swift_type}").unwrap();
     }
     writeln!(out).unwrap();
 
+    if is_evolvable_struct(s) {
+        generate_swift_evolvable_codec(&mut out, s);
+        writeln!(out, "}}").unwrap();
+        return out;
+    }
+
     // byteSize
     writeln!(out, "    public func byteSize() -> UInt32 {{").unwrap();
     if s.fields.is_empty() {
@@ -45,7 +56,7 @@ pub fn generate_swift_struct(s: &RustStruct, _config: &SwiftConfig) -> String {
             .iter()
             .map(|f| {
                 let field_name = f.id.renamed.to_case(Case::Camel);
-                format!("{field_name}.byteSize()")
+                swift_byte_size(&field_name, &f.ty)
             })
             .collect();
         writeln!(out, "        return {}", parts.join(" + ")).unwrap();
@@ -61,8 +72,8 @@ pub fn generate_swift_struct(s: &RustStruct, _config: &SwiftConfig) -> String {
     .unwrap();
     for field in &s.fields {
         let field_name = field.id.renamed.to_case(Case::Camel);
-        writeln!(out, "        try {field_name}.encode(writer: &writer)")
-            .unwrap();
+        let encode = swift_encode(&field_name, &field.ty);
+        writeln!(out, "        try {encode}").unwrap();
     }
     writeln!(out, "    }}").unwrap();
     writeln!(out).unwrap();
@@ -75,12 +86,8 @@ pub fn generate_swift_struct(s: &RustStruct, _config: &SwiftConfig) -> String {
     .unwrap();
     for field in &s.fields {
         let field_name = field.id.renamed.to_case(Case::Camel);
-        let swift_type = rust_type_to_swift(&field.ty);
-        writeln!(
-            out,
-            "        let {field_name} = try {swift_type}.decode(reader: &reader)"
-        )
-        .unwrap();
+        let decode = swift_decode(&field.ty, "reader");
+        writeln!(out, "        let {field_name} = try {decode}").unwrap();
     }
     let field_list: Vec<String> = s
         .fields
@@ -98,6 +105,158 @@ pub fn generate_swift_struct(s: &RustStruct, _config: &SwiftConfig) -> String {
     out
 }
 
+// r[impl jetstream.codegen.swift.evolvable]
+/// Codec methods for a `#[jetstream(evolvable)]` struct: the fields are
+/// prefixed with their u32 length, `default` fields missing from shorter
+/// bodies take their zero value and unknown trailing fields are skipped.
+fn generate_swift_evolvable_codec(out: &mut String, s: &RustStruct) {
+    let name = &s.id.original;
+
+    // byteSize
+    writeln!(out, "    public func byteSize() -> UInt32 {{").unwrap();
+    let parts: Vec<String> = std::iter::once("4".to_string())
+        .chain(s.fields.iter().map(|f| {
+            let field_name = f.id.renamed.to_case(Case::Camel);
+            swift_byte_size(&field_name, &f.ty)
+        }))
+        .collect();
+    writeln!(out, "        return {}", parts.join(" + ")).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+
+    // encode
+    writeln!(
+        out,
+        "    public func encode(writer: inout BinaryWriter) throws {{"
+    )
+    .unwrap();
+    writeln!(out, "        writer.writeU32(byteSize() - 4)").unwrap();
+    for field in &s.fields {
+        let field_name = field.id.renamed.to_case(Case::Camel);
+        let encode = swift_encode(&field_name, &field.ty);
+        writeln!(out, "        try {encode}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+
+    // decode
+    writeln!(
+        out,
+        "    public static func decode(reader: inout BinaryReader) throws -> {name} {{"
+    )
+    .unwrap();
+    writeln!(out, "        let size = try reader.readU32()").unwrap();
+    writeln!(
+        out,
+        "        var body = BinaryReader(data: try reader.readBytes(count: Int(size)))"
+    )
+    .unwrap();
+    for field in &s.fields {
+        let field_name = field.id.renamed.to_case(Case::Camel);
+        let swift_type = rust_type_to_swift(&field.ty);
+        let decode = swift_decode(&field.ty, "body");
+        if let Some(version) = field_since(field) {
+            writeln!(out, "        // Added in version {version}.").unwrap();
+        }
+        match swift_zero_value(&field.ty).filter(|_| field.has_default) {
+            Some(zero) => writeln!(
+                out,
+                "        let {field_name}: {swift_type} = try body.remaining > 0 ? {decode} : {zero}"
+            )
+            .unwrap(),
+            None => {
+                writeln!(out, "        let {field_name} = try {decode}").unwrap()
+            }
+        }
+    }
+    let field_list: Vec<String> = s
+        .fields
+        .iter()
+        .map(|f| {
+            let field_name = f.id.renamed.to_case(Case::Camel);
+            format!("{field_name}: {field_name}")
+        })
+        .collect();
+    writeln!(out, "        return {name}({})", field_list.join(", ")).unwrap();
+    writeln!(out, "    }}").unwrap();
+}
+
+/// The Swift value of a type's `Default`, for types that have one known
+/// without its codec.
+fn swift_zero_value(ty: &RustType) -> Option<&'static str> {
+    match ty {
+        RustType::Special(special) => match special {
+            SpecialRustType::U8
+            | SpecialRustType::U16
+            | SpecialRustType::U32
+            | SpecialRustType::U64
+            | SpecialRustType::USize
+            | SpecialRustType::I8
+            | SpecialRustType::I16
+            | SpecialRustType::I32
+            | SpecialRustType::I64
+            | SpecialRustType::ISize
+            | SpecialRustType::F32
+            | SpecialRustType::F64 => Some("0"),
+            SpecialRustType::Bool => Some("false"),
+            SpecialRustType::String => Some("\"\""),
+            SpecialRustType::Vec(_) => Some("[]"),
+            SpecialRustType::Option(_) => Some("nil"),
+            SpecialRustType::HashMap(_, _) => Some("[:]"),
+            _ => None,
+        },
+        RustType::Simple { id } if id == "LongString" => Some("LongString()"),
+        RustType::Generic { id, .. } if id == "LongVec" => Some("LongVec()"),
+        RustType::Generic { id, .. } if id == "LongMap" => Some("LongMap()"),
+        RustType::Generic { id, .. } if id == "Varint" => Some("Varint(0)"),
+        RustType::Simple { id } if id == "VarintString" => {
+            Some("VarintString()")
+        }
+        RustType::Generic { id, .. } if id == "VarintVec" => {
+            Some("VarintVec()")
+        }
+        RustType::Generic { id, .. } if id == "VarintMap" => {
+            Some("VarintMap()")
+        }
+        _ => None,
+    }
+}
+
+// r[impl jetstream.codegen.std-types]
+// Swift has no fixed-size arrays, so a `[T; N]` is a `[T]` whose codec
+// methods take the count, which isn't prefixed on the wire.
+
+/// The size of `value` on the wire.
+fn swift_byte_size(value: &str, ty: &RustType) -> String {
+    match ty {
+        RustType::Special(SpecialRustType::Array(..)) => {
+            format!("{value}.fixedByteSize()")
+        }
+        _ => format!("{value}.byteSize()"),
+    }
+}
+
+/// A throwing call encoding `value` into `writer`.
+fn swift_encode(value: &str, ty: &RustType) -> String {
+    match ty {
+        RustType::Special(SpecialRustType::Array(_, len)) => {
+            format!("{value}.encodeFixed(writer: &writer, count: {len})")
+        }
+        _ => format!("{value}.encode(writer: &writer)"),
+    }
+}
+
+/// A throwing call decoding a `ty` from `reader`.
+fn swift_decode(ty: &RustType, reader: &str) -> String {
+    match ty {
+        RustType::Special(SpecialRustType::Array(inner, len)) => format!(
+            "[{}].decodeFixed(reader: &{reader}, count: {len})",
+            rust_type_to_swift(inner)
+        ),
+        _ => format!("{}.decode(reader: &{reader})", rust_type_to_swift(ty)),
+    }
+}
+
 /// r[jetstream.codegen.swift.enum]
 /// Generate a Swift enum conforming to WireFormat from a RustEnum.
 pub fn generate_swift_enum(e: &RustEnum, _config: &SwiftConfig) -> String {
@@ -145,11 +304,12 @@ pub fn generate_swift_enum(e: &RustEnum, _config: &SwiftConfig) -> String {
                 let case_name = shared.id.original.to_case(Case::Camel);
                 writeln!(out, "        case .{case_name}: return 1").unwrap();
             }
-            RustEnumVariant::Tuple { shared, .. } => {
+            RustEnumVariant::Tuple { ty, shared } => {
                 let case_name = shared.id.original.to_case(Case::Camel);
                 writeln!(
                     out,
-                    "        case .{case_name}(let v): return 1 + v.byteSize()"
+                    "        case .{case_name}(let v): return 1 + {}",
+                    swift_byte_size("v", ty)
                 )
                 .unwrap();
             }
@@ -164,9 +324,9 @@ pub fn generate_swift_enum(e: &RustEnum, _config: &SwiftConfig) -> String {
                 let sizes: Vec<String> = fields
                     .iter()
                     .map(|f| {
-                        format!(
-                            "{}.byteSize()",
-                            f.id.renamed.to_case(Case::Camel)
+                        swift_byte_size(
+                            &f.id.renamed.to_case(Case::Camel),
+                            &f.ty,
                         )
                     })
                     .collect();
@@ -198,11 +358,11 @@ pub fn generate_swift_enum(e: &RustEnum, _config: &SwiftConfig) -> String {
                 writeln!(out, "        case .{case_name}:").unwrap();
                 writeln!(out, "            writer.writeU8({idx})").unwrap();
             }
-            RustEnumVariant::Tuple { shared, .. } => {
+            RustEnumVariant::Tuple { ty, shared } => {
                 let case_name = shared.id.original.to_case(Case::Camel);
                 writeln!(out, "        case .{case_name}(let v):").unwrap();
                 writeln!(out, "            writer.writeU8({idx})").unwrap();
-                writeln!(out, "            try v.encode(writer: &writer)")
+                writeln!(out, "            try {}", swift_encode("v", ty))
                     .unwrap();
             }
             RustEnumVariant::AnonymousStruct { fields, shared } => {
@@ -222,11 +382,8 @@ pub fn generate_swift_enum(e: &RustEnum, _config: &SwiftConfig) -> String {
                 writeln!(out, "            writer.writeU8({idx})").unwrap();
                 for f in fields {
                     let field_name = f.id.renamed.to_case(Case::Camel);
-                    writeln!(
-                        out,
-                        "            try {field_name}.encode(writer: &writer)"
-                    )
-                    .unwrap();
+                    let encode = swift_encode(&field_name, &f.ty);
+                    writeln!(out, "            try {encode}").unwrap();
                 }
             }
         }
@@ -252,10 +409,10 @@ pub fn generate_swift_enum(e: &RustEnum, _config: &SwiftConfig) -> String {
             }
             RustEnumVariant::Tuple { ty, shared } => {
                 let case_name = shared.id.original.to_case(Case::Camel);
-                let swift_type = rust_type_to_swift(ty);
+                let decode = swift_decode(ty, "reader");
                 writeln!(
                     out,
-                    "        case {idx}: return .{case_name}(try {swift_type}.decode(reader: &reader))"
+                    "        case {idx}: return .{case_name}(try {decode})"
                 )
                 .unwrap();
             }
@@ -264,10 +421,10 @@ pub fn generate_swift_enum(e: &RustEnum, _config: &SwiftConfig) -> String {
                 writeln!(out, "        case {idx}:").unwrap();
                 for f in fields {
                     let field_name = f.id.renamed.to_case(Case::Camel);
-                    let swift_type = rust_type_to_swift(&f.ty);
+                    let decode = swift_decode(&f.ty, "reader");
                     writeln!(
                         out,
-                        "            let {field_name} = try {swift_type}.decode(reader: &reader)"
+                        "            let {field_name} = try {decode}"
                     )
                     .unwrap();
                 }
@@ -340,7 +497,9 @@ pub fn rust_type_to_swift(ty: &RustType) -> String {
             SpecialRustType::F32 => "Float".into(),
             SpecialRustType::F64 => "Double".into(),
             SpecialRustType::Bool => "Bool".into(),
-            SpecialRustType::String | SpecialRustType::Char => "String".into(),
+            SpecialRustType::String => "String".into(),
+            SpecialRustType::Char => "Unicode.Scalar".into(),
+            SpecialRustType::DateTime => "Date".into(),
             SpecialRustType::Unit => "Void".into(),
             SpecialRustType::Vec(inner)
             | SpecialRustType::Array(inner, _)
@@ -359,8 +518,27 @@ pub fn rust_type_to_swift(ty: &RustType) -> String {
             }
             _ => "Any".into(),
         },
-        RustType::Simple { id } => id.clone(),
+        RustType::Simple { id } => match id.as_str() {
+            "Duration" => "WireDuration".into(),
+            "Uuid" => "UUID".into(),
+            _ => id.clone(),
+        },
+        RustType::Generic { id, parameters } if id == "IndexMap" => format!(
+            "[{}: {}]",
+            rust_type_to_swift(&parameters[0]),
+            rust_type_to_swift(&parameters[1])
+        ),
+        // Swift's `Set` is unordered; an array keeps the insertion order
+        // and encodes the same way.
+        RustType::Generic { id, parameters } if id == "IndexSet" => {
+            format!("[{}]", rust_type_to_swift(&parameters[0]))
+        }
         RustType::Generic { id, parameters } => {
+            let id = match id.as_str() {
+                "Result" => "WireResult",
+                "Range" => "WireRange",
+                id => id,
+            };
             let params: Vec<String> =
                 parameters.iter().map(rust_type_to_swift).collect();
             format!("{}<{}>", id, params.join(", "))
@@ -373,6 +551,141 @@ mod tests {
     use super::*;
     use crate::parser::parse_file;
 
+    /// r[verify jetstream.codegen.swift.evolvable]
+    #[test]
+    fn test_generate_swift_evolvable_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            #[jetstream(evolvable)]
+            struct User {
+                id: u64,
+                #[jetstream(since = 2, default)]
+                email: Option<String>,
+            }
+        "#;
+        let items = parse_file(source);
+        let swift = generate_swift_struct(
+            match &items[0] {
+                RustItem::Struct(s) => s,
+                _ => panic!("expected struct"),
+            },
+            &SwiftConfig::default(),
+        );
+        assert!(swift.contains(
+            "    /// Added in version 2.\n    public var email: String?"
+        ));
+        assert!(swift.contains("return 4 + id.byteSize() + email.byteSize()"));
+        assert!(swift.contains("writer.writeU32(byteSize() - 4)"));
+        assert!(swift.contains(
+            "var body = BinaryReader(data: try reader.readBytes(count: Int(size)))"
+        ));
+        assert!(swift.contains("let id = try UInt64.decode(reader: &body)"));
+        assert!(swift.contains(
+            "        // Added in version 2.\n        let email: String? = try body.remaining > 0 ? String?.decode(reader: &body) : nil"
+        ));
+    }
+
+    /// r[verify jetstream.codegen.long]
+    #[test]
+    fn test_generate_swift_long_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            struct Page {
+                #[jetstream(len = u32)]
+                body: String,
+                #[jetstream(len = u32)]
+                links: Vec<String>,
+                #[jetstream(len = u32)]
+                headers: BTreeMap<String, u32>,
+            }
+        "#;
+        let items = parse_file(source);
+        let swift = generate_swift_struct(
+            match &items[0] {
+                RustItem::Struct(s) => s,
+                _ => panic!("expected struct"),
+            },
+            &SwiftConfig::default(),
+        );
+        assert!(swift.contains("public var body: LongString"));
+        assert!(swift.contains("public var links: LongVec<String>"));
+        assert!(swift.contains("public var headers: LongMap<String, UInt32>"));
+        assert!(swift.contains(
+            "let links = try LongVec<String>.decode(reader: &reader)"
+        ));
+    }
+
+    /// r[verify jetstream.codegen.varint]
+    #[test]
+    fn test_generate_swift_varint_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            struct Sample {
+                #[jetstream(varint)]
+                id: u64,
+                delta: Varint<i32>,
+                #[jetstream(len = varint)]
+                values: Vec<u8>,
+            }
+        "#;
+        let items = parse_file(source);
+        let swift = generate_swift_struct(
+            match &items[0] {
+                RustItem::Struct(s) => s,
+                _ => panic!("expected struct"),
+            },
+            &SwiftConfig::default(),
+        );
+        assert!(swift.contains("public var id: Varint<UInt64>"));
+        assert!(swift.contains("public var delta: Varint<Int32>"));
+        assert!(swift.contains("public var values: VarintVec<UInt8>"));
+        assert!(swift
+            .contains("let id = try Varint<UInt64>.decode(reader: &reader)"));
+    }
+
+    /// r[verify jetstream.codegen.std-types]
+    #[test]
+    fn test_generate_swift_std_types_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            struct Job {
+                id: Uuid,
+                digest: [u8; 4],
+                grade: char,
+                timeout: Duration,
+                started: SystemTime,
+                lines: Range<u64>,
+                outcome: Result<u32, String>,
+                tags: IndexSet<String>,
+            }
+        "#;
+        let items = parse_file(source);
+        let swift = generate_swift_struct(
+            match &items[0] {
+                RustItem::Struct(s) => s,
+                _ => panic!("expected struct"),
+            },
+            &SwiftConfig::default(),
+        );
+        assert!(swift.contains("public var id: UUID"));
+        assert!(swift.contains("public var digest: [UInt8]"));
+        assert!(swift.contains("public var grade: Unicode.Scalar"));
+        assert!(swift.contains("public var timeout: WireDuration"));
+        assert!(swift.contains("public var started: Date"));
+        assert!(swift.contains("public var lines: WireRange<UInt64>"));
+        assert!(
+            swift.contains("public var outcome: WireResult<UInt32, String>")
+        );
+        assert!(swift.contains("public var tags: [String]"));
+        assert!(swift.contains("id.byteSize() + digest.fixedByteSize()"));
+        assert!(
+            swift.contains("try digest.encodeFixed(writer: &writer, count: 4)")
+        );
+        assert!(swift.contains(
+            "let digest = try [UInt8].decodeFixed(reader: &reader, count: 4)"
+        ));
+    }
+
     #[test]
     fn test_generate_swift_struct() {
         let source = r#"
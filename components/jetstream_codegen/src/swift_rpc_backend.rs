@@ -4,6 +4,7 @@ use std::fmt::Write;
 
 use convert_case::{Case, Casing};
 
+use crate::message_ids::{REXTENDED, TEXTENDED};
 use crate::service_parser::{MethodDef, ServiceDef};
 use crate::swift_backend::{rust_type_to_swift, SwiftConfig};
 
@@ -31,14 +32,29 @@ pub fn generate_swift_rpc(
     // Message ID constants
     writeln!(out, "private let MESSAGE_ID_START: UInt8 = 102").unwrap();
     writeln!(out, "private let RERROR: UInt8 = 5").unwrap();
+    let id_type = if service.extended_ids {
+        writeln!(out, "private let TEXTENDED: UInt8 = {TEXTENDED}").unwrap();
+        writeln!(out, "private let REXTENDED: UInt8 = {REXTENDED}").unwrap();
+        "UInt16"
+    } else {
+        "UInt8"
+    };
     writeln!(out).unwrap();
 
     for method in &service.methods {
         let upper = method.name.to_case(Case::UpperSnake);
-        writeln!(out, "private let T{upper}: UInt8 = {}", method.request_id)
-            .unwrap();
-        writeln!(out, "private let R{upper}: UInt8 = {}", method.response_id)
-            .unwrap();
+        writeln!(
+            out,
+            "private let T{upper}: {id_type} = {}",
+            method.request_id
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "private let R{upper}: {id_type} = {}",
+            method.response_id
+        )
+        .unwrap();
     }
     writeln!(out).unwrap();
 
@@ -49,15 +65,15 @@ pub fn generate_swift_rpc(
 
     // r[jetstream.rpc.swift.framer]
     // Tmessage enum
-    generate_swift_tmessage(&mut out, &service.methods);
+    generate_swift_tmessage(&mut out, &service.methods, service.extended_ids);
     writeln!(out).unwrap();
 
     // Rmessage enum
-    generate_swift_rmessage(&mut out, &service.methods);
+    generate_swift_rmessage(&mut out, &service.methods, service.extended_ids);
     writeln!(out).unwrap();
 
     // Protocol name and version constants
-    let name_lower = name.to_lowercase();
+    let protocol_name = &service.protocol_name;
     let version = if service.version.is_empty() {
         "0.0.0"
     } else {
@@ -66,12 +82,12 @@ pub fn generate_swift_rpc(
     let digest = &service.digest;
     writeln!(
         out,
-        "public let PROTOCOL_NAME = \"rs.jetstream.proto/{name_lower}\"",
+        "public let PROTOCOL_NAME = \"rs.jetstream.proto/{protocol_name}\"",
     )
     .unwrap();
     writeln!(
         out,
-        "public let PROTOCOL_VERSION = \"rs.jetstream.proto/{name_lower}/{version}+{digest}\"",
+        "public let PROTOCOL_VERSION = \"rs.jetstream.proto/{protocol_name}/{version}+{digest}\"",
     )
     .unwrap();
     writeln!(out).unwrap();
@@ -206,7 +222,11 @@ fn generate_swift_message_types(out: &mut String, method: &MethodDef) {
     writeln!(out).unwrap();
 }
 
-fn generate_swift_tmessage(out: &mut String, methods: &[MethodDef]) {
+fn generate_swift_tmessage(
+    out: &mut String,
+    methods: &[MethodDef],
+    extended: bool,
+) {
     writeln!(out, "public enum Tmessage: Framer {{").unwrap();
 
     // Cases
@@ -223,20 +243,26 @@ fn generate_swift_tmessage(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let case_name = method.name.to_case(Case::Camel);
         let upper = method.name.to_case(Case::UpperSnake);
-        writeln!(out, "        case .{case_name}: return T{upper}").unwrap();
+        let ty = if extended {
+            "TEXTENDED".to_string()
+        } else {
+            format!("T{upper}")
+        };
+        writeln!(out, "        case .{case_name}: return {ty}").unwrap();
     }
     writeln!(out, "        }}").unwrap();
     writeln!(out, "    }}").unwrap();
     writeln!(out).unwrap();
 
     // byteSize
+    let id_size = if extended { "2 + " } else { "" };
     writeln!(out, "    public func byteSize() -> UInt32 {{").unwrap();
     writeln!(out, "        switch self {{").unwrap();
     for method in methods {
         let case_name = method.name.to_case(Case::Camel);
         writeln!(
             out,
-            "        case .{case_name}(let msg): return msg.byteSize()"
+            "        case .{case_name}(let msg): return {id_size}msg.byteSize()"
         )
         .unwrap();
     }
@@ -254,6 +280,10 @@ fn generate_swift_tmessage(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let case_name = method.name.to_case(Case::Camel);
         writeln!(out, "        case .{case_name}(let msg):").unwrap();
+        if extended {
+            let upper = method.name.to_case(Case::UpperSnake);
+            writeln!(out, "            writer.writeU16(T{upper})").unwrap();
+        }
         writeln!(out, "            try msg.encode(writer: &writer)").unwrap();
     }
     writeln!(out, "        }}").unwrap();
@@ -267,16 +297,33 @@ fn generate_swift_tmessage(out: &mut String, methods: &[MethodDef]) {
     )
     .unwrap();
     writeln!(out, "        switch ty {{").unwrap();
+    // r[impl jetstream.service.message-ids.extended]
+    let indent = if extended {
+        writeln!(out, "        case TEXTENDED:").unwrap();
+        writeln!(out, "            let id = try reader.readU16()").unwrap();
+        writeln!(out, "            switch id {{").unwrap();
+        "    "
+    } else {
+        ""
+    };
     for method in methods {
         let upper = method.name.to_case(Case::UpperSnake);
         let case_name = method.name.to_case(Case::Camel);
         let pascal = method.name.to_case(Case::Pascal);
         writeln!(
             out,
-            "        case T{upper}: return .{case_name}(try T{pascal}.decode(reader: &reader))"
+            "{indent}        case T{upper}: return .{case_name}(try T{pascal}.decode(reader: &reader))"
         )
         .unwrap();
     }
+    if extended {
+        writeln!(
+            out,
+            "            default: throw WireFormatError.invalidMessageType(ty)"
+        )
+        .unwrap();
+        writeln!(out, "            }}").unwrap();
+    }
     writeln!(
         out,
         "        default: throw WireFormatError.invalidMessageType(ty)"
@@ -288,7 +335,11 @@ fn generate_swift_tmessage(out: &mut String, methods: &[MethodDef]) {
     writeln!(out, "}}").unwrap();
 }
 
-fn generate_swift_rmessage(out: &mut String, methods: &[MethodDef]) {
+fn generate_swift_rmessage(
+    out: &mut String,
+    methods: &[MethodDef],
+    extended: bool,
+) {
     // r[jetstream.rpc.swift.error-frame]
     writeln!(out, "public enum Rmessage: Framer {{").unwrap();
 
@@ -306,7 +357,12 @@ fn generate_swift_rmessage(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let case_name = method.name.to_case(Case::Camel);
         let upper = method.name.to_case(Case::UpperSnake);
-        writeln!(out, "        case .{case_name}: return R{upper}").unwrap();
+        let ty = if extended {
+            "REXTENDED".to_string()
+        } else {
+            format!("R{upper}")
+        };
+        writeln!(out, "        case .{case_name}: return {ty}").unwrap();
     }
     writeln!(out, "        case .error: return RERROR").unwrap();
     writeln!(out, "        }}").unwrap();
@@ -314,13 +370,14 @@ fn generate_swift_rmessage(out: &mut String, methods: &[MethodDef]) {
     writeln!(out).unwrap();
 
     // byteSize
+    let id_size = if extended { "2 + " } else { "" };
     writeln!(out, "    public func byteSize() -> UInt32 {{").unwrap();
     writeln!(out, "        switch self {{").unwrap();
     for method in methods {
         let case_name = method.name.to_case(Case::Camel);
         writeln!(
             out,
-            "        case .{case_name}(let msg): return msg.byteSize()"
+            "        case .{case_name}(let msg): return {id_size}msg.byteSize()"
         )
         .unwrap();
     }
@@ -340,6 +397,10 @@ fn generate_swift_rmessage(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let case_name = method.name.to_case(Case::Camel);
         writeln!(out, "        case .{case_name}(let msg):").unwrap();
+        if extended {
+            let upper = method.name.to_case(Case::UpperSnake);
+            writeln!(out, "            writer.writeU16(R{upper})").unwrap();
+        }
         writeln!(out, "            try msg.encode(writer: &writer)").unwrap();
     }
     writeln!(out, "        case .error(let err):").unwrap();
@@ -355,16 +416,33 @@ fn generate_swift_rmessage(out: &mut String, methods: &[MethodDef]) {
     )
     .unwrap();
     writeln!(out, "        switch ty {{").unwrap();
+    // r[impl jetstream.service.message-ids.extended]
+    let indent = if extended {
+        writeln!(out, "        case REXTENDED:").unwrap();
+        writeln!(out, "            let id = try reader.readU16()").unwrap();
+        writeln!(out, "            switch id {{").unwrap();
+        "    "
+    } else {
+        ""
+    };
     for method in methods {
         let upper = method.name.to_case(Case::UpperSnake);
         let case_name = method.name.to_case(Case::Camel);
         let pascal = method.name.to_case(Case::Pascal);
         writeln!(
             out,
-            "        case R{upper}: return .{case_name}(try R{pascal}.decode(reader: &reader))"
+            "{indent}        case R{upper}: return .{case_name}(try R{pascal}.decode(reader: &reader))"
         )
         .unwrap();
     }
+    if extended {
+        writeln!(
+            out,
+            "            default: throw WireFormatError.invalidMessageType(ty)"
+        )
+        .unwrap();
+        writeln!(out, "            }}").unwrap();
+    }
     writeln!(
         out,
         "        case RERROR: return .error(try JetStreamError.decode(reader: &reader))"
@@ -391,9 +469,16 @@ fn generate_swift_client(
 
     writeln!(out, "public class {client_name} {{").unwrap();
     writeln!(out, "    private let mux: Mux").unwrap();
+    writeln!(out, "    private let retryPolicy: RetryPolicy").unwrap();
     writeln!(out).unwrap();
-    writeln!(out, "    public init(mux: Mux) {{").unwrap();
+    // r[impl jetstream.rpc.swift.retry]
+    writeln!(
+        out,
+        "    public init(mux: Mux, retryPolicy: RetryPolicy = .default) {{"
+    )
+    .unwrap();
     writeln!(out, "        self.mux = mux").unwrap();
+    writeln!(out, "        self.retryPolicy = retryPolicy").unwrap();
     writeln!(out, "    }}").unwrap();
     writeln!(out).unwrap();
 
@@ -475,29 +560,56 @@ fn generate_swift_client(
                 format!("{name}: {name}")
             })
             .collect();
+        // r[impl jetstream.rpc.oneway.client]
+        // Oneway methods return once the request is sent.
+        if method.oneway {
+            writeln!(
+                out,
+                "        let req = Tmessage.{method_name}(T{pascal}({}))",
+                args.join(", ")
+            )
+            .unwrap();
+            writeln!(out, "        try await self.mux.oneway(req)").unwrap();
+            writeln!(out, "    }}").unwrap();
+            continue;
+        }
+        // Idempotent methods run their body inside withRetry.
+        let indent = if method.idempotent {
+            writeln!(
+                out,
+                "        return try await withRetry(retryPolicy) {{ () async throws -> {ret_type} in"
+            )
+            .unwrap();
+            "            "
+        } else {
+            "        "
+        };
         writeln!(
             out,
-            "        let req = Tmessage.{method_name}(T{pascal}({}))",
+            "{indent}let req = Tmessage.{method_name}(T{pascal}({}))",
             args.join(", ")
         )
         .unwrap();
-        writeln!(out, "        let res = try await mux.rpc(req)").unwrap();
-        writeln!(out, "        switch res {{").unwrap();
-        writeln!(out, "        case .{method_name}(let msg):").unwrap();
+        writeln!(out, "{indent}let res = try await self.mux.rpc(req)").unwrap();
+        writeln!(out, "{indent}switch res {{").unwrap();
+        writeln!(out, "{indent}case .{method_name}(let msg):").unwrap();
         if method.return_type.is_some() {
-            writeln!(out, "            return msg.value").unwrap();
+            writeln!(out, "{indent}    return msg.value").unwrap();
         } else {
-            writeln!(out, "            return").unwrap();
+            writeln!(out, "{indent}    return").unwrap();
         }
-        writeln!(out, "        case .error(let err):").unwrap();
-        writeln!(out, "            throw err").unwrap();
-        writeln!(out, "        default:").unwrap();
+        writeln!(out, "{indent}case .error(let err):").unwrap();
+        writeln!(out, "{indent}    throw err").unwrap();
+        writeln!(out, "{indent}default:").unwrap();
         writeln!(
             out,
-            "            throw WireFormatError.invalidMessageType(0)"
+            "{indent}    throw WireFormatError.invalidMessageType(0)"
         )
         .unwrap();
-        writeln!(out, "        }}").unwrap();
+        writeln!(out, "{indent}}}").unwrap();
+        if method.idempotent {
+            writeln!(out, "        }}").unwrap();
+        }
         writeln!(out, "    }}").unwrap();
     }
 
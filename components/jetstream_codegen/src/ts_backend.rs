@@ -5,6 +5,8 @@ use std::fmt::Write;
 use convert_case::{Case, Casing};
 use typeshare_core::rust_types::*;
 
+use crate::parser::{field_since, is_evolvable_struct};
+
 /// Configuration for TypeScript code generation.
 pub struct TsConfig {
     /// Import path for the wireformat module (e.g., "@sevki/jetstream-wireformat").
@@ -39,6 +41,9 @@ pub fn generate_ts_struct(s: &RustStruct, _config: &TsConfig) -> String {
     for field in &s.fields {
         let field_name = field.id.renamed.to_case(Case::Camel);
         let ts_type = rust_type_to_ts(&field.ty);
+        if let Some(version) = field_since(field) {
+            writeln!(out, "  /** @since {version} */").unwrap();
+        }
         writeln!(out, "  {field_name}: {ts_type};").unwrap();
     }
     writeln!(out, "}}").unwrap();
@@ -49,6 +54,12 @@ pub fn generate_ts_struct(s: &RustStruct, _config: &TsConfig) -> String {
     writeln!(out, "export const {codec_name}: WireFormat<{name}> = {{")
         .unwrap();
 
+    if is_evolvable_struct(s) {
+        generate_ts_evolvable_codec(&mut out, s, &codec_name);
+        writeln!(out, "}};").unwrap();
+        return out;
+    }
+
     // byteSize
     writeln!(out, "  byteSize(value: {name}): number {{").unwrap();
     if s.fields.is_empty() {
@@ -102,6 +113,134 @@ pub fn generate_ts_struct(s: &RustStruct, _config: &TsConfig) -> String {
     out
 }
 
+// r[impl jetstream.codegen.ts.evolvable]
+/// Codec methods for a `#[jetstream(evolvable)]` struct: the fields are
+/// prefixed with their u32 length, `default` fields missing from shorter
+/// bodies take their zero value and unknown trailing fields are skipped.
+fn generate_ts_evolvable_codec(
+    out: &mut String,
+    s: &RustStruct,
+    codec_name: &str,
+) {
+    let name = &s.id.original;
+
+    // byteSize
+    writeln!(out, "  byteSize(value: {name}): number {{").unwrap();
+    let parts: Vec<String> = std::iter::once("4".to_string())
+        .chain(s.fields.iter().map(|f| {
+            let field_name = f.id.renamed.to_case(Case::Camel);
+            let codec = rust_type_to_ts_codec(&f.ty);
+            format!("{codec}.byteSize(value.{field_name})")
+        }))
+        .collect();
+    writeln!(out, "    return {};", parts.join(" + ")).unwrap();
+    writeln!(out, "  }},").unwrap();
+
+    // encode
+    writeln!(
+        out,
+        "  encode(value: {name}, writer: BinaryWriter): void {{"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    writer.writeU32({codec_name}.byteSize(value) - 4);"
+    )
+    .unwrap();
+    for field in &s.fields {
+        let field_name = field.id.renamed.to_case(Case::Camel);
+        let codec = rust_type_to_ts_codec(&field.ty);
+        writeln!(out, "    {codec}.encode(value.{field_name}, writer);")
+            .unwrap();
+    }
+    writeln!(out, "  }},").unwrap();
+
+    // decode
+    writeln!(out, "  decode(reader: BinaryReader): {name} {{").unwrap();
+    writeln!(
+        out,
+        "    const body = new BinaryReader(reader.readBytes(reader.readU32()));"
+    )
+    .unwrap();
+    for field in &s.fields {
+        let field_name = field.id.renamed.to_case(Case::Camel);
+        let codec = rust_type_to_ts_codec(&field.ty);
+        if let Some(version) = field_since(field) {
+            writeln!(out, "    // Added in version {version}.").unwrap();
+        }
+        match ts_zero_value(&field.ty).filter(|_| field.has_default) {
+            Some(zero) => writeln!(
+                out,
+                "    const {field_name} = body.remaining > 0 ? {codec}.decode(body) : {zero};"
+            )
+            .unwrap(),
+            None => {
+                writeln!(out, "    const {field_name} = {codec}.decode(body);")
+                    .unwrap()
+            }
+        }
+    }
+    let field_list: Vec<String> = s
+        .fields
+        .iter()
+        .map(|f| f.id.renamed.to_case(Case::Camel))
+        .collect();
+    writeln!(out, "    return {{ {} }};", field_list.join(", ")).unwrap();
+    writeln!(out, "  }},").unwrap();
+}
+
+/// The TypeScript value of a type's `Default`, for types that have one
+/// known without its codec.
+fn ts_zero_value(ty: &RustType) -> Option<String> {
+    if let RustType::Simple { id } = ty {
+        if id == "u128" || id == "i128" {
+            return Some("0n".into());
+        }
+    }
+    match ty {
+        RustType::Special(special) => match special {
+            SpecialRustType::U8
+            | SpecialRustType::U16
+            | SpecialRustType::U32
+            | SpecialRustType::I8
+            | SpecialRustType::I16
+            | SpecialRustType::I32
+            | SpecialRustType::F32
+            | SpecialRustType::F64 => Some("0".into()),
+            SpecialRustType::U64
+            | SpecialRustType::USize
+            | SpecialRustType::I64
+            | SpecialRustType::ISize => Some("0n".into()),
+            SpecialRustType::Bool => Some("false".into()),
+            SpecialRustType::String => Some("\"\"".into()),
+            SpecialRustType::Unit => Some("undefined".into()),
+            SpecialRustType::Vec(_) => Some("[]".into()),
+            SpecialRustType::Option(_) => Some("null".into()),
+            SpecialRustType::HashMap(_, _) => Some("new Map()".into()),
+            _ => None,
+        },
+        RustType::Simple { id }
+            if id == "LongString" || id == "VarintString" =>
+        {
+            Some("\"\"".into())
+        }
+        RustType::Generic { id, .. }
+            if id == "LongVec" || id == "VarintVec" =>
+        {
+            Some("[]".into())
+        }
+        RustType::Generic { id, .. }
+            if id == "LongMap" || id == "VarintMap" =>
+        {
+            Some("new Map()".into())
+        }
+        RustType::Generic { id, parameters } if id == "Varint" => {
+            ts_zero_value(&parameters[0])
+        }
+        _ => None,
+    }
+}
+
 /// r[jetstream.codegen.ts.enum]
 /// Generate a TypeScript discriminated union type + WireFormat codec for a RustEnum.
 pub fn generate_ts_enum(e: &RustEnum, _config: &TsConfig) -> String {
@@ -331,9 +470,12 @@ pub fn generate_ts_file(items: &[RustItem], config: &TsConfig) -> String {
         config.import_path
     )
     .unwrap();
+    let mut type_imports = vec!["WireFormat"];
+    type_imports.extend(library_type_imports(needed_codecs.iter().copied()));
     writeln!(
         out,
-        "import type {{ WireFormat }} from '{}';",
+        "import type {{ {} }} from '{}';",
+        type_imports.join(", "),
         config.import_path
     )
     .unwrap();
@@ -404,6 +546,61 @@ fn collect_codecs_for_type<'a>(
             return;
         }
     }
+    // r[impl jetstream.codegen.long]
+    match ty {
+        RustType::Simple { id } if id == "LongString" => {
+            codecs.insert("longStringCodec");
+            return;
+        }
+        RustType::Generic { id, parameters } if id == "LongVec" => {
+            codecs.insert("longVecCodec");
+            parameters
+                .iter()
+                .for_each(|param| collect_codecs_for_type(param, codecs));
+            return;
+        }
+        RustType::Generic { id, parameters } if id == "LongMap" => {
+            codecs.insert("longMapCodec");
+            parameters
+                .iter()
+                .for_each(|param| collect_codecs_for_type(param, codecs));
+            return;
+        }
+        // r[impl jetstream.codegen.varint]
+        RustType::Generic { id, parameters } if id == "Varint" => {
+            if let Some(codec) = ts_varint_codec(&parameters[0]) {
+                codecs.insert(codec);
+            }
+            return;
+        }
+        RustType::Simple { id } if id == "VarintString" => {
+            codecs.insert("varintStringCodec");
+            return;
+        }
+        RustType::Generic { id, parameters } if id == "VarintVec" => {
+            codecs.insert("varintVecCodec");
+            parameters
+                .iter()
+                .for_each(|param| collect_codecs_for_type(param, codecs));
+            return;
+        }
+        RustType::Generic { id, parameters } if id == "VarintMap" => {
+            codecs.insert("varintMapCodec");
+            parameters
+                .iter()
+                .for_each(|param| collect_codecs_for_type(param, codecs));
+            return;
+        }
+        _ => {}
+    }
+    // r[impl jetstream.codegen.std-types]
+    if let Some((codec, parameters)) = ts_library_codec(ty) {
+        codecs.insert(codec);
+        parameters
+            .iter()
+            .for_each(|param| collect_codecs_for_type(param, codecs));
+        return;
+    }
     if let RustType::Special(special) = ty {
         match special {
             SpecialRustType::U8 => {
@@ -439,13 +636,23 @@ fn collect_codecs_for_type<'a>(
             SpecialRustType::Bool => {
                 codecs.insert("boolCodec");
             }
-            SpecialRustType::String | SpecialRustType::Char => {
+            SpecialRustType::String => {
                 codecs.insert("stringCodec");
             }
+            SpecialRustType::Char => {
+                codecs.insert("charCodec");
+            }
+            SpecialRustType::DateTime => {
+                codecs.insert("systemTimeCodec");
+            }
             SpecialRustType::Vec(inner) => {
                 codecs.insert("vecCodec");
                 collect_codecs_for_type(inner, codecs);
             }
+            SpecialRustType::Array(inner, _) => {
+                codecs.insert("arrayCodec");
+                collect_codecs_for_type(inner, codecs);
+            }
             SpecialRustType::Option(inner) => {
                 codecs.insert("optionCodec");
                 collect_codecs_for_type(inner, codecs);
@@ -460,6 +667,70 @@ fn collect_codecs_for_type<'a>(
     }
 }
 
+/// The codec of an integer marked `#[jetstream(varint)]` or wrapped in
+/// `Varint<T>`.
+fn ts_varint_codec(ty: &RustType) -> Option<&'static str> {
+    match ty {
+        RustType::Special(special) => match special {
+            SpecialRustType::U16 => Some("varintU16Codec"),
+            SpecialRustType::U32 => Some("varintU32Codec"),
+            SpecialRustType::U64 | SpecialRustType::USize => {
+                Some("varintU64Codec")
+            }
+            SpecialRustType::I16 => Some("varintI16Codec"),
+            SpecialRustType::I32 => Some("varintI32Codec"),
+            SpecialRustType::I64 | SpecialRustType::ISize => {
+                Some("varintI64Codec")
+            }
+            _ => None,
+        },
+        RustType::Simple { id } if id == "u128" => Some("varintU128Codec"),
+        RustType::Simple { id } if id == "i128" => Some("varintI128Codec"),
+        _ => None,
+    }
+}
+
+/// The codec the wireformat package has for a standard library or
+/// ecosystem type that isn't a `SpecialRustType`, and the type arguments it
+/// takes codecs for.
+pub(crate) fn ts_library_codec(
+    ty: &RustType,
+) -> Option<(&'static str, &[RustType])> {
+    match ty {
+        RustType::Simple { id } => match id.as_str() {
+            "Duration" => Some(("durationCodec", &[])),
+            "Uuid" => Some(("uuidCodec", &[])),
+            _ => None,
+        },
+        RustType::Generic { id, parameters } => match id.as_str() {
+            "Result" => Some(("resultCodec", parameters)),
+            "Range" => Some(("rangeCodec", parameters)),
+            "IndexMap" => Some(("indexMapCodec", parameters)),
+            "IndexSet" => Some(("indexSetCodec", parameters)),
+            _ => None,
+        },
+        RustType::Special(_) => None,
+    }
+}
+
+/// The types the wireformat package exports for the values of its codecs,
+/// which generated code imports alongside `WireFormat`.
+pub(crate) fn library_type_imports<'a>(
+    codecs: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'static str> {
+    const LIBRARY_TYPES: &[(&str, &str)] = &[
+        ("durationCodec", "Duration"),
+        ("rangeCodec", "Range"),
+        ("resultCodec", "Result"),
+    ];
+    let codecs: Vec<&str> = codecs.into_iter().collect();
+    LIBRARY_TYPES
+        .iter()
+        .filter(|(codec, _)| codecs.contains(codec))
+        .map(|(_, ty)| *ty)
+        .collect()
+}
+
 /// Map a RustType to a TypeScript type string.
 pub fn rust_type_to_ts(ty: &RustType) -> String {
     // Handle u128/i128 which typeshare_core doesn't have as SpecialRustType variants
@@ -468,6 +739,34 @@ pub fn rust_type_to_ts(ty: &RustType) -> String {
             return "bigint".into();
         }
     }
+    match ty {
+        RustType::Simple { id }
+            if id == "LongString" || id == "VarintString" || id == "Uuid" =>
+        {
+            return "string".into();
+        }
+        RustType::Generic { id, parameters } if id == "Varint" => {
+            return rust_type_to_ts(&parameters[0]);
+        }
+        RustType::Generic { id, parameters }
+            if id == "LongVec" || id == "VarintVec" =>
+        {
+            return format!("{}[]", rust_type_to_ts(&parameters[0]));
+        }
+        RustType::Generic { id, parameters }
+            if id == "LongMap" || id == "VarintMap" || id == "IndexMap" =>
+        {
+            return format!(
+                "Map<{}, {}>",
+                rust_type_to_ts(&parameters[0]),
+                rust_type_to_ts(&parameters[1])
+            );
+        }
+        RustType::Generic { id, parameters } if id == "IndexSet" => {
+            return format!("Set<{}>", rust_type_to_ts(&parameters[0]));
+        }
+        _ => {}
+    }
     match ty {
         RustType::Special(special) => match special {
             SpecialRustType::U8
@@ -482,6 +781,7 @@ pub fn rust_type_to_ts(ty: &RustType) -> String {
             SpecialRustType::Bool => "boolean".into(),
             SpecialRustType::String | SpecialRustType::Char => "string".into(),
             SpecialRustType::Unit => "undefined".into(),
+            SpecialRustType::DateTime => "Date".into(),
             SpecialRustType::Vec(inner) => {
                 format!("{}[]", rust_type_to_ts(inner))
             }
@@ -519,6 +819,54 @@ pub fn rust_type_to_ts_codec(ty: &RustType) -> String {
             return "i128Codec".into();
         }
     }
+    match ty {
+        RustType::Simple { id } if id == "LongString" => {
+            return "longStringCodec".into();
+        }
+        RustType::Generic { id, parameters } if id == "LongVec" => {
+            return format!(
+                "longVecCodec({})",
+                rust_type_to_ts_codec(&parameters[0])
+            );
+        }
+        RustType::Generic { id, parameters } if id == "LongMap" => {
+            return format!(
+                "longMapCodec({}, {}, (a, b) => a < b ? -1 : a > b ? 1 : 0)",
+                rust_type_to_ts_codec(&parameters[0]),
+                rust_type_to_ts_codec(&parameters[1])
+            );
+        }
+        RustType::Generic { id, parameters } if id == "Varint" => {
+            if let Some(codec) = ts_varint_codec(&parameters[0]) {
+                return codec.into();
+            }
+        }
+        RustType::Simple { id } if id == "VarintString" => {
+            return "varintStringCodec".into();
+        }
+        RustType::Generic { id, parameters } if id == "VarintVec" => {
+            return format!(
+                "varintVecCodec({})",
+                rust_type_to_ts_codec(&parameters[0])
+            );
+        }
+        RustType::Generic { id, parameters } if id == "VarintMap" => {
+            return format!(
+                "varintMapCodec({}, {}, (a, b) => a < b ? -1 : a > b ? 1 : 0)",
+                rust_type_to_ts_codec(&parameters[0]),
+                rust_type_to_ts_codec(&parameters[1])
+            );
+        }
+        _ => {}
+    }
+    if let Some((codec, parameters)) = ts_library_codec(ty) {
+        if parameters.is_empty() {
+            return codec.into();
+        }
+        let params: Vec<String> =
+            parameters.iter().map(rust_type_to_ts_codec).collect();
+        return format!("{codec}({})", params.join(", "));
+    }
     match ty {
         RustType::Special(special) => match special {
             SpecialRustType::U8 => "u8Codec".into(),
@@ -532,16 +880,15 @@ pub fn rust_type_to_ts_codec(ty: &RustType) -> String {
             SpecialRustType::F32 => "f32Codec".into(),
             SpecialRustType::F64 => "f64Codec".into(),
             SpecialRustType::Bool => "boolCodec".into(),
-            SpecialRustType::String | SpecialRustType::Char => {
-                "stringCodec".into()
-            }
+            SpecialRustType::String => "stringCodec".into(),
+            SpecialRustType::Char => "charCodec".into(),
+            SpecialRustType::DateTime => "systemTimeCodec".into(),
             SpecialRustType::Unit => "unitCodec".into(),
-            SpecialRustType::Vec(inner) => {
+            SpecialRustType::Vec(inner) | SpecialRustType::Slice(inner) => {
                 format!("vecCodec({})", rust_type_to_ts_codec(inner))
             }
-            SpecialRustType::Array(inner, _)
-            | SpecialRustType::Slice(inner) => {
-                format!("vecCodec({})", rust_type_to_ts_codec(inner))
+            SpecialRustType::Array(inner, len) => {
+                format!("arrayCodec({}, {len})", rust_type_to_ts_codec(inner))
             }
             SpecialRustType::Option(inner) => {
                 format!("optionCodec({})", rust_type_to_ts_codec(inner))
@@ -594,6 +941,153 @@ mod tests {
         assert!(ts.contains("u32Codec.decode"));
     }
 
+    /// r[verify jetstream.codegen.ts.evolvable]
+    #[test]
+    fn test_generate_evolvable_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            #[jetstream(evolvable)]
+            struct User {
+                id: u64,
+                #[jetstream(since = 2, default)]
+                email: Option<String>,
+                #[jetstream(since = 2, default)]
+                age: u8,
+            }
+        "#;
+        let items = parse_file(source);
+        let ts = generate_ts_file(&items, &TsConfig::default());
+        assert!(ts.contains("  /** @since 2 */\n  email: string | null;"));
+        assert!(ts.contains("return 4 + u64Codec.byteSize(value.id)"));
+        assert!(ts.contains("writer.writeU32(userCodec.byteSize(value) - 4);"));
+        assert!(ts.contains(
+            "const body = new BinaryReader(reader.readBytes(reader.readU32()));"
+        ));
+        assert!(ts.contains("const id = u64Codec.decode(body);"));
+        assert!(ts.contains(
+            "    // Added in version 2.\n    const email = body.remaining > 0 ? optionCodec(stringCodec).decode(body) : null;"
+        ));
+        assert!(ts.contains(
+            "const age = body.remaining > 0 ? u8Codec.decode(body) : 0;"
+        ));
+    }
+
+    /// r[verify jetstream.codegen.long]
+    #[test]
+    fn test_generate_long_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            struct Page {
+                #[jetstream(len = u32)]
+                body: String,
+                #[jetstream(len = u32)]
+                links: Vec<String>,
+                #[jetstream(len = u32)]
+                headers: BTreeMap<String, u32>,
+                tags: LongVec<String>,
+            }
+        "#;
+        let items = parse_file(source);
+        let ts = generate_ts_file(&items, &TsConfig::default());
+        assert!(ts.contains("longMapCodec, longStringCodec, longVecCodec"));
+        assert!(ts.contains("  body: string;"));
+        assert!(ts.contains("  links: string[];"));
+        assert!(ts.contains("  headers: Map<string, number>;"));
+        assert!(ts.contains("  tags: string[];"));
+        assert!(ts.contains("longStringCodec.encode(value.body, writer);"));
+        assert!(ts.contains(
+            "longVecCodec(stringCodec).encode(value.links, writer);"
+        ));
+        assert!(ts.contains("longMapCodec(stringCodec, u32Codec, "));
+        assert!(ts.contains(
+            "const tags = longVecCodec(stringCodec).decode(reader);"
+        ));
+    }
+
+    /// r[verify jetstream.codegen.varint]
+    #[test]
+    fn test_generate_varint_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            struct Sample {
+                #[jetstream(varint)]
+                id: u64,
+                delta: Varint<i32>,
+                #[jetstream(len = varint)]
+                name: String,
+                #[jetstream(len = varint)]
+                values: Vec<u8>,
+            }
+        "#;
+        let items = parse_file(source);
+        let ts = generate_ts_file(&items, &TsConfig::default());
+        assert!(ts.contains("  id: bigint;"));
+        assert!(ts.contains("  delta: number;"));
+        assert!(ts.contains("  name: string;"));
+        assert!(ts.contains("  values: number[];"));
+        assert!(ts.contains("varintU64Codec.encode(value.id, writer);"));
+        assert!(ts.contains("varintI32Codec.encode(value.delta, writer);"));
+        assert!(ts.contains("varintStringCodec.encode(value.name, writer);"));
+        assert!(ts.contains(
+            "const values = varintVecCodec(u8Codec).decode(reader);"
+        ));
+        assert!(ts.contains(
+            "u8Codec, varintI32Codec, varintStringCodec, varintU64Codec, varintVecCodec"
+        ));
+    }
+
+    /// r[verify jetstream.codegen.std-types]
+    #[test]
+    fn test_generate_std_types_struct() {
+        let source = r#"
+            #[derive(JetStreamWireFormat)]
+            struct Job {
+                id: Uuid,
+                digest: [u8; 4],
+                grade: char,
+                timeout: Duration,
+                started: SystemTime,
+                lines: Range<u64>,
+                outcome: Result<u32, String>,
+                artifacts: IndexMap<String, u64>,
+                tags: IndexSet<String>,
+            }
+        "#;
+        let items = parse_file(source);
+        let ts = generate_ts_file(&items, &TsConfig::default());
+        assert!(ts.contains("  id: string;"));
+        assert!(ts.contains("  digest: number[];"));
+        assert!(ts.contains("  grade: string;"));
+        assert!(ts.contains("  timeout: Duration;"));
+        assert!(ts.contains("  started: Date;"));
+        assert!(ts.contains("  lines: Range<bigint>;"));
+        assert!(ts.contains("  outcome: Result<number, string>;"));
+        assert!(ts.contains("  artifacts: Map<string, bigint>;"));
+        assert!(ts.contains("  tags: Set<string>;"));
+        assert!(ts.contains("uuidCodec.encode(value.id, writer);"));
+        assert!(
+            ts.contains("arrayCodec(u8Codec, 4).encode(value.digest, writer);")
+        );
+        assert!(ts.contains("charCodec.encode(value.grade, writer);"));
+        assert!(ts.contains("durationCodec.encode(value.timeout, writer);"));
+        assert!(ts.contains("systemTimeCodec.encode(value.started, writer);"));
+        assert!(
+            ts.contains("rangeCodec(u64Codec).encode(value.lines, writer);")
+        );
+        assert!(ts.contains(
+            "resultCodec(u32Codec, stringCodec).encode(value.outcome, writer);"
+        ));
+        assert!(ts.contains(
+            "indexMapCodec(stringCodec, u64Codec).encode(value.artifacts, writer);"
+        ));
+        assert!(ts.contains(
+            "indexSetCodec(stringCodec).encode(value.tags, writer);"
+        ));
+        assert!(ts.contains(
+            "import type { WireFormat, Duration, Range, Result } from"
+        ));
+    }
+
     #[test]
     fn test_generate_u128_struct() {
         let source = r#"
@@ -6,8 +6,12 @@ use std::fmt::Write;
 use convert_case::{Case, Casing};
 use typeshare_core::rust_types::{RustType, SpecialRustType};
 
+use crate::message_ids::{REXTENDED, TEXTENDED};
 use crate::service_parser::{MethodDef, ServiceDef};
-use crate::ts_backend::{rust_type_to_ts, rust_type_to_ts_codec, TsConfig};
+use crate::ts_backend::{
+    library_type_imports, rust_type_to_ts, rust_type_to_ts_codec,
+    ts_library_codec, TsConfig,
+};
 
 /// Collect all custom (non-primitive) type names referenced by a service's methods.
 /// These are `RustType::Simple` or `RustType::Generic` types that live in the
@@ -30,6 +34,12 @@ fn collect_custom_types(service: &ServiceDef) -> BTreeSet<String> {
 const PRIMITIVE_SCALARS: &[&str] = &["u128", "i128"];
 
 fn collect_custom_type_names(ty: &RustType, types: &mut BTreeSet<String>) {
+    if let Some((_, parameters)) = ts_library_codec(ty) {
+        for p in parameters {
+            collect_custom_type_names(p, types);
+        }
+        return;
+    }
     match ty {
         RustType::Simple { id } => {
             if !PRIMITIVE_SCALARS.contains(&id.as_str()) {
@@ -84,6 +94,13 @@ fn collect_codec_names(ty: &RustType, codecs: &mut BTreeSet<String>) {
             return;
         }
     }
+    if let Some((codec, parameters)) = ts_library_codec(ty) {
+        codecs.insert(codec.into());
+        for p in parameters {
+            collect_codec_names(p, codecs);
+        }
+        return;
+    }
     if let RustType::Special(special) = ty {
         match special {
             SpecialRustType::U8 | SpecialRustType::I8 => {
@@ -116,9 +133,15 @@ fn collect_codec_names(ty: &RustType, codecs: &mut BTreeSet<String>) {
             SpecialRustType::Bool => {
                 codecs.insert("boolCodec".into());
             }
-            SpecialRustType::String | SpecialRustType::Char => {
+            SpecialRustType::String => {
                 codecs.insert("stringCodec".into());
             }
+            SpecialRustType::Char => {
+                codecs.insert("charCodec".into());
+            }
+            SpecialRustType::DateTime => {
+                codecs.insert("systemTimeCodec".into());
+            }
             SpecialRustType::Unit => {
                 codecs.insert("unitCodec".into());
             }
@@ -127,7 +150,7 @@ fn collect_codec_names(ty: &RustType, codecs: &mut BTreeSet<String>) {
                 collect_codec_names(inner, codecs);
             }
             SpecialRustType::Array(inner, _) => {
-                codecs.insert("vecCodec".into());
+                codecs.insert("arrayCodec".into());
                 collect_codec_names(inner, codecs);
             }
             SpecialRustType::Option(inner) => {
@@ -178,21 +201,25 @@ pub fn generate_ts_rpc(
         config.import_path
     )
     .unwrap();
+    let mut type_imports = vec!["WireFormat"];
+    type_imports
+        .extend(library_type_imports(codecs.iter().map(|s| s.as_str())));
     writeln!(
         out,
-        "import type {{ WireFormat }} from '{}';",
+        "import type {{ {} }} from '{}';",
+        type_imports.join(", "),
         config.import_path
     )
     .unwrap();
     writeln!(
         out,
-        "import {{ Mux, negotiateVersion }} from '{}';",
+        "import {{ DEFAULT_RETRY_POLICY, Mux, negotiateVersion, withRetry }} from '{}';",
         config.rpc_import_path
     )
     .unwrap();
     writeln!(
         out,
-        "import type {{ Framer, FramerCodec, Context, NegotiatedVersion }} from '{}';",
+        "import type {{ Framer, FramerCodec, Context, NegotiatedVersion, RetryPolicy }} from '{}';",
         config.rpc_import_path
     )
     .unwrap();
@@ -231,6 +258,10 @@ pub fn generate_ts_rpc(
     // Message ID constants
     writeln!(out, "const MESSAGE_ID_START = 102;").unwrap();
     writeln!(out, "const RERROR = 5;").unwrap();
+    if service.extended_ids {
+        writeln!(out, "const TEXTENDED = {TEXTENDED};").unwrap();
+        writeln!(out, "const REXTENDED = {REXTENDED};").unwrap();
+    }
     writeln!(out).unwrap();
 
     for method in &service.methods {
@@ -257,9 +288,17 @@ pub fn generate_ts_rpc(
 
     // r[jetstream.rpc.ts.framer]
     // FramerCodec objects (used by ServerCodec / handler side)
-    generate_ts_tmessage_framer(&mut out, &service.methods);
+    generate_ts_tmessage_framer(
+        &mut out,
+        &service.methods,
+        service.extended_ids,
+    );
     writeln!(out).unwrap();
-    generate_ts_rmessage_framer(&mut out, &service.methods);
+    generate_ts_rmessage_framer(
+        &mut out,
+        &service.methods,
+        service.extended_ids,
+    );
     writeln!(out).unwrap();
 
     // Framer wrapper classes (implement Framer interface for use with Mux)
@@ -270,7 +309,7 @@ pub fn generate_ts_rpc(
 
     // PROTOCOL_VERSION constant — matches the Rust macro format:
     // rs.jetstream.proto/{name}/{major}.{minor}.{patch}-{digest_prefix}
-    let name_lower = name.to_lowercase();
+    let protocol_name = &service.protocol_name;
     let version = if service.version.is_empty() {
         "0.0.0"
     } else {
@@ -279,12 +318,12 @@ pub fn generate_ts_rpc(
     let digest = &service.digest;
     writeln!(
         out,
-        "export const PROTOCOL_NAME = 'rs.jetstream.proto/{name_lower}';",
+        "export const PROTOCOL_NAME = 'rs.jetstream.proto/{protocol_name}';",
     )
     .unwrap();
     writeln!(
         out,
-        "export const PROTOCOL_VERSION = 'rs.jetstream.proto/{name_lower}/{version}+{digest}';",
+        "export const PROTOCOL_VERSION = 'rs.jetstream.proto/{protocol_name}/{version}+{digest}';",
     )
     .unwrap();
     writeln!(out).unwrap();
@@ -440,7 +479,11 @@ fn generate_ts_rmessage(out: &mut String, methods: &[MethodDef]) {
     writeln!(out, "  | {{ type: 'Error'; msg: JetStreamError }};").unwrap();
 }
 
-fn generate_ts_tmessage_framer(out: &mut String, methods: &[MethodDef]) {
+fn generate_ts_tmessage_framer(
+    out: &mut String,
+    methods: &[MethodDef],
+    extended: bool,
+) {
     writeln!(
         out,
         "export const tmessageFramer: FramerCodec<Tmessage> = {{"
@@ -453,12 +496,18 @@ fn generate_ts_tmessage_framer(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let pascal = method.name.to_case(Case::Pascal);
         let upper = method.name.to_case(Case::UpperSnake);
-        writeln!(out, "      case '{pascal}': return T{upper};").unwrap();
+        let ty = if extended {
+            "TEXTENDED".to_string()
+        } else {
+            format!("T{upper}")
+        };
+        writeln!(out, "      case '{pascal}': return {ty};").unwrap();
     }
     writeln!(out, "    }}").unwrap();
     writeln!(out, "  }},").unwrap();
 
     // byteSize
+    let id_size = if extended { "2 + " } else { "" };
     writeln!(out, "  byteSize(msg: Tmessage): number {{").unwrap();
     writeln!(out, "    switch (msg.type) {{").unwrap();
     for method in methods {
@@ -466,7 +515,7 @@ fn generate_ts_tmessage_framer(out: &mut String, methods: &[MethodDef]) {
         let camel = method.name.to_case(Case::Camel);
         writeln!(
             out,
-            "      case '{pascal}': return t{camel}Codec.byteSize(msg.msg);"
+            "      case '{pascal}': return {id_size}t{camel}Codec.byteSize(msg.msg);"
         )
         .unwrap();
     }
@@ -483,9 +532,15 @@ fn generate_ts_tmessage_framer(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let pascal = method.name.to_case(Case::Pascal);
         let camel = method.name.to_case(Case::Camel);
+        let upper = method.name.to_case(Case::UpperSnake);
+        let id_write = if extended {
+            format!("writer.writeU16(T{upper}); ")
+        } else {
+            String::new()
+        };
         writeln!(
             out,
-            "      case '{pascal}': t{camel}Codec.encode(msg.msg, writer); break;"
+            "      case '{pascal}': {id_write}t{camel}Codec.encode(msg.msg, writer); break;"
         )
         .unwrap();
     }
@@ -499,16 +554,34 @@ fn generate_ts_tmessage_framer(out: &mut String, methods: &[MethodDef]) {
     )
     .unwrap();
     writeln!(out, "    switch (type) {{").unwrap();
+    // r[impl jetstream.service.message-ids.extended]
+    let indent = if extended {
+        writeln!(out, "      case TEXTENDED: {{").unwrap();
+        writeln!(out, "        const id = reader.readU16();").unwrap();
+        writeln!(out, "        switch (id) {{").unwrap();
+        "    "
+    } else {
+        ""
+    };
     for method in methods {
         let pascal = method.name.to_case(Case::Pascal);
         let upper = method.name.to_case(Case::UpperSnake);
         let camel = method.name.to_case(Case::Camel);
         writeln!(
             out,
-            "      case T{upper}: return {{ type: '{pascal}', msg: t{camel}Codec.decode(reader) }};"
+            "{indent}      case T{upper}: return {{ type: '{pascal}', msg: t{camel}Codec.decode(reader) }};"
         )
         .unwrap();
     }
+    if extended {
+        writeln!(
+            out,
+            "          default: throw new Error(`unknown Tmessage id: ${{id}}`);"
+        )
+        .unwrap();
+        writeln!(out, "        }}").unwrap();
+        writeln!(out, "      }}").unwrap();
+    }
     writeln!(
         out,
         "      default: throw new Error(`unknown Tmessage type: ${{type}}`);"
@@ -520,7 +593,11 @@ fn generate_ts_tmessage_framer(out: &mut String, methods: &[MethodDef]) {
     writeln!(out, "}};").unwrap();
 }
 
-fn generate_ts_rmessage_framer(out: &mut String, methods: &[MethodDef]) {
+fn generate_ts_rmessage_framer(
+    out: &mut String,
+    methods: &[MethodDef],
+    extended: bool,
+) {
     writeln!(
         out,
         "export const rmessageFramer: FramerCodec<Rmessage> = {{"
@@ -533,13 +610,19 @@ fn generate_ts_rmessage_framer(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let pascal = method.name.to_case(Case::Pascal);
         let upper = method.name.to_case(Case::UpperSnake);
-        writeln!(out, "      case '{pascal}': return R{upper};").unwrap();
+        let ty = if extended {
+            "REXTENDED".to_string()
+        } else {
+            format!("R{upper}")
+        };
+        writeln!(out, "      case '{pascal}': return {ty};").unwrap();
     }
     writeln!(out, "      case 'Error': return RERROR;").unwrap();
     writeln!(out, "    }}").unwrap();
     writeln!(out, "  }},").unwrap();
 
     // byteSize
+    let id_size = if extended { "2 + " } else { "" };
     writeln!(out, "  byteSize(msg: Rmessage): number {{").unwrap();
     writeln!(out, "    switch (msg.type) {{").unwrap();
     for method in methods {
@@ -547,7 +630,7 @@ fn generate_ts_rmessage_framer(out: &mut String, methods: &[MethodDef]) {
         let camel = method.name.to_case(Case::Camel);
         writeln!(
             out,
-            "      case '{pascal}': return r{camel}Codec.byteSize(msg.msg);"
+            "      case '{pascal}': return {id_size}r{camel}Codec.byteSize(msg.msg);"
         )
         .unwrap();
     }
@@ -569,9 +652,15 @@ fn generate_ts_rmessage_framer(out: &mut String, methods: &[MethodDef]) {
     for method in methods {
         let pascal = method.name.to_case(Case::Pascal);
         let camel = method.name.to_case(Case::Camel);
+        let upper = method.name.to_case(Case::UpperSnake);
+        let id_write = if extended {
+            format!("writer.writeU16(R{upper}); ")
+        } else {
+            String::new()
+        };
         writeln!(
             out,
-            "      case '{pascal}': r{camel}Codec.encode(msg.msg, writer); break;"
+            "      case '{pascal}': {id_write}r{camel}Codec.encode(msg.msg, writer); break;"
         )
         .unwrap();
     }
@@ -591,15 +680,33 @@ fn generate_ts_rmessage_framer(out: &mut String, methods: &[MethodDef]) {
     )
     .unwrap();
     writeln!(out, "    switch (type) {{").unwrap();
+    // r[impl jetstream.service.message-ids.extended]
+    let indent = if extended {
+        writeln!(out, "      case REXTENDED: {{").unwrap();
+        writeln!(out, "        const id = reader.readU16();").unwrap();
+        writeln!(out, "        switch (id) {{").unwrap();
+        "    "
+    } else {
+        ""
+    };
     for method in methods {
         let pascal = method.name.to_case(Case::Pascal);
         let upper = method.name.to_case(Case::UpperSnake);
         let camel = method.name.to_case(Case::Camel);
         writeln!(
             out,
-            "      case R{upper}: return {{ type: '{pascal}', msg: r{camel}Codec.decode(reader) }};"
+            "{indent}      case R{upper}: return {{ type: '{pascal}', msg: r{camel}Codec.decode(reader) }};"
+        )
+        .unwrap();
+    }
+    if extended {
+        writeln!(
+            out,
+            "          default: throw new Error(`unknown Rmessage id: ${{id}}`);"
         )
         .unwrap();
+        writeln!(out, "        }}").unwrap();
+        writeln!(out, "      }}").unwrap();
     }
     writeln!(
         out,
@@ -669,13 +776,16 @@ fn generate_ts_client(
     writeln!(out, "export class {client_name} {{").unwrap();
     writeln!(out, "  private mux: Mux<TmessageFramer, RmessageFramer>;")
         .unwrap();
+    writeln!(out, "  private retryPolicy: RetryPolicy;").unwrap();
     writeln!(out).unwrap();
-    writeln!(
-        out,
-        "  constructor(mux: Mux<TmessageFramer, RmessageFramer>) {{"
-    )
-    .unwrap();
+    // r[impl jetstream.rpc.ts.retry]
+    writeln!(out, "  constructor(").unwrap();
+    writeln!(out, "    mux: Mux<TmessageFramer, RmessageFramer>,").unwrap();
+    writeln!(out, "    retryPolicy: RetryPolicy = DEFAULT_RETRY_POLICY,")
+        .unwrap();
+    writeln!(out, "  ) {{").unwrap();
     writeln!(out, "    this.mux = mux;").unwrap();
+    writeln!(out, "    this.retryPolicy = retryPolicy;").unwrap();
     writeln!(out, "  }}").unwrap();
     writeln!(out).unwrap();
 
@@ -722,25 +832,54 @@ fn generate_ts_client(
             .iter()
             .map(|p| p.name.to_case(Case::Camel))
             .collect();
+        // r[impl jetstream.rpc.oneway.client]
+        // Oneway methods resolve once the request is sent.
+        if method.oneway {
+            writeln!(
+                out,
+                "    const req = new TmessageFramer({{ type: '{pascal}', msg: {{ {} }} }});",
+                args.join(", ")
+            )
+            .unwrap();
+            writeln!(out, "    await this.mux.oneway(req);").unwrap();
+            writeln!(out, "  }}").unwrap();
+            continue;
+        }
+        // Idempotent methods run their body inside withRetry.
+        let indent = if method.idempotent {
+            writeln!(
+                out,
+                "    return withRetry(this.retryPolicy, async () => {{"
+            )
+            .unwrap();
+            "      "
+        } else {
+            "    "
+        };
         writeln!(
             out,
-            "    const req = new TmessageFramer({{ type: '{pascal}', msg: {{ {} }} }});",
+            "{indent}const req = new TmessageFramer({{ type: '{pascal}', msg: {{ {} }} }});",
             args.join(", ")
         )
         .unwrap();
-        writeln!(out, "    const res = await this.mux.rpc(req);").unwrap();
-        writeln!(out, "    if (res.msg.inner.type === 'Error') {{").unwrap();
-        writeln!(out, "      throw res.msg.inner.msg;").unwrap();
-        writeln!(out, "    }}").unwrap();
-        writeln!(out, "    if (res.msg.inner.type !== '{pascal}') {{").unwrap();
+        writeln!(out, "{indent}const res = await this.mux.rpc(req);").unwrap();
+        writeln!(out, "{indent}if (res.msg.inner.type === 'Error') {{")
+            .unwrap();
+        writeln!(out, "{indent}  throw res.msg.inner.msg;").unwrap();
+        writeln!(out, "{indent}}}").unwrap();
+        writeln!(out, "{indent}if (res.msg.inner.type !== '{pascal}') {{")
+            .unwrap();
         writeln!(
             out,
-            "      throw new Error(`unexpected response type: ${{res.msg.inner.type}}`);"
+            "{indent}  throw new Error(`unexpected response type: ${{res.msg.inner.type}}`);"
         )
         .unwrap();
-        writeln!(out, "    }}").unwrap();
+        writeln!(out, "{indent}}}").unwrap();
         if method.return_type.is_some() {
-            writeln!(out, "    return res.msg.inner.msg.value;").unwrap();
+            writeln!(out, "{indent}return res.msg.inner.msg.value;").unwrap();
+        }
+        if method.idempotent {
+            writeln!(out, "    }});").unwrap();
         }
         writeln!(out, "  }}").unwrap();
     }
@@ -6,7 +6,11 @@ use h3::quic;
 use h3_webtransport::server::{AcceptedBi, WebTransportSession};
 use http::{Method, Request};
 use jetstream_quic::QuicHandler;
-use jetstream_rpc::Router as RpcRouter;
+use jetstream_rpc::{
+    context::StreamId,
+    push::{OpenUni, SendHalf, Upstream},
+    Router as RpcRouter,
+};
 use quinn::Connection;
 use rustls::pki_types::CertificateDer;
 use rustls::server::danger::ClientCertVerifier;
@@ -100,6 +104,7 @@ impl H3Service {
     {
         info!(?req, "Received request");
 
+        let ctx = ctx.with_extension(StreamId(stream.id().into_inner()));
         let (mut send_stream, recv_stream) = stream.split();
         let request_stream = RequestStream(recv_stream);
         let body = axum::body::Body::from_stream(request_stream);
@@ -282,8 +287,11 @@ impl QuicHandler for H3Service {
                             Some(jetstream_rpc::context::RemoteAddr::IpAddr(
                                 remote.ip(),
                             ));
-                        let ctx =
+                        let mut session_ctx =
                             jetstream_rpc::context::Context::new(remote, peer);
+                        *session_ctx.extensions_mut() =
+                            ctx.extensions().clone();
+                        let ctx = session_ctx;
                         info!("WebTransport context peer: {:?}", ctx.peer());
 
                         // r[impl jetstream.webtransport.session]
@@ -348,19 +356,40 @@ impl QuicHandler for H3Service {
     }
 }
 
+/// Opens unidirectional streams back to the client of a session.
+struct SessionUpstream(Arc<WebTransportSession<h3_quinn::Connection, Bytes>>);
+
+#[async_trait]
+impl OpenUni for SessionUpstream {
+    async fn open_uni(&self) -> std::io::Result<SendHalf> {
+        let send = self
+            .0
+            .open_uni(self.0.session_id())
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(Box::new(send))
+    }
+}
+
 async fn handle_session(
     rpc_router: Arc<RpcRouter>,
     session: WebTransportSession<h3_quinn::Connection, Bytes>,
     ctx: jetstream_rpc::context::Context,
 ) -> jetstream_error::Result<()> {
     let handler = rpc_router.clone();
+    let session = Arc::new(session);
+    // r[impl jetstream.push.upstream]
+    let ctx =
+        ctx.with_extension(Upstream::new(SessionUpstream(session.clone())));
     tokio::spawn(async move {
         loop {
             match session.accept_bi().await {
                 Ok(Some(AcceptedBi::BidiStream(_, stream))) => {
                     let (send, recv) = quic::BidiStream::split(stream);
                     let handler = handler.clone();
-                    let ctx = ctx.clone();
+                    let ctx = ctx.clone().with_extension(StreamId(
+                        quic::RecvStream::recv_id(&recv).into_inner(),
+                    ));
 
                     tokio::spawn(async move {
                         handler
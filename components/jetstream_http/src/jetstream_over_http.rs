@@ -4,25 +4,44 @@ use axum::{body::Body, response::Response};
 use axum::{routing::get, Router};
 use http::header::CONTENT_LENGTH;
 use jetstream_rpc::{
-    context::Context, server::Server, ErrorFrame, Frame, Framer, IntoError,
+    auth::Authorizer, context::Context, server::Server, ErrorFrame, Frame,
+    Framer, IntoError,
 };
 use jetstream_wireformat::WireFormat;
-use std::{convert::Infallible, io::Cursor};
+use std::{convert::Infallible, io::Cursor, sync::Arc};
 use tower_service::Service;
 
 /// Wrap a `Server` implementation into a `tower_service::Service`
 #[derive(Clone)]
-pub struct ProtocolService<S: Server + Clone>(S);
+pub struct ProtocolService<S: Server + Clone> {
+    server: S,
+    authorizer: Option<Arc<dyn Authorizer>>,
+}
 
 impl<S: Server + Clone> From<S> for ProtocolService<S> {
     fn from(server: S) -> Self {
-        ProtocolService(server)
+        ProtocolService::new(server)
     }
 }
 
 impl<S: Server + Clone> ProtocolService<S> {
     pub fn new(server: S) -> Self {
-        ProtocolService(server)
+        ProtocolService {
+            server,
+            authorizer: None,
+        }
+    }
+
+    // r[impl jetstream.auth.router]
+    /// Attach an authorizer to the context of every request, as
+    /// [`Router::with_authorizer`](jetstream_rpc::Router::with_authorizer)
+    /// does.
+    pub fn with_authorizer(
+        mut self,
+        authorizer: impl Authorizer + 'static,
+    ) -> Self {
+        self.authorizer = Some(Arc::new(authorizer));
+        self
     }
 }
 
@@ -53,7 +72,13 @@ where
     }
 
     fn call(&mut self, req: axum::http::Request<Body>) -> Self::Future {
-        let mut service = self.0.clone();
+        let mut service = self.server.clone();
+        let ctx = match &self.authorizer {
+            Some(authorizer) => {
+                Context::default().with_shared_authorizer(authorizer.clone())
+            }
+            None => Context::default(),
+        };
         let (parts, body) = req.into_parts();
         let request_size = parts
             .headers
@@ -86,7 +111,7 @@ where
                         ));
                     }
                 };
-            let resp = service.rpc(Context::default(), frame).await;
+            let resp = service.rpc(ctx, frame).await;
             match resp {
                 Ok(frame) => Ok(frame_to_response(frame)),
                 Err(err) => Ok(error_to_response(err.into_error())),
@@ -20,8 +20,10 @@ pub struct IrohTransport<P: Protocol> {
 impl<P: Protocol> From<(SendStream, RecvStream)> for IrohTransport<P> {
     fn from(value: (SendStream, RecvStream)) -> Self {
         let (send_stream, recv_stream) = value;
-        let send_stream = FramedWrite::new(send_stream, ClientCodec::default());
-        let recv_stream = FramedRead::new(recv_stream, ClientCodec::default());
+        // Both halves share the compression negotiated on this stream.
+        let codec = ClientCodec::default();
+        let send_stream = FramedWrite::new(send_stream, codec.clone());
+        let recv_stream = FramedRead::new(recv_stream, codec);
         Self {
             send_stream,
             recv_stream,
@@ -42,7 +42,7 @@ pub fn endpoint_builder<P: Protocol>() -> iroh::endpoint::Builder {
         .relay_mode(iroh::RelayMode::Custom(RelayMap::from_iter([
             RelayConfig::new(RELAY_URL.clone(), None),
         ])))
-        .alpns(vec![P::NAME.as_bytes().to_vec()])
+        .alpns(vec![P::protocol_name().as_bytes().to_vec()])
         .address_lookup(jetstream_publisher_builder())
         .address_lookup(jetstream_resolver())
 }
@@ -52,7 +52,7 @@ pub async fn client_builder<P: Protocol>(
 ) -> Result<client::IrohTransport<P>, Box<dyn std::error::Error + 'static>> {
     let endpoint = endpoint_builder::<P>().bind().await.map_err(Box::new)?;
     let conn = endpoint
-        .connect(addr, P::NAME.as_bytes())
+        .connect(addr, P::protocol_name().as_bytes())
         .await
         .map_err(Box::new)?;
     let streams = conn.open_bi().await?;
@@ -65,7 +65,7 @@ pub async fn server_builder<P: Server + Protocol + Debug + Clone + 'static>(
     let endpoint = endpoint_builder::<P>().bind().await.map_err(Box::new)?;
 
     let router = Router::builder(endpoint)
-        .accept(P::NAME.as_bytes(), IrohServer::new(inner))
+        .accept(P::protocol_name().as_bytes(), IrohServer::new(inner))
         .spawn();
     Ok(router)
 }
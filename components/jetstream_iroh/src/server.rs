@@ -1,27 +1,62 @@
 use std::fmt::Debug;
+use std::marker::PhantomData;
 use std::sync::Arc;
 
-use futures::{SinkExt, StreamExt};
 use iroh::{endpoint::Connection, protocol::ProtocolHandler};
 use jetstream_rpc::{
+    auth::Authorizer,
     context::{Context, NodeId},
-    server::{Server, ServerCodec},
-    Frame, IntoError, Protocol, Router as RpcRouter,
+    push::Upstream,
+    server::Server,
+    Protocol, Router as RpcRouter,
 };
 use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::sync::mpsc;
-use tokio_util::codec::{FramedRead, FramedWrite};
 
-#[derive(Debug)]
+/// An Iroh protocol handler serving one protocol.
+///
+/// Every stream negotiates its version through an [`RpcRouter`], so the
+/// server sees the negotiated version, compression and schema mismatch in
+/// its [`Context`].
 pub struct IrohServer<P: Protocol + Server + Debug + Clone + 'static> {
-    inner: P,
+    router: IrohRouter,
+    _protocol: PhantomData<P>,
+}
+
+impl<P: Protocol + Server + Debug + Clone + 'static> Debug for IrohServer<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IrohServer")
+            .field("router", &self.router.router)
+            .finish()
+    }
 }
 
 impl<P: Protocol + Server + Debug + Clone + Send + Sync + 'static>
     IrohServer<P>
 {
     pub fn new(protocol: P) -> Self {
-        IrohServer { inner: protocol }
+        Self::from_router(
+            RpcRouter::new().with_handler(&P::protocol_name(), protocol),
+        )
+    }
+
+    fn from_router(router: RpcRouter) -> Self {
+        IrohServer {
+            router: IrohRouter::new(Arc::new(router)),
+            _protocol: PhantomData,
+        }
+    }
+
+    // r[impl jetstream.auth.router]
+    /// Attach an authorizer to the context of every accepted stream, as
+    /// [`Router::with_authorizer`](RpcRouter::with_authorizer) does.
+    pub fn with_authorizer(
+        self,
+        authorizer: impl Authorizer + 'static,
+    ) -> Self {
+        Self::from_router(
+            Arc::unwrap_or_clone(self.router.router)
+                .with_authorizer(authorizer),
+        )
     }
 }
 
@@ -32,68 +67,7 @@ impl<P: Protocol + Server + Debug + Clone + 'static> ProtocolHandler
         &self,
         connection: Connection,
     ) -> Result<(), iroh::protocol::AcceptError> {
-        let handler = self.inner.clone();
-        let node_id: NodeId = connection.remote_id().into();
-
-        loop {
-            let (send_stream, recv_stream) = match connection.accept_bi().await
-            {
-                Ok(streams) => streams,
-                Err(_) => break,
-            };
-            let handler = handler.clone();
-            let node_id = node_id.clone();
-            tokio::spawn(async move {
-                let mut reader =
-                    FramedRead::new(recv_stream, ServerCodec::<P>::new());
-                let mut writer =
-                    FramedWrite::new(send_stream, ServerCodec::<P>::new());
-
-                // Channel for sending responses back to the writer
-                let (resp_tx, mut resp_rx) =
-                    mpsc::channel::<Frame<P::Response>>(256);
-
-                // Spawn a task to write responses as they complete
-                let writer_task = tokio::spawn(async move {
-                    while let Some(resp) = resp_rx.recv().await {
-                        if writer.send(resp).await.is_err() {
-                            break;
-                        }
-                    }
-                });
-
-                // Process requests concurrently
-                while let Some(req) = reader.next().await {
-                    let context = Context::from(node_id.clone());
-                    match req {
-                        Ok(req) => {
-                            let mut handler = handler.clone();
-                            let resp_tx = resp_tx.clone();
-                            tokio::spawn(async move {
-                                match handler.rpc(context, req).await {
-                                    Ok(resp) => {
-                                        let _ = resp_tx.send(resp).await;
-                                    }
-                                    Err(err) => {
-                                        let error = err.into_error();
-                                        eprintln!(
-                                            "Error processing request: {}",
-                                            error
-                                        );
-                                    }
-                                }
-                            });
-                        }
-                        Err(_err) => {}
-                    }
-                }
-
-                // Drop the sender so the writer task knows to finish
-                drop(resp_tx);
-                let _ = writer_task.await;
-            });
-        }
-        Ok(())
+        self.router.accept(connection).await
     }
 }
 
@@ -117,6 +91,8 @@ impl ProtocolHandler for IrohRouter {
     ) -> Result<(), iroh::protocol::AcceptError> {
         let router = self.router.clone();
         let node_id: NodeId = connection.remote_id().into();
+        // r[impl jetstream.push.upstream]
+        let upstream = Upstream::new(connection.clone());
 
         loop {
             let (send_stream, recv_stream) = match connection.accept_bi().await
@@ -125,7 +101,8 @@ impl ProtocolHandler for IrohRouter {
                 Err(_) => break,
             };
             let router = router.clone();
-            let ctx = Context::from(node_id.clone());
+            let ctx =
+                Context::from(node_id.clone()).with_extension(upstream.clone());
             tokio::spawn(async move {
                 let reader: Box<dyn AsyncRead + Send + Sync + Unpin> =
                     Box::new(recv_stream);
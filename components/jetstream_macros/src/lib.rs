@@ -31,8 +31,37 @@
 //! }
 //! ```
 //!
+//! With the `opentelemetry` feature the client sends the current span's W3C
+//! trace context with each request and the server's `rpc_server` span becomes
+//! its child, once both sides negotiated it.
+//!
 //! See the [Tracing Guide](../../docs/tracing.md) for detailed documentation on tracing support.
 //!
+//! ### Generic Services
+//!
+//! Traits may be generic over the types they send, and may declare associated
+//! types. Every instantiation is a protocol of its own, named after its
+//! arguments (`my_crate.store-u64.String_`), so several can share a router.
+//!
+//! ```ignore
+//! #[service]
+//! pub trait Store<K, V> {
+//!     async fn get(&mut self, key: K) -> Result<Option<V>, Error>;
+//! }
+//! ```
+//!
+//! ### Composed Services
+//!
+//! A service whose supertraits are services is one protocol carrying all of
+//! their methods. Its channel implements every trait in the hierarchy.
+//!
+//! ```ignore
+//! #[service]
+//! pub trait Admin: Health + Echo {
+//!     async fn restart(&mut self) -> Result<(), Error>;
+//! }
+//! ```
+//!
 //! ## `JetStreamWireFormat`
 //! The `JetStreamWireFormat` macro is used to derive the `WireFormat` trait for a struct.
 #![cfg_attr(docsrs, feature(doc_cfg))]
@@ -61,9 +90,31 @@ pub fn jetstream_wire_format(input: TokenStream) -> TokenStream {
 /// ## Attributes
 ///
 /// - `async_trait` - Use async_trait instead of the default make(Send + Sync)
+/// - `name = "com.acme.storage"` - Name the protocol, instead of after the
+///   crate, module and trait (`my_crate.storage.kv`). Connections are routed
+///   by it, and it is the ALPN on QUIC and iroh.
 /// - `tracing` - Enable auto-instrumentation for all methods
 /// - `uses(path::to::mod::*)` - Add use statements to the generated protocol module.
 ///   Multiple paths can be specified: `uses(some::mod::*, other::mod::Type)`
+/// - `mock` - Also generate `Mock{Trait}`, an implementation of the trait
+///   answering from expectations, servable through the generated server. See
+///   `jetstream_rpc::mock`.
+/// - `blocking` - Also generate `{Trait}BlockingChannel`, a synchronous client,
+///   and `Blocking{Trait}`, the trait without `async`, servable through
+///   `jetstream_rpc::blocking::Blocking`.
+///
+/// ## Method attributes
+///
+/// - `authorize` - Require the peer to pass the context's authorizer before the
+///   method runs. `#[authorize("admin", ...)]` additionally requires one of the
+///   listed roles. See `jetstream_rpc::auth`.
+/// - `idempotent` - Let the generated channel retry the method on retriable
+///   failures, following its `RetryPolicy`. Arguments must be `Clone`. See
+///   `jetstream_rpc::retry`.
+/// - `since("1.3.0")` - Only call the method once a version at least this
+///   high was negotiated; both sides refuse it otherwise. `#[deprecated]`
+///   methods are logged and counted by the server. See
+///   `jetstream_rpc::lifecycle`.
 ///
 /// ## Example
 ///
@@ -0,0 +1,70 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, Attribute, LitStr, Token, TraitItemFn};
+
+/// Roles from a method's `#[authorize(...)]` attribute.
+///
+/// `None` means the method has no attribute, `Some(vec![])` a bare
+/// `#[authorize]`.
+pub type MethodRoles = Option<Vec<LitStr>>;
+
+pub fn is_authorize_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("authorize")
+}
+
+/// Parse `#[authorize]` or `#[authorize("role", ...)]` on a trait method.
+pub fn extract_method_roles(method: &TraitItemFn) -> syn::Result<MethodRoles> {
+    let mut roles: MethodRoles = None;
+    for attr in method.attrs.iter().filter(|attr| is_authorize_attr(attr)) {
+        if roles.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate #[authorize] attribute",
+            ));
+        }
+        let parsed = match &attr.meta {
+            syn::Meta::Path(_) => Vec::new(),
+            syn::Meta::List(_) => attr
+                .parse_args_with(
+                    Punctuated::<LitStr, Token![,]>::parse_terminated,
+                )?
+                .into_iter()
+                .collect(),
+            syn::Meta::NameValue(_) => {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected #[authorize] or #[authorize(\"role\", ...)]",
+                ))
+            }
+        };
+        roles = Some(parsed);
+    }
+    Ok(roles)
+}
+
+// r[impl jetstream.auth.macro]
+/// Wrap a server match arm body in an authorization check.
+pub fn guard(
+    method_name: &syn::Ident,
+    roles: &MethodRoles,
+    body: TokenStream,
+) -> TokenStream {
+    let method = method_name.to_string();
+    let roles = match roles {
+        Some(roles) => quote! { Some(&[#(#roles),*]) },
+        None => quote! { None },
+    };
+    quote! {
+        {
+            let request = jetstream::prelude::jetstream_rpc::auth::AuthRequest {
+                protocol: PROTOCOL_NAME,
+                method: #method,
+                roles: #roles,
+            };
+            match jetstream::prelude::jetstream_rpc::auth::authorize(&ctx, &request) {
+                Ok(()) => #body,
+                Err(err) => Err(err),
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{FnArg, Ident, TraitItem};
+
+// r[impl jetstream.blocking.macro]
+/// Generates `{Trait}BlockingChannel`, a synchronous client wrapping the
+/// generated channel, and `Blocking{Trait}`, the service trait without
+/// `async`, served through `jetstream_rpc::blocking::Blocking`.
+pub fn generate_blocking(
+    trait_name: &Ident,
+    channel_name: &Ident,
+    trait_items: &[TraitItem],
+    is_async_trait: bool,
+) -> TokenStream {
+    let blocking_channel = format_ident!("{}BlockingChannel", trait_name);
+    let blocking_trait = format_ident!("Blocking{}", trait_name);
+    let mut client_calls = Vec::new();
+    let mut trait_methods = Vec::new();
+    let mut server_calls = Vec::new();
+
+    for item in trait_items {
+        let TraitItem::Fn(method) = item else {
+            continue;
+        };
+        let method_name = &method.sig.ident;
+        let docs = method
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .collect::<Vec<_>>();
+        // Deprecated methods stay deprecated for blocking callers, while
+        // the calls in between do not warn.
+        let deprecated = method
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("deprecated"))
+            .collect::<Vec<_>>();
+        let allow_deprecated = if deprecated.is_empty() {
+            quote! {}
+        } else {
+            quote! { #[allow(deprecated)] }
+        };
+        let mut inputs = Vec::new();
+        let mut args = Vec::new();
+        let mut owned = Vec::new();
+        let mut server_args = Vec::new();
+        let mut receiver = quote! { &self };
+        let mut channel = quote! { &self.channel };
+        for arg in &method.sig.inputs {
+            match arg {
+                FnArg::Receiver(recv) => {
+                    if recv.mutability.is_some() {
+                        receiver = quote! { &mut self };
+                        channel = quote! { &mut self.channel };
+                    }
+                }
+                FnArg::Typed(pat) => {
+                    let name = &pat.pat;
+                    let ty = &pat.ty;
+                    inputs.push(quote! { #name: #ty });
+                    args.push(name.clone());
+                    // Borrows can't cross to the blocking thread pool.
+                    if let syn::Type::Reference(_) = &**ty {
+                        owned.push(
+                            quote! { let #name = ToOwned::to_owned(#name); },
+                        );
+                        server_args.push(quote! { &#name });
+                    } else {
+                        server_args.push(quote! { #name });
+                    }
+                }
+            }
+        }
+        let retn = &method.sig.output;
+
+        client_calls.push(quote! {
+            #(#docs)*
+            #(#deprecated)*
+            #allow_deprecated
+            pub fn #method_name(#receiver, #(#inputs),*) #retn {
+                self.runtime.call(self.timeout, <#channel_name as #trait_name>::#method_name(#channel, #(#args),*))
+            }
+        });
+        trait_methods.push(quote! {
+            #(#docs)*
+            #(#deprecated)*
+            fn #method_name(#receiver, #(#inputs),*) #retn;
+        });
+        server_calls.push(quote! {
+            #allow_deprecated
+            async fn #method_name(#receiver, #(#inputs),*) #retn {
+                #(#owned)*
+                self.run(move |inner| inner.#method_name(#(#server_args),*)).await
+            }
+        });
+    }
+
+    let trait_attribute = if is_async_trait {
+        quote! { #[jetstream::prelude::async_trait] }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        /// A synchronous client of the service. Every call blocks the
+        /// calling thread until it completes, fails or times out.
+        pub struct #blocking_channel {
+            runtime: jetstream::prelude::jetstream_rpc::blocking::BlockingRuntime,
+            channel: #channel_name,
+            timeout: Option<std::time::Duration>,
+        }
+
+        impl #blocking_channel {
+            /// Wraps `channel`, which must have been created on `runtime`.
+            pub fn new(
+                runtime: impl Into<jetstream::prelude::jetstream_rpc::blocking::BlockingRuntime>,
+                channel: #channel_name,
+            ) -> Self {
+                Self {
+                    runtime: runtime.into(),
+                    channel,
+                    timeout: None,
+                }
+            }
+
+            /// Fails calls taking longer than `timeout` with
+            /// `DEADLINE_EXCEEDED`.
+            pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+                self.timeout = Some(timeout);
+                self
+            }
+
+            pub fn runtime(&self) -> &jetstream::prelude::jetstream_rpc::blocking::BlockingRuntime {
+                &self.runtime
+            }
+
+            pub fn channel(&self) -> &#channel_name {
+                &self.channel
+            }
+
+            pub fn into_inner(self) -> #channel_name {
+                self.channel
+            }
+
+            /// Perform Tversion/Rversion handshake with the server.
+            pub fn negotiate_version(&self, msize: u32) -> std::result::Result<jetstream::prelude::Rversion, Error> {
+                self.runtime.call(self.timeout, self.channel.negotiate_version(msize))
+            }
+
+            #(#client_calls)*
+        }
+
+        /// The service without `async`, for synchronous implementations.
+        /// Wrap one in `Blocking` to serve it.
+        pub trait #blocking_trait: Send + 'static {
+            #(#trait_methods)*
+        }
+
+        #trait_attribute
+        impl<T: #blocking_trait> #trait_name for jetstream::prelude::jetstream_rpc::blocking::Blocking<T> {
+            #(#server_calls)*
+        }
+    }
+}
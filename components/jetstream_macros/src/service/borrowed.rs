@@ -0,0 +1,37 @@
+use syn::{parse_quote, FnArg, Signature, Type};
+
+// r[impl jetstream.service.borrowed-args]
+/// The owned type a borrowed parameter is received as: `&str` as `String`,
+/// `&[T]` as `Vec<T>` and `&T` as `T`. `None` for parameters taken by value.
+pub fn owned_type(ty: &Type) -> syn::Result<Option<Type>> {
+    let Type::Reference(reference) = ty else {
+        return Ok(None);
+    };
+    if let Some(mutability) = reference.mutability {
+        return Err(syn::Error::new_spanned(
+            mutability,
+            "service methods cannot take `&mut` arguments, nothing is sent \
+             back through them",
+        ));
+    }
+    let owned = match &*reference.elem {
+        Type::Path(path)
+            if path.qself.is_none() && path.path.is_ident("str") =>
+        {
+            parse_quote! { String }
+        }
+        Type::Slice(slice) => {
+            let elem = &slice.elem;
+            parse_quote! { Vec<#elem> }
+        }
+        elem => elem.clone(),
+    };
+    Ok(Some(owned))
+}
+
+/// Whether any parameter of `sig` is borrowed.
+pub fn has_borrowed_args(sig: &Signature) -> bool {
+    sig.inputs.iter().any(|arg| {
+        matches!(arg, FnArg::Typed(pat) if matches!(*pat.ty, Type::Reference(_)))
+    })
+}
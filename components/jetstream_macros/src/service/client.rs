@@ -1,8 +1,24 @@
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{Attribute, Ident, TraitItem};
 
+use super::borrowed::has_borrowed_args;
+use super::compose::Composition;
+use super::generics::Instance;
+use super::lifecycle;
 use crate::utils::case_conversion::IdentCased;
+
+fn is_context(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Context"),
+        _ => false,
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn generate_client(
     channel_name: &Ident,
@@ -10,10 +26,36 @@ pub fn generate_client(
     trait_items: &[TraitItem],
     tmsgs: &[(Ident, TokenStream)],
     method_attrs: &[Vec<Attribute>],
+    idempotent_methods: &[bool],
+    oneway_methods: &[bool],
+    method_since: &[Option<[u64; 3]>],
     enable_tracing: bool,
+    extended: bool,
+    instance: &Instance,
+    composition: &Composition,
 ) -> TokenStream {
-    let client_calls =
-        generate_client_calls(trait_items, tmsgs, method_attrs, enable_tracing);
+    let client_calls = generate_client_calls(
+        trait_items,
+        tmsgs,
+        method_attrs,
+        idempotent_methods,
+        oneway_methods,
+        method_since,
+        enable_tracing,
+        extended,
+        false,
+    );
+    let balanced_calls = generate_client_calls(
+        trait_items,
+        tmsgs,
+        method_attrs,
+        idempotent_methods,
+        oneway_methods,
+        method_since,
+        enable_tracing,
+        extended,
+        true,
+    );
 
     // Add RPC-level tracing span if tracing is enabled
     let _rpc_span = if enable_tracing {
@@ -29,14 +71,120 @@ pub fn generate_client(
         quote! {}
     };
 
+    // r[impl jetstream.service.generics]
+    let generics = instance.generics();
+    let where_clause = instance.where_clause();
+    let channel_ty = quote! { #channel_name #generics };
+    let (marker, marker_init) = match &instance.marker {
+        Some(marker) => (
+            quote! { _marker: #marker, },
+            quote! { _marker: std::marker::PhantomData, },
+        ),
+        None => (quote! {}, quote! {}),
+    };
+    let tmessage = &instance.tmessage;
+    let rmessage = &instance.rmessage;
+    let trait_ref = &instance.trait_ref;
+    let assoc_types = &instance.assoc_types;
+    let protocol_fns = instance.protocol_fns();
+    let endpoint = &instance.free;
+    let balancer_params = (!instance.params.is_empty())
+        .then(|| instance.params.clone())
+        .into_iter()
+        .collect::<Vec<_>>();
+    let predicates = &instance.predicates;
+    // r[impl jetstream.service.compose]
+    let supertrait_impls =
+        composition.impls(&client_calls, |supertrait, calls| {
+            quote! {
+                impl #generics #supertrait for #channel_ty #where_clause {
+                    #(#calls)*
+                }
+            }
+        });
+    let balanced_supertrait_impls =
+        composition.impls(&balanced_calls, |supertrait, calls| {
+            quote! {
+                impl<#endpoint> #supertrait for jetstream::prelude::jetstream_rpc::balance::Balancer<#channel_ty, #endpoint>
+                where
+                    #endpoint: Clone + PartialEq + std::fmt::Debug + Send + Sync + 'static,
+                {
+                    #(#calls)*
+                }
+            }
+        });
+    let client_calls = &client_calls[composition.own..];
+    let balanced_calls = &balanced_calls[composition.own..];
+    // r[impl jetstream.balance.service]
+    // The orphan rule keeps supertraits from other crates from being
+    // implemented for `Balancer`, and the trait needs all of them.
+    let balanced_impls = composition.is_local().then(|| {
+        quote! {
+            impl<#endpoint #(, #balancer_params)*> #trait_ref for jetstream::prelude::jetstream_rpc::balance::Balancer<#channel_ty, #endpoint>
+            where
+                #endpoint: Clone + PartialEq + std::fmt::Debug + Send + Sync + 'static,
+                #(#predicates,)*
+            {
+                #(#assoc_types)*
+                #(#balanced_calls)*
+            }
+
+            #balanced_supertrait_impls
+        }
+    });
+    let version = if instance.generic {
+        quote! { &<Self as Protocol>::protocol_version() }
+    } else {
+        quote! { PROTOCOL_VERSION }
+    };
+
     quote! {
-        pub struct #channel_name {
+        pub struct #channel_ty #where_clause {
             mux: Mux<Self>,
+            retry: RetryPolicy,
+            digest_policy: jetstream::prelude::jetstream_rpc::schema::DigestPolicy,
+            schema: jetstream::prelude::jetstream_rpc::schema::SchemaState,
+            negotiated: jetstream::prelude::jetstream_rpc::lifecycle::NegotiatedVersion,
+            #marker
         }
 
-        impl #channel_name {
+        impl #generics #channel_ty #where_clause {
             pub fn new(max_concurrent_requests:u16,inner: Box<dyn ClientTransport<Self>>) -> Self {
-                Self { mux: Mux::new(max_concurrent_requests,inner) }
+                Self {
+                    mux: Mux::new(max_concurrent_requests,inner),
+                    retry: RetryPolicy::default(),
+                    digest_policy: Default::default(),
+                    schema: Default::default(),
+                    negotiated: Default::default(),
+                    #marker_init
+                }
+            }
+
+            // r[impl jetstream.retry.channel]
+            /// Sets how `#[idempotent]` methods are retried. Other methods are
+            /// never retried.
+            pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+                self.retry = retry;
+                self
+            }
+
+            // r[impl jetstream.schema.client]
+            /// Sets how servers built from a different definition of the
+            /// service are treated. Defaults to `DigestPolicy::Warn`.
+            pub fn with_digest_policy(mut self, policy: jetstream::prelude::jetstream_rpc::schema::DigestPolicy) -> Self {
+                self.digest_policy = policy;
+                self
+            }
+
+            /// How this channel's service differs from the server's, as
+            /// found by the last version negotiation.
+            pub fn schema_mismatch(&self) -> Option<jetstream::prelude::jetstream_rpc::schema::Mismatch> {
+                self.schema.get()
+            }
+
+            /// The version the last version negotiation agreed on.
+            pub fn negotiated_version(&self) -> Option<jetstream::prelude::Version> {
+                self.negotiated.get()
             }
 
             // r[impl jetstream.version.framer.client-handshake]
@@ -45,7 +193,8 @@ pub fn generate_client(
             pub async fn negotiate_version(&self, msize: u32) -> std::result::Result<jetstream::prelude::Rversion, Error> {
                 let req = Tmessage::Version(jetstream::prelude::Tversion {
                     msize,
-                    version: PROTOCOL_VERSION.to_string(),
+                    version: jetstream::prelude::jetstream_rpc::schema::Schema::of::<Self>()
+                        .advertise(&<Self as Protocol>::CAPABILITIES.offer(#version)),
                 });
                 let context = Context::default();
                 let rframe = self.mux.rpc(context, req).await.await?;
@@ -54,6 +203,20 @@ pub fn generate_client(
                         if rversion.version == "unknown" {
                             Err(Error::new("server rejected version negotiation"))
                         } else {
+                            let mismatch = self.digest_policy.check(
+                                jetstream::prelude::jetstream_rpc::schema::Schema::of::<Self>()
+                                    .compare(PROTOCOL_NAME, &rversion.version),
+                            )?;
+                            self.schema.set(mismatch);
+                            // r[impl jetstream.service.since.client]
+                            self.negotiated.set(
+                                std::str::FromStr::from_str(&rversion.version).ok(),
+                            );
+                            // r[impl jetstream.version.capabilities.client]
+                            self.mux.set_capabilities(
+                                Capabilities::acknowledged(&rversion.version)
+                                    & <Self as Protocol>::CAPABILITIES,
+                            );
                             Ok(rversion)
                         }
                     }
@@ -61,29 +224,67 @@ pub fn generate_client(
                     _ => Err(Error::new("unexpected response to Tversion")),
                 }
             }
+
+            // r[impl jetstream.trace.client]
+            /// Wraps `req` with the current span's trace context if the server
+            /// acknowledged trace context propagation.
+            fn with_trace_context(&self, req: #tmessage) -> #tmessage {
+                if !self.mux.capabilities().contains(Capabilities::TRACE_CONTEXT) {
+                    return req;
+                }
+                match jetstream::prelude::TraceContext::current() {
+                    Some(trace) => Tmessage::Traced(trace, Box::new(req)),
+                    None => req,
+                }
+            }
         }
 
-        impl Protocol for #channel_name {
-            type Request = Tmessage;
-            type Response = Rmessage;
+        impl #generics Protocol for #channel_ty #where_clause {
+            type Request = #tmessage;
+            type Response = #rmessage;
             // r[impl jetstream.macro.error-type]
             type Error = Error;
             const VERSION: &'static str = PROTOCOL_VERSION;
             const NAME: &'static str = PROTOCOL_NAME;
+            const CAPABILITIES: Capabilities =
+                Capabilities::TRACE_CONTEXT.union(Capabilities::COMPRESSION);
+            const METHODS: &'static [jetstream::prelude::jetstream_rpc::schema::MethodDigest] = METHODS;
+            #protocol_fns
         }
 
-        impl #trait_name for #channel_name
+        impl #generics #trait_ref for #channel_ty #where_clause
         {
+            #(#assoc_types)*
             #(#client_calls)*
         }
+
+        impl #generics jetstream::prelude::jetstream_rpc::balance::Backend for #channel_ty #where_clause {
+            fn in_flight(&self) -> usize {
+                self.mux.in_flight()
+            }
+
+            fn is_closed(&self) -> bool {
+                self.mux.is_closed()
+            }
+        }
+
+        #balanced_impls
+
+        #supertrait_impls
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_client_calls(
     trait_items: &[TraitItem],
     tmsgs: &[(Ident, TokenStream)],
     method_attrs: &[Vec<Attribute>],
+    idempotent_methods: &[bool],
+    oneway_methods: &[bool],
+    method_since: &[Option<[u64; 3]>],
     enable_tracing: bool,
+    extended: bool,
+    balanced: bool,
 ) -> Vec<TokenStream> {
     trait_items
         .iter()
@@ -107,6 +308,7 @@ fn generate_client_calls(
                 let maybe_async = if is_async { quote! { async } } else { quote! {} };
 
                 let request_struct_ident = &tmsgs[index].0;
+                let tmsg_const = format_ident!("T{}", method_name.to_string().to_uppercase());
 
                 let inputs = method.sig.inputs.iter().map(|arg| {
                     match arg {
@@ -147,11 +349,60 @@ fn generate_client_calls(
                                     }
                                 }
                             }
-                            Some(quote! { #name, })
+                            Some(name)
                         }
                         syn::FnArg::Receiver(_) => None,
                     }
-                });
+                }).collect::<Vec<_>>();
+
+                // r[impl jetstream.service.borrowed-args.client]
+                // Methods with borrowed arguments encode the request from the
+                // borrows instead of moving them into the request struct.
+                let request = |fields: TokenStream| {
+                    if !has_borrowed_args(&method.sig) {
+                        return quote! {
+                            Tmessage::#variant_name(#request_struct_ident {
+                                #fields
+                            })
+                        };
+                    }
+                    let encode_args = method.sig.inputs.iter().filter_map(|arg| match arg {
+                        syn::FnArg::Typed(pat) if !is_context(&pat.ty) => {
+                            let name = &pat.pat;
+                            match &*pat.ty {
+                                syn::Type::Reference(_) => Some(quote! { #name }),
+                                _ => Some(quote! { &#name }),
+                            }
+                        }
+                        _ => None,
+                    }).collect::<Vec<_>>();
+                    let (message_type, id_size, id) = if extended {
+                        (
+                            quote! { TEXTENDED },
+                            quote! { mem::size_of::<u16>() as u32 },
+                            quote! { WireFormat::encode(&#tmsg_const, writer)?; },
+                        )
+                    } else {
+                        (quote! { #tmsg_const }, quote! { 0 }, quote! {})
+                    };
+                    quote! {
+                        Tmessage::Encoded(jetstream::prelude::jetstream_rpc::Encoded::new(
+                            #message_type,
+                            #id_size #(+ jetstream::prelude::jetstream_wireformat::BorrowedWireFormat::borrowed_byte_size(#encode_args))*,
+                            |writer| {
+                                #id
+                                #(jetstream::prelude::jetstream_wireformat::BorrowedWireFormat::encode_borrowed(#encode_args, writer)?;)*
+                                Ok(())
+                            },
+                        )?)
+                    }
+                };
+
+                // r[impl jetstream.service.since.client]
+                // Methods newer than the negotiated version are refused
+                // before anything is sent.
+                let chan_since = lifecycle::client_check(method_name, method_since[index], quote! { chan });
+                let self_since = lifecycle::client_check(method_name, method_since[index], quote! { self });
 
                 // Get tracing attributes for this method
                 let attrs = &method_attrs[index];
@@ -163,13 +414,127 @@ fn generate_client_calls(
                     attrs.iter().map(|attr| quote! { #attr }).collect()
                 };
 
+                // r[impl jetstream.rpc.oneway.client]
+                // Oneway calls return once the request is queued; no tag is
+                // taken and no response is awaited.
+                if oneway_methods[index] {
+                    let oneway_request = request(quote! { #(#args,)* });
+                    let (chan, report) = if balanced {
+                        (
+                            quote! { let chan = self.pick()?; },
+                            quote! { self.report(&chan, &result); },
+                        )
+                    } else {
+                        (quote! { let chan = self; }, quote! {})
+                    };
+                    return Some(quote! {
+                        #(#tracing_attrs)*
+                        #maybe_async fn #method_name(#reciever, #(#inputs)*) #retn {
+                            #chan
+                            #chan_since
+                            chan.schema.check_method(#tmsg_const)?;
+                            let req = chan.with_trace_context(#oneway_request);
+                            let context = Context::default();
+                            let result = chan.mux.oneway(context, req).await;
+                            #report
+                            result?;
+                            Ok(())
+                        }
+                    });
+                }
+
                 // r[impl jetstream.macro.client-error]
+                if balanced {
+                    // Every attempt goes to whichever endpoint the balancer
+                    // picks, and its outcome feeds endpoint ejection.
+                    let idempotent = idempotent_methods[index];
+                    let fields = if idempotent {
+                        quote! { #(#args: #args.clone(),)* }
+                    } else {
+                        quote! { #(#args,)* }
+                    };
+                    let balanced_request = request(fields);
+                    let call = quote! {
+                        let chan = self.pick()?;
+                        #chan_since
+                        chan.schema.check_method(#tmsg_const)?;
+                        let req = chan.with_trace_context(#balanced_request);
+                        let context = Context::default();
+                        let result = match chan.mux.rpc(context, req).await.await {
+                            Ok(rframe) => match rframe.msg {
+                                Rmessage::#variant_name(msg) => Ok(msg.0),
+                                Rmessage::Error(err) => Err(err),
+                                _ => Err(Error::new("invalid response")),
+                            },
+                            Err(err) => Err(err),
+                        };
+                        self.report(&chan, &result);
+                    };
+                    let body = if idempotent {
+                        quote! {
+                            let mut attempt = 0;
+                            loop {
+                                #call
+                                match result {
+                                    Err(err) if chan.retry.backoff(attempt, &err).await => attempt += 1,
+                                    result => return result,
+                                }
+                            }
+                        }
+                    } else {
+                        quote! {
+                            #call
+                            result
+                        }
+                    };
+                    return Some(quote! {
+                        #(#tracing_attrs)*
+                        #maybe_async fn #method_name(#reciever, #(#inputs)*) #retn {
+                            #body
+                        }
+                    });
+                }
+
+                if idempotent_methods[index] {
+                    // The request is rebuilt for every attempt, so arguments
+                    // of idempotent methods must be `Clone`.
+                    // r[impl jetstream.retry.idempotent]
+                    let idempotent_request = request(quote! { #(#args: #args.clone(),)* });
+                    return Some(quote! {
+                        #(#tracing_attrs)*
+                        #maybe_async fn #method_name(#reciever, #(#inputs)*) #retn {
+                            #self_since
+                            self.schema.check_method(#tmsg_const)?;
+                            let mut attempt = 0;
+                            loop {
+                                let req = self.with_trace_context(#idempotent_request);
+                                let context = Context::default();
+                                let result = match self.mux.rpc(context, req).await.await {
+                                    Ok(rframe) => match rframe.msg {
+                                        Rmessage::#variant_name(msg) => Ok(msg.0),
+                                        Rmessage::Error(err) => Err(err),
+                                        _ => Err(Error::new("invalid response")),
+                                    },
+                                    Err(err) => Err(err),
+                                };
+                                // A closed mux never recovers, so nothing is
+                                // gained by sending the call on it again.
+                                match result {
+                                    Err(err) if !self.mux.is_closed() && self.retry.backoff(attempt, &err).await => attempt += 1,
+                                    result => return result,
+                                }
+                            }
+                        }
+                    });
+                }
+
+                let default_request = request(quote! { #(#args,)* });
                 Some(quote! {
                     #(#tracing_attrs)*
                     #maybe_async fn #method_name(#reciever, #(#inputs)*) #retn {
-                        let req = Tmessage::#variant_name(#request_struct_ident {
-                            #(#args)*
-                        });
+                        #self_since
+                        self.schema.check_method(#tmsg_const)?;
+                        let req = self.with_trace_context(#default_request);
                         let context = Context::default();
                         let rframe = self.mux.rpc(context, req).await.await?;
                         let rmsg = rframe.msg;
@@ -177,7 +542,7 @@ fn generate_client_calls(
                             Rmessage::#variant_name(msg) => Ok(msg.0),
                             // When client receives an error frame, convert it to jetstream::prelude::Error
                             Rmessage::Error(err) => Err(err),
-                            _ => Err(Error::new("invalid reposne")),
+                            _ => Err(Error::new("invalid response")),
                         }
                     }
                 })
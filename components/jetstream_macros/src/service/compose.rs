@@ -0,0 +1,337 @@
+// r[impl jetstream.service.compose]
+//! Services composed of other services: `trait Admin: Health + Echo`.
+//!
+//! A proc macro only sees the trait it is attached to, so every service
+//! exports its methods through a `methods!` macro in its protocol module.
+//! `#[service]` on a trait with service supertraits hands itself to the
+//! `methods!` of the first supertrait it knows nothing of, which expands to
+//! the trait again with that supertrait's methods attached as
+//! `#[supertrait(Path { .. })]`. Once every supertrait is attached the
+//! service is generated over all of their methods.
+
+use std::ops::Range;
+
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{Ident, ItemTrait, LitStr, Path, Token, TraitItem};
+
+pub(crate) use jetstream_codegen::service_parser::service_bounds;
+
+/// The methods of a supertrait, as attached to the composed trait:
+/// `#[supertrait(Path in "crate" { .. })]`.
+pub struct Inherited {
+    pub path: Path,
+    /// The crate declaring the supertrait.
+    pub krate: Option<LitStr>,
+    pub items: Vec<TraitItem>,
+}
+
+impl Inherited {
+    /// Whether the supertrait is declared in the crate being compiled, so
+    /// its trait can be implemented for foreign types like `Balancer`.
+    fn is_local(&self) -> bool {
+        match (&self.krate, current_crate()) {
+            (Some(krate), Some(current)) => krate.value() == current,
+            _ => true,
+        }
+    }
+}
+
+impl syn::parse::Parse for Inherited {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path = input.call(Path::parse_mod_style)?;
+        let krate = if input.peek(Token![in]) {
+            input.parse::<Token![in]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let content;
+        syn::braced!(content in input);
+        let mut items = Vec::new();
+        while !content.is_empty() {
+            items.push(content.parse()?);
+        }
+        Ok(Inherited { path, krate, items })
+    }
+}
+
+/// The crate being compiled, as cargo names it to the compiler.
+fn current_crate() -> Option<String> {
+    std::env::var("CARGO_CRATE_NAME").ok()
+}
+
+/// The methods of the supertraits of a composed service, followed by its
+/// own, and which of them belong to which supertrait.
+pub struct Composition {
+    trait_name: Ident,
+    supertraits: Vec<Supertrait>,
+    /// Index of the first of the trait's own methods.
+    pub own: usize,
+}
+
+struct Supertrait {
+    path: Path,
+    methods: Range<usize>,
+    local: bool,
+}
+
+impl Composition {
+    pub fn is_composed(&self) -> bool {
+        !self.supertraits.is_empty()
+    }
+
+    /// The trait declaring the method at `index`, if it is inherited.
+    pub fn declared_by(&self, index: usize) -> Option<&Path> {
+        self.supertraits
+            .iter()
+            .find(|supertrait| supertrait.methods.contains(&index))
+            .map(|supertrait| &supertrait.path)
+    }
+
+    /// The name the protocol module knows the `index`th supertrait by.
+    fn alias(&self, index: usize) -> Ident {
+        format_ident!("__{}Supertrait{}", self.trait_name, index)
+    }
+
+    /// Names the supertraits next to the trait, where their paths are
+    /// written, for the protocol module to reach as `super::` aliases.
+    pub fn aliases(&self) -> TokenStream {
+        let aliases = self.supertraits.iter().enumerate().map(|(index, s)| {
+            let path = &s.path;
+            let alias = self.alias(index);
+            quote! {
+                #[doc(hidden)]
+                use #path as #alias;
+            }
+        });
+        quote! { #(#aliases)* }
+    }
+
+    /// Brings the supertraits' methods into scope in the protocol module.
+    pub fn imports(&self) -> TokenStream {
+        let aliases =
+            (0..self.supertraits.len()).map(|index| self.alias(index));
+        quote! { #(use super::#aliases as _;)* }
+    }
+
+    /// Implementations of each supertrait for `ty`, made of the generated
+    /// per-method `items`.
+    pub fn impls(
+        &self,
+        items: &[TokenStream],
+        imp: impl Fn(&TokenStream, &[TokenStream]) -> TokenStream,
+    ) -> TokenStream {
+        let impls = self.supertraits.iter().enumerate().map(|(index, s)| {
+            let alias = self.alias(index);
+            imp(&quote! { super::#alias }, &items[s.methods.clone()])
+        });
+        quote! { #(#impls)* }
+    }
+
+    /// Whether every supertrait is declared in the crate being compiled, so
+    /// the trait can be implemented for foreign types like `Balancer`.
+    pub fn is_local(&self) -> bool {
+        self.supertraits.iter().all(|supertrait| supertrait.local)
+    }
+}
+
+fn is_supertrait_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("supertrait")
+}
+
+/// Parses an attached supertrait. Its methods come out of the supertrait's
+/// `methods!`, whose hygiene would keep `self` and the arguments from the
+/// generated bodies, so they are moved to the call site. `$crate` keeps its
+/// span, which names the crate that exported the methods.
+fn parse_inherited(attr: &syn::Attribute) -> syn::Result<Inherited> {
+    let list = attr.meta.require_list()?;
+    syn::parse2(respan(list.tokens.clone()))
+}
+
+fn respan(tokens: TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Group(group) => {
+                let mut respanned =
+                    Group::new(group.delimiter(), respan(group.stream()));
+                respanned.set_span(Span::call_site());
+                TokenTree::Group(respanned)
+            }
+            TokenTree::Ident(ident) if ident == "$crate" => {
+                TokenTree::Ident(ident)
+            }
+            mut token => {
+                token.set_span(Span::call_site());
+                token
+            }
+        })
+        .collect()
+}
+
+fn same(a: &Path, b: &Path) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+/// The protocol module of the service at `path`: `a::Health` becomes
+/// `a::health_protocol`.
+fn protocol_module(path: &Path) -> Path {
+    let mut module = path.clone();
+    if let Some(last) = module.segments.last_mut() {
+        last.ident =
+            format_ident!("{}_protocol", last.ident.to_string().to_lowercase());
+    }
+    module
+}
+
+/// If a supertrait's methods are not attached yet, the expansion asking its
+/// `methods!` for them.
+pub fn request(
+    item: &ItemTrait,
+    bounds: &[Path],
+    attr: &TokenStream,
+) -> syn::Result<Option<TokenStream>> {
+    let mut attached = Vec::new();
+    for attr in item.attrs.iter().filter(|attr| is_supertrait_attr(attr)) {
+        attached.push(parse_inherited(attr)?.path);
+    }
+    let Some(missing) = bounds
+        .iter()
+        .find(|bound| !attached.iter().any(|path| same(path, bound)))
+    else {
+        return Ok(None);
+    };
+    let module = protocol_module(missing);
+    Ok(Some(quote! {
+        #module::methods! {
+            [#missing]
+            #[jetstream::prelude::service(#attr)]
+            #item
+        }
+    }))
+}
+
+/// Takes the attached methods of the supertraits off `item`. A supertrait
+/// reached through several others is only inherited once.
+pub fn take_inherited(item: &mut ItemTrait) -> syn::Result<Vec<Inherited>> {
+    let mut inherited: Vec<Inherited> = Vec::new();
+    for attr in item.attrs.iter().filter(|attr| is_supertrait_attr(attr)) {
+        let supertrait = parse_inherited(attr)?;
+        if !inherited.iter().any(|i| same(&i.path, &supertrait.path)) {
+            inherited.push(supertrait);
+        }
+    }
+    item.attrs.retain(|attr| !is_supertrait_attr(attr));
+    Ok(inherited)
+}
+
+/// Lays out the inherited methods before `own`, which are the methods of
+/// `trait_name` itself.
+pub fn compose(
+    trait_name: &Ident,
+    inherited: &[Inherited],
+    own: Vec<TraitItem>,
+) -> (Vec<TraitItem>, Composition) {
+    let mut items = Vec::new();
+    let mut supertraits = Vec::new();
+    for supertrait in inherited {
+        let start = items.len();
+        items.extend(
+            supertrait
+                .items
+                .iter()
+                .filter(|item| matches!(item, TraitItem::Fn(_)))
+                .cloned(),
+        );
+        supertraits.push(Supertrait {
+            path: supertrait.path.clone(),
+            methods: start..items.len(),
+            local: supertrait.is_local(),
+        });
+    }
+    let own_start = items.len();
+    items.extend(own);
+    (
+        items,
+        Composition {
+            trait_name: trait_name.clone(),
+            supertraits,
+            own: own_start,
+        },
+    )
+}
+
+/// `methods!`, handing the methods of the service, and of the services it
+/// is composed of, to a trait composed of it.
+pub fn export(
+    item: &ItemTrait,
+    inherited: &[Inherited],
+    generic: bool,
+) -> TokenStream {
+    let body = if generic {
+        quote! {
+            compile_error!("services cannot be composed of generic services");
+        }
+    } else {
+        let own = item.items.iter().filter(|i| matches!(i, TraitItem::Fn(_)));
+        let krate = current_crate().map(|name| quote! { in #name });
+        let inherited = inherited.iter().map(|supertrait| {
+            let path = exported_path(&supertrait.path);
+            let krate = match &supertrait.krate {
+                Some(name) => Some(quote! { in #name }),
+                None => krate.clone(),
+            };
+            let items = &supertrait.items;
+            quote! { #[supertrait(#path #krate { #(#items)* })] }
+        });
+        quote! {
+            $pound $service
+            #[supertrait($($path)* #krate { #(#own)* })]
+            #(#inherited)*
+            $($item)*
+        }
+    };
+    // Exported macros share the crate root, so the name is made unique to
+    // the trait and where it is declared, as identical traits can be
+    // declared in different modules; the protocol module re-exports it as
+    // `methods`.
+    let span = item.ident.span();
+    let start = span.start();
+    let digest = sha256::digest(format!(
+        "{}:{}:{} {}",
+        span.file(),
+        start.line,
+        start.column,
+        item.to_token_stream()
+    ));
+    let name =
+        format_ident!("__jetstream_methods_{}_{}", item.ident, &digest[0..16]);
+    quote! {
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! #name {
+            // The `#[service]` is passed through as is, so the expansion
+            // keeps the composed trait's hygiene.
+            ([$($path:tt)*] $pound:tt $service:tt $($item:tt)*) => {
+                #body
+            };
+        }
+        #[doc(hidden)]
+        pub use #name as methods;
+    }
+}
+
+/// `path` as seen from the crate `methods!` is expanded in: `crate::` paths
+/// are written `$crate::`, so other crates can compose the service.
+fn exported_path(path: &Path) -> TokenStream {
+    match path.segments.first() {
+        Some(first)
+            if path.leading_colon.is_none() && first.ident == "crate" =>
+        {
+            let rest = path.segments.iter().skip(1);
+            quote! { $crate #(:: #rest)* }
+        }
+        _ => path.to_token_stream(),
+    }
+}
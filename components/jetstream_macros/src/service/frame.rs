@@ -2,33 +2,48 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::Ident;
 
+use super::generics::wire_bounds;
 use crate::utils::case_conversion::IdentCased;
 
-pub fn generate_tframe(tmsgs: &[(Ident, TokenStream)]) -> TokenStream {
+pub fn generate_tframe(
+    tmsgs: &[(Ident, TokenStream)],
+    msg_params: &[Vec<Ident>],
+    params: &[Ident],
+    extended: bool,
+    encoded: bool,
+) -> TokenStream {
     let enum_name = quote! { Tmessage };
-
-    let msg_variants = tmsgs.iter().map(|(ident, _p)| {
-        let name: IdentCased = ident.into();
-        let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
-        let constant_name: Ident = name.to_screaming_snake_case().into();
-        quote! {
-            #variant_name(#ident) = #constant_name,
-        }
-    });
+    let (decl, impl_generics, ty, where_clause) = enum_generics(params);
+
+    let msg_variants =
+        tmsgs
+            .iter()
+            .zip(msg_params)
+            .map(|((ident, _p), msg_params)| {
+                let name: IdentCased = ident.into();
+                let variant_name: Ident =
+                    name.remove_prefix().to_pascal_case().into();
+                let constant_name: Ident =
+                    name.to_screaming_snake_case().into();
+                let args = type_args(msg_params);
+                discriminant(
+                    quote! { #variant_name(#ident #args) },
+                    quote! { #constant_name },
+                    extended,
+                )
+            });
 
     let cloned_byte_sizes = tmsgs.iter().map(|(ident, _)| {
         let name: IdentCased = ident.into();
         let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
-        quote! {
-            #enum_name::#variant_name(msg) => msg.byte_size()
-        }
-    });
-
-    let match_arms = tmsgs.iter().map(|(ident, _)| {
-        let name: IdentCased = ident.into();
-        let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
-        quote! {
-            #enum_name::#variant_name(msg)
+        if extended {
+            quote! {
+                #enum_name::#variant_name(msg) => mem::size_of::<u16>() as u32 + msg.byte_size()
+            }
+        } else {
+            quote! {
+                #enum_name::#variant_name(msg) => msg.byte_size()
+            }
         }
     });
 
@@ -41,9 +56,21 @@ pub fn generate_tframe(tmsgs: &[(Ident, TokenStream)]) -> TokenStream {
         }
     });
 
-    let encode_match_arms = match_arms.clone().map(|arm| {
-        quote! {
-            #arm => msg.encode(writer)?,
+    let encode_match_arms = tmsgs.iter().map(|(ident, _)| {
+        let name: IdentCased = ident.into();
+        let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
+        let const_name: Ident = name.to_screaming_snake_case().into();
+        if extended {
+            quote! {
+                #enum_name::#variant_name(msg) => {
+                    WireFormat::encode(&#const_name, writer)?;
+                    msg.encode(writer)?
+                }
+            }
+        } else {
+            quote! {
+                #enum_name::#variant_name(msg) => msg.encode(writer)?,
+            }
         }
     });
 
@@ -51,48 +78,144 @@ pub fn generate_tframe(tmsgs: &[(Ident, TokenStream)]) -> TokenStream {
         let name: IdentCased = ident.into();
         let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
         let const_name: Ident = name.to_screaming_snake_case().into();
-        quote! {
-            #enum_name::#variant_name(_) => #const_name
+        if extended {
+            quote! { #enum_name::#variant_name(_) => TEXTENDED }
+        } else {
+            quote! { #enum_name::#variant_name(_) => #const_name }
         }
     });
 
     // r[impl jetstream.version.framer.tmessage]
     // Add version variant for TVERSION handling
-    let version_variant = quote! {
-        Version(jetstream::prelude::Tversion) = TVERSION,
+    let version_variant = discriminant(
+        quote! { Version(jetstream::prelude::Tversion) },
+        quote! { TVERSION },
+        extended,
+    );
+
+    // r[impl jetstream.trace.envelope]
+    // Add trace context envelope for TTRACE handling
+    let traced_variant = discriminant(
+        quote! { Traced(jetstream::prelude::TraceContext, Box<#enum_name #ty>) },
+        quote! { TTRACE },
+        extended,
+    );
+
+    // r[impl jetstream.service.borrowed-args.client]
+    // Requests to methods with borrowed arguments are encoded by the client
+    // when they are made. They never arrive, so they are never decoded; the
+    // discriminant is below every message type.
+    let (
+        encoded_variant,
+        encoded_byte_size,
+        encoded_message_type,
+        encoded_encode,
+    ) = if encoded {
+        (
+            discriminant(
+                quote! { Encoded(jetstream::prelude::jetstream_rpc::Encoded) },
+                quote! { 0 },
+                extended,
+            ),
+            quote! { #enum_name::Encoded(msg) => msg.byte_size(), },
+            quote! { #enum_name::Encoded(msg) => msg.message_type(), },
+            quote! { #enum_name::Encoded(msg) => writer.write_all(msg.as_bytes())?, },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {}, quote! {})
     };
 
     let version_byte_size = quote! {
         #enum_name::Version(v) => v.byte_size()
     };
 
+    let traced_byte_size = quote! {
+        #enum_name::Traced(trace, msg) => trace.byte_size() + mem::size_of::<u8>() as u32 + Framer::byte_size(&**msg)
+    };
+
     let version_message_type = quote! {
         #enum_name::Version(_) => TVERSION
     };
 
+    let traced_message_type = quote! {
+        #enum_name::Traced(..) => TTRACE
+    };
+
     let version_encode = quote! {
         #enum_name::Version(v) => v.encode(writer)?,
     };
 
+    let traced_encode = quote! {
+        #enum_name::Traced(trace, msg) => {
+            trace.encode(writer)?;
+            msg.message_type().encode(writer)?;
+            Framer::encode(&**msg, writer)?;
+        }
+    };
+
     let version_decode = quote! {
         TVERSION => Ok(#enum_name::Version(WireFormat::decode(reader)?)),
     };
 
+    let traced_decode = quote! {
+        TTRACE => {
+            let trace: jetstream::prelude::TraceContext = WireFormat::decode(reader)?;
+            let ty: u8 = WireFormat::decode(reader)?;
+            if ty == TTRACE {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "nested trace context envelope",
+                ));
+            }
+            let msg = <#enum_name #ty as Framer>::decode(reader, ty)?;
+            Ok(#enum_name::Traced(trace, Box::new(msg)))
+        }
+    };
+
+    // r[impl jetstream.service.message-ids.extended]
+    let extended_decode = if extended {
+        quote! {
+            TEXTENDED => {
+                let id: u16 = WireFormat::decode(reader)?;
+                match id {
+                    #(
+                        #decode_bodies
+                     )*
+                    _ => Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("unknown message id: {}", id),
+                    )),
+                }
+            }
+        }
+    } else {
+        quote! { #(#decode_bodies)* }
+    };
+    let repr = if extended {
+        quote! {}
+    } else {
+        quote! { #[repr(u8)] }
+    };
+
     quote! {
         #[derive(Debug)]
-        #[repr(u8)]
-        pub enum #enum_name {
+        #repr
+        pub enum #enum_name #decl {
             #( #msg_variants )*
             #version_variant
+            #traced_variant
+            #encoded_variant
         }
 
-        impl Framer for #enum_name {
+        impl #impl_generics Framer for #enum_name #ty #where_clause {
             fn byte_size(&self) -> u32 {
                 match &self {
                     #(
                         #cloned_byte_sizes,
                      )*
                     #version_byte_size,
+                    #traced_byte_size,
+                    #encoded_byte_size
                 }
             }
 
@@ -102,6 +225,8 @@ pub fn generate_tframe(tmsgs: &[(Ident, TokenStream)]) -> TokenStream {
                         #message_type_match_arms,
                      )*
                     #version_message_type,
+                    #traced_message_type,
+                    #encoded_message_type
                 }
             }
 
@@ -111,16 +236,17 @@ pub fn generate_tframe(tmsgs: &[(Ident, TokenStream)]) -> TokenStream {
                         #encode_match_arms
                      )*
                     #version_encode
+                    #traced_encode
+                    #encoded_encode
                 }
                 Ok(())
             }
 
-            fn decode<R: std::io::Read>(reader: &mut R, ty: u8) -> std::io::Result<#enum_name> {
+            fn decode<R: std::io::Read>(reader: &mut R, ty: u8) -> std::io::Result<#enum_name #ty> {
                 match ty {
-                    #(
-                        #decode_bodies
-                     )*
+                    #extended_decode
                     #version_decode
+                    #traced_decode
                     _ => Err(std::io::Error::new(
                         std::io::ErrorKind::InvalidData,
                         format!("unknown message type: {}", ty),
@@ -131,37 +257,62 @@ pub fn generate_tframe(tmsgs: &[(Ident, TokenStream)]) -> TokenStream {
     }
 }
 
-pub fn generate_rframe(rmsgs: &[(Ident, TokenStream)]) -> TokenStream {
+pub fn generate_rframe(
+    rmsgs: &[(Ident, TokenStream)],
+    msg_params: &[Vec<Ident>],
+    params: &[Ident],
+    extended: bool,
+) -> TokenStream {
     let enum_name = quote! { Rmessage };
+    let (decl, impl_generics, ty, where_clause) = enum_generics(params);
 
     // Generate regular message variants
-    let msg_variants = rmsgs.iter().map(|(ident, _p)| {
-        let name: IdentCased = ident.into();
-        let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
-        let constant_name: Ident = name.to_screaming_snake_case().into();
-        quote! {
-            #variant_name(#ident) = #constant_name,
-        }
-    });
+    let msg_variants =
+        rmsgs
+            .iter()
+            .zip(msg_params)
+            .map(|((ident, _p), msg_params)| {
+                let name: IdentCased = ident.into();
+                let variant_name: Ident =
+                    name.remove_prefix().to_pascal_case().into();
+                let constant_name: Ident =
+                    name.to_screaming_snake_case().into();
+                let args = type_args(msg_params);
+                discriminant(
+                    quote! { #variant_name(#ident #args) },
+                    quote! { #constant_name },
+                    extended,
+                )
+            });
 
     // r[impl jetstream.error-message-frame]
     // Add error variant for RERROR handling - this is the error message type
     // for serializing errors across requests
-    let error_variant = quote! {
-        Error(jetstream::prelude::Error) = RERROR,
-    };
+    let error_variant = discriminant(
+        quote! { Error(jetstream::prelude::Error) },
+        quote! { RERROR },
+        extended,
+    );
 
     // r[impl jetstream.version.framer.rmessage]
     // Add version variant for RVERSION handling
-    let rversion_variant = quote! {
-        Version(jetstream::prelude::Rversion) = RVERSION,
-    };
+    let rversion_variant = discriminant(
+        quote! { Version(jetstream::prelude::Rversion) },
+        quote! { RVERSION },
+        extended,
+    );
 
     let cloned_byte_sizes = rmsgs.iter().map(|(ident, _)| {
         let name: IdentCased = ident.into();
         let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
-        quote! {
-            #enum_name::#variant_name(msg) => msg.byte_size()
+        if extended {
+            quote! {
+                #enum_name::#variant_name(msg) => mem::size_of::<u16>() as u32 + msg.byte_size()
+            }
+        } else {
+            quote! {
+                #enum_name::#variant_name(msg) => msg.byte_size()
+            }
         }
     });
 
@@ -174,14 +325,6 @@ pub fn generate_rframe(rmsgs: &[(Ident, TokenStream)]) -> TokenStream {
         #enum_name::Version(v) => v.byte_size()
     };
 
-    let match_arms = rmsgs.iter().map(|(ident, _)| {
-        let name: IdentCased = ident.into();
-        let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
-        quote! {
-            #enum_name::#variant_name(msg)
-        }
-    });
-
     let error_match_arm = quote! {
         #enum_name::Error(err)
     };
@@ -208,9 +351,21 @@ pub fn generate_rframe(rmsgs: &[(Ident, TokenStream)]) -> TokenStream {
         RVERSION => Ok(#enum_name::Version(WireFormat::decode(reader)?)),
     };
 
-    let encode_match_arms = match_arms.clone().map(|arm| {
-        quote! {
-            #arm => msg.encode(writer)?,
+    let encode_match_arms = rmsgs.iter().map(|(ident, _)| {
+        let name: IdentCased = ident.into();
+        let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
+        let const_name: Ident = name.to_screaming_snake_case().into();
+        if extended {
+            quote! {
+                #enum_name::#variant_name(msg) => {
+                    WireFormat::encode(&#const_name, writer)?;
+                    msg.encode(writer)?
+                }
+            }
+        } else {
+            quote! {
+                #enum_name::#variant_name(msg) => msg.encode(writer)?,
+            }
         }
     });
 
@@ -227,8 +382,10 @@ pub fn generate_rframe(rmsgs: &[(Ident, TokenStream)]) -> TokenStream {
         let name: IdentCased = ident.into();
         let variant_name: Ident = name.remove_prefix().to_pascal_case().into();
         let const_name: Ident = name.to_screaming_snake_case().into();
-        quote! {
-            #enum_name::#variant_name(_) => #const_name
+        if extended {
+            quote! { #enum_name::#variant_name(_) => REXTENDED }
+        } else {
+            quote! { #enum_name::#variant_name(_) => #const_name }
         }
     });
 
@@ -241,16 +398,41 @@ pub fn generate_rframe(rmsgs: &[(Ident, TokenStream)]) -> TokenStream {
         #enum_name::Version(_) => RVERSION
     };
 
+    // r[impl jetstream.service.message-ids.extended]
+    let extended_decode = if extended {
+        quote! {
+            REXTENDED => {
+                let id: u16 = WireFormat::decode(reader)?;
+                match id {
+                    #(
+                        #decode_bodies
+                     )*
+                    _ => Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("unknown message id: {}", id),
+                    )),
+                }
+            }
+        }
+    } else {
+        quote! { #(#decode_bodies)* }
+    };
+    let repr = if extended {
+        quote! {}
+    } else {
+        quote! { #[repr(u8)] }
+    };
+
     quote! {
         #[derive(Debug)]
-        #[repr(u8)]
-        pub enum #enum_name {
+        #repr
+        pub enum #enum_name #decl {
             #( #msg_variants )*
             #error_variant
             #rversion_variant
         }
 
-        impl Framer for #enum_name {
+        impl #impl_generics Framer for #enum_name #ty #where_clause {
 
             fn byte_size(&self) -> u32 {
                 match &self {
@@ -283,11 +465,9 @@ pub fn generate_rframe(rmsgs: &[(Ident, TokenStream)]) -> TokenStream {
                 Ok(())
             }
 
-            fn decode<R: std::io::Read>(reader: &mut R, ty: u8) -> std::io::Result<#enum_name> {
+            fn decode<R: std::io::Read>(reader: &mut R, ty: u8) -> std::io::Result<#enum_name #ty> {
                 match ty {
-                    #(
-                        #decode_bodies
-                     )*
+                    #extended_decode
                     #error_decode
                     #rversion_decode
                     _ => Err(std::io::Error::new(
@@ -299,3 +479,45 @@ pub fn generate_rframe(rmsgs: &[(Ident, TokenStream)]) -> TokenStream {
         }
     }
 }
+
+// `<..>` of a message generic over `params`.
+fn type_args(params: &[Ident]) -> TokenStream {
+    if params.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#params),*> }
+    }
+}
+
+// r[impl jetstream.service.generics]
+// The declaration, impl generics, type arguments and where clause of a
+// message enum generic over `params`.
+fn enum_generics(
+    params: &[Ident],
+) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+    if params.is_empty() {
+        return (quote! {}, quote! {}, quote! {}, quote! {});
+    }
+    let bounds = wire_bounds();
+    let args = type_args(params);
+    (
+        args.clone(),
+        args.clone(),
+        args,
+        quote! { where #(#params: #bounds),* },
+    )
+}
+
+// Services with extended message ids have more methods than a `u8`
+// discriminant can number, so their message enums have none.
+fn discriminant(
+    variant: TokenStream,
+    message_type: TokenStream,
+    extended: bool,
+) -> TokenStream {
+    if extended {
+        quote! { #variant, }
+    } else {
+        quote! { #variant = #message_type, }
+    }
+}
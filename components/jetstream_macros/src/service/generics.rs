@@ -0,0 +1,406 @@
+use proc_macro2::{Group, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::{
+    GenericParam, Ident, ItemTrait, TraitItem, TraitItemType, Type, TypeParam,
+};
+
+/// Bounds every type a generic service sends must meet.
+pub fn wire_bounds() -> TokenStream {
+    quote! {
+        jetstream::prelude::jetstream_wireformat::WireFormat
+            + jetstream::prelude::jetstream_wireformat::Describe
+            + Send + Sync + std::fmt::Debug + 'static
+    }
+}
+
+// r[impl jetstream.service.generics]
+/// The type parameters and associated types of a service trait. Messages
+/// are generic over both; an associated type is named after itself.
+#[derive(Default)]
+pub struct ServiceGenerics {
+    params: Vec<TypeParam>,
+    assoc: Vec<TraitItemType>,
+    predicates: Vec<TokenStream>,
+}
+
+impl ServiceGenerics {
+    pub fn parse(item: &ItemTrait) -> syn::Result<Self> {
+        let mut generics = ServiceGenerics::default();
+        for param in &item.generics.params {
+            match param {
+                GenericParam::Type(param) => {
+                    if let Some(default) = &param.default {
+                        return Err(syn::Error::new_spanned(
+                            default,
+                            "service type parameters cannot have defaults",
+                        ));
+                    }
+                    generics.params.push(param.clone());
+                }
+                param => {
+                    return Err(syn::Error::new_spanned(
+                        param,
+                        "services can only be generic over types, which \
+                         are sent as they are",
+                    ));
+                }
+            }
+        }
+        if let Some(where_clause) = &item.generics.where_clause {
+            generics.predicates.extend(
+                where_clause.predicates.iter().map(|pred| quote! { #pred }),
+            );
+        }
+        for item in &item.items {
+            if let TraitItem::Type(assoc) = item {
+                if !assoc.generics.params.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        &assoc.generics,
+                        "associated types of services cannot be generic",
+                    ));
+                }
+                if let Some((_, default)) = &assoc.default {
+                    return Err(syn::Error::new_spanned(
+                        default,
+                        "associated types of services cannot have defaults",
+                    ));
+                }
+                generics.assoc.push(assoc.clone());
+            }
+        }
+        Ok(generics)
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.params.is_empty() || !self.assoc.is_empty()
+    }
+
+    /// What messages can be generic over: the type parameters, then the
+    /// associated types.
+    fn message_params(&self) -> impl Iterator<Item = &Ident> {
+        self.params
+            .iter()
+            .map(|param| &param.ident)
+            .chain(self.assoc.iter().map(|assoc| &assoc.ident))
+    }
+
+    /// The message parameters appearing in `tokens`, in declaration order.
+    pub fn used(&self, tokens: &TokenStream) -> Vec<Ident> {
+        let mut found = Vec::new();
+        collect_idents(tokens.clone(), &mut found);
+        self.message_params()
+            .filter(|param| found.contains(param))
+            .cloned()
+            .collect()
+    }
+
+    /// Rewrites `Self::Assoc` in `ty` to the message parameter `Assoc`.
+    pub fn strip_self(&self, ty: &Type) -> Type {
+        if self.assoc.is_empty() {
+            return ty.clone();
+        }
+        let assoc = self.assoc.iter().map(|a| &a.ident).collect::<Vec<_>>();
+        let tokens = strip_self(quote! { #ty }, &assoc);
+        syn::parse2(tokens).unwrap_or_else(|_| ty.clone())
+    }
+
+    /// The parameters any of `used` contains, in declaration order.
+    pub fn union(&self, used: &[Vec<Ident>]) -> Vec<Ident> {
+        self.message_params()
+            .filter(|param| used.iter().any(|u| u.contains(param)))
+            .cloned()
+            .collect()
+    }
+
+    /// The parameters no message uses. They would never reach the wire,
+    /// so two instantiations differing in them could not be told apart.
+    pub fn unused<'a>(
+        &'a self,
+        used: &'a [Vec<Ident>],
+    ) -> impl Iterator<Item = &'a Ident> {
+        self.message_params()
+            .filter(move |param| !used.iter().any(|u| u.contains(param)))
+    }
+
+    /// Bounds on the type parameters: the trait's own and the wire bounds.
+    fn param_predicates(&self) -> Vec<TokenStream> {
+        let wire = wire_bounds();
+        let mut predicates = self
+            .params
+            .iter()
+            .map(|param| {
+                let ident = &param.ident;
+                let bounds = &param.bounds;
+                if bounds.is_empty() {
+                    quote! { #ident: #wire }
+                } else {
+                    quote! { #ident: #bounds + #wire }
+                }
+            })
+            .collect::<Vec<_>>();
+        predicates.extend(self.predicates.iter().cloned());
+        predicates
+    }
+
+    /// A parameter name for a generated type that doesn't shadow one of
+    /// the trait's.
+    pub fn fresh(&self, name: &str) -> Ident {
+        let mut ident = format_ident!("{}", name);
+        while self.message_params().any(|param| *param == ident) {
+            ident = format_ident!("{}_", ident);
+        }
+        ident
+    }
+
+    /// How `{Trait}Service<T>` instantiates the generics: associated types
+    /// are the ones of the served `T`.
+    pub fn service(
+        &self,
+        trait_name: &Ident,
+        tparams: &[Ident],
+        rparams: &[Ident],
+    ) -> Instance {
+        let inner = self.fresh("T");
+        let trait_ref = self.trait_ref(trait_name);
+        let params = self.params.iter().map(|param| &param.ident);
+        let wire = wire_bounds();
+        let assoc_ty =
+            |ident: &Ident| quote! { <#inner as #trait_ref>::#ident };
+        let mut predicates =
+            vec![quote! { #inner: #trait_ref + Send + Sync + Sized }];
+        predicates.extend(self.param_predicates());
+        predicates.extend(self.assoc.iter().map(|assoc| {
+            let ty = assoc_ty(&assoc.ident);
+            quote! { #ty: #wire }
+        }));
+        let arg = |ident: &Ident| {
+            if self.assoc.iter().any(|assoc| assoc.ident == *ident) {
+                assoc_ty(ident)
+            } else {
+                quote! { #ident }
+            }
+        };
+        Instance {
+            generic: self.is_generic(),
+            params: quote! { #inner #(, #params)* },
+            free: inner.clone(),
+            predicates,
+            marker: self.marker(self.params.iter().map(|param| &param.ident)),
+            trait_ref: trait_ref.clone(),
+            assoc_types: self
+                .assoc
+                .iter()
+                .map(|assoc| {
+                    let ident = &assoc.ident;
+                    let ty = assoc_ty(ident);
+                    quote! { type #ident = #ty; }
+                })
+                .collect(),
+            described: self.message_params().map(arg).collect(),
+            tmessage: message_type(quote! { Tmessage }, tparams, arg),
+            rmessage: message_type(quote! { Rmessage }, rparams, arg),
+        }
+    }
+
+    /// How `{Trait}Channel` instantiates the generics: it takes a parameter
+    /// for each associated type too.
+    pub fn channel(
+        &self,
+        trait_name: &Ident,
+        tparams: &[Ident],
+        rparams: &[Ident],
+    ) -> Instance {
+        let params = self.message_params().collect::<Vec<_>>();
+        let wire = wire_bounds();
+        let mut predicates = self.param_predicates();
+        predicates.extend(self.assoc.iter().map(|assoc| {
+            let ident = &assoc.ident;
+            let bounds = &assoc.bounds;
+            if bounds.is_empty() {
+                quote! { #ident: #wire }
+            } else {
+                quote! { #ident: #bounds + #wire }
+            }
+        }));
+        let arg = |ident: &Ident| quote! { #ident };
+        Instance {
+            generic: self.is_generic(),
+            params: quote! { #(#params),* },
+            free: self.fresh("E"),
+            predicates,
+            marker: self.marker(params.iter().copied()),
+            trait_ref: self.trait_ref(trait_name),
+            assoc_types: self
+                .assoc
+                .iter()
+                .map(|assoc| {
+                    let ident = &assoc.ident;
+                    quote! { type #ident = #ident; }
+                })
+                .collect(),
+            described: params.iter().map(|param| arg(param)).collect(),
+            tmessage: message_type(quote! { Tmessage }, tparams, arg),
+            rmessage: message_type(quote! { Rmessage }, rparams, arg),
+        }
+    }
+
+    /// `Trait<K, V>`.
+    fn trait_ref(&self, trait_name: &Ident) -> TokenStream {
+        if self.params.is_empty() {
+            return quote! { #trait_name };
+        }
+        let params = self.params.iter().map(|param| &param.ident);
+        quote! { #trait_name<#(#params),*> }
+    }
+
+    fn marker<'a>(
+        &self,
+        params: impl Iterator<Item = &'a Ident>,
+    ) -> Option<TokenStream> {
+        let params = params.collect::<Vec<_>>();
+        if params.is_empty() {
+            return None;
+        }
+        Some(quote! { std::marker::PhantomData<fn() -> (#(#params,)*)> })
+    }
+}
+
+/// The generics of a generated service or channel.
+pub struct Instance {
+    pub generic: bool,
+    /// Parameters of the generated type, without bounds.
+    pub params: TokenStream,
+    /// A parameter not shadowing the trait's: the served type of a service
+    /// and the endpoint of a balanced channel.
+    pub free: Ident,
+    pub predicates: Vec<TokenStream>,
+    /// Type of the field holding parameters no other field uses.
+    pub marker: Option<TokenStream>,
+    /// The implemented trait, with its type arguments.
+    pub trait_ref: TokenStream,
+    /// Associated types of the trait implementation.
+    pub assoc_types: Vec<TokenStream>,
+    /// The types the protocol name is made of.
+    pub described: Vec<TokenStream>,
+    pub tmessage: TokenStream,
+    pub rmessage: TokenStream,
+}
+
+impl Instance {
+    /// `<..>` of the generated type, in both its impls and its uses.
+    pub fn generics(&self) -> TokenStream {
+        if self.params.is_empty() {
+            return quote! {};
+        }
+        let params = &self.params;
+        quote! { <#params> }
+    }
+
+    pub fn where_clause(&self) -> TokenStream {
+        if self.predicates.is_empty() {
+            return quote! {};
+        }
+        let predicates = &self.predicates;
+        quote! { where #(#predicates),* }
+    }
+
+    // r[impl jetstream.service.generics.name]
+    /// Overrides of the protocol name and version naming the instantiation.
+    pub fn protocol_fns(&self) -> TokenStream {
+        if !self.generic {
+            return quote! {};
+        }
+        let described = &self.described;
+        let params = quote! {
+            &[#(<#described as jetstream::prelude::jetstream_wireformat::Describe>::describe()),*]
+        };
+        quote! {
+            fn protocol_name() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Owned(instance_name(#params))
+            }
+
+            fn protocol_version() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Owned(instance_version(#params))
+            }
+        }
+    }
+}
+
+/// Helpers naming instantiations of a generic service after their type
+/// arguments, e.g. `store-u64.String_`.
+pub fn instance_fns() -> TokenStream {
+    quote! {
+        /// Protocol name of the instantiation with the described type
+        /// arguments.
+        pub fn instance_name(params: &[String]) -> String {
+            jetstream::prelude::jetstream_rpc::name::instance(PROTOCOL_NAME, params)
+        }
+
+        /// Protocol version string of the instantiation with the described
+        /// type arguments.
+        pub fn instance_version(params: &[String]) -> String {
+            PROTOCOL_VERSION.replacen(
+                &format!("/{}/", PROTOCOL_NAME),
+                &format!("/{}/", instance_name(params)),
+                1,
+            )
+        }
+    }
+}
+
+/// `Tmessage<..>` with the arguments for `params`.
+fn message_type(
+    name: TokenStream,
+    params: &[Ident],
+    arg: impl Fn(&Ident) -> TokenStream,
+) -> TokenStream {
+    if params.is_empty() {
+        return name;
+    }
+    let args = params.iter().map(arg);
+    quote! { #name<#(#args),*> }
+}
+
+fn collect_idents(tokens: TokenStream, found: &mut Vec<Ident>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => found.push(ident),
+            TokenTree::Group(group) => collect_idents(group.stream(), found),
+            _ => {}
+        }
+    }
+}
+
+fn strip_self(tokens: TokenStream, assoc: &[&Ident]) -> TokenStream {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut index = 0;
+    while index < tokens.len() {
+        if let [TokenTree::Ident(this), TokenTree::Punct(a), TokenTree::Punct(b), TokenTree::Ident(ident), ..] =
+            &tokens[index..]
+        {
+            if this == "Self"
+                && a.as_char() == ':'
+                && b.as_char() == ':'
+                && assoc.contains(&ident)
+            {
+                out.push(TokenTree::Ident(ident.clone()));
+                index += 4;
+                continue;
+            }
+        }
+        match &tokens[index] {
+            TokenTree::Group(group) => {
+                let mut stripped = Group::new(
+                    group.delimiter(),
+                    strip_self(group.stream(), assoc),
+                );
+                stripped.set_span(group.span());
+                out.push(TokenTree::Group(stripped));
+            }
+            token => out.push(token.clone()),
+        }
+        index += 1;
+    }
+    out.into_iter().collect()
+}
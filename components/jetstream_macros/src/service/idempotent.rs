@@ -0,0 +1,26 @@
+use syn::{Attribute, TraitItemFn};
+
+pub fn is_idempotent_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("idempotent")
+}
+
+/// Whether a trait method is marked `#[idempotent]`.
+pub fn is_idempotent(method: &TraitItemFn) -> syn::Result<bool> {
+    let mut idempotent = false;
+    for attr in method.attrs.iter().filter(|attr| is_idempotent_attr(attr)) {
+        if idempotent {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate #[idempotent] attribute",
+            ));
+        }
+        if !matches!(attr.meta, syn::Meta::Path(_)) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "#[idempotent] takes no arguments",
+            ));
+        }
+        idempotent = true;
+    }
+    Ok(idempotent)
+}
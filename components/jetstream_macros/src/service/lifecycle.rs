@@ -0,0 +1,139 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use syn::{Attribute, Expr, Lit, LitStr, Meta, TraitItemFn};
+
+pub fn is_since_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("since")
+}
+
+// r[impl jetstream.service.since]
+/// The version a trait method is marked `#[since("1.3.0")]` with, as its
+/// major, minor and patch.
+pub fn since(method: &TraitItemFn) -> syn::Result<Option<[u64; 3]>> {
+    let mut since = None;
+    for attr in method.attrs.iter().filter(|attr| is_since_attr(attr)) {
+        if since.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate #[since] attribute",
+            ));
+        }
+        let version: LitStr = attr.parse_args()?;
+        let parts = version
+            .value()
+            .split('.')
+            .map(str::parse::<u64>)
+            .collect::<Result<Vec<_>, _>>();
+        match parts.as_deref() {
+            Ok(&[major, minor, patch]) => since = Some([major, minor, patch]),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    version,
+                    "expected a version such as #[since(\"1.3.0\")]",
+                ))
+            }
+        }
+    }
+    Ok(since)
+}
+
+// r[impl jetstream.service.deprecated]
+/// The note of a trait method's `#[deprecated]` attribute, `Some(None)` if
+/// it is deprecated without one.
+pub fn deprecation(method: &TraitItemFn) -> Option<Option<LitStr>> {
+    let attr = method
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("deprecated"))?;
+    let note = match &attr.meta {
+        Meta::NameValue(meta) => match &meta.value {
+            Expr::Lit(expr) => match &expr.lit {
+                Lit::Str(note) => Some(note.clone()),
+                _ => None,
+            },
+            _ => None,
+        },
+        Meta::List(_) => {
+            let mut note = None;
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("note") {
+                    note = Some(meta.value()?.parse()?);
+                } else {
+                    // `since = "..."`
+                    meta.value()?.parse::<LitStr>()?;
+                }
+                Ok(())
+            });
+            note
+        }
+        Meta::Path(_) => None,
+    };
+    Some(note)
+}
+
+/// Counts a server's calls to a `#[deprecated]` method, the `index`th in
+/// its protocol module's `DEPRECATED`.
+pub fn record(deprecation: Option<usize>, body: TokenStream) -> TokenStream {
+    match deprecation.map(Literal::usize_unsuffixed) {
+        Some(index) => quote! {
+            {
+                DEPRECATED[#index].record();
+                #body
+            }
+        },
+        None => body,
+    }
+}
+
+// r[impl jetstream.service.since.server]
+/// Wraps a server match arm body in the check of its method's `#[since]`.
+pub fn guard(
+    method_name: &syn::Ident,
+    since: Option<[u64; 3]>,
+    body: TokenStream,
+) -> TokenStream {
+    let Some(since) = since else {
+        return body;
+    };
+    let since = since_literal(method_name, since);
+    quote! {
+        {
+            let since = #since;
+            match since.check_context(&ctx) {
+                Ok(()) => #body,
+                Err(err) => Err(err),
+            }
+        }
+    }
+}
+
+// r[impl jetstream.service.since.client]
+/// The client side of `#[since]`: refuses the call if the channel `chan`
+/// negotiated a version predating the method.
+pub fn client_check(
+    method_name: &syn::Ident,
+    since: Option<[u64; 3]>,
+    chan: TokenStream,
+) -> TokenStream {
+    let Some(since) = since else {
+        return quote! {};
+    };
+    let since = since_literal(method_name, since);
+    quote! {
+        #chan.negotiated.check(&#since)?;
+    }
+}
+
+fn since_literal(method_name: &syn::Ident, since: [u64; 3]) -> TokenStream {
+    let method = method_name.to_string();
+    let [major, minor, patch] = since.map(Literal::u64_unsuffixed);
+    quote! {
+        jetstream::prelude::jetstream_rpc::lifecycle::Since {
+            protocol: PROTOCOL_NAME,
+            method: #method,
+            major: #major,
+            minor: #minor,
+            patch: #patch,
+        }
+    }
+}
@@ -2,6 +2,10 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Ident, Signature};
 
+use jetstream_codegen::message_ids::MessageIds;
+
+use super::{borrowed::owned_type, generics::ServiceGenerics};
+
 pub fn request_struct_name(method_name: &Ident) -> Ident {
     Ident::new(&format!("T{}", method_name), method_name.span())
 }
@@ -10,7 +14,13 @@ pub fn return_struct_name(method_name: &Ident) -> Ident {
     Ident::new(&format!("R{}", method_name), method_name.span())
 }
 
-pub fn generate_msg_id(index: usize, method_name: &Ident) -> TokenStream {
+pub fn generate_msg_id(
+    index: usize,
+    method_name: &Ident,
+    ids: MessageIds,
+    positional: bool,
+    extended: bool,
+) -> TokenStream {
     let upper_cased_method_name = method_name.to_string().to_uppercase();
     let tmsg_const_name = Ident::new(
         &format!("T{}", upper_cased_method_name),
@@ -20,6 +30,23 @@ pub fn generate_msg_id(index: usize, method_name: &Ident) -> TokenStream {
         &format!("R{}", upper_cased_method_name),
         method_name.span(),
     );
+
+    if extended {
+        let request = ids.request;
+        let response = ids.response;
+        return quote! {
+            pub const #tmsg_const_name: u16 = #request;
+            pub const #rmsg_const_name: u16 = #response;
+        };
+    }
+    if !positional {
+        let request = ids.request as u8;
+        let response = ids.response as u8;
+        return quote! {
+            pub const #tmsg_const_name: u8 = #request;
+            pub const #rmsg_const_name: u8 = #response;
+        };
+    }
     let offset = 2 * index as u8;
 
     quote! {
@@ -28,10 +55,13 @@ pub fn generate_msg_id(index: usize, method_name: &Ident) -> TokenStream {
     }
 }
 
+/// The request message of a method, and the parameters of the service it
+/// is generic over.
 pub fn generate_input_struct(
     request_struct_ident: &Ident,
     method_sig: &Signature,
-) -> TokenStream {
+    generics: &ServiceGenerics,
+) -> (TokenStream, Vec<Ident>) {
     let inputs = method_sig.inputs.iter().map(|arg| match arg {
         syn::FnArg::Typed(pat) => {
             let name = pat.pat.clone();
@@ -44,25 +74,68 @@ pub fn generate_input_struct(
                     }
                 }
             }
+            // Borrowed arguments are received owned; `service_impl` has
+            // already rejected the ones that can't be.
+            let ty = match owned_type(&ty) {
+                Ok(Some(owned)) => owned,
+                _ => *ty,
+            };
+            let ty = generics.strip_self(&ty);
             quote! {
                 pub #name: #ty,
             }
         }
         syn::FnArg::Receiver(_) => quote! {},
     });
+    let inputs = inputs.collect::<Vec<_>>();
+    let params = generics.used(&quote! { #(#inputs)* });
+    let decl = if params.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#params),*> }
+    };
 
-    quote! {
+    let def = quote! {
         #[allow(non_camel_case_types)]
         #[derive(Debug, JetStreamWireFormat)]
-        pub struct #request_struct_ident {
+        pub struct #request_struct_ident #decl {
             #(#inputs)*
         }
-    }
+    };
+    (def, params)
 }
 
+/// The response message of a method, and the parameters of the service it
+/// is generic over.
 pub fn generate_return_struct(
     return_struct_ident: &Ident,
     method_sig: &Signature,
+    generics: &ServiceGenerics,
+) -> (TokenStream, Vec<Ident>) {
+    let (sig, params) = match &method_sig.output {
+        syn::ReturnType::Type(arrow, ty) => {
+            let ty = generics.strip_self(ty);
+            let params = generics.used(&quote! { #ty });
+            let sig = Signature {
+                output: syn::ReturnType::Type(*arrow, Box::new(ty)),
+                ..method_sig.clone()
+            };
+            (sig, params)
+        }
+        syn::ReturnType::Default => (method_sig.clone(), Vec::new()),
+    };
+    let decl = if params.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#params),*> }
+    };
+    (return_struct(return_struct_ident, &sig, decl), params)
+}
+
+fn return_struct(
+    return_struct_ident: &Ident,
+    method_sig: &Signature,
+    decl: TokenStream,
 ) -> TokenStream {
     match &method_sig.output {
         syn::ReturnType::Type(_, ty) => {
@@ -82,7 +155,7 @@ pub fn generate_return_struct(
                                     return quote! {
                                         #[allow(non_camel_case_types)]
                                         #[derive(Debug, JetStreamWireFormat)]
-                                        pub struct #return_struct_ident(pub #success_type);
+                                        pub struct #return_struct_ident #decl(pub #success_type);
                                     };
                                 }
                             }
@@ -92,7 +165,7 @@ pub fn generate_return_struct(
                     quote! {
                         #[allow(non_camel_case_types)]
                         #[derive(Debug, JetStreamWireFormat)]
-                        pub struct #return_struct_ident(pub #ty);
+                        pub struct #return_struct_ident #decl(pub #ty);
                     }
                 }
                 // Handle other return type variants if needed
@@ -100,7 +173,7 @@ pub fn generate_return_struct(
                     quote! {
                         #[allow(non_camel_case_types)]
                         #[derive(Debug, JetStreamWireFormat)]
-                        pub struct #return_struct_ident(pub #ty);
+                        pub struct #return_struct_ident #decl(pub #ty);
                     }
                 }
             }
@@ -0,0 +1,185 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use syn::{FnArg, Ident, ReturnType, TraitItem, Type};
+
+use super::borrowed::owned_type;
+
+fn is_context(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Context"),
+        _ => false,
+    }
+}
+
+// r[impl jetstream.mock.macro]
+/// Generates `Mock{Trait}`, a clonable implementation of the service trait
+/// whose methods answer from expectations set on it.
+pub fn generate_mock(
+    trait_name: &Ident,
+    service_name: &Ident,
+    trait_items: &[TraitItem],
+    is_async_trait: bool,
+) -> TokenStream {
+    let mock_name = format_ident!("Mock{}", trait_name);
+    let mut fields = Vec::new();
+    let mut inits = Vec::new();
+    let mut accessors = Vec::new();
+    let mut verifies = Vec::new();
+    let mut failures = Vec::new();
+    let mut impls = Vec::new();
+
+    for item in trait_items {
+        let TraitItem::Fn(method) = item else {
+            continue;
+        };
+        let method_name = &method.sig.ident;
+        let expect = format_ident!("expect_{}", method_name);
+        let calls = format_ident!("{}_calls", method_name);
+        let full_name = Literal::string(&format!("{mock_name}::{method_name}"));
+
+        let mut inputs = Vec::new();
+        let mut arg_names = Vec::new();
+        let mut arg_types = Vec::new();
+        for arg in &method.sig.inputs {
+            match arg {
+                FnArg::Receiver(recv) => {
+                    if recv.mutability.is_some() {
+                        inputs.push(quote! { &mut self });
+                    } else {
+                        inputs.push(quote! { &self });
+                    }
+                }
+                FnArg::Typed(pat) => {
+                    let name = &pat.pat;
+                    let ty = &pat.ty;
+                    if is_context(ty) {
+                        inputs.push(quote! { #[allow(unused)] #name: #ty });
+                    } else if let Ok(Some(owned)) = owned_type(ty) {
+                        // Expectations see borrowed arguments owned.
+                        inputs.push(quote! { #name: #ty });
+                        arg_names.push(quote! { ToOwned::to_owned(#name) });
+                        arg_types.push(owned);
+                    } else {
+                        inputs.push(quote! { #name: #ty });
+                        arg_names.push(quote! { #name });
+                        arg_types.push((**ty).clone());
+                    }
+                }
+            }
+        }
+        let (args, args_ty) = match arg_names.len() {
+            0 => (quote! { () }, quote! { () }),
+            1 => (quote! { #(#arg_names)* }, quote! { #(#arg_types)* }),
+            _ => (quote! { (#(#arg_names),*) }, quote! { (#(#arg_types),*) }),
+        };
+        let (retn, output) = match &method.sig.output {
+            ReturnType::Type(_, ty) => (quote! { -> #ty }, quote! { #ty }),
+            ReturnType::Default => (quote! {}, quote! { () }),
+        };
+        let method_ty = quote! {
+            jetstream::prelude::jetstream_rpc::mock::MockMethod<#args_ty, #output>
+        };
+        let maybe_async = method.sig.asyncness;
+
+        fields.push(quote! { #method_name: #method_ty, });
+        inits.push(quote! {
+            #method_name: jetstream::prelude::jetstream_rpc::mock::MockMethod::new(#full_name),
+        });
+        accessors.push(quote! {
+            /// Adds an expectation for calls to this method.
+            pub fn #expect(&self) -> jetstream::prelude::jetstream_rpc::mock::Expectation<#args_ty, #output> {
+                self.#method_name.expect()
+            }
+
+            /// Calls made to this method so far.
+            pub fn #calls(&self) -> usize {
+                self.#method_name.calls()
+            }
+        });
+        verifies.push(quote! { self.#method_name.verify(), });
+        failures.push(quote! { self.#method_name.take_failure(), });
+        impls.push(quote! {
+            #maybe_async fn #method_name(#(#inputs),*) #retn {
+                if self.served {
+                    self.#method_name.serve(#args)
+                } else {
+                    self.#method_name.call(#args)
+                }
+            }
+        });
+    }
+
+    let trait_attribute = if is_async_trait {
+        quote! { #[jetstream::prelude::async_trait] }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        /// A mock of the service. Clones share their expectations.
+        #[derive(Clone, Debug)]
+        pub struct #mock_name {
+            #(#fields)*
+            served: bool,
+        }
+
+        impl Default for #mock_name {
+            fn default() -> Self {
+                Self {
+                    #(#inits)*
+                    served: false,
+                }
+            }
+        }
+
+        // r[impl jetstream.mock.served]
+        impl Drop for #mock_name {
+            /// Panics with the first served call no expectation answered,
+            /// unless `verify` reported it already.
+            fn drop(&mut self) {
+                if self.served || std::thread::panicking() {
+                    return;
+                }
+                if let Some(failure) = [#(#failures)*].into_iter().flatten().next() {
+                    panic!("{}", failure);
+                }
+            }
+        }
+
+        impl #mock_name {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            #(#accessors)*
+
+            /// Panics unless every expectation limited with `times` was met
+            /// and every call matched an expectation.
+            pub fn verify(&self) {
+                for result in [#(#verifies)*] {
+                    if let Err(err) = result {
+                        panic!("{}", err);
+                    }
+                }
+            }
+
+            // r[impl jetstream.mock.service]
+            /// Serves the mock through the generated server, so requests go
+            /// through a real transport and codec.
+            pub fn service(&self) -> #service_name<Self> {
+                let mut mock = self.clone();
+                mock.served = true;
+                #service_name { inner: mock }
+            }
+        }
+
+        #trait_attribute
+        impl #trait_name for #mock_name {
+            #(#impls)*
+        }
+    }
+}
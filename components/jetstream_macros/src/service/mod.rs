@@ -1,6 +1,15 @@
+mod authorize;
+mod blocking;
+mod borrowed;
 mod client;
+mod compose;
 mod frame;
+mod generics;
+mod idempotent;
+mod lifecycle;
 mod message;
+mod mock;
+mod oneway;
 mod server;
 mod tests;
 mod tests_tracing;
@@ -11,53 +20,10 @@ use quote::{format_ident, quote, ToTokens};
 
 use syn::{ItemTrait, TraitItem};
 
-use crate::service::tracing::take_attributes;
+use jetstream_codegen::message_ids::{self, IdOptions, IdScheme, MethodIds};
+pub(crate) use jetstream_codegen::service_attr::ServiceAttr;
 
-mod kw {
-    syn::custom_keyword!(uses);
-    syn::custom_keyword!(tracing);
-    syn::custom_keyword!(async_trait);
-}
-
-/// Parsed service attribute arguments
-#[derive(Default)]
-pub(crate) struct ServiceAttr {
-    pub use_paths: Vec<syn::UseTree>,
-    pub enable_tracing: bool,
-    pub is_async_trait: bool,
-}
-
-impl syn::parse::Parse for ServiceAttr {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut attr = ServiceAttr::default();
-
-        while !input.is_empty() {
-            let lookahead = input.lookahead1();
-            if lookahead.peek(kw::uses) {
-                input.parse::<kw::uses>()?;
-                let content;
-                syn::parenthesized!(content in input);
-                let trees = content
-                    .parse_terminated(syn::UseTree::parse, syn::Token![,])?;
-                attr.use_paths.extend(trees);
-            } else if lookahead.peek(kw::tracing) {
-                input.parse::<kw::tracing>()?;
-                attr.enable_tracing = true;
-            } else if lookahead.peek(kw::async_trait) {
-                input.parse::<kw::async_trait>()?;
-                attr.is_async_trait = true;
-            } else {
-                return Err(lookahead.error());
-            }
-            // Trailing comma is optional
-            if input.peek(syn::Token![,]) {
-                input.parse::<syn::Token![,]>()?;
-            }
-        }
-
-        Ok(attr)
-    }
-}
+use crate::service::{generics::ServiceGenerics, tracing::take_attributes};
 
 /// Parses service attribute arguments
 pub(crate) fn parse_service_attr(attr: TokenStream) -> ServiceAttr {
@@ -65,18 +31,98 @@ pub(crate) fn parse_service_attr(attr: TokenStream) -> ServiceAttr {
         return ServiceAttr::default();
     }
 
-    syn::parse2::<ServiceAttr>(attr).unwrap_or_default()
+    let mut parsed =
+        syn::parse2::<ServiceAttr>(attr.clone()).unwrap_or_default();
+    parsed.tokens = attr;
+    parsed
 }
 
-pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
+pub(crate) fn service_impl(
+    mut item: ItemTrait,
+    attr: ServiceAttr,
+) -> TokenStream {
     let ServiceAttr {
         use_paths,
         enable_tracing,
         is_async_trait,
+        mut ids,
+        mock,
+        blocking,
+        name,
+        tokens,
     } = attr;
+    // r[impl jetstream.service.compose]
+    let bounds = match compose::service_bounds(&item) {
+        Ok(bounds) => bounds,
+        Err(err) => return err.to_compile_error(),
+    };
+    match compose::request(&item, &bounds, &tokens) {
+        Ok(Some(request)) => return request,
+        Ok(None) => {}
+        Err(err) => return err.to_compile_error(),
+    }
+    // The digest covers the inherited methods too.
+    let source = item.to_token_stream().to_string();
+    let inherited = match compose::take_inherited(&mut item) {
+        Ok(inherited) => inherited,
+        Err(err) => return err.to_compile_error(),
+    };
+    let item = item;
     let trait_name = &item.ident;
-    let maps = take_attributes(
+    let generics = match ServiceGenerics::parse(&item) {
+        Ok(generics) => generics,
+        Err(err) => return err.to_compile_error(),
+    };
+    if generics.is_generic() && (mock || blocking) {
+        return syn::Error::new_spanned(
+            &item.ident,
+            "`mock` and `blocking` are not supported on generic services",
+        )
+        .to_compile_error();
+    }
+    if !inherited.is_empty() {
+        if generics.is_generic() {
+            return syn::Error::new_spanned(
+                &item.ident,
+                "generic services cannot be composed of other services",
+            )
+            .to_compile_error();
+        }
+        if mock || blocking {
+            return syn::Error::new_spanned(
+                &item.ident,
+                "`mock` and `blocking` are not supported on composed services",
+            )
+            .to_compile_error();
+        }
+        // r[impl jetstream.service.compose.ids]
+        // Hashing qualified names keeps every method's id when any of the
+        // traits gains or loses methods.
+        ids = IdOptions {
+            scheme: IdScheme::Hashed,
+            extended: true,
+        };
+    }
+    let methods_export =
+        compose::export(&item, &inherited, generics.is_generic());
+    // Associated types are declared before the methods; the generators only
+    // see the methods, so they are numbered as before.
+    let assoc_items = item
+        .items
+        .iter()
+        .filter(|item| matches!(item, TraitItem::Type(_)))
+        .collect::<Vec<_>>();
+    let (fn_items, composition) = compose::compose(
+        &item.ident,
+        &inherited,
         item.items
+            .iter()
+            .filter(|item| matches!(item, TraitItem::Fn(_)))
+            .cloned()
+            .collect(),
+    );
+    let mut maps = take_attributes(
+        fn_items
             .iter()
             .flat_map(|i| match i {
                 TraitItem::Fn(trait_item_fn) => Some(trait_item_fn.clone()),
@@ -85,41 +131,165 @@ pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
             .collect::<Vec<_>>()
             .as_slice(),
     );
-    let trait_items = maps.iter().map(|(item, _)| item).collect::<Vec<_>>();
+    for (item, _) in maps.iter_mut() {
+        item.attrs.retain(|attr| {
+            !authorize::is_authorize_attr(attr)
+                && !idempotent::is_idempotent_attr(attr)
+                && !oneway::is_oneway_attr(attr)
+                && !message_ids::is_id_attr(attr)
+                && !lifecycle::is_since_attr(attr)
+        });
+    }
+    let trait_items = maps[composition.own..]
+        .iter()
+        .map(|(item, _)| item)
+        .collect::<Vec<_>>();
     let vis = &item.vis;
 
     // Generate protocol metadata
     let service_name = format_ident!("{}Service", trait_name);
     let channel_name = format_ident!("{}Channel", trait_name);
-    let digest = Literal::string(
-        sha256::digest(item.to_token_stream().to_string()).as_str(),
-    );
+    let digest = Literal::string(sha256::digest(&source).as_str());
+
+    // r[impl jetstream.service.message-ids]
+    let mut methods = Vec::new();
+    for (position, item) in fn_items.iter().enumerate() {
+        if let TraitItem::Fn(method) = item {
+            let name = method.sig.ident.to_string();
+            if let Some(path) = composition.declared_by(position) {
+                // Pinned ids belong to the supertrait's own protocol.
+                let supertrait = &path.segments.last().unwrap().ident;
+                methods.push(MethodIds {
+                    name: format!("{supertrait}::{name}"),
+                    position,
+                    explicit: None,
+                });
+                continue;
+            }
+            let name = if composition.is_composed() {
+                format!("{trait_name}::{name}")
+            } else {
+                name
+            };
+            match message_ids::explicit_id(&method.attrs) {
+                Ok(explicit) => methods.push(MethodIds {
+                    name,
+                    position,
+                    explicit,
+                }),
+                Err(err) => return err.to_compile_error(),
+            }
+        }
+    }
+    let method_ids = match message_ids::assign(&methods, ids) {
+        Ok(method_ids) => method_ids,
+        Err((index, message)) => {
+            let method = fn_items
+                .iter()
+                .filter_map(|item| match item {
+                    TraitItem::Fn(method) => Some(&method.sig.ident),
+                    _ => None,
+                })
+                .nth(index);
+            return syn::Error::new_spanned(method, message).to_compile_error();
+        }
+    };
 
     // Generate message structs and collect metadata
     let mut tmsgs = Vec::new();
     let mut rmsgs = Vec::new();
     let mut msg_ids = Vec::new();
     let mut method_attrs = Vec::new();
-
-    for (index, item) in item.items.iter().enumerate() {
+    let mut method_roles = Vec::new();
+    let mut idempotent_methods = Vec::new();
+    let mut oneway_methods = Vec::new();
+    let mut method_since = Vec::new();
+    let mut deprecated_methods = Vec::new();
+    let mut deprecations = Vec::new();
+    let mut method_digests = Vec::new();
+    let mut tmsg_params = Vec::new();
+    let mut rmsg_params = Vec::new();
+
+    for (index, item) in fn_items.iter().enumerate() {
         if let TraitItem::Fn(method) = item {
             let method_name = &method.sig.ident;
+            let method_index = tmsgs.len();
+            let positional = methods[method_index].explicit.is_none()
+                && ids.scheme == IdScheme::Positional;
 
             let request_struct_ident =
                 message::request_struct_name(method_name);
             let return_struct_ident = message::return_struct_name(method_name);
 
-            let msg_id = message::generate_msg_id(index, method_name);
+            let msg_id = message::generate_msg_id(
+                index,
+                method_name,
+                method_ids[method_index],
+                positional,
+                ids.extended,
+            );
             msg_ids.push(msg_id);
 
-            let request_struct = message::generate_input_struct(
-                &request_struct_ident,
-                &method.sig,
-            );
-            let return_struct = message::generate_return_struct(
-                &return_struct_ident,
-                &method.sig,
-            );
+            for arg in &method.sig.inputs {
+                if let syn::FnArg::Typed(pat) = arg {
+                    if let Err(err) = borrowed::owned_type(&pat.ty) {
+                        return err.to_compile_error();
+                    }
+                }
+            }
+            let (request_struct, request_params) =
+                message::generate_input_struct(
+                    &request_struct_ident,
+                    &method.sig,
+                    &generics,
+                );
+            let (return_struct, return_params) =
+                message::generate_return_struct(
+                    &return_struct_ident,
+                    &method.sig,
+                    &generics,
+                );
+            tmsg_params.push(request_params);
+            rmsg_params.push(return_params);
+
+            // r[impl jetstream.schema.method-digest]
+            // Only what reaches the wire: the message id and the fields of
+            // both messages. Positional ids hash the index they come from.
+            let wire_id = if positional {
+                index
+            } else {
+                method_ids[method_index].request as usize
+            };
+            // Oneway calls get no response, so a method that became oneway
+            // is a different method.
+            let oneway = match oneway::is_oneway(method) {
+                Ok(oneway) => oneway,
+                Err(err) => return err.to_compile_error(),
+            };
+            let wire =
+                format!("{} {} {}", wire_id, request_struct, return_struct);
+            let wire = if oneway {
+                format!("{wire} oneway")
+            } else {
+                wire
+            };
+            // As many hex characters as `schema::METHOD_DIGEST_LEN`.
+            let method_digest = Literal::string(&sha256::digest(wire)[0..16]);
+            let method_name_lit = Literal::string(&method_name.to_string());
+            let tmsg_const =
+                format_ident!("T{}", method_name.to_string().to_uppercase());
+            let method_id = if ids.extended {
+                quote! { #tmsg_const }
+            } else {
+                quote! { #tmsg_const as u16 }
+            };
+            method_digests.push(quote! {
+                jetstream::prelude::jetstream_rpc::schema::MethodDigest {
+                    name: #method_name_lit,
+                    id: #method_id,
+                    digest: #method_digest,
+                }
+            });
 
             tmsgs.push((request_struct_ident, request_struct));
             rmsgs.push((return_struct_ident, return_struct));
@@ -127,34 +297,140 @@ pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
             // Collect tracing attributes from method
             let attrs = tracing::extract_method_tracing_attrs(method);
             method_attrs.push(attrs);
+
+            match authorize::extract_method_roles(method) {
+                Ok(roles) => method_roles.push(roles),
+                Err(err) => return err.to_compile_error(),
+            }
+            match idempotent::is_idempotent(method) {
+                Ok(true) if oneway => {
+                    return syn::Error::new_spanned(
+                        method_name,
+                        "#[oneway] methods cannot be #[idempotent], nothing \
+                         tells the client whether they failed",
+                    )
+                    .to_compile_error();
+                }
+                Ok(idempotent) => idempotent_methods.push(idempotent),
+                Err(err) => return err.to_compile_error(),
+            }
+            oneway_methods.push(oneway);
+            match lifecycle::since(method) {
+                Ok(since) => method_since.push(since),
+                Err(err) => return err.to_compile_error(),
+            }
+            // r[impl jetstream.service.deprecated]
+            match lifecycle::deprecation(method) {
+                Some(note) => {
+                    deprecated_methods.push(Some(deprecations.len()));
+                    let method = method_name.to_string();
+                    let note = match note {
+                        Some(note) => quote! { Some(#note) },
+                        None => quote! { None },
+                    };
+                    deprecations.push(quote! {
+                        jetstream::prelude::jetstream_rpc::lifecycle::Deprecation::new(
+                            PROTOCOL_NAME,
+                            #method,
+                            #note,
+                        )
+                    });
+                }
+                None => deprecated_methods.push(None),
+            }
         }
     }
 
+    // r[impl jetstream.service.generics]
+    let used = [tmsg_params.as_slice(), rmsg_params.as_slice()].concat();
+    if let Some(unused) = generics.unused(&used).next() {
+        return syn::Error::new_spanned(
+            unused,
+            "no method sends or returns this type, so instantiations \
+             differing in it could not be told apart",
+        )
+        .to_compile_error();
+    }
+    let tparams = generics.union(&tmsg_params);
+    let rparams = generics.union(&rmsg_params);
+    let service_instance = generics.service(trait_name, &tparams, &rparams);
+    let channel_instance = generics.channel(trait_name, &tparams, &rparams);
+    let instance_fns = if generics.is_generic() {
+        generics::instance_fns()
+    } else {
+        quote! {}
+    };
+
     // Generate frame implementations
-    let tmessage = frame::generate_tframe(&tmsgs);
-    let rmessage = frame::generate_rframe(&rmsgs);
+    let encoded = fn_items.iter().any(|item| {
+        matches!(item, TraitItem::Fn(method) if borrowed::has_borrowed_args(&method.sig))
+    });
+    let tmessage = frame::generate_tframe(
+        &tmsgs,
+        &tmsg_params,
+        &tparams,
+        ids.extended,
+        encoded,
+    );
+    let rmessage =
+        frame::generate_rframe(&rmsgs, &rmsg_params, &rparams, ids.extended);
 
     // Generate server implementation
     let server_impl = server::generate_server(
         &service_name,
         trait_name,
-        &item.items,
+        &fn_items,
         &tmsgs,
         &rmsgs,
         &method_attrs,
+        &method_roles,
+        &oneway_methods,
+        &method_since,
+        &deprecated_methods,
         enable_tracing,
+        encoded,
+        &service_instance,
+        &composition,
     );
 
     // Generate client implementation
     let client_impl = client::generate_client(
         &channel_name,
         trait_name,
-        &item.items,
+        &fn_items,
         &tmsgs,
         &method_attrs,
+        &idempotent_methods,
+        &oneway_methods,
+        &method_since,
         enable_tracing,
+        ids.extended,
+        &channel_instance,
+        &composition,
     );
 
+    let mock_impl = if mock {
+        mock::generate_mock(
+            trait_name,
+            &service_name,
+            &fn_items,
+            is_async_trait,
+        )
+    } else {
+        quote! {}
+    };
+
+    let blocking_impl = if blocking {
+        blocking::generate_blocking(
+            trait_name,
+            &channel_name,
+            &fn_items,
+            is_async_trait,
+        )
+    } else {
+        quote! {}
+    };
+
     // Generate final trait with attribute
     let trait_attribute = if is_async_trait {
         quote! { #[jetstream::prelude::async_trait] }
@@ -165,16 +441,59 @@ pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
     let proto_mod =
         format_ident!("{}_protocol", trait_name.to_string().to_lowercase());
     let digest_lit = digest.clone();
-    let trait_name_lower =
-        Literal::string(&trait_name.to_string().to_lowercase());
-    let digest_prefix = Literal::string(
-        &sha256::digest(item.to_token_stream().to_string())[0..8],
-    );
+    // r[impl jetstream.service.name]
+    // Without a name the service is named after its crate and module, which
+    // only the protocol module's `module_path!()` knows.
+    let protocol_name = match &name {
+        Some(name) => quote! { #name },
+        None => quote! {
+            jetstream::prelude::jetstream_rpc::derived_name!(module_path!())
+        },
+    };
+    // r[impl jetstream.service.name.valid]
+    let name_span = name
+        .as_ref()
+        .map_or_else(proc_macro2::Span::call_site, |name| name.span());
+    let validate_name = quote::quote_spanned! {name_span=>
+        const _: () = assert!(
+            jetstream::prelude::jetstream_rpc::name::is_valid(PROTOCOL_NAME),
+            "protocol names are 1 to 255 ASCII letters, digits, `.`, `_` and \
+             `-`, and neither start nor end with `.`; name the service with \
+             `#[service(name = \"...\")]`",
+        );
+    };
+    let digest_prefix = Literal::string(&sha256::digest(&source)[0..8]);
+
+    let supertrait_aliases = composition.aliases();
+    let supertrait_imports = composition.imports();
+
+    let deprecated = if deprecations.is_empty() {
+        quote! {}
+    } else {
+        let len = Literal::usize_unsuffixed(deprecations.len());
+        quote! {
+            /// The service's `#[deprecated]` methods, and how often they
+            /// were called
+            pub static DEPRECATED: [jetstream::prelude::jetstream_rpc::lifecycle::Deprecation; #len] = [
+                #(#deprecations),*
+            ];
+        }
+    };
 
     // Generate message definitions
     let tmsg_definitions = tmsgs.iter().map(|(_ident, def)| quote! { #def });
     let rmsg_definitions = rmsgs.iter().map(|(_ident, def)| quote! { #def });
 
+    let trait_generics = &item.generics;
+    let trait_where = &item.generics.where_clause;
+    // Only the services among the supertraits are kept, as other bounds
+    // always were dropped.
+    let supertraits = if bounds.is_empty() {
+        quote! {}
+    } else {
+        quote! { : #(#bounds)+* }
+    };
+
     // Generate additional use statements
     let additional_uses = use_paths.iter().map(|tree| quote! { use #tree; });
 
@@ -184,6 +503,7 @@ pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
             use jetstream::prelude::*;
             use std::mem;
             use super::#trait_name;
+            #supertrait_imports
             #(#additional_uses)*
 
             const MESSAGE_ID_START: u8 = 102;
@@ -193,12 +513,15 @@ pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
             pub const TVERSION: u8 = jetstream::prelude::TVERSION;
             /// Version response message type constant
             pub const RVERSION: u8 = jetstream::prelude::RVERSION;
+            /// Trace context envelope message type constant
+            pub const TTRACE: u8 = jetstream::prelude::TTRACE;
             /// Protocol name — used for routing
-            pub const PROTOCOL_NAME: &str = #trait_name_lower;
+            pub const PROTOCOL_NAME: &str = #protocol_name;
+            #validate_name
             /// Protocol version string constructed from the generated crate's version
-            pub const PROTOCOL_VERSION: &str = concat!(
+            pub const PROTOCOL_VERSION: &str = jetstream::prelude::jetstream_rpc::const_concat!(
                 "rs.jetstream.proto/",
-                #trait_name_lower,
+                PROTOCOL_NAME,
                 "/",
                 env!("CARGO_PKG_VERSION_MAJOR"),
                 ".",
@@ -209,6 +532,16 @@ pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
                 #digest_prefix
             );
             const DIGEST: &str = #digest_lit;
+            /// Digests of the service's methods, in declaration order
+            pub const METHODS: &[jetstream::prelude::jetstream_rpc::schema::MethodDigest] = &[
+                #(#method_digests),*
+            ];
+
+            #deprecated
+
+            #instance_fns
+
+            #methods_export
 
             #(#msg_ids)*
 
@@ -223,10 +556,17 @@ pub(crate) fn service_impl(item: ItemTrait, attr: ServiceAttr) -> TokenStream {
             #server_impl
 
             #client_impl
+
+            #mock_impl
+
+            #blocking_impl
         }
 
+        #supertrait_aliases
+
         #trait_attribute
-        #vis trait #trait_name {
+        #vis trait #trait_name #trait_generics #supertraits #trait_where {
+            #(#assoc_items)*
             #(#trait_items)*
         }
     }
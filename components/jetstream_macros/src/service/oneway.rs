@@ -0,0 +1,56 @@
+use syn::{
+    Attribute, GenericArgument, PathArguments, ReturnType, TraitItemFn, Type,
+};
+
+pub fn is_oneway_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("oneway")
+}
+
+// r[impl jetstream.rpc.oneway.macro]
+/// Whether a trait method is marked `#[oneway]`. Nothing is sent back for
+/// oneway calls, so they must return `Result<()>`.
+pub fn is_oneway(method: &TraitItemFn) -> syn::Result<bool> {
+    let mut oneway = false;
+    for attr in method.attrs.iter().filter(|attr| is_oneway_attr(attr)) {
+        if oneway {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate #[oneway] attribute",
+            ));
+        }
+        if !matches!(attr.meta, syn::Meta::Path(_)) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "#[oneway] takes no arguments",
+            ));
+        }
+        oneway = true;
+    }
+    if oneway && !returns_unit(&method.sig.output) {
+        return Err(syn::Error::new_spanned(
+            &method.sig,
+            "#[oneway] methods must return `Result<()>`",
+        ));
+    }
+    Ok(oneway)
+}
+
+fn returns_unit(output: &ReturnType) -> bool {
+    let ReturnType::Type(_, ty) = output else {
+        return false;
+    };
+    let Type::Path(path) = ty.as_ref() else {
+        return false;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return false;
+    };
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return false;
+    };
+    segment.ident == "Result"
+        && matches!(
+            args.args.first(),
+            Some(GenericArgument::Type(Type::Tuple(unit))) if unit.elems.is_empty()
+        )
+}
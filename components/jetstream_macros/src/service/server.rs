@@ -1,7 +1,11 @@
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{Attribute, Ident, TraitItem};
 
+use super::authorize::{self, MethodRoles};
+use super::compose::Composition;
+use super::generics::Instance;
+use super::lifecycle;
 use crate::utils::case_conversion::IdentCased;
 
 #[allow(clippy::too_many_arguments)]
@@ -12,7 +16,14 @@ pub fn generate_server(
     tmsgs: &[(Ident, TokenStream)],
     rmsgs: &[(Ident, TokenStream)],
     method_attrs: &[Vec<Attribute>],
+    method_roles: &[MethodRoles],
+    oneway_methods: &[bool],
+    method_since: &[Option<[u64; 3]>],
+    deprecated_methods: &[Option<usize>],
     enable_tracing: bool,
+    encoded: bool,
+    instance: &Instance,
+    composition: &Composition,
 ) -> TokenStream {
     let match_arms = generate_match_arms(
         tmsgs.iter().map(|(id, ts)| (id.clone(), ts.clone())),
@@ -44,12 +55,16 @@ pub fn generate_server(
                                     }
                                 }
                             }
+                            // r[impl jetstream.service.borrowed-args.server]
+                            if let syn::Type::Reference(_) = &**ty {
+                                return Some(quote! { &msg.#name });
+                            }
                             Some(quote! { msg.#name })
                         }
                         syn::FnArg::Receiver(_) => None,
                     });
 
-                Some(quote! {
+                let body = quote! {
                     {
                         match self.#method_name(#(#params),*).await {
                             Ok(result) => {
@@ -59,7 +74,12 @@ pub fn generate_server(
                             Err(err) => Err(err.into()),
                         }
                     }
-                })
+                };
+                let body = lifecycle::record(deprecated_methods[index], body);
+                let body =
+                    authorize::guard(method_name, &method_roles[index], body);
+                let body = schema_guard(method_name, body);
+                Some(lifecycle::guard(method_name, method_since[index], body))
             }
             _ => None,
         })
@@ -75,10 +95,19 @@ pub fn generate_server(
             use std::str::FromStr;
             let client_version = jetstream::prelude::Version::from_str(&tversion.version)
                 .map_err(|e| Error::new(e))?;
+            // r[impl jetstream.version.capabilities.server]
+            let capabilities = Capabilities::offered(&tversion.version).negotiate(Self::CAPABILITIES);
+            // r[impl jetstream.schema.server]
+            // Without a `Router` there is no stream to remember the mismatch
+            // on, so it is only logged; the client still refuses the methods
+            // that differ.
+            let schema = jetstream::prelude::jetstream_rpc::schema::Schema::of::<Self>();
+            let _ = jetstream::prelude::jetstream_rpc::schema::DigestPolicy::Warn
+                .check(schema.compare(PROTOCOL_NAME, &tversion.version));
             match Self::version(client_version) {
                 Ok(negotiated) => Ok(Rmessage::Version(jetstream::prelude::Rversion {
-                    msize: tversion.msize,
-                    version: negotiated.to_string(),
+                    msize: tversion.msize.min(jetstream::prelude::jetstream_rpc::MAX_MSIZE),
+                    version: schema.acknowledge(&negotiated.to_string(), capabilities),
                 })),
                 Err(_) => Ok(Rmessage::Version(jetstream::prelude::Rversion {
                     msize: 0,
@@ -88,15 +117,61 @@ pub fn generate_server(
         }
     };
 
-    // Add RPC-level tracing span if tracing is enabled
-    let rpc_span = if enable_tracing {
+    // r[impl jetstream.trace.server]
+    // Unwrap the trace context envelope; with tracing enabled the remote
+    // parent becomes the parent of the rpc_server span.
+    let (unwrap_trace, instrument) = if enable_tracing {
+        (
+            quote! {
+                let (req, trace) = match frame.msg {
+                    Tmessage::Traced(trace, req) => (*req, Some(trace)),
+                    req => (req, None),
+                };
+                let span = tracing::debug_span!(
+                    "rpc_server",
+                    service = stringify!(#trait_name),
+                    tag = tag
+                );
+                if let Some(trace) = &trace {
+                    trace.set_parent(&span);
+                }
+            },
+            quote! { tracing::Instrument::instrument(fut, span) },
+        )
+    } else {
+        (
+            quote! {
+                let req = match frame.msg {
+                    Tmessage::Traced(_, req) => *req,
+                    req => req,
+                };
+            },
+            quote! { fut },
+        )
+    };
+
+    // r[impl jetstream.rpc.oneway.server]
+    // Nobody waits for the response of a oneway call, so its error can only
+    // be logged.
+    let (oneway, error_arm) = if oneway_methods.contains(&true) {
+        (
+            quote! { let oneway = frame.is_oneway(); },
+            quote! {
+                Err(err) => {
+                    if oneway {
+                        jetstream::prelude::jetstream_rpc::server::oneway_failed(PROTOCOL_NAME, &err);
+                    }
+                    Rmessage::Error(err)
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! { Err(err) => Rmessage::Error(err), })
+    };
+
+    let encoded_arm = if encoded {
         quote! {
-            let _span = tracing::debug_span!(
-                "rpc_server",
-                service = stringify!(#trait_name),
-                tag = frame.tag
-            );
-            let _enter = _span.enter();
+            Tmessage::Encoded(_) => Err(Error::new("pre-encoded requests are only sent")),
         }
     } else {
         quote! {}
@@ -106,56 +181,143 @@ pub fn generate_server(
     let trait_methods =
         generate_trait_methods(trait_items, method_attrs, enable_tracing);
 
+    // r[impl jetstream.service.generics]
+    let generics = instance.generics();
+    let where_clause = instance.where_clause();
+    let service_ty = quote! { #service_name #generics };
+    let inner = &instance.free;
+    let service = match (instance.generic, &instance.marker) {
+        (false, _) => quote! {
+            #[derive(Clone, Debug)]
+            pub struct #service_name<T: #trait_name> {
+                pub inner: T,
+            }
+        },
+        // The trait's type parameters are only named by the marker, so the
+        // service is built with `new`.
+        (true, Some(marker)) => quote! {
+            #[derive(Debug)]
+            pub struct #service_ty #where_clause {
+                pub inner: #inner,
+                _marker: #marker,
+            }
+
+            impl #generics #service_ty #where_clause {
+                pub fn new(inner: #inner) -> Self {
+                    Self {
+                        inner,
+                        _marker: std::marker::PhantomData,
+                    }
+                }
+            }
+
+            impl #generics Clone for #service_ty #where_clause, #inner: Clone {
+                fn clone(&self) -> Self {
+                    Self::new(self.inner.clone())
+                }
+            }
+        },
+        (true, None) => quote! {
+            #[derive(Clone, Debug)]
+            pub struct #service_ty #where_clause {
+                pub inner: #inner,
+            }
+        },
+    };
+    let trait_ref = &instance.trait_ref;
+    let request = &instance.tmessage;
+    let response = &instance.rmessage;
+    let protocol_fns = instance.protocol_fns();
+    let assoc_types = &instance.assoc_types;
+    // The server calls the `#[deprecated]` methods it serves.
+    let allow_deprecated = if deprecated_methods.iter().any(Option::is_some) {
+        quote! { #[allow(deprecated)] }
+    } else {
+        quote! {}
+    };
+    // r[impl jetstream.service.compose]
+    let supertrait_impls =
+        composition.impls(&trait_methods, |supertrait, methods| {
+            quote! {
+                #allow_deprecated
+                impl #generics #supertrait for #service_ty #where_clause {
+                    #(#methods)*
+                }
+            }
+        });
+    let trait_methods = &trait_methods[composition.own..];
+
     quote! {
-        #[derive(Clone, Debug)]
-        pub struct #service_name<T: #trait_name> {
-            pub inner: T,
-        }
+        #service
 
-        impl<T> Protocol for #service_name<T>
-        where
-            T: #trait_name + Send + Sync + Sized
+        impl #generics Protocol for #service_ty
+        #where_clause
         {
-            type Request = Tmessage;
-            type Response = Rmessage;
+            type Request = #request;
+            type Response = #response;
             // r[impl jetstream.macro.error-type]
             type Error = Error;
             const VERSION: &'static str = PROTOCOL_VERSION;
             const NAME: &'static str = PROTOCOL_NAME;
+            const CAPABILITIES: Capabilities =
+                Capabilities::TRACE_CONTEXT.union(Capabilities::COMPRESSION);
+            const METHODS: &'static [jetstream::prelude::jetstream_rpc::schema::MethodDigest] = METHODS;
+            #protocol_fns
         }
 
-        impl<T> Server for #service_name<T>
-        where
-            T: #trait_name + Send + Sync + Sized
+        #allow_deprecated
+        impl #generics Server for #service_ty
+        #where_clause
         {
             fn rpc(&mut self, ctx: Context, frame: Frame<<Self as Protocol>::Request>) -> impl ::core::future::Future<
                 Output = Result<Frame<<Self as Protocol>::Response>>,
             > + Send + Sync {
-                Box::pin(async move {
-                    #rpc_span
-                    let req: <Self as Protocol>::Request = frame.msg;
+                let tag = frame.tag;
+                #oneway
+                #unwrap_trace
+                let fut = async move {
+                    let req: <Self as Protocol>::Request = req;
                     let res: std::result::Result<<Self as Protocol>::Response, Self::Error> = match req {
                         #version_match_arm
                         #(#matches)*
+                        Tmessage::Traced(..) => Err(Error::new("nested trace context envelope")),
+                        #encoded_arm
                     };
                     // r[impl jetstream.macro.server-error]
                     // When server inner returns an error, serialize it as an Error frame
                     let response = match res {
                         Ok(msg) => msg,
-                        Err(err) => Rmessage::Error(err),
+                        #error_arm
                     };
-                    let rframe: Frame<<Self as Protocol>::Response> = Frame::from((frame.tag, response));
+                    let rframe: Frame<<Self as Protocol>::Response> = Frame::from((tag, response));
                     Ok(rframe)
-                })
+                };
+                Box::pin(#instrument)
             }
         }
 
-        impl<T> #trait_name for #service_name<T>
-        where
-            T: #trait_name + Send + Sync + Sized
+        #allow_deprecated
+        impl #generics #trait_ref for #service_ty
+        #where_clause
         {
+            #(#assoc_types)*
             #(#trait_methods)*
         }
+
+        #supertrait_impls
+    }
+}
+
+// r[impl jetstream.schema.server]
+/// Wraps a server match arm body in the check that the method's digest
+/// matches the client's, when a `Router` found the services to differ.
+fn schema_guard(method_name: &Ident, body: TokenStream) -> TokenStream {
+    let id = format_ident!("T{}", method_name.to_string().to_uppercase());
+    quote! {
+        match jetstream::prelude::jetstream_rpc::schema::check_context(&ctx, #id) {
+            Ok(()) => #body,
+            Err(err) => Err(err),
+        }
     }
 }
 
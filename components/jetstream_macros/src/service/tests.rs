@@ -2,6 +2,7 @@
 
 use super::{parse_service_attr, service_impl, ServiceAttr};
 use core::panic;
+use jetstream_codegen::message_ids::IdScheme;
 use quote::quote;
 use syn::parse_quote;
 
@@ -26,6 +27,15 @@ where
     });
 }
 
+/// The output without the exported `methods!`, which hands the methods on
+/// to composed services with their attributes.
+fn without_export(output: &str) -> String {
+    let start = output.find("macro_rules! __jetstream_methods").unwrap();
+    let end = " as methods;";
+    let end = output.find(end).unwrap() + end.len();
+    format!("{}{}", &output[..start], &output[end..])
+}
+
 /// r[verify jetstream.macro.source-span]
 /// r[verify jetstream.macro.error-type]
 /// r[verify jetstream.error-message-frame]
@@ -149,3 +159,403 @@ fn test_service_with_uses() {
         insta::assert_snapshot!(output_str);
     })
 }
+
+/// r[verify jetstream.auth.macro]
+#[test]
+fn test_service_with_authorize() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Vault {
+            async fn read(&self) -> Result<String, std::io::Error>;
+            #[authorize]
+            async fn list(&self) -> Result<Vec<String>, std::io::Error>;
+            #[authorize("admin", "ops")]
+            async fn wipe(&self) -> Result<(), std::io::Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    assert!(!without_export(&output_str).contains("#[authorize"));
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+#[test]
+fn test_service_with_invalid_authorize() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Vault {
+            #[authorize(admin)]
+            async fn wipe(&self) -> Result<(), std::io::Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(output.contains("compile_error"));
+}
+
+/// r[verify jetstream.retry.idempotent]
+#[test]
+fn test_service_with_idempotent() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            #[idempotent]
+            async fn get(&self, key: String, ctx: Context) -> Result<Vec<u8>, Error>;
+            async fn put(&self, key: String, value: Vec<u8>) -> Result<(), Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    assert!(!output_str.contains("#[idempotent]\n"));
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+#[test]
+fn test_service_with_invalid_idempotent() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            #[idempotent(always)]
+            async fn get(&self, key: String) -> Result<Vec<u8>, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(output.contains("compile_error"));
+}
+
+/// r[verify jetstream.rpc.oneway.macro]
+#[test]
+fn test_service_with_oneway() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Telemetry {
+            #[oneway]
+            async fn record(&self, event: String) -> Result<(), Error>;
+            async fn flush(&self) -> Result<u64, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    assert!(!without_export(&output_str).contains("#[oneway]"));
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+#[test]
+fn test_service_with_invalid_oneway() {
+    for input in [
+        parse_quote! {
+            pub trait Telemetry {
+                #[oneway]
+                async fn record(&self, event: String) -> Result<u64, Error>;
+            }
+        },
+        parse_quote! {
+            pub trait Telemetry {
+                #[oneway]
+                #[idempotent]
+                async fn record(&self, event: String) -> Result<(), Error>;
+            }
+        },
+        parse_quote! {
+            pub trait Telemetry {
+                #[oneway(quiet)]
+                async fn record(&self, event: String) -> Result<(), Error>;
+            }
+        },
+    ] {
+        let output = service_impl(input, ServiceAttr::default()).to_string();
+        assert!(output.contains("compile_error"), "{output}");
+    }
+}
+
+#[test]
+fn test_parse_attr_message_ids() {
+    let parsed = parse_service_attr(quote! { hashed_ids, extended_ids });
+    assert_eq!(parsed.ids.scheme, IdScheme::Hashed);
+    assert!(parsed.ids.extended);
+}
+
+/// r[verify jetstream.service.message-ids]
+#[test]
+fn test_service_with_explicit_ids() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            #[id = 200]
+            async fn get(&self, key: String) -> Result<Vec<u8>, Error>;
+            async fn put(&self, key: String, value: Vec<u8>) -> Result<(), Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    assert!(!without_export(&output_str).contains("#[id ="));
+    assert!(output_str.contains("pub const TGET: u8 = 200u8;"));
+    assert!(output_str.contains("pub const RGET: u8 = 201u8;"));
+    assert!(output_str.contains("pub const TPUT: u8 = MESSAGE_ID_START + 2u8;"));
+}
+
+/// r[verify jetstream.service.message-ids]
+#[test]
+fn test_service_with_colliding_ids() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            async fn get(&self, key: String) -> Result<Vec<u8>, Error>;
+            #[id = 103]
+            async fn put(&self, key: String, value: Vec<u8>) -> Result<(), Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(output.contains("compile_error"));
+    assert!(output.contains("message id 103 of `put` collides with `get`"));
+
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            #[id = 12]
+            async fn get(&self, key: String) -> Result<Vec<u8>, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(output.contains("message id 12 of `get` is outside 102..=254"));
+
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            #[id = 120]
+            #[id = 121]
+            async fn get(&self, key: String) -> Result<Vec<u8>, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(output.contains("duplicate #[id] attribute"));
+}
+
+/// r[verify jetstream.service.message-ids.extended]
+#[test]
+fn test_service_with_extended_ids() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            async fn get(&self, key: String) -> Result<Vec<u8>, Error>;
+            #[id = 1000]
+            async fn put(&self, key: String, value: Vec<u8>) -> Result<(), Error>;
+        }
+    };
+    let attr = parse_service_attr(quote! { extended_ids });
+    let output = service_impl(input, attr);
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+/// r[verify jetstream.mock.macro]
+#[test]
+fn test_service_with_mock() {
+    let attr = parse_service_attr(quote! { mock });
+    assert!(attr.mock);
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Echo {
+            async fn ping(&mut self) -> Result<(), Error>;
+            async fn echo(&mut self, ctx: Context, message: String) -> Result<String, Error>;
+            async fn add(&self, a: u32, b: u32) -> Result<u32, Error>;
+        }
+    };
+    let output = service_impl(input, attr);
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+/// r[verify jetstream.blocking.macro]
+#[test]
+fn test_service_with_blocking() {
+    let attr = parse_service_attr(quote! { blocking });
+    assert!(attr.blocking);
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Echo {
+            /// Checks the server is up.
+            async fn ping(&mut self) -> Result<(), Error>;
+            async fn echo(&mut self, ctx: Context, message: String) -> Result<String, Error>;
+            async fn add(&self, a: u32, b: u32) -> Result<u32, Error>;
+        }
+    };
+    let output = service_impl(input, attr);
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+/// r[verify jetstream.service.borrowed-args]
+#[test]
+fn test_service_with_borrowed_args() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            async fn put(&mut self, key: &str, value: &[u8]) -> Result<u32, Error>;
+            #[idempotent]
+            async fn get(&mut self, ctx: Context, key: &str) -> Result<Vec<u8>, Error>;
+            async fn count(&self) -> Result<u64, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+/// r[verify jetstream.service.borrowed-args]
+#[test]
+fn test_service_rejects_mut_borrowed_args() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store {
+            async fn fill(&mut self, buf: &mut [u8]) -> Result<(), Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(output.contains("compile_error"), "{output}");
+    assert!(output.contains("cannot take `&mut` arguments"), "{output}");
+}
+
+/// r[verify jetstream.service.generics]
+#[test]
+fn test_service_with_generics() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store<K: Clone, V> {
+            type Revision: WireFormat;
+            async fn put(&mut self, key: K, value: V) -> Result<Self::Revision, Error>;
+            #[idempotent]
+            async fn get(&mut self, key: K) -> Result<Option<V>, Error>;
+            async fn len(&self) -> Result<u64, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+/// r[verify jetstream.service.generics]
+#[test]
+fn test_service_rejects_lifetime_and_unsent_params() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store<'a> {
+            async fn get(&mut self, key: String) -> Result<String, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(
+        output.contains("can only be generic over types"),
+        "{output}"
+    );
+
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Store<K, Unsent> {
+            async fn get(&mut self, key: K) -> Result<String, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default()).to_string();
+    assert!(output.contains("no method sends or returns"), "{output}");
+}
+
+/// r[verify jetstream.service.compose]
+#[test]
+fn test_service_requests_supertrait_methods() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Admin: Health + Send {
+            async fn restart(&self) -> Result<(), Error>;
+        }
+    };
+    let attr = parse_service_attr(quote! { tracing });
+    let output = service_impl(input, attr).to_string();
+    assert!(
+        output.starts_with(
+            "health_protocol :: methods ! { [Health] # [jetstream :: prelude \
+             :: service (tracing)] pub trait Admin"
+        ),
+        "{output}"
+    );
+}
+
+/// r[verify jetstream.service.compose]
+/// r[verify jetstream.service.compose.ids]
+#[test]
+fn test_service_composed() {
+    let input: syn::ItemTrait = parse_quote! {
+        #[supertrait(Health {
+            #[idempotent]
+            #[id = 110]
+            async fn status(&self) -> Result<String, Error>;
+        })]
+        pub trait Admin: Health + Send {
+            async fn restart(&self) -> Result<(), Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+/// r[verify jetstream.service.since.client]
+/// r[verify jetstream.service.since.server]
+/// r[verify jetstream.service.deprecated]
+#[test]
+fn test_service_with_since_and_deprecated() {
+    let input: syn::ItemTrait = parse_quote! {
+        pub trait Storage {
+            async fn get(&self, key: String) -> Result<String, Error>;
+            #[since("1.3.0")]
+            async fn scan(&self, prefix: String) -> Result<Vec<String>, Error>;
+            #[deprecated(since = "1.3.0", note = "use `get`")]
+            async fn fetch(&self, key: String) -> Result<String, Error>;
+        }
+    };
+    let output = service_impl(input, ServiceAttr::default());
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    let without_export = without_export(&output_str);
+    assert!(!without_export.contains("#[since"));
+    assert!(without_export.contains("#[deprecated"));
+    run_test_with_filters(|| {
+        insta::assert_snapshot!(output_str);
+    })
+}
+
+#[test]
+fn test_service_with_invalid_since() {
+    for input in [
+        parse_quote! {
+            pub trait Storage {
+                #[since("1.3")]
+                async fn scan(&self, prefix: String) -> Result<(), Error>;
+            }
+        },
+        parse_quote! {
+            pub trait Storage {
+                #[since = "1.3.0"]
+                async fn scan(&self, prefix: String) -> Result<(), Error>;
+            }
+        },
+        parse_quote! {
+            pub trait Storage {
+                #[since("1.3.0")]
+                #[since("1.4.0")]
+                async fn scan(&self, prefix: String) -> Result<(), Error>;
+            }
+        },
+    ] {
+        let output = service_impl(input, ServiceAttr::default()).to_string();
+        assert!(output.contains("compile_error"), "{output}");
+    }
+}
@@ -95,7 +95,7 @@ fn end_to_end() {
     let output = wire_format_inner(input);
     let syntax_tree: syn::File = syn::parse2(output).unwrap();
     let output_str = prettyplease::unparse(&syntax_tree);
-    insta::assert_snapshot!(output_str, @r"
+    insta::assert_snapshot!(output_str, @r#"
     const _: () = {
         extern crate std;
         use std::io;
@@ -137,8 +137,13 @@ fn end_to_end() {
                 })
             }
         }
+        impl jetstream_wireformat::Describe for Niijima_先輩 {
+            fn describe() -> std::string::String {
+                std::string::String::from("Niijima_先輩")
+            }
+        }
     };
-    ");
+    "#);
 }
 
 #[test]
@@ -150,7 +155,7 @@ fn end_to_end_unnamed() {
     let output = wire_format_inner(input);
     let syntax_tree: syn::File = syn::parse2(output).unwrap();
     let output_str = prettyplease::unparse(&syntax_tree);
-    insta::assert_snapshot!(output_str, @r"
+    insta::assert_snapshot!(output_str, @r#"
     const _: () = {
         extern crate std;
         use std::io;
@@ -184,8 +189,13 @@ fn end_to_end_unnamed() {
                 Ok(Niijima_先輩(__0, __1, __2, __3, __4, __5, __6))
             }
         }
+        impl jetstream_wireformat::Describe for Niijima_先輩 {
+            fn describe() -> std::string::String {
+                std::string::String::from("Niijima_先輩")
+            }
+        }
     };
-    ");
+    "#);
 }
 
 #[test]
@@ -340,6 +350,11 @@ fn enum_end_to_end() {
                 }
             }
         }
+        impl jetstream_wireformat::Describe for Message {
+            fn describe() -> std::string::String {
+                std::string::String::from("Message")
+            }
+        }
     };
     "#);
 }
@@ -533,7 +548,7 @@ fn test_end_to_end_with_skip() {
     let output = wire_format_inner(input);
     let syntax_tree: syn::File = syn::parse2(output).unwrap();
     let output_str = prettyplease::unparse(&syntax_tree);
-    insta::assert_snapshot!(output_str, @r"
+    insta::assert_snapshot!(output_str, @r#"
     const _: () = {
         extern crate std;
         use std::io;
@@ -563,6 +578,11 @@ fn test_end_to_end_with_skip() {
                 })
             }
         }
+        impl jetstream_wireformat::Describe for Item {
+            fn describe() -> std::string::String {
+                std::string::String::from("Item")
+            }
+        }
     };
-    ");
+    "#);
 }
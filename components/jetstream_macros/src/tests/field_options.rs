@@ -17,7 +17,7 @@ fn test_with_option() {
     let output = wire_format_inner(input);
     let syntax_tree: syn::File = syn::parse2(output).unwrap();
     let output_str = prettyplease::unparse(&syntax_tree);
-    insta::assert_snapshot!(output_str, @r"
+    insta::assert_snapshot!(output_str, @r#"
     const _: () = {
         extern crate std;
         use std::io;
@@ -46,8 +46,13 @@ fn test_with_option() {
                 })
             }
         }
+        impl jetstream_wireformat::Describe for ItemWithWith {
+            fn describe() -> std::string::String {
+                std::string::String::from("ItemWithWith")
+            }
+        }
     };
-    ");
+    "#);
 }
 
 #[test]
@@ -64,7 +69,7 @@ fn test_specific_encode_decode_options() {
     let output = wire_format_inner(input);
     let syntax_tree: syn::File = syn::parse2(output).unwrap();
     let output_str = prettyplease::unparse(&syntax_tree);
-    insta::assert_snapshot!(output_str, @r"
+    insta::assert_snapshot!(output_str, @r#"
     const _: () = {
         extern crate std;
         use std::io;
@@ -92,8 +97,13 @@ fn test_specific_encode_decode_options() {
                 })
             }
         }
+        impl jetstream_wireformat::Describe for ItemWithSpecificEncodeDecode {
+            fn describe() -> std::string::String {
+                std::string::String::from("ItemWithSpecificEncodeDecode")
+            }
+        }
     };
-    ");
+    "#);
 }
 
 #[test]
@@ -114,7 +124,7 @@ fn test_from_into_as_options() {
     let output = wire_format_inner(input);
     let syntax_tree: syn::File = syn::parse2(output).unwrap();
     let output_str = prettyplease::unparse(&syntax_tree);
-    insta::assert_snapshot!(output_str, @r"
+    insta::assert_snapshot!(output_str, @r#"
     const _: () = {
         extern crate std;
         use std::io;
@@ -149,8 +159,13 @@ fn test_from_into_as_options() {
                 })
             }
         }
+        impl jetstream_wireformat::Describe for ItemWithFromIntoAs {
+            fn describe() -> std::string::String {
+                std::string::String::from("ItemWithFromIntoAs")
+            }
+        }
     };
-    ");
+    "#);
 }
 
 #[test]
@@ -226,6 +241,266 @@ fn test_options_with_enum() {
                 }
             }
         }
+        impl jetstream_wireformat::Describe for EnumWithOptions {
+            fn describe() -> std::string::String {
+                std::string::String::from("EnumWithOptions")
+            }
+        }
     };
     "#);
 }
+
+#[test]
+fn test_evolvable_struct() {
+    let input: DeriveInput = parse_quote! {
+        #[jetstream(evolvable)]
+        struct Evolving {
+            a: u8,
+            #[jetstream(since = 2, default)]
+            b: u32,
+        }
+    };
+
+    let output = wire_format_inner(input);
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    insta::assert_snapshot!(output_str, @r#"
+    const _: () = {
+        extern crate std;
+        use std::io;
+        use std::result::Result::Ok;
+        use jetstream_wireformat::WireFormat;
+        impl WireFormat for Evolving {
+            fn byte_size(&self) -> u32 {
+                4 + { 0 + WireFormat::byte_size(&self.a) + WireFormat::byte_size(&self.b) }
+            }
+            fn encode<W: io::Write>(&self, _writer: &mut W) -> io::Result<()> {
+                let __size: u32 = {
+                    0 + WireFormat::byte_size(&self.a) + WireFormat::byte_size(&self.b)
+                };
+                WireFormat::encode(&__size, _writer)?;
+                WireFormat::encode(&self.a, _writer)?;
+                WireFormat::encode(&self.b, _writer)?;
+                Ok(())
+            }
+            fn decode<R: io::Read>(_reader: &mut R) -> io::Result<Self> {
+                let __size: u32 = WireFormat::decode(_reader)?;
+                let mut __body = io::Read::take(_reader, __size as u64);
+                let _reader = &mut __body;
+                let __value: io::Result<Self> = {
+                    let a = WireFormat::decode(_reader)?;
+                    let b = if _reader.limit() == 0 {
+                        Default::default()
+                    } else {
+                        WireFormat::decode(_reader)?
+                    };
+                    Ok(Evolving { a: a, b: b })
+                };
+                let __value = __value?;
+                let __rest = _reader.limit();
+                if io::copy(_reader, &mut io::sink())? != __rest {
+                    return std::result::Result::Err(
+                        io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "evolvable struct is shorter than its length prefix",
+                        ),
+                    );
+                }
+                Ok(__value)
+            }
+        }
+        impl jetstream_wireformat::Describe for Evolving {
+            fn describe() -> std::string::String {
+                std::string::String::from("Evolving")
+            }
+        }
+    };
+    "#);
+}
+
+#[test]
+fn test_evolvable_requires_default_after_default() {
+    let input: DeriveInput = parse_quote! {
+        #[jetstream(evolvable)]
+        struct Evolving {
+            #[jetstream(default)]
+            a: u8,
+            b: u32,
+        }
+    };
+
+    let output = wire_format_inner(input).to_string();
+    assert!(output.contains("fields after a `default` field must be `default`"));
+}
+
+#[test]
+fn test_evolvable_since_requires_default() {
+    let input: DeriveInput = parse_quote! {
+        #[jetstream(evolvable)]
+        struct Evolving {
+            a: u8,
+            #[jetstream(since = 2)]
+            b: u32,
+        }
+    };
+
+    let output = wire_format_inner(input).to_string();
+    assert!(output.contains("fields added `since` a version must be `default`"));
+}
+
+#[test]
+fn test_evolvable_since_must_not_decrease() {
+    let input: DeriveInput = parse_quote! {
+        #[jetstream(evolvable)]
+        struct Evolving {
+            a: u8,
+            #[jetstream(since = 3, default)]
+            b: u32,
+            #[jetstream(since = 2, default)]
+            c: u32,
+        }
+    };
+
+    let output = wire_format_inner(input).to_string();
+    assert!(output.contains("`since` versions must not decrease"));
+}
+
+#[test]
+fn test_len_option() {
+    let input: DeriveInput = parse_quote! {
+        struct Page {
+            #[jetstream(len = u32)]
+            body: String,
+            #[jetstream(len = u16)]
+            title: String,
+        }
+    };
+
+    let output = wire_format_inner(input);
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    insta::assert_snapshot!(output_str, @r#"
+    const _: () = {
+        extern crate std;
+        use std::io;
+        use std::result::Result::Ok;
+        use jetstream_wireformat::WireFormat;
+        impl WireFormat for Page {
+            fn byte_size(&self) -> u32 {
+                0 + jetstream_wireformat::long::byte_size(&self.body)
+                    + WireFormat::byte_size(&self.title)
+            }
+            fn encode<W: io::Write>(&self, _writer: &mut W) -> io::Result<()> {
+                jetstream_wireformat::long::encode(&self.body, _writer)?;
+                WireFormat::encode(&self.title, _writer)?;
+                Ok(())
+            }
+            fn decode<R: io::Read>(_reader: &mut R) -> io::Result<Self> {
+                let body = jetstream_wireformat::long::decode(_reader)?;
+                let title = WireFormat::decode(_reader)?;
+                Ok(Page { body: body, title: title })
+            }
+        }
+        impl jetstream_wireformat::Describe for Page {
+            fn describe() -> std::string::String {
+                std::string::String::from("Page")
+            }
+        }
+    };
+    "#);
+}
+
+#[test]
+fn test_len_rejects_other_widths() {
+    let input: DeriveInput = parse_quote! {
+        struct Page {
+            #[jetstream(len = u64)]
+            body: String,
+        }
+    };
+
+    let output = wire_format_inner(input).to_string();
+    assert!(output.contains("`len` must be `u16`, `u32` or `varint`"));
+}
+
+#[test]
+fn test_varint_option() {
+    let input: DeriveInput = parse_quote! {
+        enum Sample {
+            Point {
+                #[jetstream(varint)]
+                id: u64,
+                #[jetstream(len = varint)]
+                values: Vec<i32>,
+            },
+        }
+    };
+
+    let output = wire_format_inner(input);
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    insta::assert_snapshot!(output_str, @r#"
+    const _: () = {
+        extern crate std;
+        use std::io;
+        use std::result::Result::Ok;
+        use jetstream_wireformat::WireFormat;
+        impl WireFormat for Sample {
+            fn byte_size(&self) -> u32 {
+                match self {
+                    Self::Point { ref id, ref values } => {
+                        1 + jetstream_wireformat::varint::byte_size(id)
+                            + jetstream_wireformat::varint::len::byte_size(values)
+                    }
+                }
+            }
+            fn encode<W: io::Write>(&self, _writer: &mut W) -> io::Result<()> {
+                match self {
+                    Self::Point { ref id, ref values } => {
+                        WireFormat::encode(&(0u8), _writer)?;
+                        jetstream_wireformat::varint::encode(id, _writer)?;
+                        jetstream_wireformat::varint::len::encode(values, _writer)?;
+                    }
+                }
+                Ok(())
+            }
+            fn decode<R: io::Read>(_reader: &mut R) -> io::Result<Self> {
+                let variant_index: u8 = WireFormat::decode(_reader)?;
+                match variant_index {
+                    0u8 => {
+                        let id = jetstream_wireformat::varint::decode(_reader)?;
+                        let values = jetstream_wireformat::varint::len::decode(_reader)?;
+                        Ok(Self::Point { id, values })
+                    }
+                    _ => {
+                        Err(
+                            ::std::io::Error::new(
+                                ::std::io::ErrorKind::InvalidData,
+                                "invalid variant index",
+                            ),
+                        )
+                    }
+                }
+            }
+        }
+        impl jetstream_wireformat::Describe for Sample {
+            fn describe() -> std::string::String {
+                std::string::String::from("Sample")
+            }
+        }
+    };
+    "#);
+}
+
+#[test]
+fn test_varint_rejects_len() {
+    let input: DeriveInput = parse_quote! {
+        struct Sample {
+            #[jetstream(varint, len = u32)]
+            id: u64,
+        }
+    };
+
+    let output = wire_format_inner(input).to_string();
+    assert!(output.contains("`varint` cannot be combined with `len`"));
+}
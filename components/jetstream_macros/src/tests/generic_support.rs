@@ -16,7 +16,7 @@ fn test_generics_support() {
     let output = wire_format_inner(input);
     let syntax_tree: syn::File = syn::parse2(output).unwrap();
     let output_str = prettyplease::unparse(&syntax_tree);
-    insta::assert_snapshot!(output_str, @r"
+    insta::assert_snapshot!(output_str, @r#"
     const _: () = {
         extern crate std;
         use std::io;
@@ -49,8 +49,21 @@ fn test_generics_support() {
                 })
             }
         }
+        impl<T, U> jetstream_wireformat::Describe for GenericItem<T, U>
+        where
+            T: jetstream_wireformat::Describe,
+            U: jetstream_wireformat::Describe,
+        {
+            fn describe() -> std::string::String {
+                let params: &[std::string::String] = &[
+                    <T as jetstream_wireformat::Describe>::describe(),
+                    <U as jetstream_wireformat::Describe>::describe(),
+                ];
+                std::format!("GenericItem<{}>", params.join(","))
+            }
+        }
     };
-    ");
+    "#);
 }
 
 #[test]
@@ -123,6 +136,106 @@ fn test_generic_enum() {
                 }
             }
         }
+        impl<T, U> jetstream_wireformat::Describe for GenericEnum<T, U>
+        where
+            T: jetstream_wireformat::Describe,
+            U: jetstream_wireformat::Describe,
+        {
+            fn describe() -> std::string::String {
+                let params: &[std::string::String] = &[
+                    <T as jetstream_wireformat::Describe>::describe(),
+                    <U as jetstream_wireformat::Describe>::describe(),
+                ];
+                std::format!("GenericEnum<{}>", params.join(","))
+            }
+        }
+    };
+    "#);
+}
+
+#[test]
+fn test_lifetime_support() {
+    let input: DeriveInput = parse_quote! {
+        struct Put<'a, T> {
+            key: &'a str,
+            value: T,
+            #[jetstream(len = u32)]
+            tags: Vec<String>,
+        }
+    };
+
+    let output = wire_format_inner(input);
+    let syntax_tree: syn::File = syn::parse2(output).unwrap();
+    let output_str = prettyplease::unparse(&syntax_tree);
+    insta::assert_snapshot!(output_str, @r#"
+    const _: () = {
+        extern crate std;
+        use std::io;
+        use std::result::Result::Ok;
+        use jetstream_wireformat::{BorrowedWireFormat, WireFormatRef};
+        impl<'a, T> BorrowedWireFormat for Put<'a, T>
+        where
+            T: jetstream_wireformat::BorrowedWireFormat,
+        {
+            fn borrowed_byte_size(&self) -> u32 {
+                0 + (&self.key).borrowed_byte_size() + (&self.value).borrowed_byte_size()
+                    + jetstream_wireformat::long::byte_size(&self.tags)
+            }
+            fn encode_borrowed<W: io::Write>(&self, _writer: &mut W) -> io::Result<()> {
+                (&self.key).encode_borrowed(_writer)?;
+                (&self.value).encode_borrowed(_writer)?;
+                jetstream_wireformat::long::encode(&self.tags, _writer)?;
+                Ok(())
+            }
+        }
+        impl<'a, T> WireFormatRef<'a> for Put<'a, T>
+        where
+            T: jetstream_wireformat::WireFormatRef<'a>,
+        {
+            fn decode_ref(_reader: &mut &'a [u8]) -> io::Result<Self> {
+                let key = WireFormatRef::decode_ref(_reader)?;
+                let value = WireFormatRef::decode_ref(_reader)?;
+                let tags = jetstream_wireformat::long::decode(_reader)?;
+                Ok(Put {
+                    key: key,
+                    value: value,
+                    tags: tags,
+                })
+            }
+        }
+        impl<'a, T> jetstream_wireformat::Describe for Put<'a, T>
+        where
+            T: jetstream_wireformat::Describe,
+        {
+            fn describe() -> std::string::String {
+                let params: &[std::string::String] = &[
+                    <T as jetstream_wireformat::Describe>::describe(),
+                ];
+                std::format!("Put<{}>", params.join(","))
+            }
+        }
     };
     "#);
 }
+
+#[test]
+fn test_lifetime_rejects_enums_and_extra_lifetimes() {
+    let input: DeriveInput = parse_quote! {
+        enum Put<'a> {
+            Key(&'a str),
+        }
+    };
+    let output = wire_format_inner(input).to_string();
+    assert!(
+        output.contains("lifetime parameters are only supported on structs")
+    );
+
+    let input: DeriveInput = parse_quote! {
+        struct Put<'a, 'b> {
+            key: &'a str,
+            value: &'b [u8],
+        }
+    };
+    let output = wire_format_inner(input).to_string();
+    assert!(output.contains("only one lifetime parameter is supported"));
+}
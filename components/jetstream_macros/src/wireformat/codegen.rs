@@ -1,13 +1,63 @@
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, quote_spanned};
-use syn::{spanned::Spanned, Data, Fields, Ident};
+use syn::{spanned::Spanned, Data, DeriveInput, Fields, Ident};
 
 use crate::utils::error;
-use jetstream_codegen::attributes::{extract_field_options, has_skip_attr};
+use jetstream_codegen::attributes::{
+    extract_field_options, has_skip_attr, Options,
+};
+
+/// The traits the fields of a struct are encoded and decoded through.
+#[derive(Clone, Copy)]
+pub enum FieldCodec {
+    /// `WireFormat`.
+    Owned,
+    /// `BorrowedWireFormat` and `WireFormatRef`, for structs with a lifetime
+    /// parameter, whose fields may borrow from the decoded buffer.
+    Borrowed,
+}
+
+impl FieldCodec {
+    fn byte_size(self, span: Span, value: TokenStream) -> TokenStream {
+        match self {
+            FieldCodec::Owned => {
+                quote_spanned! {span=> WireFormat::byte_size(#value) }
+            }
+            // Method calls, so `&&'a str` derefs to the `str` impl.
+            FieldCodec::Borrowed => {
+                quote_spanned! {span=> (#value).borrowed_byte_size() }
+            }
+        }
+    }
+
+    fn encode(self, span: Span, value: TokenStream) -> TokenStream {
+        match self {
+            FieldCodec::Owned => {
+                quote_spanned! {span=> WireFormat::encode(#value, _writer)?; }
+            }
+            FieldCodec::Borrowed => {
+                quote_spanned! {span=> (#value).encode_borrowed(_writer)?; }
+            }
+        }
+    }
+
+    fn decode(self, span: Span) -> TokenStream {
+        match self {
+            FieldCodec::Owned => {
+                quote_spanned! {span=> WireFormat::decode(_reader)? }
+            }
+            FieldCodec::Borrowed => {
+                quote_spanned! {span=> WireFormatRef::decode_ref(_reader)? }
+            }
+        }
+    }
+}
 
 pub fn byte_size_sum(data: &Data) -> TokenStream {
     match data {
-        Data::Struct(ref data) => generate_struct_byte_size(&data.fields),
+        Data::Struct(ref data) => {
+            generate_struct_byte_size(&data.fields, FieldCodec::Owned)
+        }
         Data::Enum(ref data) => generate_enum_byte_size(data),
         Data::Union(_) => error::unsupported_data_type(),
     }
@@ -15,7 +65,9 @@ pub fn byte_size_sum(data: &Data) -> TokenStream {
 
 pub fn encode_wire_format(data: &Data) -> TokenStream {
     match data {
-        Data::Struct(ref data) => generate_struct_encode(&data.fields),
+        Data::Struct(ref data) => {
+            generate_struct_encode(&data.fields, FieldCodec::Owned)
+        }
         Data::Enum(ref data) => generate_enum_encode(data),
         Data::Union(_) => error::unsupported_data_type(),
     }
@@ -24,28 +76,192 @@ pub fn encode_wire_format(data: &Data) -> TokenStream {
 pub fn decode_wire_format(data: &Data, container: &Ident) -> TokenStream {
     match data {
         Data::Struct(ref data) => {
-            generate_struct_decode(&data.fields, container)
+            generate_struct_decode(&data.fields, container, FieldCodec::Owned)
         }
         Data::Enum(ref data) => generate_enum_decode(data, container),
         Data::Union(_) => error::unsupported_data_type(),
     }
 }
 
+// r[impl jetstream.wireformat.evolvable]
+/// Checks the `evolvable`, `since` and `default` attributes, returning a
+/// compile error if they are misused.
+pub fn validate_evolvable(data: &Data, evolvable: bool) -> Option<TokenStream> {
+    let fields: Vec<&syn::Field> = match data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => {
+            data.variants.iter().flat_map(|v| v.fields.iter()).collect()
+        }
+        Data::Union(_) => return None,
+    };
+    if evolvable && !matches!(data, Data::Struct(_)) {
+        return Some(error::compile_error(
+            Span::call_site(),
+            "`#[jetstream(evolvable)]` is only supported on structs",
+        ));
+    }
+
+    let mut defaulted = false;
+    let mut since = 0;
+    for field in fields.into_iter().filter(|f| !has_skip_attr(f)) {
+        let options = extract_field_options(field);
+        if !evolvable && (options.default || options.since.is_some()) {
+            return Some(error::compile_error(
+                field,
+                "`since` and `default` require `#[jetstream(evolvable)]` \
+                 on the struct",
+            ));
+        }
+        if options.since.is_some() && !options.default {
+            return Some(error::compile_error(
+                field,
+                "fields added `since` a version must be `default`",
+            ));
+        }
+        if defaulted && !options.default {
+            return Some(error::compile_error(
+                field,
+                "fields after a `default` field must be `default`",
+            ));
+        }
+        if let Some(version) = options.since {
+            if version < since {
+                return Some(error::compile_error(
+                    field,
+                    "`since` versions must not decrease",
+                ));
+            }
+            since = version;
+        }
+        defaulted |= options.default;
+    }
+    None
+}
+
+// r[impl jetstream.wireformat.long]
+// r[impl jetstream.wireformat.varint]
+/// Checks the `len` and `varint` attributes, returning a compile error if
+/// they are misused.
+pub fn validate_len(data: &Data) -> Option<TokenStream> {
+    let fields: Vec<&syn::Field> = match data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => {
+            data.variants.iter().flat_map(|v| v.fields.iter()).collect()
+        }
+        Data::Union(_) => return None,
+    };
+    for field in fields {
+        let options = extract_field_options(field);
+        if let Some(width) = &options.len {
+            if width != "u16" && width != "u32" && width != "varint" {
+                return Some(error::compile_error(
+                    width,
+                    "`len` must be `u16`, `u32` or `varint`",
+                ));
+            }
+        }
+        if options.varint && options.len.is_some() {
+            return Some(error::compile_error(
+                field,
+                "`varint` cannot be combined with `len`",
+            ));
+        }
+        let encoded =
+            options.is_long() || options.is_varint_len() || options.varint;
+        if encoded
+            && (options.with.is_some()
+                || options.encode.is_some()
+                || options.decode.is_some()
+                || options.byte_size.is_some())
+        {
+            return Some(error::compile_error(
+                field,
+                "`len` and `varint` cannot be combined with `with` attributes",
+            ));
+        }
+    }
+    None
+}
+
+// r[impl jetstream.wireformat.borrowed]
+/// Checks that a type with lifetime parameters can derive
+/// `BorrowedWireFormat` and `WireFormatRef`, returning a compile error if not.
+pub fn validate_borrowed(
+    input: &DeriveInput,
+    evolvable: bool,
+    message: bool,
+) -> Option<TokenStream> {
+    let mut lifetimes = input.generics.lifetimes();
+    lifetimes.next()?;
+    if let Some(lifetime) = lifetimes.next() {
+        return Some(error::compile_error(
+            lifetime,
+            "only one lifetime parameter is supported",
+        ));
+    }
+    if !matches!(input.data, Data::Struct(_)) {
+        return Some(error::compile_error(
+            &input.ident,
+            "lifetime parameters are only supported on structs",
+        ));
+    }
+    if evolvable || message {
+        return Some(error::compile_error(
+            &input.ident,
+            "`evolvable` and `jetstream_type` are not supported on structs \
+             with lifetime parameters",
+        ));
+    }
+    None
+}
+
+// The options of `field`, with fields marked `len = u32`, `len = varint` or
+// `varint` encoded through `jetstream_wireformat::long`,
+// `jetstream_wireformat::varint::len` or `jetstream_wireformat::varint`.
+fn field_options(field: &syn::Field) -> Options {
+    let mut options = extract_field_options(field);
+    if options.is_long() {
+        options.with = Some(syn::parse_quote!(jetstream_wireformat::long));
+    } else if options.is_varint_len() {
+        options.with =
+            Some(syn::parse_quote!(jetstream_wireformat::varint::len));
+    } else if options.varint {
+        options.with = Some(syn::parse_quote!(jetstream_wireformat::varint));
+    }
+    options
+}
+
 // Struct implementations
-fn generate_struct_byte_size(fields: &Fields) -> TokenStream {
+/// The `borrowed_byte_size`, `encode_borrowed` and `decode_ref` of a struct
+/// with a lifetime parameter.
+pub fn borrowed_wire_format(
+    fields: &Fields,
+    container: &Ident,
+) -> (TokenStream, TokenStream, TokenStream) {
+    (
+        generate_struct_byte_size(fields, FieldCodec::Borrowed),
+        generate_struct_encode(fields, FieldCodec::Borrowed),
+        generate_struct_decode(fields, container, FieldCodec::Borrowed),
+    )
+}
+
+fn generate_struct_byte_size(
+    fields: &Fields,
+    codec: FieldCodec,
+) -> TokenStream {
     match fields {
         Fields::Named(ref fields) => {
             let fields = fields.named.iter().filter(|f| !has_skip_attr(f)).map(|f| {
                 let field = &f.ident;
                 let span = field.span();
-                let options = extract_field_options(f);
+                let options = field_options(f);
 
                 if let Some(byte_size_fn) = options.byte_size {
                     quote_spanned! {span=> #byte_size_fn(&self.#field) }
                 } else if let Some(with_fn) = options.with {
                     quote_spanned! {span=> #with_fn::byte_size(&self.#field) }
                 } else {
-                    quote_spanned! {span=> WireFormat::byte_size(&self.#field) }
+                    codec.byte_size(span, quote! { &self.#field })
                 }
             });
 
@@ -59,14 +275,17 @@ fn generate_struct_byte_size(fields: &Fields) -> TokenStream {
                 .filter(|(_, f)| !has_skip_attr(f))
                 .map(|(i, f)| {
                     let index = syn::Index::from(i);
-                    let options = extract_field_options(f);
+                    let options = field_options(f);
 
                     if let Some(byte_size_fn) = options.byte_size {
                         quote! { #byte_size_fn(&self.#index) }
                     } else if let Some(with_fn) = options.with {
                         quote! { #with_fn::byte_size(&self.#index) }
                     } else {
-                        quote! { WireFormat::byte_size(&self.#index) }
+                        codec.byte_size(
+                            Span::call_site(),
+                            quote! { &self.#index },
+                        )
                     }
                 });
 
@@ -76,24 +295,24 @@ fn generate_struct_byte_size(fields: &Fields) -> TokenStream {
     }
 }
 
-fn generate_struct_encode(fields: &Fields) -> TokenStream {
+fn generate_struct_encode(fields: &Fields, codec: FieldCodec) -> TokenStream {
     let encode_fields = match fields {
         Fields::Named(ref fields) => {
             let fields = fields.named.iter().filter(|f| !has_skip_attr(f)).map(|f| {
                 let field = &f.ident;
                 let span = field.span();
-                let options = extract_field_options(f);
+                let options = field_options(f);
                 
                 if let Some(encode_fn) = options.encode {
                     quote_spanned! {span=> #encode_fn(&self.#field, _writer)?; }
                 } else if let Some(with_fn) = options.with {
                     quote_spanned! {span=> #with_fn::encode(&self.#field, _writer)?; }
                 } else if let Some(into_fn) = options.into {
-                    quote_spanned! {span=> WireFormat::encode(&(#into_fn(&self.#field)), _writer)?; }
+                    codec.encode(span, quote! { &(#into_fn(&self.#field)) })
                 } else if let Some(as_fn) = options.as_ {
-                    quote_spanned! {span=> WireFormat::encode(&#as_fn(&self.#field), _writer)?; }
+                    codec.encode(span, quote! { &#as_fn(&self.#field) })
                 } else {
-                    quote_spanned! {span=> WireFormat::encode(&self.#field, _writer)?; }
+                    codec.encode(span, quote! { &self.#field })
                 }
             });
             quote! { #(#fields)* }
@@ -106,18 +325,24 @@ fn generate_struct_encode(fields: &Fields) -> TokenStream {
                 .filter(|(_, f)| !has_skip_attr(f))
                 .map(|(i, f)| {
                     let index = syn::Index::from(i);
-                    let options = extract_field_options(f);
+                    let options = field_options(f);
                     
                     if let Some(encode_fn) = options.encode {
                         quote! { #encode_fn(&self.#index, _writer)?; }
                     } else if let Some(with_fn) = options.with {
                         quote! { #with_fn::encode(&self.#index, _writer)?; }
                     } else if let Some(into_fn) = options.into {
-                        quote! { WireFormat::encode(&(#into_fn(&self.#index)), _writer)?; }
+                        codec.encode(
+                            Span::call_site(),
+                            quote! { &(#into_fn(&self.#index)) },
+                        )
                     } else if let Some(as_fn) = options.as_ {
-                        quote! { WireFormat::encode(&#as_fn(&self.#index), _writer)?; }
+                        codec.encode(
+                            Span::call_site(),
+                            quote! { &#as_fn(&self.#index) },
+                        )
                     } else {
-                        quote! { WireFormat::encode(&self.#index, _writer)?; }
+                        codec.encode(Span::call_site(), quote! { &self.#index })
                     }
                 });
             quote! { #(#fields)* }
@@ -131,7 +356,11 @@ fn generate_struct_encode(fields: &Fields) -> TokenStream {
     }
 }
 
-fn generate_struct_decode(fields: &Fields, container: &Ident) -> TokenStream {
+fn generate_struct_decode(
+    fields: &Fields,
+    container: &Ident,
+    codec: FieldCodec,
+) -> TokenStream {
     match fields {
         Fields::Named(ref fields) => {
             let all_fields = fields.named.iter().collect::<Vec<_>>();
@@ -139,17 +368,20 @@ fn generate_struct_decode(fields: &Fields, container: &Ident) -> TokenStream {
                 fields.named.iter().filter(|f| !has_skip_attr(f)).map(|f| {
                     let field = &f.ident;
                     let span = field.span();
-                    let options = extract_field_options(f);
+                    let options = field_options(f);
                     
-                    if let Some(decode_fn) = options.decode {
-                        quote_spanned! {span=> let #field = #decode_fn(_reader)?; }
+                    let value = if let Some(decode_fn) = options.decode {
+                        quote_spanned! {span=> #decode_fn(_reader)? }
                     } else if let Some(with_fn) = options.with {
-                        quote_spanned! {span=> let #field = #with_fn::decode(_reader)?; }
+                        quote_spanned! {span=> #with_fn::decode(_reader)? }
                     } else if let Some(from_fn) = options.from {
-                        quote_spanned! {span=> let #field = #from_fn(WireFormat::decode(_reader)?); }
+                        let value = codec.decode(span);
+                        quote_spanned! {span=> #from_fn(#value) }
                     } else {
-                        quote_spanned! {span=> let #field = WireFormat::decode(_reader)?; }
-                    }
+                        codec.decode(span)
+                    };
+                    let value = decode_or_default(value, options.default);
+                    quote_spanned! {span=> let #field = #value; }
                 });
 
             let members = all_fields.iter().map(|f| {
@@ -182,18 +414,22 @@ fn generate_struct_decode(fields: &Fields, container: &Ident) -> TokenStream {
                 .enumerate()
                 .filter(|(_, f)| !has_skip_attr(f))
                 .map(|(i, f)| {
-                    let ident = Ident::new(&format!("__{}", i), Span::call_site());
-                    let options = extract_field_options(f);
-                    
-                    if let Some(decode_fn) = options.decode {
-                        quote! { let #ident = #decode_fn(_reader)?; }
+                    let ident =
+                        Ident::new(&format!("__{}", i), Span::call_site());
+                    let options = field_options(f);
+
+                    let value = if let Some(decode_fn) = options.decode {
+                        quote! { #decode_fn(_reader)? }
                     } else if let Some(with_fn) = options.with {
-                        quote! { let #ident = #with_fn::decode(_reader)?; }
+                        quote! { #with_fn::decode(_reader)? }
                     } else if let Some(from_fn) = options.from {
-                        quote! { let #ident = #from_fn(WireFormat::decode(_reader)?); }
+                        let value = codec.decode(Span::call_site());
+                        quote! { #from_fn(#value) }
                     } else {
-                        quote! { let #ident = WireFormat::decode(_reader)?; }
-                    }
+                        codec.decode(Span::call_site())
+                    };
+                    let value = decode_or_default(value, options.default);
+                    quote! { let #ident = #value; }
                 });
 
             let members = all_fields.iter().map(|(i, is_skipped)| {
@@ -215,6 +451,20 @@ fn generate_struct_decode(fields: &Fields, container: &Ident) -> TokenStream {
     }
 }
 
+// r[impl jetstream.wireformat.evolvable]
+// Fields marked `default` are only read while the length-prefixed body of an
+// evolvable struct has bytes left; peers built before the field was added
+// stop short of it.
+fn decode_or_default(value: TokenStream, default: bool) -> TokenStream {
+    if default {
+        quote! {
+            if _reader.limit() == 0 { Default::default() } else { #value }
+        }
+    } else {
+        value
+    }
+}
+
 // Enum implementations
 fn generate_enum_byte_size(data: &syn::DataEnum) -> TokenStream {
     let variants = data.variants.iter().map(|variant| {
@@ -229,7 +479,7 @@ fn generate_enum_byte_size(data: &syn::DataEnum) -> TokenStream {
                     .collect::<Vec<_>>();
 
                 let size_calcs = field_idents.iter().map(|(f, ident)| {
-                    let options = extract_field_options(f);
+                    let options = field_options(f);
                     if let Some(byte_size_fn) = options.byte_size {
                         quote! { + #byte_size_fn(#ident) }
                     } else if let Some(with_fn) = options.with {
@@ -258,7 +508,7 @@ fn generate_enum_byte_size(data: &syn::DataEnum) -> TokenStream {
 
                 let size_calcs = refs_with_fields.iter().map(|(f, name)| {
                     let ident = Ident::new(name, Span::call_site());
-                    let options = extract_field_options(f);
+                    let options = field_options(f);
 
                     if let Some(byte_size_fn) = options.byte_size {
                         quote! { + #byte_size_fn(#ident) }
@@ -309,7 +559,7 @@ fn generate_enum_encode(data: &syn::DataEnum) -> TokenStream {
                     .collect::<Vec<_>>();
                 
                 let encode_stmts = field_idents_with_fields.iter().map(|(f, ident)| {
-                    let options = extract_field_options(f);
+                    let options = field_options(f);
                     
                     if let Some(encode_fn) = options.encode {
                         quote! { #encode_fn(#ident, _writer)?; }
@@ -344,7 +594,7 @@ fn generate_enum_encode(data: &syn::DataEnum) -> TokenStream {
                 
                 let encode_stmts = field_refs_with_fields.iter().map(|(f, name)| {
                     let ident = Ident::new(name, Span::call_site());
-                    let options = extract_field_options(f);
+                    let options = field_options(f);
                     
                     if let Some(encode_fn) = options.encode {
                         quote! { #encode_fn(#ident, _writer)?; }
@@ -405,7 +655,7 @@ fn generate_enum_decode(
                     let field_decodes =
                         fields.named.iter().filter(|f| !has_skip_attr(f)).map(|f| {
                             let field_ident = &f.ident;
-                            let options = extract_field_options(f);
+                            let options = field_options(f);
                             
                             if let Some(decode_fn) = options.decode {
                                 quote! { let #field_ident = #decode_fn(_reader)?; }
@@ -442,7 +692,7 @@ fn generate_enum_decode(
                         .filter(|(_, f)| !has_skip_attr(f))
                         .map(|(i, f)| {
                             let field_name = Ident::new(&format!("__{}", i), Span::call_site());
-                            let options = extract_field_options(f);
+                            let options = field_options(f);
                             
                             if let Some(decode_fn) = options.decode {
                                 quote! { let #field_name = #decode_fn(_reader)?; }
@@ -1,17 +1,21 @@
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{
-    punctuated::Punctuated, DeriveInput, GenericParam, Generics, Path,
+    punctuated::Punctuated, DeriveInput, GenericParam, Generics, Ident, Path,
     PathSegment, TraitBound, Type, TypeParam, TypeParamBound, WherePredicate,
 };
 
-use super::codegen::{byte_size_sum, decode_wire_format, encode_wire_format};
-use jetstream_codegen::attributes::extract_jetstream_type;
+use super::codegen::{
+    borrowed_wire_format, byte_size_sum, decode_wire_format,
+    encode_wire_format, validate_borrowed, validate_evolvable, validate_len,
+};
+use jetstream_codegen::attributes::{extract_jetstream_type, is_evolvable};
 
-// Add WireFormat bounds to generic type parameters
-fn add_wireformat_bounds(
+// Add `trait_path` bounds to generic type parameters
+fn add_bounds(
     generics: &Generics,
     predicates: &mut Punctuated<WherePredicate, syn::token::Comma>,
+    trait_path: &str,
 ) {
     for param in &generics.params {
         if let GenericParam::Type(TypeParam { ident, .. }) = param {
@@ -30,10 +34,7 @@ fn add_wireformat_bounds(
                 },
             });
 
-            // Create the WireFormat trait bound
-            let trait_path =
-                syn::parse_str::<Path>("jetstream_wireformat::WireFormat")
-                    .unwrap();
+            let trait_path = syn::parse_str::<Path>(trait_path).unwrap();
             let trait_bound = TypeParamBound::Trait(TraitBound {
                 paren_token: None,
                 modifier: syn::TraitBoundModifier::None,
@@ -41,7 +42,7 @@ fn add_wireformat_bounds(
                 path: trait_path,
             });
 
-            // Create the where predicate: T: WireFormat
+            // Create the where predicate: T: Trait
             let mut bounds = Punctuated::new();
             bounds.push(trait_bound);
 
@@ -59,35 +60,33 @@ fn add_wireformat_bounds(
 
 pub fn wire_format_inner(input: DeriveInput) -> TokenStream {
     let jetstream_type = extract_jetstream_type(&input);
+    let evolvable = is_evolvable(&input);
     let container = input.ident.clone();
 
+    if let Some(error) = validate_evolvable(&input.data, evolvable) {
+        return error;
+    }
+    if let Some(error) = validate_len(&input.data) {
+        return error;
+    }
+    if let Some(error) =
+        validate_borrowed(&input, evolvable, jetstream_type.is_some())
+    {
+        return error;
+    }
+    if input.generics.lifetimes().next().is_some() {
+        return borrowed_wire_format_inner(input);
+    }
+
     // Extract generics information
     let generics = input.generics;
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
 
     // Create a where clause for WireFormat bounds on generic types
-    let where_clause = match where_clause {
-        Some(where_clause) => {
-            let mut predicates = where_clause.predicates.clone();
-            add_wireformat_bounds(&generics, &mut predicates);
-            Some(syn::WhereClause {
-                where_token: syn::token::Where::default(),
-                predicates,
-            })
-        }
-        None if !generics.params.is_empty() => {
-            let mut predicates = syn::punctuated::Punctuated::new();
-            add_wireformat_bounds(&generics, &mut predicates);
-            Some(syn::WhereClause {
-                where_token: syn::token::Where::default(),
-                predicates,
-            })
-        }
-        None => None,
-    };
-
     let where_clause_tokens =
-        where_clause.map_or_else(|| quote! {}, |wc| quote! { #wc });
+        bounded_where_clause(&generics, "jetstream_wireformat::WireFormat");
+    let describe_where_clause =
+        bounded_where_clause(&generics, "jetstream_wireformat::Describe");
 
     // Generate message type implementation
     let message_impl = if let Some(msg_type) = jetstream_type {
@@ -100,9 +99,16 @@ pub fn wire_format_inner(input: DeriveInput) -> TokenStream {
         quote! {}
     };
 
+    let describe_impl = describe_body(&container, &generics);
+
     let byte_size_impl = byte_size_sum(&input.data);
     let encode_impl = encode_wire_format(&input.data);
     let decode_impl = decode_wire_format(&input.data, &container);
+    let (byte_size_impl, encode_impl, decode_impl) = if evolvable {
+        evolvable_impls(byte_size_impl, encode_impl, decode_impl)
+    } else {
+        (byte_size_impl, encode_impl, decode_impl)
+    };
 
     // Use const block for hygiene
     quote! {
@@ -126,6 +132,146 @@ pub fn wire_format_inner(input: DeriveInput) -> TokenStream {
                 }
             }
             #message_impl
+
+            impl #impl_generics jetstream_wireformat::Describe for #container #ty_generics #describe_where_clause {
+                fn describe() -> std::string::String {
+                    #describe_impl
+                }
+            }
+        };
+    }
+}
+
+// r[impl jetstream.wireformat.describe]
+// The body of `Describe::describe`: the type's name, followed by its type
+// arguments.
+fn describe_body(container: &Ident, generics: &Generics) -> TokenStream {
+    let type_params = generics.type_params().map(|param| &param.ident);
+    if generics.type_params().next().is_none() {
+        let name = container.to_string();
+        quote! { std::string::String::from(#name) }
+    } else {
+        let format = format!("{}<{{}}>", container);
+        quote! {
+            let params: &[std::string::String] = &[
+                #(<#type_params as jetstream_wireformat::Describe>::describe()),*
+            ];
+            std::format!(#format, params.join(","))
+        }
+    }
+}
+
+// r[impl jetstream.wireformat.borrowed]
+// Structs with a lifetime parameter may borrow from the buffer they are
+// decoded from, so they implement `BorrowedWireFormat` and
+// `WireFormatRef<'a>` instead of `WireFormat`.
+fn borrowed_wire_format_inner(input: DeriveInput) -> TokenStream {
+    let container = input.ident;
+    let generics = input.generics;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let lifetime = &generics
+        .lifetimes()
+        .next()
+        .expect("validated to have one lifetime")
+        .lifetime;
+
+    let encode_where_clause = bounded_where_clause(
+        &generics,
+        "jetstream_wireformat::BorrowedWireFormat",
+    );
+    let decode_where_clause = bounded_where_clause(
+        &generics,
+        &format!("jetstream_wireformat::WireFormatRef<{lifetime}>"),
+    );
+    let describe_where_clause =
+        bounded_where_clause(&generics, "jetstream_wireformat::Describe");
+
+    let describe_impl = describe_body(&container, &generics);
+
+    let syn::Data::Struct(data) = &input.data else {
+        unreachable!("validated to be a struct");
+    };
+    let (byte_size_impl, encode_impl, decode_impl) =
+        borrowed_wire_format(&data.fields, &container);
+
+    quote! {
+        const _: () = {
+            extern crate std;
+            use std::io;
+            use std::result::Result::Ok;
+            use jetstream_wireformat::{BorrowedWireFormat, WireFormatRef};
+
+            impl #impl_generics BorrowedWireFormat for #container #ty_generics #encode_where_clause {
+                fn borrowed_byte_size(&self) -> u32 {
+                    #byte_size_impl
+                }
+
+                fn encode_borrowed<W: io::Write>(&self, _writer: &mut W) -> io::Result<()> {
+                    #encode_impl
+                }
+            }
+
+            impl #impl_generics WireFormatRef<#lifetime> for #container #ty_generics #decode_where_clause {
+                fn decode_ref(_reader: &mut &#lifetime [u8]) -> io::Result<Self> {
+                    #decode_impl
+                }
+            }
+
+            impl #impl_generics jetstream_wireformat::Describe for #container #ty_generics #describe_where_clause {
+                fn describe() -> std::string::String {
+                    #describe_impl
+                }
+            }
         };
     }
 }
+
+// The where clause of `generics` with every type parameter bounded by
+// `trait_path`.
+fn bounded_where_clause(generics: &Generics, trait_path: &str) -> TokenStream {
+    let mut predicates = generics
+        .where_clause
+        .as_ref()
+        .map(|where_clause| where_clause.predicates.clone())
+        .unwrap_or_default();
+    add_bounds(generics, &mut predicates, trait_path);
+    if predicates.is_empty() {
+        return quote! {};
+    }
+    quote! { where #predicates }
+}
+
+// r[impl jetstream.wireformat.evolvable]
+// Evolvable structs are prefixed with the u32 length of their fields. The
+// fields are decoded from a reader limited to that length, so fields missing
+// from older peers can be defaulted and fields added by newer peers skipped.
+fn evolvable_impls(
+    byte_size_impl: TokenStream,
+    encode_impl: TokenStream,
+    decode_impl: TokenStream,
+) -> (TokenStream, TokenStream, TokenStream) {
+    let byte_size = quote! {
+        4 + { #byte_size_impl }
+    };
+    let encode = quote! {
+        let __size: u32 = { #byte_size_impl };
+        WireFormat::encode(&__size, _writer)?;
+        #encode_impl
+    };
+    let decode = quote! {
+        let __size: u32 = WireFormat::decode(_reader)?;
+        let mut __body = io::Read::take(_reader, __size as u64);
+        let _reader = &mut __body;
+        let __value: io::Result<Self> = { #decode_impl };
+        let __value = __value?;
+        let __rest = _reader.limit();
+        if io::copy(_reader, &mut io::sink())? != __rest {
+            return std::result::Result::Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "evolvable struct is shorter than its length prefix",
+            ));
+        }
+        Ok(__value)
+    };
+    (byte_size, encode, decode)
+}
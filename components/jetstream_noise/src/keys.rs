@@ -0,0 +1,111 @@
+use std::{fmt, str::FromStr};
+
+use jetstream_rpc::{context::NodeId, Error};
+
+use crate::NOISE_PARAMS;
+
+/// A peer's static X25519 public key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The node id under which the key appears in a `Context`.
+    pub fn node_id(&self) -> NodeId {
+        NodeId::from_public_key(&self.0)
+    }
+}
+
+impl From<[u8; 32]> for PublicKey {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes.try_into().map(Self).map_err(|_| {
+            Error::new(format!(
+                "public key must be 32 bytes, got {}",
+                bytes.len()
+            ))
+        })
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKey({})", self)
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s)
+            .map_err(|e| Error::new(format!("invalid public key: {}", e)))?;
+        Self::try_from(bytes.as_slice())
+    }
+}
+
+/// A static X25519 keypair identifying this side of a connection.
+#[derive(Clone)]
+pub struct Keypair {
+    private: [u8; 32],
+    public: PublicKey,
+}
+
+impl Keypair {
+    pub fn generate() -> Result<Self, Error> {
+        let params = NOISE_PARAMS.parse().expect("valid noise parameters");
+        let keypair = snow::Builder::new(params)
+            .generate_keypair()
+            .map_err(|e| Error::new(format!("key generation failed: {}", e)))?;
+        Ok(Self {
+            private: keypair
+                .private
+                .as_slice()
+                .try_into()
+                .expect("32 byte private key"),
+            public: PublicKey::try_from(keypair.public.as_slice())?,
+        })
+    }
+
+    /// Restores a keypair from its private key.
+    pub fn from_private_key(private: [u8; 32]) -> Self {
+        let secret = x25519_dalek::StaticSecret::from(private);
+        let public = x25519_dalek::PublicKey::from(&secret);
+        Self {
+            private,
+            public: PublicKey(public.to_bytes()),
+        }
+    }
+
+    pub fn private_key(&self) -> &[u8; 32] {
+        &self.private
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
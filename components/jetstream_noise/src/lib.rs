@@ -0,0 +1,36 @@
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/sevki/jetstream/main/logo/JetStream.png"
+)]
+#![doc(
+    html_favicon_url = "https://raw.githubusercontent.com/sevki/jetstream/main/logo/JetStream.png"
+)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+//! Noise protocol secure transport for plain byte streams.
+//!
+//! Wraps any `AsyncRead + AsyncWrite` (TCP, Unix sockets, pipes) in a
+//! `Noise_XX_25519_ChaChaPoly_BLAKE2s` session, giving mutual authentication
+//! and encryption without certificates. Each side is identified by a static
+//! X25519 key; servers see the client's key as a [`Peer::NodeId`], so
+//! authorization policies can name it with `node:<hex>`.
+//!
+//! ```ignore
+//! // server
+//! let config = NoiseConfig::new(server_keypair).allow(client_key);
+//! let (stream, addr) = listener.accept().await?;
+//! config.serve(&router, stream, Some(RemoteAddr::IpAddr(addr.ip()))).await?;
+//!
+//! // client, pinning the server's key
+//! let config = NoiseConfig::new(client_keypair).allow(server_key);
+//! let stream = config.connect(TcpStream::connect(addr).await?).await?;
+//! let chan = EchoChannel::new(10, Box::new(Framed::new(stream, ClientCodec::default())));
+//! ```
+//!
+//! [`Peer::NodeId`]: jetstream_rpc::context::Peer::NodeId
+mod keys;
+mod stream;
+
+pub use keys::{Keypair, PublicKey};
+pub use stream::{NoiseConfig, NoiseStream};
+
+/// The Noise handshake pattern and primitives.
+pub const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
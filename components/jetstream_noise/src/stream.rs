@@ -0,0 +1,410 @@
+use std::{
+    collections::HashSet,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use jetstream_rpc::{
+    auth::PERMISSION_DENIED,
+    context::{Context, Peer, RemoteAddr},
+    Error, Router,
+};
+use snow::{HandshakeState, StatelessTransportState};
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf,
+    ReadHalf, WriteHalf,
+};
+
+use crate::{Keypair, PublicKey, NOISE_PARAMS};
+
+/// Largest Noise message, including the authentication tag.
+const MAX_MESSAGE: usize = 65535;
+const TAG_LEN: usize = 16;
+const MAX_PLAINTEXT: usize = MAX_MESSAGE - TAG_LEN;
+
+fn noise_error(err: snow::Error) -> Error {
+    Error::new(format!("noise: {}", err))
+}
+
+// r[impl jetstream.noise.config]
+/// This side's identity and the peers it trusts.
+#[derive(Debug, Clone)]
+pub struct NoiseConfig {
+    keypair: Keypair,
+    allowed: Option<HashSet<PublicKey>>,
+}
+
+impl NoiseConfig {
+    /// Trusts any peer; use [`NoiseConfig::allow`] to restrict that.
+    pub fn new(keypair: Keypair) -> Self {
+        Self {
+            keypair,
+            allowed: None,
+        }
+    }
+
+    // r[impl jetstream.noise.allowlist]
+    /// Only complete handshakes with peers whose static key has been
+    /// allowed. A client that allows a single key pins its server.
+    pub fn allow(mut self, key: PublicKey) -> Self {
+        self.allowed.get_or_insert_with(HashSet::new).insert(key);
+        self
+    }
+
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+
+    /// Runs the handshake as the initiator.
+    pub async fn connect<S>(&self, stream: S) -> Result<NoiseStream, Error>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        self.handshake(stream, true).await
+    }
+
+    /// Runs the handshake as the responder.
+    pub async fn accept<S>(&self, stream: S) -> Result<NoiseStream, Error>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        self.handshake(stream, false).await
+    }
+
+    // r[impl jetstream.noise.router]
+    /// Accepts a stream and hands it to `router`, with the peer's static key
+    /// as its [`Peer::NodeId`].
+    pub async fn serve<S>(
+        &self,
+        router: &Router,
+        stream: S,
+        remote: Option<RemoteAddr>,
+    ) -> Result<(), Error>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let stream = self.accept(stream).await?;
+        let ctx = Context::new(
+            remote,
+            Some(Peer::NodeId(stream.remote_public_key().node_id())),
+        );
+        let (reader, writer) = tokio::io::split(stream);
+        router.accept(ctx, Box::new(reader), Box::new(writer)).await
+    }
+
+    // r[impl jetstream.noise.handshake]
+    async fn handshake<S>(
+        &self,
+        mut stream: S,
+        initiator: bool,
+    ) -> Result<NoiseStream, Error>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let params = NOISE_PARAMS.parse().expect("valid noise parameters");
+        let builder = snow::Builder::new(params)
+            .local_private_key(self.keypair.private_key())
+            .map_err(noise_error)?;
+        let mut state = if initiator {
+            builder.build_initiator()
+        } else {
+            builder.build_responder()
+        }
+        .map_err(noise_error)?;
+
+        let mut message = vec![0u8; MAX_MESSAGE];
+        let mut payload = vec![0u8; MAX_MESSAGE];
+        while !state.is_handshake_finished() {
+            if state.is_my_turn() {
+                // In XX the initiator learns the responder's key before it
+                // reveals its own, so an untrusted server never sees it.
+                self.verify_remote(&state)?;
+                let len = state
+                    .write_message(&[], &mut message)
+                    .map_err(noise_error)?;
+                send(&mut stream, &message[..len]).await?;
+            } else {
+                let len = recv(&mut stream, &mut message)
+                    .await?
+                    .ok_or_else(|| Error::new("noise: handshake aborted"))?;
+                state
+                    .read_message(&message[..len], &mut payload)
+                    .map_err(noise_error)?;
+            }
+        }
+        let remote = self
+            .verify_remote(&state)?
+            .ok_or_else(|| Error::new("noise: peer sent no static key"))?;
+        let transport =
+            state.into_stateless_transport_mode().map_err(noise_error)?;
+        Ok(NoiseStream::spawn(stream, transport, remote))
+    }
+
+    fn verify_remote(
+        &self,
+        state: &HandshakeState,
+    ) -> Result<Option<PublicKey>, Error> {
+        let Some(remote) = state.get_remote_static() else {
+            return Ok(None);
+        };
+        let remote = PublicKey::try_from(remote)?;
+        match &self.allowed {
+            Some(allowed) if !allowed.contains(&remote) => {
+                Err(Error::with_code(
+                    format!("noise: peer {} is not allowed", remote),
+                    PERMISSION_DENIED,
+                ))
+            }
+            _ => Ok(Some(remote)),
+        }
+    }
+}
+
+/// Writes one length-prefixed Noise message.
+async fn send<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &[u8],
+) -> Result<(), Error> {
+    writer.write_u16_le(message.len() as u16).await?;
+    writer.write_all(message).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Reads one length-prefixed Noise message, or `None` at end of stream.
+async fn recv<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut [u8],
+) -> Result<Option<usize>, Error> {
+    let len = match reader.read_u16_le().await {
+        Ok(len) => len as usize,
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+            return Ok(None)
+        }
+        Err(err) => return Err(err.into()),
+    };
+    reader.read_exact(&mut buf[..len]).await?;
+    Ok(Some(len))
+}
+
+// r[impl jetstream.noise.transport]
+/// An encrypted, mutually authenticated stream.
+///
+/// Plaintext written to the stream is sealed into Noise messages of at most
+/// 64 KiB by a background task, and a second task opens incoming messages,
+/// so the stream works with any reader or writer that expects plain bytes.
+#[derive(Debug)]
+pub struct NoiseStream {
+    inner: DuplexStream,
+    remote: PublicKey,
+}
+
+impl NoiseStream {
+    fn spawn<S>(
+        stream: S,
+        transport: StatelessTransportState,
+        remote: PublicKey,
+    ) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let transport = Arc::new(transport);
+        let (inner, plain) = tokio::io::duplex(MAX_MESSAGE);
+        let (socket_rx, socket_tx) = tokio::io::split(stream);
+        let (plain_rx, plain_tx) = tokio::io::split(plain);
+        tokio::spawn(seal(transport.clone(), plain_rx, socket_tx));
+        tokio::spawn(open(transport, socket_rx, plain_tx));
+        Self { inner, remote }
+    }
+
+    /// The static key the peer authenticated with.
+    pub fn remote_public_key(&self) -> &PublicKey {
+        &self.remote
+    }
+
+    /// A context identifying the peer by its static key.
+    pub fn context(&self) -> Context {
+        Context::new(None, Some(Peer::NodeId(self.remote.node_id())))
+    }
+}
+
+async fn seal<S: AsyncWrite>(
+    transport: Arc<StatelessTransportState>,
+    mut plain: ReadHalf<DuplexStream>,
+    mut socket: WriteHalf<S>,
+) {
+    let mut buf = vec![0u8; MAX_PLAINTEXT];
+    let mut message = vec![0u8; MAX_MESSAGE];
+    let mut nonce = 0u64;
+    let result: Result<(), Error> = async {
+        loop {
+            let n = plain.read(&mut buf).await?;
+            if n == 0 {
+                socket.shutdown().await?;
+                return Ok(());
+            }
+            let len = transport
+                .write_message(nonce, &buf[..n], &mut message)
+                .map_err(noise_error)?;
+            nonce += 1;
+            send(&mut socket, &message[..len]).await?;
+        }
+    }
+    .await;
+    if let Err(err) = result {
+        tracing::debug!(error = %err, "noise writer stopped");
+    }
+}
+
+async fn open<S: AsyncRead>(
+    transport: Arc<StatelessTransportState>,
+    mut socket: ReadHalf<S>,
+    mut plain: WriteHalf<DuplexStream>,
+) {
+    let mut message = vec![0u8; MAX_MESSAGE];
+    let mut buf = vec![0u8; MAX_MESSAGE];
+    let mut nonce = 0u64;
+    let result: Result<(), Error> = async {
+        while let Some(len) = recv(&mut socket, &mut message).await? {
+            let n = transport
+                .read_message(nonce, &message[..len], &mut buf)
+                .map_err(noise_error)?;
+            nonce += 1;
+            plain.write_all(&buf[..n]).await?;
+        }
+        Ok(())
+    }
+    .await;
+    if let Err(err) = result {
+        tracing::debug!(error = %err, "noise reader stopped");
+    }
+    let _ = plain.shutdown().await;
+}
+
+impl AsyncRead for NoiseStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for NoiseStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn pair(
+        client: NoiseConfig,
+        server: NoiseConfig,
+    ) -> (Result<NoiseStream, Error>, Result<NoiseStream, Error>) {
+        let (a, b) = tokio::io::duplex(1024);
+        tokio::join!(client.connect(a), server.accept(b))
+    }
+
+    /// r[verify jetstream.noise.handshake]
+    /// r[verify jetstream.noise.transport]
+    #[tokio::test]
+    async fn round_trip() {
+        let client = Keypair::generate().unwrap();
+        let server = Keypair::generate().unwrap();
+        let (c, s) = pair(
+            NoiseConfig::new(client.clone()),
+            NoiseConfig::new(server.clone()),
+        )
+        .await;
+        let (mut c, mut s) = (c.unwrap(), s.unwrap());
+        assert_eq!(c.remote_public_key(), server.public_key());
+        assert_eq!(s.remote_public_key(), client.public_key());
+        assert_eq!(
+            s.context().peer(),
+            Some(&Peer::NodeId(client.public_key().node_id()))
+        );
+
+        // Larger than one Noise message.
+        let payload = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let expected = payload.clone();
+        let writer = tokio::spawn(async move {
+            c.write_all(&payload).await.unwrap();
+            c.shutdown().await.unwrap();
+            c
+        });
+        let mut received = Vec::new();
+        s.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, expected);
+
+        let mut c = writer.await.unwrap();
+        s.write_all(b"pong").await.unwrap();
+        let mut pong = [0u8; 4];
+        c.read_exact(&mut pong).await.unwrap();
+        assert_eq!(&pong, b"pong");
+    }
+
+    /// r[verify jetstream.noise.allowlist]
+    #[tokio::test]
+    async fn pinned_server_key() {
+        let server = Keypair::generate().unwrap();
+        let impostor = Keypair::generate().unwrap();
+        let client = NoiseConfig::new(Keypair::generate().unwrap())
+            .allow(*server.public_key());
+
+        let (c, s) = pair(client.clone(), NoiseConfig::new(server)).await;
+        assert!(c.is_ok() && s.is_ok());
+
+        let (c, s) = pair(client, NoiseConfig::new(impostor)).await;
+        assert_eq!(c.unwrap_err().code(), Some(PERMISSION_DENIED));
+        assert!(s.is_err());
+    }
+
+    /// r[verify jetstream.noise.allowlist]
+    #[tokio::test]
+    async fn client_allowlist() {
+        let trusted = Keypair::generate().unwrap();
+        let server = NoiseConfig::new(Keypair::generate().unwrap())
+            .allow(*trusted.public_key());
+
+        let (c, s) = pair(NoiseConfig::new(trusted), server.clone()).await;
+        assert!(c.is_ok() && s.is_ok());
+
+        let stranger = NoiseConfig::new(Keypair::generate().unwrap());
+        let (_, s) = pair(stranger, server).await;
+        assert_eq!(s.unwrap_err().code(), Some(PERMISSION_DENIED));
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        let keypair = Keypair::generate().unwrap();
+        let restored = Keypair::from_private_key(*keypair.private_key());
+        assert_eq!(restored.public_key(), keypair.public_key());
+        let parsed: PublicKey =
+            keypair.public_key().to_string().parse().unwrap();
+        assert_eq!(&parsed, keypair.public_key());
+        assert!("abcd".parse::<PublicKey>().is_err());
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+use futures::Stream;
+use jetstream_error::{Error, Result};
+use jetstream_wireformat::JetStreamWireFormat;
+use tokio::sync::Notify;
+
+use crate::{topic::validate_topic, Pattern, SLOW_CONSUMER};
+
+/// Messages buffered for a subscriber unless it asks for another limit.
+pub const DEFAULT_BUFFER: usize = 256;
+
+// r[impl jetstream.pubsub.slow-consumer]
+/// What happens to a message published while a subscriber's buffer is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, JetStreamWireFormat)]
+pub enum SlowConsumer {
+    /// Keep the buffered messages and drop the new one.
+    DropNewest,
+    /// Drop the oldest buffered message to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Drop the subscription; its next receive fails with
+    /// [`SLOW_CONSUMER`].
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscribeOptions {
+    /// Messages buffered before `policy` applies.
+    pub buffer: usize,
+    pub policy: SlowConsumer,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        Self {
+            buffer: DEFAULT_BUFFER,
+            policy: SlowConsumer::default(),
+        }
+    }
+}
+
+/// A message delivered to a subscription.
+#[derive(Debug)]
+pub struct Message<T> {
+    pub topic: String,
+    pub payload: Arc<T>,
+    /// Messages this subscription dropped since it received the previous
+    /// one.
+    pub dropped: u64,
+}
+
+impl<T> Clone for Message<T> {
+    fn clone(&self) -> Self {
+        Self {
+            topic: self.topic.clone(),
+            payload: self.payload.clone(),
+            dropped: self.dropped,
+        }
+    }
+}
+
+struct State<T> {
+    messages: VecDeque<(String, Arc<T>)>,
+    dropped: u64,
+    disconnected: bool,
+    closed: bool,
+}
+
+struct Queue<T> {
+    state: Mutex<State<T>>,
+    notify: Notify,
+    options: SubscribeOptions,
+}
+
+impl<T> Queue<T> {
+    fn new(options: SubscribeOptions) -> Self {
+        Self {
+            state: Mutex::new(State {
+                messages: VecDeque::new(),
+                dropped: 0,
+                disconnected: false,
+                closed: false,
+            }),
+            notify: Notify::new(),
+            options,
+        }
+    }
+
+    /// Buffers a message, returning whether it was kept and whether the
+    /// subscriber is still connected.
+    fn push(&self, topic: &str, payload: &Arc<T>) -> (bool, bool) {
+        let mut state = self.state.lock().unwrap();
+        let kept = if state.messages.len() < self.options.buffer.max(1) {
+            true
+        } else {
+            state.dropped += 1;
+            match self.options.policy {
+                SlowConsumer::DropNewest => false,
+                SlowConsumer::DropOldest => {
+                    state.messages.pop_front();
+                    true
+                }
+                SlowConsumer::Disconnect => {
+                    state.messages.clear();
+                    state.disconnected = true;
+                    false
+                }
+            }
+        };
+        if kept {
+            state
+                .messages
+                .push_back((topic.to_string(), payload.clone()));
+        }
+        let connected = !state.disconnected;
+        drop(state);
+        self.notify.notify_one();
+        (kept, connected)
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+
+    async fn recv_many(&self, max: usize) -> Result<Vec<Message<T>>> {
+        loop {
+            let notified = self.notify.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.disconnected {
+                    return Err(Error::with_code(
+                        "subscriber fell behind and was disconnected",
+                        SLOW_CONSUMER,
+                    ));
+                }
+                if !state.messages.is_empty() {
+                    let mut dropped = std::mem::take(&mut state.dropped);
+                    let count = state.messages.len().min(max.max(1));
+                    return Ok(state
+                        .messages
+                        .drain(..count)
+                        .map(|(topic, payload)| Message {
+                            topic,
+                            payload,
+                            dropped: std::mem::take(&mut dropped),
+                        })
+                        .collect());
+                }
+                if state.closed {
+                    return Ok(Vec::new());
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+struct Subscriber<T> {
+    id: u64,
+    pattern: Pattern,
+    queue: Arc<Queue<T>>,
+}
+
+struct Inner<T> {
+    subscribers: Mutex<Vec<Subscriber<T>>>,
+    next_id: AtomicU64,
+}
+
+impl<T> Inner<T> {
+    fn unsubscribe(&self, id: u64) {
+        self.subscribers.lock().unwrap().retain(|sub| sub.id != id);
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        for sub in self.subscribers.get_mut().unwrap().drain(..) {
+            sub.queue.close();
+        }
+    }
+}
+
+// r[impl jetstream.pubsub.broker]
+/// Routes published messages to the subscriptions whose pattern matches
+/// their topic.
+///
+/// A broker works in-process on its own; wrap it in a
+/// [`BrokerService`](crate::BrokerService) to serve it through a `Router`.
+/// Clones share subscriptions.
+pub struct Broker<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for Broker<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Default for Broker<T> {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                subscribers: Mutex::new(Vec::new()),
+                next_id: AtomicU64::new(1),
+            }),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Broker<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Broker")
+            .field("subscribers", &self.subscribers())
+            .finish()
+    }
+}
+
+impl<T> Broker<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publishes `payload` to `topic`, returning how many subscriptions
+    /// kept it.
+    pub fn publish(&self, topic: &str, payload: T) -> Result<usize> {
+        validate_topic(topic)?;
+        let payload = Arc::new(payload);
+        let mut delivered = 0;
+        self.inner.subscribers.lock().unwrap().retain(|sub| {
+            if !sub.pattern.matches(topic) {
+                return true;
+            }
+            let (kept, connected) = sub.queue.push(topic, &payload);
+            delivered += usize::from(kept);
+            connected
+        });
+        Ok(delivered)
+    }
+
+    pub fn subscribe(&self, pattern: &str) -> Result<Subscription<T>> {
+        self.subscribe_with(pattern, SubscribeOptions::default())
+    }
+
+    pub fn subscribe_with(
+        &self,
+        pattern: &str,
+        options: SubscribeOptions,
+    ) -> Result<Subscription<T>> {
+        let pattern = Pattern::parse(pattern)?;
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let queue = Arc::new(Queue::new(options));
+        self.inner.subscribers.lock().unwrap().push(Subscriber {
+            id,
+            pattern,
+            queue: queue.clone(),
+        });
+        Ok(Subscription {
+            id,
+            queue,
+            broker: Arc::downgrade(&self.inner),
+        })
+    }
+
+    /// Subscriptions currently receiving messages.
+    pub fn subscribers(&self) -> usize {
+        self.inner.subscribers.lock().unwrap().len()
+    }
+}
+
+/// Messages published to a [`Broker`] on topics matching a pattern.
+/// Dropping it unsubscribes.
+pub struct Subscription<T> {
+    id: u64,
+    queue: Arc<Queue<T>>,
+    broker: Weak<Inner<T>>,
+}
+
+impl<T> std::fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl<T> Subscription<T> {
+    /// Identifies the subscription within its broker.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The next message, or `None` once the broker is gone.
+    pub async fn recv(&mut self) -> Result<Option<Message<T>>> {
+        Ok(self.queue.recv_many(1).await?.pop())
+    }
+
+    /// Waits for at least one message and returns up to `max` of them. An
+    /// empty batch means the broker is gone.
+    pub async fn recv_many(&mut self, max: usize) -> Result<Vec<Message<T>>> {
+        self.queue.recv_many(max).await
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<Message<T>>> {
+        futures::stream::unfold(self, |mut sub| async move {
+            sub.recv().await.transpose().map(|message| (message, sub))
+        })
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        if let Some(broker) = self.broker.upgrade() {
+            broker.unsubscribe(self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// r[verify jetstream.pubsub.broker]
+    #[tokio::test]
+    async fn delivers_to_matching_subscriptions() {
+        let broker = Broker::new();
+        let mut kitchen = broker.subscribe("sensors.kitchen.*").unwrap();
+        let mut all = broker.subscribe("sensors.>").unwrap();
+
+        assert_eq!(broker.publish("sensors.kitchen.temp", 21).unwrap(), 2);
+        assert_eq!(broker.publish("sensors.garage.temp", 9).unwrap(), 1);
+        assert_eq!(broker.publish("alerts.fire", 1).unwrap(), 0);
+
+        let message = kitchen.recv().await.unwrap().unwrap();
+        assert_eq!(message.topic, "sensors.kitchen.temp");
+        assert_eq!(*message.payload, 21);
+        let topics: Vec<_> = all
+            .recv_many(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.topic)
+            .collect();
+        assert_eq!(topics, ["sensors.kitchen.temp", "sensors.garage.temp"]);
+
+        drop(kitchen);
+        assert_eq!(broker.subscribers(), 1);
+        drop(broker);
+        assert!(all.recv().await.unwrap().is_none());
+    }
+
+    /// r[verify jetstream.pubsub.slow-consumer]
+    #[tokio::test]
+    async fn slow_consumer_policies() {
+        let broker = Broker::new();
+        let options = |policy| SubscribeOptions { buffer: 2, policy };
+        let mut newest = broker
+            .subscribe_with("t", options(SlowConsumer::DropNewest))
+            .unwrap();
+        let mut oldest = broker
+            .subscribe_with("t", options(SlowConsumer::DropOldest))
+            .unwrap();
+        let mut disconnect = broker
+            .subscribe_with("t", options(SlowConsumer::Disconnect))
+            .unwrap();
+        for i in 0..3 {
+            broker.publish("t", i).unwrap();
+        }
+        assert_eq!(broker.subscribers(), 2);
+
+        let kept = |batch: Vec<Message<i32>>| {
+            batch
+                .into_iter()
+                .map(|m| (*m.payload, m.dropped))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kept(newest.recv_many(10).await.unwrap()), [(0, 1), (1, 0)]);
+        assert_eq!(kept(oldest.recv_many(10).await.unwrap()), [(1, 1), (2, 0)]);
+        let err = disconnect.recv().await.unwrap_err();
+        assert_eq!(err.code(), Some(SLOW_CONSUMER));
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use futures::StreamExt;
+use jetstream_error::{Error, Result};
+use jetstream_rpc::{
+    client::{ClientCodec, ClientTransport},
+    context::Context,
+    push::{AcceptUni, RecvHalf},
+    Mux, Tversion,
+};
+use jetstream_wireformat::{Data, WireFormat};
+use tokio::sync::oneshot;
+use tokio_util::codec::FramedRead;
+
+use crate::{
+    protocol::{
+        PubSub, Rmessage, Tmessage, Tnext, Tpublish, Tsubscribe, Tunsubscribe,
+        PROTOCOL_VERSION,
+    },
+    Message, SubscribeOptions,
+};
+
+/// Most messages fetched by one `Tnext`.
+const POLL_BATCH: u32 = 64;
+
+type Pushed = FramedRead<RecvHalf, ClientCodec<PubSub>>;
+
+/// Streams the server opened for subscriptions, matched up with the
+/// subscriptions as both arrive.
+#[derive(Default)]
+struct Downstream {
+    waiting: HashMap<u64, oneshot::Sender<Pushed>>,
+    arrived: HashMap<u64, Pushed>,
+}
+
+impl Downstream {
+    fn arrive(&mut self, id: u64, stream: Pushed) {
+        match self.waiting.remove(&id) {
+            Some(tx) => {
+                let _ = tx.send(stream);
+            }
+            None => {
+                self.arrived.insert(id, stream);
+            }
+        }
+    }
+
+    fn claim(&mut self, id: u64) -> oneshot::Receiver<Pushed> {
+        let (tx, rx) = oneshot::channel();
+        match self.arrived.remove(&id) {
+            Some(stream) => {
+                let _ = tx.send(stream);
+            }
+            None => {
+                self.waiting.insert(id, tx);
+            }
+        }
+        rx
+    }
+}
+
+// r[impl jetstream.pubsub.client]
+/// Publishes to and subscribes on a remote [`BrokerService`].
+///
+/// Subscriptions poll the server with `Tnext` unless the client accepts the
+/// streams the server opens, see [`PubSubClient::with_downstream`].
+///
+/// [`BrokerService`]: crate::BrokerService
+pub struct PubSubClient<T> {
+    mux: Arc<Mux<PubSub>>,
+    downstream: Option<Arc<Mutex<Downstream>>>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for PubSubClient<T> {
+    fn clone(&self) -> Self {
+        Self {
+            mux: self.mux.clone(),
+            downstream: self.downstream.clone(),
+            _payload: PhantomData,
+        }
+    }
+}
+
+impl<T: WireFormat + Send + Sync + 'static> PubSubClient<T> {
+    pub fn new(
+        max_concurrent_requests: u16,
+        transport: Box<dyn ClientTransport<PubSub>>,
+    ) -> Self {
+        Self {
+            mux: Arc::new(Mux::new(max_concurrent_requests, transport)),
+            downstream: None,
+            _payload: PhantomData,
+        }
+    }
+
+    // r[impl jetstream.pubsub.client.downstream]
+    /// Accepts the streams the server opens on `connection` to push
+    /// messages, so subscriptions stop polling.
+    pub fn with_downstream(
+        mut self,
+        connection: impl AcceptUni + 'static,
+    ) -> Self {
+        let downstream = Arc::new(Mutex::new(Downstream::default()));
+        let routes = downstream.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(recv)) = connection.accept_uni().await {
+                let routes = routes.clone();
+                tokio::spawn(async move {
+                    let mut stream =
+                        FramedRead::new(recv, ClientCodec::<PubSub>::default());
+                    match stream.next().await {
+                        Some(Ok(frame)) => match frame.msg {
+                            Rmessage::Subscribe(sub) if sub.pushed => {
+                                routes.lock().unwrap().arrive(sub.id, stream)
+                            }
+                            msg => tracing::warn!(
+                                "unexpected first frame on pushed stream: {:?}",
+                                msg
+                            ),
+                        },
+                        Some(Err(err)) => {
+                            tracing::warn!("bad pushed stream: {}", err)
+                        }
+                        None => {}
+                    }
+                });
+            }
+        });
+        self.downstream = Some(downstream);
+        self
+    }
+
+    pub async fn negotiate_version(&self, msize: u32) -> Result<()> {
+        let req = Tmessage::Version(Tversion {
+            msize,
+            version: PROTOCOL_VERSION.to_string(),
+        });
+        match self.call(req).await? {
+            Rmessage::Version(rversion) if rversion.version == "unknown" => {
+                Err(Error::new("server rejected version negotiation"))
+            }
+            Rmessage::Version(_) => Ok(()),
+            msg => Err(unexpected(msg)),
+        }
+    }
+
+    /// Publishes `payload` to `topic`, returning how many subscriptions
+    /// kept it.
+    pub async fn publish(&self, topic: &str, payload: &T) -> Result<u32> {
+        let mut data = Vec::with_capacity(payload.byte_size() as usize);
+        payload.encode(&mut data)?;
+        let req = Tmessage::Publish(Tpublish {
+            topic: topic.to_string(),
+            payload: Data(data),
+        });
+        match self.call(req).await? {
+            Rmessage::Publish(publish) => Ok(publish.delivered),
+            msg => Err(unexpected(msg)),
+        }
+    }
+
+    pub async fn subscribe(
+        &self,
+        pattern: &str,
+    ) -> Result<RemoteSubscription<T>> {
+        self.subscribe_with(pattern, SubscribeOptions::default())
+            .await
+    }
+
+    pub async fn subscribe_with(
+        &self,
+        pattern: &str,
+        options: SubscribeOptions,
+    ) -> Result<RemoteSubscription<T>> {
+        let req = Tmessage::Subscribe(Tsubscribe {
+            pattern: pattern.to_string(),
+            buffer: options.buffer.try_into().unwrap_or(u32::MAX),
+            policy: options.policy,
+            push: self.downstream.is_some(),
+        });
+        let sub = match self.call(req).await? {
+            Rmessage::Subscribe(sub) => sub,
+            msg => return Err(unexpected(msg)),
+        };
+        let source = match (&self.downstream, sub.pushed) {
+            (Some(downstream), true) => {
+                Source::Waiting(downstream.lock().unwrap().claim(sub.id))
+            }
+            _ => Source::Polled,
+        };
+        Ok(RemoteSubscription {
+            id: sub.id,
+            mux: self.mux.clone(),
+            source,
+            buffered: VecDeque::new(),
+            done: false,
+            _payload: PhantomData,
+        })
+    }
+
+    async fn call(&self, req: Tmessage) -> Result<Rmessage> {
+        call(&self.mux, req).await
+    }
+}
+
+async fn call(mux: &Mux<PubSub>, req: Tmessage) -> Result<Rmessage> {
+    match mux.rpc(Context::default(), req).await.await?.msg {
+        Rmessage::Error(err) => Err(err),
+        msg => Ok(msg),
+    }
+}
+
+fn unexpected(msg: Rmessage) -> Error {
+    Error::new(format!("unexpected pubsub response: {:?}", msg))
+}
+
+enum Source {
+    /// The server is pushing, but its stream has not been accepted yet.
+    Waiting(oneshot::Receiver<Pushed>),
+    Pushed(Box<Pushed>),
+    Polled,
+}
+
+/// Messages on topics matching a pattern, delivered by a remote broker.
+/// Dropping it unsubscribes.
+pub struct RemoteSubscription<T> {
+    id: u64,
+    mux: Arc<Mux<PubSub>>,
+    source: Source,
+    buffered: VecDeque<Message<T>>,
+    done: bool,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for RemoteSubscription<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteSubscription")
+            .field("id", &self.id)
+            .field("pushed", &!matches!(self.source, Source::Polled))
+            .finish()
+    }
+}
+
+impl<T: WireFormat> RemoteSubscription<T> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Whether the server pushes messages rather than waiting to be
+    /// polled.
+    pub fn is_pushed(&self) -> bool {
+        !matches!(self.source, Source::Polled)
+    }
+
+    /// The next message, or `None` once the subscription has ended.
+    pub async fn recv(&mut self) -> Result<Option<Message<T>>> {
+        loop {
+            if let Some(message) = self.buffered.pop_front() {
+                return Ok(Some(message));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            let msg = match &mut self.source {
+                Source::Waiting(rx) => {
+                    let stream = rx.await.map_err(|_| {
+                        Error::new("connection closed before the push stream")
+                    })?;
+                    self.source = Source::Pushed(Box::new(stream));
+                    continue;
+                }
+                Source::Pushed(stream) => match stream.next().await {
+                    Some(frame) => frame?.msg,
+                    None => {
+                        self.done = true;
+                        continue;
+                    }
+                },
+                Source::Polled => {
+                    let req = Tmessage::Next(Tnext {
+                        id: self.id,
+                        max: POLL_BATCH,
+                    });
+                    self.mux.rpc(Context::default(), req).await.await?.msg
+                }
+            };
+            let next = match msg {
+                Rmessage::Next(next) => next,
+                Rmessage::Error(err) => {
+                    self.done = true;
+                    return Err(err);
+                }
+                msg => return Err(unexpected(msg)),
+            };
+            self.done = next.messages.is_empty();
+            for delivery in next.messages {
+                self.buffered.push_back(Message {
+                    topic: delivery.topic,
+                    payload: Arc::new(T::decode(
+                        &mut delivery.payload.as_slice(),
+                    )?),
+                    dropped: delivery.dropped,
+                });
+            }
+        }
+    }
+
+    /// Ends the subscription on the server.
+    pub async fn unsubscribe(mut self) -> Result<()> {
+        self.done = true;
+        match call(
+            &self.mux,
+            Tmessage::Unsubscribe(Tunsubscribe { id: self.id }),
+        )
+        .await?
+        {
+            Rmessage::Unsubscribe(_) => Ok(()),
+            msg => Err(unexpected(msg)),
+        }
+    }
+}
+
+impl<T> Drop for RemoteSubscription<T> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let mux = self.mux.clone();
+        let id = self.id;
+        runtime.spawn(async move {
+            let _ =
+                call(&mux, Tmessage::Unsubscribe(Tunsubscribe { id })).await;
+        });
+    }
+}
@@ -0,0 +1,57 @@
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/sevki/jetstream/main/logo/JetStream.png"
+)]
+#![doc(
+    html_favicon_url = "https://raw.githubusercontent.com/sevki/jetstream/main/logo/JetStream.png"
+)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+//! Publish/subscribe over JetStream transports.
+//!
+//! A [`Broker`] routes messages published to dot-separated topics to the
+//! subscriptions whose [`Pattern`] matches them, buffering up to a limit per
+//! subscriber and applying its [`SlowConsumer`] policy beyond that. Payloads
+//! are any `WireFormat` type.
+//!
+//! Brokers work in-process, or behind a `Router` as a [`BrokerService`] that
+//! [`PubSubClient`]s talk to. On QUIC, iroh and WebTransport the service
+//! pushes messages on streams it opens to the client; elsewhere clients poll.
+//!
+//! ```ignore
+//! // server
+//! let broker = Broker::<Reading>::new();
+//! let router = Router::new()
+//!     .with_handler(PROTOCOL_NAME, BrokerService::new(broker.clone()));
+//!
+//! // client, accepting pushed streams from a QUIC connection
+//! let client = PubSubClient::<Reading>::new(16, Box::new(framed))
+//!     .with_downstream(conn.clone());
+//! client.negotiate_version(u32::MAX).await?;
+//! let mut kitchen = client.subscribe("sensors.kitchen.*").await?;
+//! while let Some(message) = kitchen.recv().await? {
+//!     println!("{}: {:?}", message.topic, message.payload);
+//! }
+//! ```
+mod broker;
+mod client;
+pub mod protocol;
+mod server;
+mod topic;
+
+pub use broker::{
+    Broker, Message, SlowConsumer, SubscribeOptions, Subscription,
+    DEFAULT_BUFFER,
+};
+pub use client::{PubSubClient, RemoteSubscription};
+pub use protocol::{PubSub, PROTOCOL_NAME, PROTOCOL_VERSION};
+pub use server::{BrokerService, DEFAULT_MAX_BUFFER};
+pub use topic::{validate_topic, Pattern};
+
+/// A topic or pattern was empty, had an empty segment, or used a wildcard
+/// where it can't.
+pub const INVALID_TOPIC: &str = "jetstream_pubsub::error::invalid_topic";
+/// The subscription fell behind with the [`SlowConsumer::Disconnect`]
+/// policy.
+pub const SLOW_CONSUMER: &str = "jetstream_pubsub::error::slow_consumer";
+/// The stream has no subscription with that id.
+pub const UNKNOWN_SUBSCRIPTION: &str =
+    "jetstream_pubsub::error::unknown_subscription";
@@ -0,0 +1,226 @@
+//! Messages of the `pubsub` protocol.
+//!
+//! ```text
+//! size[4] Tsubscribe tag[2] pattern[s] buffer[4] policy[1] push[1]
+//! size[4] Rsubscribe tag[2] id[8] pushed[1]
+//! size[4] Tunsubscribe tag[2] id[8]
+//! size[4] Runsubscribe tag[2]
+//! size[4] Tpublish tag[2] topic[s] payload[4+n]
+//! size[4] Rpublish tag[2] delivered[4]
+//! size[4] Tnext tag[2] id[8] max[4]
+//! size[4] Rnext tag[2] messages[2+n]
+//! ```
+use std::io::{self, Read, Write};
+
+use jetstream_error::Error;
+use jetstream_rpc::{
+    Framer, Protocol, Rversion, Tversion, RJETSTREAMERROR, RVERSION, TVERSION,
+};
+use jetstream_wireformat::{Data, JetStreamWireFormat, WireFormat};
+
+use crate::SlowConsumer;
+
+pub const PROTOCOL_NAME: &str = "pubsub";
+pub const PROTOCOL_VERSION: &str =
+    concat!("rs.jetstream.proto/pubsub/", env!("CARGO_PKG_VERSION"));
+
+pub const TSUBSCRIBE: u8 = RVERSION + 1;
+pub const RSUBSCRIBE: u8 = TSUBSCRIBE + 1;
+pub const TUNSUBSCRIBE: u8 = RSUBSCRIBE + 1;
+pub const RUNSUBSCRIBE: u8 = TUNSUBSCRIBE + 1;
+pub const TPUBLISH: u8 = RUNSUBSCRIBE + 1;
+pub const RPUBLISH: u8 = TPUBLISH + 1;
+pub const TNEXT: u8 = RPUBLISH + 1;
+pub const RNEXT: u8 = TNEXT + 1;
+
+/// Subscribes the stream to topics matching `pattern`. With `push` set the
+/// server delivers messages on a stream it opens, if the transport can.
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Tsubscribe {
+    pub pattern: String,
+    pub buffer: u32,
+    pub policy: SlowConsumer,
+    pub push: bool,
+}
+
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Rsubscribe {
+    pub id: u64,
+    /// Whether messages arrive on a pushed stream rather than through
+    /// [`Tnext`].
+    pub pushed: bool,
+}
+
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Tunsubscribe {
+    pub id: u64,
+}
+
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Runsubscribe {}
+
+/// Publishes an encoded payload to `topic`.
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Tpublish {
+    pub topic: String,
+    pub payload: Data,
+}
+
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Rpublish {
+    pub delivered: u32,
+}
+
+/// Waits for messages on a subscription that is not pushed and returns up
+/// to `max` of them.
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Tnext {
+    pub id: u64,
+    pub max: u32,
+}
+
+/// A batch of messages. An empty batch ends the subscription.
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Rnext {
+    pub messages: Vec<Delivery>,
+}
+
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Delivery {
+    pub topic: String,
+    pub payload: Data,
+    pub dropped: u64,
+}
+
+#[derive(Debug)]
+pub enum Tmessage {
+    Version(Tversion),
+    Subscribe(Tsubscribe),
+    Unsubscribe(Tunsubscribe),
+    Publish(Tpublish),
+    Next(Tnext),
+}
+
+#[derive(Debug)]
+pub enum Rmessage {
+    Version(Rversion),
+    Subscribe(Rsubscribe),
+    Unsubscribe(Runsubscribe),
+    Publish(Rpublish),
+    Next(Rnext),
+    Error(Error),
+}
+
+impl Framer for Tmessage {
+    fn message_type(&self) -> u8 {
+        match self {
+            Tmessage::Version(_) => TVERSION,
+            Tmessage::Subscribe(_) => TSUBSCRIBE,
+            Tmessage::Unsubscribe(_) => TUNSUBSCRIBE,
+            Tmessage::Publish(_) => TPUBLISH,
+            Tmessage::Next(_) => TNEXT,
+        }
+    }
+
+    fn byte_size(&self) -> u32 {
+        match self {
+            Tmessage::Version(msg) => msg.byte_size(),
+            Tmessage::Subscribe(msg) => msg.byte_size(),
+            Tmessage::Unsubscribe(msg) => msg.byte_size(),
+            Tmessage::Publish(msg) => msg.byte_size(),
+            Tmessage::Next(msg) => msg.byte_size(),
+        }
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Tmessage::Version(msg) => msg.encode(writer),
+            Tmessage::Subscribe(msg) => msg.encode(writer),
+            Tmessage::Unsubscribe(msg) => msg.encode(writer),
+            Tmessage::Publish(msg) => msg.encode(writer),
+            Tmessage::Next(msg) => msg.encode(writer),
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R, ty: u8) -> io::Result<Self> {
+        match ty {
+            TVERSION => Ok(Tmessage::Version(WireFormat::decode(reader)?)),
+            TSUBSCRIBE => Ok(Tmessage::Subscribe(WireFormat::decode(reader)?)),
+            TUNSUBSCRIBE => {
+                Ok(Tmessage::Unsubscribe(WireFormat::decode(reader)?))
+            }
+            TPUBLISH => Ok(Tmessage::Publish(WireFormat::decode(reader)?)),
+            TNEXT => Ok(Tmessage::Next(WireFormat::decode(reader)?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid pubsub request type: {}", ty),
+            )),
+        }
+    }
+}
+
+impl Framer for Rmessage {
+    fn message_type(&self) -> u8 {
+        match self {
+            Rmessage::Version(_) => RVERSION,
+            Rmessage::Subscribe(_) => RSUBSCRIBE,
+            Rmessage::Unsubscribe(_) => RUNSUBSCRIBE,
+            Rmessage::Publish(_) => RPUBLISH,
+            Rmessage::Next(_) => RNEXT,
+            Rmessage::Error(_) => RJETSTREAMERROR,
+        }
+    }
+
+    fn byte_size(&self) -> u32 {
+        match self {
+            Rmessage::Version(msg) => msg.byte_size(),
+            Rmessage::Subscribe(msg) => msg.byte_size(),
+            Rmessage::Unsubscribe(msg) => msg.byte_size(),
+            Rmessage::Publish(msg) => msg.byte_size(),
+            Rmessage::Next(msg) => msg.byte_size(),
+            Rmessage::Error(err) => err.byte_size(),
+        }
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Rmessage::Version(msg) => msg.encode(writer),
+            Rmessage::Subscribe(msg) => msg.encode(writer),
+            Rmessage::Unsubscribe(msg) => msg.encode(writer),
+            Rmessage::Publish(msg) => msg.encode(writer),
+            Rmessage::Next(msg) => msg.encode(writer),
+            Rmessage::Error(err) => err.encode(writer),
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R, ty: u8) -> io::Result<Self> {
+        match ty {
+            RVERSION => Ok(Rmessage::Version(WireFormat::decode(reader)?)),
+            RSUBSCRIBE => Ok(Rmessage::Subscribe(WireFormat::decode(reader)?)),
+            RUNSUBSCRIBE => {
+                Ok(Rmessage::Unsubscribe(WireFormat::decode(reader)?))
+            }
+            RPUBLISH => Ok(Rmessage::Publish(WireFormat::decode(reader)?)),
+            RNEXT => Ok(Rmessage::Next(WireFormat::decode(reader)?)),
+            RJETSTREAMERROR => Ok(Rmessage::Error(WireFormat::decode(reader)?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid pubsub response type: {}", ty),
+            )),
+        }
+    }
+}
+
+// r[impl jetstream.pubsub.protocol]
+/// The `pubsub` protocol, served by [`BrokerService`](crate::BrokerService)
+/// and spoken by [`PubSubClient`](crate::PubSubClient).
+#[derive(Debug, Clone, Copy)]
+pub struct PubSub;
+
+impl Protocol for PubSub {
+    type Request = Tmessage;
+    type Response = Rmessage;
+    type Error = Error;
+    const VERSION: &'static str = PROTOCOL_VERSION;
+    const NAME: &'static str = PROTOCOL_NAME;
+}
@@ -0,0 +1,356 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use jetstream_error::{Error, IntoError, Result};
+use jetstream_rpc::{
+    context::Context, push::Upstream, server::ServerCodec, Frame, Handler,
+    NOTAG,
+};
+use jetstream_wireformat::{Data, WireFormat};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+    task::AbortHandle,
+};
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+use crate::{
+    protocol::{
+        Delivery, PubSub, Rmessage, Rnext, Rpublish, Rsubscribe, Runsubscribe,
+        Tmessage, Tsubscribe,
+    },
+    Broker, Message, SubscribeOptions, Subscription, UNKNOWN_SUBSCRIPTION,
+};
+
+/// Most messages sent in one pushed batch.
+const PUSH_BATCH: usize = 64;
+/// Bytes of an `Rnext` frame besides its deliveries: size, type, tag and
+/// the message count.
+const RNEXT_OVERHEAD: usize = 4 + 1 + 2 + 2;
+
+/// Most messages a client may ask a subscription to buffer, unless the
+/// service was built with another limit.
+pub const DEFAULT_MAX_BUFFER: usize = 4096;
+
+// r[impl jetstream.pubsub.service]
+/// Serves a [`Broker`] as the `pubsub` protocol.
+///
+/// ```ignore
+/// let broker = Broker::<Reading>::new();
+/// let router = Router::new()
+///     .with_handler(PROTOCOL_NAME, BrokerService::new(broker.clone()));
+/// ```
+///
+/// Subscriptions belong to the stream that made them and end with it.
+pub struct BrokerService<T> {
+    broker: Broker<T>,
+    max_buffer: usize,
+}
+
+impl<T> BrokerService<T> {
+    pub fn new(broker: Broker<T>) -> Self {
+        Self {
+            broker,
+            max_buffer: DEFAULT_MAX_BUFFER,
+        }
+    }
+
+    /// Caps the buffer clients may ask for in `Tsubscribe`. Defaults to
+    /// [`DEFAULT_MAX_BUFFER`].
+    pub fn with_max_buffer(mut self, max_buffer: usize) -> Self {
+        self.max_buffer = max_buffer;
+        self
+    }
+}
+
+/// Subscriptions made on one stream.
+struct Session<T> {
+    broker: Broker<T>,
+    upstream: Option<Upstream>,
+    max_buffer: usize,
+    msize: Option<u32>,
+    polled: Mutex<HashMap<u64, Arc<tokio::sync::Mutex<Polled<T>>>>>,
+    pushed: Mutex<HashMap<u64, AbortHandle>>,
+}
+
+/// A subscription the client polls, and the messages received for it that
+/// did not fit in the last `Rnext`.
+struct Polled<T> {
+    sub: Subscription<T>,
+    pending: VecDeque<Delivery>,
+}
+
+impl<T> Drop for Session<T> {
+    fn drop(&mut self) {
+        for (_, task) in self.pushed.get_mut().unwrap().drain() {
+            task.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl<T: WireFormat + Send + Sync + 'static> Handler for BrokerService<T> {
+    async fn handle(
+        &self,
+        ctx: Context,
+        reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+        writer: Box<dyn AsyncWrite + Send + Sync + Unpin>,
+    ) -> Result<()> {
+        let session = Arc::new(Session {
+            broker: self.broker.clone(),
+            upstream: ctx.upstream().cloned(),
+            max_buffer: self.max_buffer,
+            msize: ctx.msize(),
+            polled: Mutex::new(HashMap::new()),
+            pushed: Mutex::new(HashMap::new()),
+        });
+        tokio::spawn(async move {
+            let codec = ServerCodec::<PubSub>::new();
+            codec.compression().set(ctx.compression());
+            codec.compression().set_msize(ctx.msize());
+            let mut reader = FramedRead::new(reader, codec.clone());
+            let mut writer = FramedWrite::new(writer, codec);
+
+            let (resp_tx, mut resp_rx) = mpsc::channel::<Frame<Rmessage>>(256);
+            let writer_task = tokio::spawn(async move {
+                while let Some(resp) = resp_rx.recv().await {
+                    let tag = resp.tag;
+                    let Err(err) = writer.send(resp).await else {
+                        continue;
+                    };
+                    // A response that can't be encoded fails its request,
+                    // not the stream.
+                    let msg = Rmessage::Error(err);
+                    if writer.send(Frame { tag, msg }).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            // Requests run concurrently, a Tnext waiting for messages must
+            // not hold up the rest of the stream.
+            let mut requests = tokio::task::JoinSet::new();
+            while let Some(req) = reader.next().await {
+                match req {
+                    Ok(req) => {
+                        let session = session.clone();
+                        let resp_tx = resp_tx.clone();
+                        requests.spawn(async move {
+                            let msg = session
+                                .rpc(req.msg)
+                                .await
+                                .unwrap_or_else(Rmessage::Error);
+                            let _ =
+                                resp_tx.send(Frame { tag: req.tag, msg }).await;
+                        });
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            "Error decoding pubsub frame: {}",
+                            err.into_error()
+                        );
+                    }
+                }
+            }
+
+            // r[impl jetstream.pubsub.service.stream]
+            requests.abort_all();
+            drop(session);
+            drop(resp_tx);
+            let _ = writer_task.await;
+        });
+        Ok(())
+    }
+}
+
+impl<T: WireFormat + Send + Sync + 'static> Session<T> {
+    async fn rpc(&self, req: Tmessage) -> Result<Rmessage> {
+        match req {
+            Tmessage::Version(_) => {
+                Err(Error::new("version already negotiated"))
+            }
+            Tmessage::Publish(publish) => {
+                let payload = T::decode(&mut publish.payload.as_slice())?;
+                let delivered = self.broker.publish(&publish.topic, payload)?;
+                Ok(Rmessage::Publish(Rpublish {
+                    delivered: delivered as u32,
+                }))
+            }
+            Tmessage::Subscribe(subscribe) => self.subscribe(subscribe).await,
+            Tmessage::Unsubscribe(unsubscribe) => {
+                let id = unsubscribe.id;
+                let polled = self.polled.lock().unwrap().remove(&id);
+                let pushed = self.pushed.lock().unwrap().remove(&id);
+                match (polled, pushed) {
+                    (None, None) => Err(unknown_subscription(id)),
+                    (_, pushed) => {
+                        if let Some(task) = pushed {
+                            task.abort();
+                        }
+                        Ok(Rmessage::Unsubscribe(Runsubscribe {}))
+                    }
+                }
+            }
+            Tmessage::Next(next) => {
+                let polled = self
+                    .polled
+                    .lock()
+                    .unwrap()
+                    .get(&next.id)
+                    .cloned()
+                    .ok_or_else(|| unknown_subscription(next.id))?;
+                let mut polled = polled.lock().await;
+                // The count of an Rnext is a u16.
+                let max = (next.max as usize).clamp(1, u16::MAX as usize);
+                if polled.pending.is_empty() {
+                    match polled.sub.recv_many(max).await {
+                        Ok(messages) => {
+                            polled.pending.extend(encode(messages)?)
+                        }
+                        Err(err) => {
+                            self.polled.lock().unwrap().remove(&next.id);
+                            return Err(err);
+                        }
+                    }
+                }
+                Ok(Rmessage::Next(batch(&mut polled.pending, max, self.msize)))
+            }
+        }
+    }
+
+    async fn subscribe(&self, subscribe: Tsubscribe) -> Result<Rmessage> {
+        let mut sub = self.broker.subscribe_with(
+            &subscribe.pattern,
+            SubscribeOptions {
+                buffer: (subscribe.buffer as usize).min(self.max_buffer),
+                policy: subscribe.policy,
+            },
+        )?;
+        let id = sub.id();
+        // r[impl jetstream.pubsub.push]
+        if let (true, Some(upstream)) = (subscribe.push, &self.upstream) {
+            match upstream.open_uni().await {
+                Ok(send) => {
+                    let mut writer =
+                        FramedWrite::new(send, ServerCodec::<PubSub>::new());
+                    writer
+                        .send(Frame {
+                            tag: NOTAG,
+                            msg: Rmessage::Subscribe(Rsubscribe {
+                                id,
+                                pushed: true,
+                            }),
+                        })
+                        .await?;
+                    let msize = self.msize;
+                    let task = tokio::spawn(async move {
+                        let mut pending = VecDeque::new();
+                        let err = 'push: loop {
+                            let messages = match sub.recv_many(PUSH_BATCH).await
+                            {
+                                Ok(messages) if messages.is_empty() => {
+                                    break None
+                                }
+                                Ok(messages) => messages,
+                                Err(err) => break Some(err),
+                            };
+                            match encode(messages) {
+                                Ok(deliveries) => pending.extend(deliveries),
+                                Err(err) => break Some(err),
+                            }
+                            while !pending.is_empty() {
+                                let msg = Rmessage::Next(batch(
+                                    &mut pending,
+                                    PUSH_BATCH,
+                                    msize,
+                                ));
+                                let frame = Frame { tag: NOTAG, msg };
+                                if let Err(err) = writer.send(frame).await {
+                                    break 'push Some(err);
+                                }
+                            }
+                        };
+                        // A subscription that fails ends its stream with an
+                        // error frame.
+                        if let Some(err) = err {
+                            let msg = Rmessage::Error(err);
+                            let _ =
+                                writer.send(Frame { tag: NOTAG, msg }).await;
+                        }
+                        let _ = writer.close().await;
+                    });
+                    self.pushed.lock().unwrap().insert(id, task.abort_handle());
+                    return Ok(Rmessage::Subscribe(Rsubscribe {
+                        id,
+                        pushed: true,
+                    }));
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "couldn't open a stream to push subscription {id}, \
+                         falling back to polling: {err}"
+                    );
+                }
+            }
+        }
+        self.polled.lock().unwrap().insert(
+            id,
+            Arc::new(tokio::sync::Mutex::new(Polled {
+                sub,
+                pending: VecDeque::new(),
+            })),
+        );
+        Ok(Rmessage::Subscribe(Rsubscribe { id, pushed: false }))
+    }
+}
+
+fn encode<T: WireFormat>(messages: Vec<Message<T>>) -> Result<Vec<Delivery>> {
+    messages
+        .into_iter()
+        .map(|message| {
+            let mut payload =
+                Vec::with_capacity(message.payload.byte_size() as usize);
+            message.payload.encode(&mut payload)?;
+            Ok(Delivery {
+                topic: message.topic,
+                payload: Data(payload),
+                dropped: message.dropped,
+            })
+        })
+        .collect()
+}
+
+// r[impl jetstream.pubsub.service.limits]
+/// Takes up to `max` deliveries off `pending`, as many as fit in one frame
+/// of `msize` bytes. The first is always taken, so a delivery too large for
+/// any frame fails to encode instead of stalling the subscription.
+fn batch(
+    pending: &mut VecDeque<Delivery>,
+    max: usize,
+    msize: Option<u32>,
+) -> Rnext {
+    let budget = msize
+        .map_or(usize::MAX, |msize| msize as usize)
+        .saturating_sub(RNEXT_OVERHEAD);
+    let mut size = 0;
+    let mut messages = Vec::new();
+    while let Some(delivery) = pending.front() {
+        let len = delivery.byte_size() as usize;
+        if messages.len() == max
+            || (!messages.is_empty() && size + len > budget)
+        {
+            break;
+        }
+        size += len;
+        messages.extend(pending.pop_front());
+    }
+    Rnext { messages }
+}
+
+fn unknown_subscription(id: u64) -> Error {
+    Error::with_code(format!("unknown subscription {id}"), UNKNOWN_SUBSCRIPTION)
+}
@@ -0,0 +1,155 @@
+use std::{fmt, str::FromStr};
+
+use jetstream_error::{Error, Result};
+
+use crate::INVALID_TOPIC;
+
+// r[impl jetstream.pubsub.topic]
+/// Checks that `topic` names a single topic: one or more non-empty segments
+/// separated by `.`, none of them a wildcard.
+pub fn validate_topic(topic: &str) -> Result<()> {
+    for segment in topic.split('.') {
+        match segment {
+            "" => return Err(invalid(topic, "empty segment")),
+            "*" | ">" => {
+                return Err(invalid(topic, "wildcards only match topics"))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn invalid(topic: &str, reason: &str) -> Error {
+    Error::with_code(
+        format!("invalid topic {topic:?}: {reason}"),
+        INVALID_TOPIC,
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `*`, exactly one segment.
+    One,
+    /// `>`, one or more trailing segments.
+    Rest,
+}
+
+// r[impl jetstream.pubsub.pattern]
+/// Topics a subscription receives.
+///
+/// A pattern is a topic whose segments may be `*`, matching any one segment,
+/// and whose last segment may be `>`, matching one or more segments.
+///
+/// ```
+/// # use jetstream_pubsub::Pattern;
+/// let pattern: Pattern = "sensors.*.temperature".parse().unwrap();
+/// assert!(pattern.matches("sensors.kitchen.temperature"));
+/// assert!(!pattern.matches("sensors.kitchen.humidity"));
+///
+/// let pattern: Pattern = "sensors.>".parse().unwrap();
+/// assert!(pattern.matches("sensors.kitchen.humidity"));
+/// assert!(!pattern.matches("sensors"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let count = pattern.split('.').count();
+        let segments = pattern
+            .split('.')
+            .enumerate()
+            .map(|(i, segment)| match segment {
+                "" => Err(invalid(pattern, "empty segment")),
+                "*" => Ok(Segment::One),
+                ">" if i + 1 == count => Ok(Segment::Rest),
+                ">" => Err(invalid(pattern, "`>` must be the last segment")),
+                literal => Ok(Segment::Literal(literal.to_string())),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            source: pattern.to_string(),
+            segments,
+        })
+    }
+
+    /// Whether messages published to `topic` match the pattern.
+    pub fn matches(&self, topic: &str) -> bool {
+        let mut topic = topic.split('.');
+        for segment in &self.segments {
+            let Some(next) = topic.next() else {
+                return false;
+            };
+            match segment {
+                Segment::Literal(literal) if literal != next => return false,
+                Segment::Literal(_) | Segment::One => {}
+                Segment::Rest => return true,
+            }
+        }
+        topic.next().is_none()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// r[verify jetstream.pubsub.pattern]
+    #[test]
+    fn wildcards() {
+        let cases = [
+            ("a.b.c", "a.b.c", true),
+            ("a.b.c", "a.b", false),
+            ("a.b", "a.b.c", false),
+            ("a.*.c", "a.x.c", true),
+            ("a.*.c", "a.x.y.c", false),
+            ("*", "a", true),
+            ("*", "a.b", false),
+            ("a.>", "a.b", true),
+            ("a.>", "a.b.c.d", true),
+            ("a.>", "a", false),
+            ("*.>", "a.b", true),
+            (">", "a", true),
+        ];
+        for (pattern, topic, matches) in cases {
+            let parsed = Pattern::parse(pattern).unwrap();
+            assert_eq!(parsed.matches(topic), matches, "{pattern} ~ {topic}");
+        }
+    }
+
+    /// r[verify jetstream.pubsub.topic]
+    #[test]
+    fn invalid_topics_and_patterns() {
+        for topic in ["", "a..b", ".a", "a.", "a.*", "a.>"] {
+            let err = validate_topic(topic).unwrap_err();
+            assert_eq!(err.code(), Some(INVALID_TOPIC), "{topic}");
+        }
+        for pattern in ["", "a..b", "a.>.b", ">.a"] {
+            assert!(Pattern::parse(pattern).is_err(), "{pattern}");
+        }
+        validate_topic("sensors.kitchen").unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+use std::{io, time::Duration};
+
+use async_trait::async_trait;
+use jetstream_pubsub::{
+    Broker, BrokerService, PubSub, PubSubClient, SlowConsumer,
+    SubscribeOptions, PROTOCOL_NAME, SLOW_CONSUMER,
+};
+use jetstream_rpc::{
+    client::ClientCodec,
+    context::Context,
+    push::{AcceptUni, OpenUni, RecvHalf, SendHalf, Upstream},
+    Framed, Router,
+};
+use jetstream_wireformat::JetStreamWireFormat;
+use tokio::{io::DuplexStream, sync::mpsc};
+
+#[derive(Debug, Clone, PartialEq, JetStreamWireFormat)]
+struct Reading {
+    room: String,
+    celsius: i32,
+}
+
+fn reading(room: &str, celsius: i32) -> Reading {
+    Reading {
+        room: room.to_string(),
+        celsius,
+    }
+}
+
+/// Server-opened streams over in-memory pipes.
+struct Pipes(mpsc::UnboundedSender<DuplexStream>);
+
+#[async_trait]
+impl OpenUni for Pipes {
+    async fn open_uni(&self) -> io::Result<SendHalf> {
+        let (send, recv) = tokio::io::duplex(64 * 1024);
+        self.0
+            .send(recv)
+            .map_err(|_| io::Error::other("client went away"))?;
+        Ok(Box::new(send))
+    }
+}
+
+struct Accepted(tokio::sync::Mutex<mpsc::UnboundedReceiver<DuplexStream>>);
+
+#[async_trait]
+impl AcceptUni for Accepted {
+    async fn accept_uni(&self) -> io::Result<Option<RecvHalf>> {
+        let recv = self.0.lock().await.recv().await;
+        Ok(recv.map(|recv| Box::new(recv) as RecvHalf))
+    }
+}
+
+async fn connect(
+    broker: &Broker<Reading>,
+    push: bool,
+) -> PubSubClient<Reading> {
+    let router = Router::new()
+        .with_handler(PROTOCOL_NAME, BrokerService::new(broker.clone()));
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let (pipes, accepted) = mpsc::unbounded_channel();
+    let ctx = if push {
+        Context::default().with_extension(Upstream::new(Pipes(pipes)))
+    } else {
+        Context::default()
+    };
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router.accept(ctx, Box::new(reader), Box::new(writer)).await
+    });
+    let framed = Framed::new(client, ClientCodec::<PubSub>::default());
+    let client = PubSubClient::new(16, Box::new(framed));
+    let client = if push {
+        client.with_downstream(Accepted(tokio::sync::Mutex::new(accepted)))
+    } else {
+        client
+    };
+    client.negotiate_version(64 * 1024).await.unwrap();
+    client
+}
+
+async fn wait_for_subscribers(broker: &Broker<Reading>, count: usize) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while broker.subscribers() != count {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+/// r[verify jetstream.pubsub.client]
+#[tokio::test]
+async fn remote_subscriptions_poll_without_push() {
+    let broker = Broker::new();
+    let client = connect(&broker, false).await;
+
+    let mut kitchen = client.subscribe("sensors.kitchen.*").await.unwrap();
+    assert!(!kitchen.is_pushed());
+
+    assert_eq!(
+        client
+            .publish("sensors.kitchen.temp", &reading("kitchen", 21))
+            .await
+            .unwrap(),
+        1
+    );
+    broker
+        .publish("sensors.garage.temp", reading("garage", 9))
+        .unwrap();
+    broker
+        .publish("sensors.kitchen.temp", reading("kitchen", 22))
+        .unwrap();
+
+    let message = kitchen.recv().await.unwrap().unwrap();
+    assert_eq!(message.topic, "sensors.kitchen.temp");
+    assert_eq!(*message.payload, reading("kitchen", 21));
+    let message = kitchen.recv().await.unwrap().unwrap();
+    assert_eq!(*message.payload, reading("kitchen", 22));
+
+    kitchen.unsubscribe().await.unwrap();
+    assert_eq!(broker.subscribers(), 0);
+}
+
+/// r[verify jetstream.pubsub.push]
+/// r[verify jetstream.pubsub.client.downstream]
+#[tokio::test]
+async fn remote_subscriptions_are_pushed_on_server_streams() {
+    let broker = Broker::new();
+    let client = connect(&broker, true).await;
+
+    let mut all = client.subscribe("sensors.>").await.unwrap();
+    let mut garage = client.subscribe("sensors.garage.*").await.unwrap();
+    assert!(all.is_pushed());
+
+    for celsius in 0..3 {
+        broker
+            .publish("sensors.garage.temp", reading("garage", celsius))
+            .unwrap();
+    }
+    for celsius in 0..3 {
+        let message = all.recv().await.unwrap().unwrap();
+        assert_eq!(*message.payload, reading("garage", celsius));
+        let message = garage.recv().await.unwrap().unwrap();
+        assert_eq!(*message.payload, reading("garage", celsius));
+    }
+
+    drop(garage);
+    wait_for_subscribers(&broker, 1).await;
+}
+
+/// r[verify jetstream.pubsub.slow-consumer]
+#[tokio::test]
+async fn slow_remote_consumers_are_disconnected() {
+    let broker = Broker::new();
+    let client = connect(&broker, false).await;
+
+    let options = SubscribeOptions {
+        buffer: 1,
+        policy: SlowConsumer::Disconnect,
+    };
+    let mut sub = client.subscribe_with("alerts", options).await.unwrap();
+    broker.publish("alerts", reading("attic", 60)).unwrap();
+    broker.publish("alerts", reading("attic", 70)).unwrap();
+
+    let err = sub.recv().await.unwrap_err();
+    assert_eq!(err.code(), Some(SLOW_CONSUMER));
+    assert!(sub.recv().await.unwrap().is_none());
+}
+
+/// r[verify jetstream.pubsub.service.stream]
+#[tokio::test]
+async fn subscriptions_end_with_their_stream() {
+    use futures::{SinkExt, StreamExt};
+    use jetstream_pubsub::protocol::{Rmessage, Tmessage, Tsubscribe};
+    use jetstream_rpc::{Frame, Tversion, NOTAG};
+
+    let broker = Broker::<Reading>::new();
+    let router = Router::new()
+        .with_handler(PROTOCOL_NAME, BrokerService::new(broker.clone()));
+    for push in [false, true] {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (pipes, _accepted) = mpsc::unbounded_channel();
+        let ctx =
+            Context::default().with_extension(Upstream::new(Pipes(pipes)));
+        let router = router.clone();
+        tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(server);
+            router.accept(ctx, Box::new(reader), Box::new(writer)).await
+        });
+
+        let mut framed = Framed::new(client, ClientCodec::<PubSub>::default());
+        let version = Tmessage::Version(Tversion {
+            msize: 64 * 1024,
+            version: jetstream_pubsub::PROTOCOL_VERSION.to_string(),
+        });
+        framed
+            .send(Frame {
+                tag: NOTAG,
+                msg: version,
+            })
+            .await
+            .unwrap();
+        framed.next().await.unwrap().unwrap();
+        let subscribe = Tmessage::Subscribe(Tsubscribe {
+            pattern: "a.>".to_string(),
+            buffer: 8,
+            policy: SlowConsumer::DropOldest,
+            push,
+        });
+        framed
+            .send(Frame {
+                tag: 1,
+                msg: subscribe,
+            })
+            .await
+            .unwrap();
+        let rframe = framed.next().await.unwrap().unwrap();
+        assert!(
+            matches!(rframe.msg, Rmessage::Subscribe(sub) if sub.pushed == push)
+        );
+        assert_eq!(broker.subscribers(), 1);
+
+        drop(framed);
+        wait_for_subscribers(&broker, 0).await;
+    }
+}
+
+/// r[verify jetstream.pubsub.service.limits]
+#[tokio::test]
+async fn polled_batches_fit_the_msize_and_buffers_are_capped() {
+    use futures::{SinkExt, StreamExt};
+    use jetstream_pubsub::protocol::{Rmessage, Tmessage, Tnext, Tsubscribe};
+    use jetstream_rpc::{Frame, Tversion, NOTAG};
+
+    let broker = Broker::<Reading>::new();
+    let router = Router::new().with_handler(
+        PROTOCOL_NAME,
+        BrokerService::new(broker.clone()).with_max_buffer(4),
+    );
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+
+    let mut framed = Framed::new(client, ClientCodec::<PubSub>::default());
+    let version = Tmessage::Version(Tversion {
+        msize: 256,
+        version: jetstream_pubsub::PROTOCOL_VERSION.to_string(),
+    });
+    framed.send(Frame::from((NOTAG, version))).await.unwrap();
+    framed.next().await.unwrap().unwrap();
+    let subscribe = Tmessage::Subscribe(Tsubscribe {
+        pattern: "a.>".to_string(),
+        buffer: u32::MAX,
+        policy: SlowConsumer::DropOldest,
+        push: false,
+    });
+    framed.send(Frame::from((1, subscribe))).await.unwrap();
+    let Rmessage::Subscribe(sub) = framed.next().await.unwrap().unwrap().msg
+    else {
+        panic!("expected Rsubscribe");
+    };
+
+    // Each delivery is about 90 bytes, and only the last four are kept.
+    let room = "r".repeat(60);
+    for celsius in 0..6 {
+        broker.publish("a.b", reading(&room, celsius)).unwrap();
+    }
+
+    let mut received = Vec::new();
+    while received.len() < 4 {
+        let next = Tmessage::Next(Tnext {
+            id: sub.id,
+            max: u32::MAX,
+        });
+        framed.send(Frame::from((2, next))).await.unwrap();
+        let Rmessage::Next(next) = framed.next().await.unwrap().unwrap().msg
+        else {
+            panic!("expected Rnext");
+        };
+        assert!(
+            (1..=2).contains(&next.messages.len()),
+            "{} deliveries do not fit in 256 bytes",
+            next.messages.len()
+        );
+        received.extend(next.messages);
+    }
+    assert_eq!(received[0].dropped, 2);
+    let celsius = received
+        .iter()
+        .map(|delivery| {
+            <Reading as jetstream_wireformat::WireFormat>::decode(
+                &mut delivery.payload.as_slice(),
+            )
+            .unwrap()
+            .celsius
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(celsius, [2, 3, 4, 5]);
+}
@@ -108,9 +108,11 @@ pub struct QuicTransport<P: Protocol> {
 
 impl<P: Protocol> From<(SendStream, RecvStream)> for QuicTransport<P> {
     fn from((send, recv): (SendStream, RecvStream)) -> Self {
+        // Both halves share the compression negotiated on this stream.
+        let codec = ClientCodec::<P>::default();
         Self {
-            send_stream: FramedWrite::new(send, ClientCodec::<P>::default()),
-            recv_stream: FramedRead::new(recv, ClientCodec::<P>::default()),
+            send_stream: FramedWrite::new(send, codec.clone()),
+            recv_stream: FramedRead::new(recv, codec),
         }
     }
 }
@@ -1,6 +1,10 @@
 use crate::QuicHandler;
 use async_trait::async_trait;
-use jetstream_rpc::{context::Context, Router};
+use jetstream_rpc::{
+    context::{Context, StreamId},
+    push::Upstream,
+    Router,
+};
 use quinn::Connection;
 use std::sync::Arc;
 
@@ -26,9 +30,11 @@ impl QuicHandler for QuicRouterHandler {
 
     async fn accept(&self, ctx: Context, conn: Connection) {
         let router = self.router.clone();
+        // r[impl jetstream.push.upstream]
+        let ctx = ctx.with_extension(Upstream::new(conn.clone()));
         while let Ok((send, recv)) = conn.accept_bi().await {
             let router = router.clone();
-            let ctx = ctx.clone();
+            let ctx = ctx.clone().with_extension(StreamId(send.id().into()));
             tokio::spawn(async move {
                 if let Err(e) =
                     router.accept(ctx, Box::new(recv), Box::new(send)).await
@@ -11,9 +11,11 @@ mod jetstream_over_quic;
 mod quic_handler;
 mod router;
 mod server;
+mod streams;
 
 pub use client::{Client, QuicTransport};
 pub use jetstream_over_quic::QuicRouterHandler;
 pub use quic_handler::QuicHandler;
 pub use router::Router as QuicRouter;
 pub use server::Server;
+pub use streams::{PerCall, StreamPool};
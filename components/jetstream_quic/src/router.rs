@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::Arc};
 
-use jetstream_rpc::context::{Peer, RemoteAddr, TlsPeer};
+use jetstream_rpc::context::{ConnectionId, Peer, RemoteAddr, TlsPeer};
 use quinn::{crypto::rustls::HandshakeData, Incoming};
 use tracing::{error, info, warn};
 
@@ -68,7 +68,8 @@ impl Router {
                 let ctx = jetstream_rpc::context::Context::new(
                     Some(RemoteAddr::IpAddr(conn.remote_address().ip())),
                     peer,
-                );
+                )
+                .with_extension(ConnectionId(conn.stable_id() as u64));
 
                 // Get ALPN protocol from handshake data
                 let alpn_protocol = if let Some(handshake_data) =
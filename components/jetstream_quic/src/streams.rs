@@ -0,0 +1,198 @@
+//! Spreading one client's calls over several streams of a QUIC connection.
+//!
+//! A channel built on a single [`QuicTransport`] multiplexes every call over
+//! one bidirectional stream, so a large response holds up every call queued
+//! behind it. [`StreamPool`] opens several bidi streams on the same
+//! [`quinn::Connection`], negotiates a channel on each (the server's
+//! [`QuicRouterHandler`](crate::QuicRouterHandler) routes every stream
+//! separately) and schedules calls on the stream with the fewest requests in
+//! flight.
+//!
+//! [`StreamPool::per_call`] instead opens a fresh stream for every call, so
+//! no two calls ever share one, at the cost of a version negotiation round
+//! trip per call.
+//!
+//! ```ignore
+//! let conn = client.connect(addr, "localhost").await?;
+//! let chan = StreamPool::new(conn)
+//!     .streams(8)
+//!     .balancer(|transport| async move {
+//!         let chan = EchoChannel::new(10, Box::new(transport));
+//!         chan.negotiate_version(u32::MAX).await?;
+//!         Ok(chan)
+//!     })
+//!     .build()
+//!     .await?;
+//! chan.ping().await?;
+//!
+//! let calls = StreamPool::new(conn).per_call(|transport| async move {
+//!     let chan = EchoChannel::new(1, Box::new(transport));
+//!     chan.negotiate_version(u32::MAX).await?;
+//!     Ok(chan)
+//! });
+//! calls.call(|mut chan| async move { chan.ping().await }).await?;
+//! ```
+use std::{future::Future, sync::Arc};
+
+use futures::future::BoxFuture;
+use jetstream_rpc::{
+    balance::{Backend, Balancer, Builder, Resolver, Strategy},
+    Error, Protocol,
+};
+use quinn::Connection;
+use tokio::sync::Semaphore;
+
+use crate::QuicTransport;
+
+// r[impl jetstream.quic.streams]
+/// A fixed number of bidi streams on one QUIC connection.
+///
+/// The pool resolves to stream slots `0..streams`; each slot is backed by its
+/// own stream and channel, and a slot whose stream fails is reopened on the
+/// balancer's next refresh.
+#[derive(Debug, Clone)]
+pub struct StreamPool {
+    conn: Connection,
+    streams: usize,
+    max_streams: usize,
+}
+
+impl StreamPool {
+    /// Streams opened when [`StreamPool::streams`] is not called.
+    pub const DEFAULT_STREAMS: usize = 4;
+    /// quinn's default `max_concurrent_bidi_streams`.
+    pub const DEFAULT_MAX_STREAMS: usize = 100;
+
+    pub fn new(conn: Connection) -> Self {
+        Self {
+            conn,
+            streams: Self::DEFAULT_STREAMS,
+            max_streams: Self::DEFAULT_MAX_STREAMS,
+        }
+    }
+
+    /// Number of streams to keep open, at least one and at most
+    /// [`max_streams`](Self::max_streams).
+    pub fn streams(mut self, streams: usize) -> Self {
+        self.streams = streams.max(1);
+        self
+    }
+
+    // r[impl jetstream.quic.streams.limit]
+    /// The server's `max_concurrent_bidi_streams` transport limit, minus any
+    /// streams opened outside the pool. Opening more streams than this would
+    /// wait for the server to allow them, so the pool never keeps more open,
+    /// and [`per_call`](Self::per_call) never has more in flight.
+    pub fn max_streams(mut self, max_streams: usize) -> Self {
+        self.max_streams = max_streams.max(1);
+        self
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// A least-outstanding balancer over the pool's streams. `channel`
+    /// wraps a freshly opened stream in a channel and negotiates its version.
+    pub fn balancer<C, F, Fut>(self, channel: F) -> Builder<C, usize>
+    where
+        C: Backend + Protocol,
+        F: Fn(QuicTransport<C>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<C, Error>> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let channel = Arc::new(channel);
+        Balancer::builder(self, move |_slot| {
+            let conn = conn.clone();
+            let channel = channel.clone();
+            async move {
+                let stream =
+                    conn.open_bi().await.map_err(std::io::Error::from)?;
+                channel(QuicTransport::from(stream)).await
+            }
+        })
+        .strategy(Strategy::LeastOutstanding)
+    }
+
+    // r[impl jetstream.quic.streams.per-call]
+    /// Opens a stream per call instead of keeping a pool. `channel` wraps
+    /// each freshly opened stream in a channel and negotiates its version.
+    pub fn per_call<C, F, Fut>(self, channel: F) -> PerCall<C>
+    where
+        C: Protocol,
+        F: Fn(QuicTransport<C>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<C, Error>> + Send + 'static,
+    {
+        PerCall {
+            conn: self.conn,
+            max_streams: self.max_streams,
+            permits: Arc::new(Semaphore::new(self.max_streams)),
+            channel: Arc::new(move |transport| Box::pin(channel(transport))),
+        }
+    }
+}
+
+impl Resolver<usize> for StreamPool {
+    fn resolve(&self) -> BoxFuture<'_, Result<Vec<usize>, Error>> {
+        let slots = match self.conn.close_reason() {
+            Some(reason) => Err(std::io::Error::from(reason).into()),
+            None => Ok((0..self.streams.min(self.max_streams)).collect()),
+        };
+        Box::pin(futures::future::ready(slots))
+    }
+}
+
+type Channel<C> = Arc<
+    dyn Fn(QuicTransport<C>) -> BoxFuture<'static, Result<C, Error>>
+        + Send
+        + Sync,
+>;
+
+/// Runs every call on a stream of its own, opened for it on one QUIC
+/// connection. Built with [`StreamPool::per_call`].
+pub struct PerCall<C: Protocol> {
+    conn: Connection,
+    max_streams: usize,
+    permits: Arc<Semaphore>,
+    channel: Channel<C>,
+}
+
+impl<C: Protocol> Clone for PerCall<C> {
+    fn clone(&self) -> Self {
+        Self {
+            conn: self.conn.clone(),
+            max_streams: self.max_streams,
+            permits: self.permits.clone(),
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl<C: Protocol> PerCall<C> {
+    /// Opens a stream, negotiates a channel on it and hands the channel to
+    /// `call`. The stream is closed when the channel is dropped. Waits while
+    /// [`max_streams`](StreamPool::max_streams) calls are in flight.
+    pub async fn call<T, F, Fut>(&self, call: F) -> Result<T, Error>
+    where
+        F: FnOnce(C) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("the stream limit is never closed");
+        let stream = self
+            .conn
+            .open_bi()
+            .await
+            .map_err(std::io::Error::from)?;
+        let chan = (self.channel)(QuicTransport::from(stream)).await?;
+        call(chan).await
+    }
+
+    /// Calls that currently hold a stream.
+    pub fn in_flight(&self) -> usize {
+        self.max_streams - self.permits.available_permits()
+    }
+}
@@ -9,7 +9,7 @@ use super::Error;
 
 #[async_trait]
 pub trait AnyServer: Send + Sync {
-    fn protocol_version(&self) -> &'static str;
+    fn protocol_version(&self) -> std::borrow::Cow<'static, str>;
     async fn rpc(
         &mut self,
         context: context::Context,
@@ -19,8 +19,8 @@ pub trait AnyServer: Send + Sync {
 
 #[async_trait]
 impl<P: Server> AnyServer for P {
-    fn protocol_version(&self) -> &'static str {
-        P::VERSION
+    fn protocol_version(&self) -> std::borrow::Cow<'static, str> {
+        <P as crate::Protocol>::protocol_version()
     }
 
     async fn rpc(
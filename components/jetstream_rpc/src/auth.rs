@@ -0,0 +1,449 @@
+//! Per-method authorization based on peer identity.
+//!
+//! An [`Authorizer`] decides whether the peer described by a [`Context`] may
+//! call a method. The generated `Server::rpc` asks the context's authorizer
+//! before dispatching every request, and rejects with the
+//! [`PERMISSION_DENIED`] error code.
+//!
+//! [`Policy`] is the rule-based authorizer. Rules can be built in code or
+//! loaded from a file:
+//!
+//! ```text
+//! # allow <principal> <protocol> <method>
+//! allow dns:*.example.com   echo  ping
+//! allow node:7f3a...        *     *
+//! # role <name> <principal>
+//! role  admin uri:spiffe://cluster.local/ns/ops/sa/admin
+//! ```
+//!
+//! Methods marked `#[authorize("admin")]` additionally require the peer to be
+//! bound to one of the listed roles; a bare `#[authorize]` only requires that
+//! some policy is installed.
+//!
+//! Transports attach an authorizer with `with_authorizer` on the
+//! [`Router`](crate::Router), `IrohServer` or `ProtocolService`. Without one,
+//! annotated methods are denied and a warning is logged.
+use std::{fmt, path::Path, str::FromStr, sync::Arc};
+
+use crate::{
+    context::{Context, Peer},
+    Error,
+};
+
+/// Error code returned when a call is rejected.
+pub const PERMISSION_DENIED: &str = "jetstream_rpc::error::permission_denied";
+
+/// A call awaiting authorization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthRequest<'a> {
+    /// Protocol name, e.g. `echo`.
+    pub protocol: &'a str,
+    /// Method name as written in the service trait.
+    pub method: &'a str,
+    /// Roles from the method's `#[authorize(...)]` attribute, or `None` if
+    /// the method carries no attribute.
+    pub roles: Option<&'a [&'a str]>,
+}
+
+/// Decides whether a peer may call a method.
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, ctx: &Context, request: &AuthRequest<'_>) -> bool;
+}
+
+impl<F> Authorizer for F
+where
+    F: Fn(&Context, &AuthRequest<'_>) -> bool + Send + Sync,
+{
+    fn authorize(&self, ctx: &Context, request: &AuthRequest<'_>) -> bool {
+        self(ctx, request)
+    }
+}
+
+/// Shared handle to an [`Authorizer`], compared by identity so it can live in
+/// a [`Context`].
+#[derive(Clone)]
+pub(crate) struct SharedAuthorizer(pub(crate) Arc<dyn Authorizer>);
+
+impl fmt::Debug for SharedAuthorizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Authorizer")
+    }
+}
+
+impl PartialEq for SharedAuthorizer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedAuthorizer {}
+
+impl std::hash::Hash for SharedAuthorizer {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+// r[impl jetstream.auth.enforce]
+/// Checks `request` against the context's authorizer.
+///
+/// Without an authorizer, methods that carry no `#[authorize]` attribute are
+/// allowed and annotated methods fail closed.
+pub fn authorize(
+    ctx: &Context,
+    request: &AuthRequest<'_>,
+) -> Result<(), Error> {
+    let allowed = match ctx.authorizer() {
+        Some(authorizer) => authorizer.authorize(ctx, request),
+        None if request.roles.is_some() => {
+            tracing::warn!(
+                protocol = request.protocol,
+                method = request.method,
+                "no authorizer is attached to this transport; denying #[authorize] method"
+            );
+            false
+        }
+        None => true,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(Error::with_code(
+            format!(
+                "permission denied: {} may not call {}.{}",
+                ctx, request.protocol, request.method
+            ),
+            PERMISSION_DENIED,
+        ))
+    }
+}
+
+/// Matches the identity of a peer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Principal {
+    /// Anyone, including anonymous peers.
+    Any,
+    /// iroh node id.
+    NodeId(String),
+    /// TLS SAN DNS name; a leading `*.` matches any subdomain.
+    DnsName(String),
+    /// TLS SAN email address.
+    Email(String),
+    /// TLS SAN URI (e.g. SPIFFE id); a trailing `*` matches any suffix.
+    Uri(String),
+    /// TLS subject common name.
+    CommonName(String),
+    /// Hex SHA-256 fingerprint of the TLS leaf certificate.
+    Fingerprint(String),
+    /// Unix peer user id.
+    Uid(u32),
+    /// Unix peer group id.
+    Gid(u32),
+    /// Exact web credentials header value.
+    WebCredentials(String),
+}
+
+impl Principal {
+    pub fn matches(&self, peer: Option<&Peer>) -> bool {
+        if let Principal::Any = self {
+            return true;
+        }
+        let Some(peer) = peer else {
+            return false;
+        };
+        match (self, peer) {
+            (Principal::NodeId(id), Peer::NodeId(node)) => *id == node.0,
+            #[cfg(feature = "x509")]
+            (principal, Peer::Tls(tls)) => {
+                let Some(leaf) = tls.leaf() else {
+                    return false;
+                };
+                match principal {
+                    Principal::DnsName(pattern) => leaf
+                        .dns_names
+                        .iter()
+                        .any(|name| dns_matches(pattern, name)),
+                    Principal::Email(email) => leaf.emails.contains(email),
+                    Principal::Uri(pattern) => leaf
+                        .uris
+                        .iter()
+                        .any(|uri| prefix_matches(pattern, uri.as_str())),
+                    Principal::CommonName(cn) => {
+                        leaf.common_name.as_ref() == Some(cn)
+                    }
+                    Principal::Fingerprint(fp) => {
+                        leaf.fingerprint.eq_ignore_ascii_case(fp)
+                    }
+                    _ => false,
+                }
+            }
+            #[cfg(tokio_unix)]
+            (Principal::Uid(uid), Peer::Unix(unix)) => unix.uid() == *uid,
+            #[cfg(tokio_unix)]
+            (Principal::Gid(gid), Peer::Unix(unix)) => unix.gid() == *gid,
+            (Principal::WebCredentials(value), Peer::WebCredentials(creds)) => {
+                creds.0.as_bytes() == value.as_bytes()
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "x509")]
+fn dns_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let name = name.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => name
+            .strip_suffix(suffix)
+            .is_some_and(|label| label.len() > 1 && label.ends_with('.')),
+        None => pattern == name,
+    }
+}
+
+#[cfg(feature = "x509")]
+fn prefix_matches(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+impl FromStr for Principal {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "*" {
+            return Ok(Principal::Any);
+        }
+        let Some((kind, value)) = s.split_once(':') else {
+            return Err(Error::new(format!("invalid principal: {}", s)));
+        };
+        let value = value.to_string();
+        let parse_id = |value: &str| {
+            value.parse::<u32>().map_err(|e| {
+                Error::new(format!("invalid principal {}: {}", s, e))
+            })
+        };
+        Ok(match kind {
+            "node" => Principal::NodeId(value),
+            "dns" => Principal::DnsName(value),
+            "email" => Principal::Email(value),
+            "uri" => Principal::Uri(value),
+            "cn" => Principal::CommonName(value),
+            "fingerprint" => Principal::Fingerprint(value),
+            "uid" => Principal::Uid(parse_id(&value)?),
+            "gid" => Principal::Gid(parse_id(&value)?),
+            "web" => Principal::WebCredentials(value),
+            _ => {
+                return Err(Error::new(format!(
+                    "unknown principal kind: {}",
+                    kind
+                )))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    principal: Principal,
+    protocol: String,
+    method: String,
+}
+
+impl Rule {
+    fn matches(&self, peer: Option<&Peer>, request: &AuthRequest<'_>) -> bool {
+        (self.protocol == "*" || self.protocol == request.protocol)
+            && (self.method == "*" || self.method == request.method)
+            && self.principal.matches(peer)
+    }
+}
+
+// r[impl jetstream.auth.policy]
+/// Rule-based [`Authorizer`].
+///
+/// A call is allowed when an `allow` rule matches the peer, protocol and
+/// method, and, if the method lists roles, the peer is bound to one of them.
+/// Everything else is denied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    rules: Vec<Rule>,
+    roles: Vec<(String, Principal)>,
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows `principal` to call `method` of `protocol`; either may be `*`.
+    pub fn allow(
+        mut self,
+        principal: Principal,
+        protocol: impl Into<String>,
+        method: impl Into<String>,
+    ) -> Self {
+        self.rules.push(Rule {
+            principal,
+            protocol: protocol.into(),
+            method: method.into(),
+        });
+        self
+    }
+
+    /// Binds `principal` to `role`.
+    pub fn bind(
+        mut self,
+        role: impl Into<String>,
+        principal: Principal,
+    ) -> Self {
+        self.roles.push((role.into(), principal));
+        self
+    }
+
+    /// Loads a rule file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    fn has_role(&self, peer: Option<&Peer>, roles: &[&str]) -> bool {
+        self.roles.iter().any(|(role, principal)| {
+            roles.contains(&role.as_str()) && principal.matches(peer)
+        })
+    }
+}
+
+impl Authorizer for Policy {
+    fn authorize(&self, ctx: &Context, request: &AuthRequest<'_>) -> bool {
+        let peer = ctx.peer();
+        if !self.rules.iter().any(|rule| rule.matches(peer, request)) {
+            return false;
+        }
+        match request.roles {
+            Some(roles) if !roles.is_empty() => self.has_role(peer, roles),
+            _ => true,
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = Policy::new();
+        for (lineno, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let invalid = |reason: &dyn fmt::Display| {
+                Error::new(format!("line {}: {}: {}", lineno + 1, reason, line))
+            };
+            policy = match fields.as_slice() {
+                ["allow", principal, protocol, method] => policy.allow(
+                    principal.parse().map_err(|err| invalid(&err))?,
+                    *protocol,
+                    *method,
+                ),
+                ["role", role, principal] => policy.bind(
+                    *role,
+                    principal.parse().map_err(|err| invalid(&err))?,
+                ),
+                _ => return Err(invalid(&"invalid rule")),
+            };
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(
+        method: &'a str,
+        roles: Option<&'a [&'a str]>,
+    ) -> AuthRequest<'a> {
+        AuthRequest {
+            protocol: "echo",
+            method,
+            roles,
+        }
+    }
+
+    fn node(id: &str) -> Context {
+        Context::new(
+            None,
+            Some(Peer::NodeId(crate::context::NodeId(id.into()))),
+        )
+    }
+
+    #[test]
+    fn policy_from_rules() {
+        let policy: Policy = "
+            # comment
+            allow node:alice echo ping
+            allow node:bob   *    *     # trailing comment
+            role  admin node:bob
+        "
+        .parse()
+        .unwrap();
+
+        let alice = node("alice");
+        let bob = node("bob");
+        assert!(policy.authorize(&alice, &request("ping", None)));
+        assert!(!policy.authorize(&alice, &request("pong", None)));
+        assert!(policy.authorize(&bob, &request("pong", None)));
+        assert!(!policy.authorize(&Context::default(), &request("ping", None)));
+
+        let admin: &[&str] = &["admin"];
+        assert!(!policy.authorize(&alice, &request("ping", Some(admin))));
+        assert!(policy.authorize(&bob, &request("ping", Some(admin))));
+        assert!(policy.authorize(&bob, &request("ping", Some(&[]))));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        assert!("allow node:alice echo".parse::<Policy>().is_err());
+        assert!("allow nobody echo ping".parse::<Policy>().is_err());
+        assert!("allow uid:root echo ping".parse::<Policy>().is_err());
+        assert!("permit * * *".parse::<Policy>().is_err());
+
+        let err = "# header\nallow uid:root echo ping"
+            .parse::<Policy>()
+            .unwrap_err();
+        assert!(err.message().starts_with("line 2: "), "{}", err.message());
+    }
+
+    #[test]
+    fn enforcement_without_authorizer() {
+        let ctx = Context::default();
+        assert!(authorize(&ctx, &request("ping", None)).is_ok());
+        let err = authorize(&ctx, &request("ping", Some(&[]))).unwrap_err();
+        assert_eq!(err.code(), Some(PERMISSION_DENIED));
+    }
+
+    #[test]
+    fn enforcement_with_authorizer() {
+        let ctx = node("alice").with_authorizer(Policy::new().allow(
+            Principal::Any,
+            "echo",
+            "ping",
+        ));
+        assert!(authorize(&ctx, &request("ping", None)).is_ok());
+        let err = authorize(&ctx, &request("pong", None)).unwrap_err();
+        assert_eq!(err.code(), Some(PERMISSION_DENIED));
+    }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn dns_wildcards() {
+        assert!(dns_matches("*.example.com", "api.example.com"));
+        assert!(!dns_matches("*.example.com", "example.com"));
+        assert!(!dns_matches("*.example.com", "apiexample.com"));
+        assert!(dns_matches("Example.com", "example.com"));
+        assert!(dns_matches("*.Example.com", "API.example.COM"));
+    }
+}
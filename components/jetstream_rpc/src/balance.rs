@@ -0,0 +1,412 @@
+//! Client-side load balancing over a pool of endpoints.
+//!
+//! A [`Balancer`] keeps a negotiated channel open to every endpoint a
+//! [`Resolver`] returns and spreads calls over them. `#[service]` implements
+//! the service trait for `Balancer<XxxChannel, E>`, so a balancer can be used
+//! wherever a single channel was.
+//!
+//! Endpoints whose calls keep failing with retriable errors, or whose
+//! connection is lost, are ejected. A background task re-resolves the
+//! endpoint set and reconnects ejected endpoints, re-adding them once the
+//! optional health check passes.
+//!
+//! ```ignore
+//! let balancer = Balancer::builder(
+//!     DnsResolver::new("echo.internal:4433"),
+//!     move |addr: SocketAddr| {
+//!         let client = client.clone();
+//!         async move {
+//!             let conn = client.connect(addr, "echo.internal").await?;
+//!             let transport = QuicTransport::from(conn.open_bi().await?);
+//!             let chan = EchoChannel::new(10, Box::new(transport));
+//!             chan.negotiate_version(u32::MAX).await?;
+//!             Ok(chan)
+//!         }
+//!     },
+//! )
+//! .strategy(Strategy::LeastOutstanding)
+//! .build()
+//! .await?;
+//! balancer.ping("hello".into()).await?;
+//! ```
+use std::{
+    fmt::Debug,
+    future::Future,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    retry::{is_retriable, UNAVAILABLE},
+    Error,
+};
+
+/// A negotiated client channel the balancer can route calls to.
+///
+/// Implemented by the channels `#[service]` generates.
+pub trait Backend: Send + Sync + 'static {
+    /// Requests sent and still waiting for a response.
+    fn in_flight(&self) -> usize;
+    /// Whether the underlying connection has gone away.
+    fn is_closed(&self) -> bool;
+}
+
+/// Produces the current set of endpoints of a service.
+pub trait Resolver<E>: Send + Sync + 'static {
+    fn resolve(&self) -> BoxFuture<'_, Result<Vec<E>, Error>>;
+}
+
+/// A fixed list of endpoints, e.g. socket addresses or iroh node ids.
+impl<E> Resolver<E> for Vec<E>
+where
+    E: Clone + Send + Sync + 'static,
+{
+    fn resolve(&self) -> BoxFuture<'_, Result<Vec<E>, Error>> {
+        Box::pin(futures::future::ready(Ok(self.clone())))
+    }
+}
+
+/// Resolves a `host:port` name to every address DNS returns for it.
+#[cfg(native)]
+#[derive(Debug, Clone)]
+pub struct DnsResolver {
+    host: String,
+}
+
+#[cfg(native)]
+impl DnsResolver {
+    pub fn new(host: impl Into<String>) -> Self {
+        Self { host: host.into() }
+    }
+}
+
+#[cfg(native)]
+impl Resolver<std::net::SocketAddr> for DnsResolver {
+    fn resolve(
+        &self,
+    ) -> BoxFuture<'_, Result<Vec<std::net::SocketAddr>, Error>> {
+        Box::pin(async move {
+            Ok(tokio::net::lookup_host(self.host.as_str()).await?.collect())
+        })
+    }
+}
+
+/// How the balancer picks an endpoint for each call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Strategy {
+    /// Cycle through healthy endpoints.
+    #[default]
+    RoundRobin,
+    /// Pick the healthy endpoint with the fewest requests in flight.
+    LeastOutstanding,
+}
+
+type Connect<C, E> =
+    Arc<dyn Fn(E) -> BoxFuture<'static, Result<C, Error>> + Send + Sync>;
+type HealthCheck<C> =
+    Arc<dyn Fn(C) -> BoxFuture<'static, Result<C, Error>> + Send + Sync>;
+
+struct Member<C, E> {
+    endpoint: E,
+    channel: Option<Arc<C>>,
+    failures: u32,
+}
+
+struct Inner<C, E> {
+    members: Mutex<Vec<Member<C, E>>>,
+    next: AtomicUsize,
+    resolver: Box<dyn Resolver<E>>,
+    connect: Connect<C, E>,
+    health_check: Option<HealthCheck<C>>,
+    strategy: Strategy,
+    max_failures: u32,
+}
+
+// r[impl jetstream.balance.balancer]
+/// Spreads calls over negotiated channels to a set of endpoints.
+pub struct Balancer<C, E> {
+    inner: Arc<Inner<C, E>>,
+}
+
+impl<C, E> Clone for Balancer<C, E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// The channel a call was routed to.
+pub struct Picked<C, E> {
+    endpoint: E,
+    channel: Arc<C>,
+}
+
+impl<C, E> Picked<C, E> {
+    pub fn endpoint(&self) -> &E {
+        &self.endpoint
+    }
+}
+
+impl<C, E> Deref for Picked<C, E> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.channel
+    }
+}
+
+/// Configures and connects a [`Balancer`].
+pub struct Builder<C, E> {
+    resolver: Box<dyn Resolver<E>>,
+    connect: Connect<C, E>,
+    health_check: Option<HealthCheck<C>>,
+    strategy: Strategy,
+    max_failures: u32,
+    refresh_interval: Duration,
+}
+
+impl<C, E> Builder<C, E>
+where
+    C: Backend,
+    E: Clone + PartialEq + Debug + Send + Sync + 'static,
+{
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Consecutive retriable failures after which an endpoint is ejected.
+    pub fn max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures.max(1);
+        self
+    }
+
+    /// How often endpoints are re-resolved and ejected ones reconnected.
+    pub fn refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Check a freshly connected channel before it takes calls. The check
+    /// hands the channel back if it is healthy.
+    pub fn health_check<F, Fut>(mut self, check: F) -> Self
+    where
+        F: Fn(C) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<C, Error>> + Send + 'static,
+    {
+        self.health_check =
+            Some(Arc::new(move |channel| Box::pin(check(channel))));
+        self
+    }
+
+    /// Resolves and connects to the initial endpoints, then keeps the pool
+    /// up to date in the background until the balancer is dropped.
+    pub async fn build(self) -> Result<Balancer<C, E>, Error> {
+        let balancer = Balancer {
+            inner: Arc::new(Inner {
+                members: Mutex::new(Vec::new()),
+                next: AtomicUsize::new(0),
+                resolver: self.resolver,
+                connect: self.connect,
+                health_check: self.health_check,
+                strategy: self.strategy,
+                max_failures: self.max_failures,
+            }),
+        };
+        balancer.refresh().await?;
+
+        let inner = Arc::downgrade(&balancer.inner);
+        let interval = self.refresh_interval;
+        tokio::spawn(Balancer::maintain(inner, interval));
+        Ok(balancer)
+    }
+}
+
+impl<C, E> Balancer<C, E>
+where
+    C: Backend,
+    E: Clone + PartialEq + Debug + Send + Sync + 'static,
+{
+    /// `connect` opens a transport to an endpoint and returns the negotiated
+    /// channel.
+    pub fn builder<R, F, Fut>(resolver: R, connect: F) -> Builder<C, E>
+    where
+        R: Resolver<E>,
+        F: Fn(E) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<C, Error>> + Send + 'static,
+    {
+        Builder {
+            resolver: Box::new(resolver),
+            connect: Arc::new(move |endpoint| Box::pin(connect(endpoint))),
+            health_check: None,
+            strategy: Strategy::default(),
+            max_failures: 3,
+            refresh_interval: Duration::from_secs(5),
+        }
+    }
+
+    /// Every known endpoint and whether it is currently taking calls.
+    pub fn endpoints(&self) -> Vec<(E, bool)> {
+        self.members()
+            .iter()
+            .map(|member| {
+                let healthy = member
+                    .channel
+                    .as_ref()
+                    .is_some_and(|channel| !channel.is_closed());
+                (member.endpoint.clone(), healthy)
+            })
+            .collect()
+    }
+
+    // r[impl jetstream.balance.strategy]
+    /// Picks a healthy channel for the next call.
+    pub fn pick(&self) -> Result<Picked<C, E>, Error> {
+        let mut members = self.members();
+        for member in members.iter_mut() {
+            if member.channel.as_ref().is_some_and(|c| c.is_closed()) {
+                tracing::debug!(endpoint = ?member.endpoint, "ejecting closed endpoint");
+                member.channel = None;
+            }
+        }
+        let healthy = members
+            .iter()
+            .filter_map(|member| {
+                member.channel.as_ref().map(|channel| (member, channel))
+            })
+            .collect::<Vec<_>>();
+        if healthy.is_empty() {
+            return Err(Error::with_code("no healthy endpoints", UNAVAILABLE));
+        }
+
+        let start = self.inner.next.fetch_add(1, Ordering::Relaxed);
+        let index = match self.inner.strategy {
+            Strategy::RoundRobin => start % healthy.len(),
+            Strategy::LeastOutstanding => (0..healthy.len())
+                .map(|offset| (start + offset) % healthy.len())
+                .min_by_key(|&index| healthy[index].1.in_flight())
+                .unwrap_or_default(),
+        };
+        let (member, channel) = healthy[index];
+        Ok(Picked {
+            endpoint: member.endpoint.clone(),
+            channel: channel.clone(),
+        })
+    }
+
+    // r[impl jetstream.balance.eject]
+    /// Records the outcome of a call routed to `picked`, ejecting its
+    /// endpoint after too many consecutive retriable failures.
+    pub fn report<T>(&self, picked: &Picked<C, E>, result: &Result<T, Error>) {
+        let mut members = self.members();
+        let Some(member) = members.iter_mut().find(|member| {
+            member
+                .channel
+                .as_ref()
+                .is_some_and(|channel| Arc::ptr_eq(channel, &picked.channel))
+        }) else {
+            return;
+        };
+        match result {
+            Err(err) if is_retriable(err) => {
+                member.failures += 1;
+                if member.failures >= self.inner.max_failures
+                    || picked.channel.is_closed()
+                {
+                    tracing::warn!(endpoint = ?member.endpoint, error = %err, "ejecting endpoint");
+                    member.channel = None;
+                }
+            }
+            _ => member.failures = 0,
+        }
+    }
+
+    // r[impl jetstream.balance.refresh]
+    /// Re-resolves the endpoint set and reconnects endpoints without a
+    /// healthy channel.
+    pub async fn refresh(&self) -> Result<(), Error> {
+        let endpoints = self.inner.resolver.resolve().await?;
+        let pending = {
+            let mut members = self.members();
+            members.retain(|member| endpoints.contains(&member.endpoint));
+            for endpoint in endpoints {
+                if !members.iter().any(|member| member.endpoint == endpoint) {
+                    members.push(Member {
+                        endpoint,
+                        channel: None,
+                        failures: 0,
+                    });
+                }
+            }
+            members
+                .iter()
+                .filter(|member| {
+                    member.channel.as_ref().is_none_or(|c| c.is_closed())
+                })
+                .map(|member| member.endpoint.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let connected =
+            futures::future::join_all(pending.into_iter().map(|endpoint| {
+                let inner = self.inner.clone();
+                async move {
+                    let connected = match &inner.health_check {
+                        Some(check) => {
+                            match (inner.connect)(endpoint.clone()).await {
+                                Ok(channel) => check(channel).await,
+                                Err(err) => Err(err),
+                            }
+                        }
+                        None => (inner.connect)(endpoint.clone()).await,
+                    };
+                    match connected {
+                        Ok(channel) => Some((endpoint, Arc::new(channel))),
+                        Err(err) => {
+                            tracing::debug!(?endpoint, error = %err, "endpoint is not healthy");
+                            None
+                        }
+                    }
+                }
+            }))
+            .await;
+
+        let mut members = self.members();
+        for (endpoint, channel) in connected.into_iter().flatten() {
+            if let Some(member) = members
+                .iter_mut()
+                .find(|member| member.endpoint == endpoint)
+            {
+                member.channel = Some(channel);
+                member.failures = 0;
+            }
+        }
+        Ok(())
+    }
+
+    async fn maintain(inner: Weak<Inner<C, E>>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            if let Err(err) = (Balancer { inner }).refresh().await {
+                tracing::warn!(error = %err, "failed to refresh endpoints");
+            }
+        }
+    }
+
+    fn members(&self) -> std::sync::MutexGuard<'_, Vec<Member<C, E>>> {
+        self.inner
+            .members
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,231 @@
+//! Synchronous clients and servers for `#[service(blocking)]` services.
+//!
+//! A service `Echo` gets an `EchoBlockingChannel`, whose methods block the
+//! calling thread on a [`BlockingRuntime`] instead of returning futures:
+//!
+//! ```ignore
+//! let runtime = BlockingRuntime::new()?;
+//! let chan = runtime.block_on(async {
+//!     let stream = TcpStream::connect(addr).await?;
+//!     let framed = Framed::new(stream, ClientCodec::<EchoChannel>::default());
+//!     std::io::Result::Ok(EchoChannel::new(10, Box::new(framed)))
+//! })?;
+//! let mut echo = EchoBlockingChannel::new(runtime, chan)
+//!     .with_timeout(Duration::from_secs(5));
+//! echo.negotiate_version(8192)?;
+//! let pong = echo.ping("hello".to_string())?;
+//! ```
+//!
+//! It also gets a `BlockingEcho` trait, the service trait without `async`.
+//! Wrapped in [`Blocking`], its implementations serve like any other:
+//!
+//! ```ignore
+//! let service = EchoService { inner: Blocking::new(SyncEcho::default()) };
+//! let router = Router::new().with_handler(PROTOCOL_NAME, service);
+//! ```
+//!
+//! [`Blocking::new`] serves one call at a time. [`Blocking::pool`] serves
+//! as many at once as it is given instances:
+//!
+//! ```ignore
+//! let service = EchoService { inner: Blocking::pool(vec![SyncEcho::default(); 4]) };
+//! ```
+use std::{
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    runtime::{Handle, Runtime, RuntimeFlavor},
+    sync::Semaphore,
+};
+
+use crate::{retry::DEADLINE_EXCEEDED, Error};
+
+#[derive(Debug, Clone)]
+enum Inner {
+    Owned(Arc<Runtime>),
+    Borrowed(Handle),
+}
+
+// r[impl jetstream.blocking.runtime]
+/// The runtime a blocking channel runs its calls on. It either owns a tokio
+/// runtime or borrows a handle to one.
+///
+/// A borrowed handle must belong to a multi-threaded runtime: the channel's
+/// background tasks only make progress while that runtime's workers run, so
+/// borrowing a `current_thread` handle panics rather than hang on the first
+/// call. Blocking on it from inside an async task panics.
+#[derive(Debug, Clone)]
+pub struct BlockingRuntime(Inner);
+
+impl BlockingRuntime {
+    /// Starts a runtime with a single worker thread, owned by the returned
+    /// value and its clones.
+    pub fn new() -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("jetstream-blocking")
+            .enable_all()
+            .build()?;
+        Ok(runtime.into())
+    }
+
+    pub fn handle(&self) -> &Handle {
+        match &self.0 {
+            Inner::Owned(runtime) => runtime.handle(),
+            Inner::Borrowed(handle) => handle,
+        }
+    }
+
+    /// Runs `future` to completion on the runtime, blocking the current
+    /// thread.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match &self.0 {
+            Inner::Owned(runtime) => runtime.block_on(future),
+            Inner::Borrowed(handle) => handle.block_on(future),
+        }
+    }
+
+    // r[impl jetstream.blocking.timeout]
+    /// Runs a call to completion. Calls taking longer than `timeout` are
+    /// abandoned and fail with [`DEADLINE_EXCEEDED`].
+    pub fn call<T>(
+        &self,
+        timeout: Option<Duration>,
+        call: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        self.block_on(async {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, call)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(Error::with_code(
+                            format!("call timed out after {timeout:?}"),
+                            DEADLINE_EXCEEDED,
+                        ))
+                    }),
+                None => call.await,
+            }
+        })
+    }
+}
+
+impl From<Runtime> for BlockingRuntime {
+    fn from(runtime: Runtime) -> Self {
+        Self(Inner::Owned(Arc::new(runtime)))
+    }
+}
+
+impl From<Arc<Runtime>> for BlockingRuntime {
+    fn from(runtime: Arc<Runtime>) -> Self {
+        Self(Inner::Owned(runtime))
+    }
+}
+
+impl From<Handle> for BlockingRuntime {
+    /// # Panics
+    ///
+    /// If `handle` belongs to a `current_thread` runtime.
+    fn from(handle: Handle) -> Self {
+        assert!(
+            handle.runtime_flavor() != RuntimeFlavor::CurrentThread,
+            "BlockingRuntime needs a multi-threaded runtime: nothing drives \
+             a current_thread runtime while a call blocks on its handle"
+        );
+        Self(Inner::Borrowed(handle))
+    }
+}
+
+// r[impl jetstream.blocking.server]
+/// Serves a synchronous implementation of a service, a `Blocking{Trait}`,
+/// as the async service trait.
+///
+/// Every call runs on tokio's blocking thread pool with exclusive use of one
+/// of the instances it was given, so it serves as many calls at once as it
+/// has instances, and queues the rest. Clones share the instances.
+#[derive(Debug)]
+pub struct Blocking<T>(Arc<Pool<T>>);
+
+#[derive(Debug)]
+struct Pool<T> {
+    idle: Mutex<Vec<T>>,
+    permits: Semaphore,
+}
+
+impl<T> Clone for Blocking<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    /// Serves `inner`, one call at a time.
+    pub fn new(inner: T) -> Self {
+        Self::pool([inner])
+    }
+
+    /// Serves a call on each of `instances` at once.
+    ///
+    /// # Panics
+    ///
+    /// If `instances` is empty.
+    pub fn pool(instances: impl IntoIterator<Item = T>) -> Self {
+        let idle: Vec<T> = instances.into_iter().collect();
+        assert!(!idle.is_empty(), "Blocking::pool needs an instance");
+        Self(Arc::new(Pool {
+            permits: Semaphore::new(idle.len()),
+            idle: Mutex::new(idle),
+        }))
+    }
+
+    /// Runs `f` on the blocking thread pool with an idle instance, waiting
+    /// for one if they are all busy. A panic in `f` resumes on the calling
+    /// task, and the instance is served again.
+    pub async fn run<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut T) -> R + Send + 'static,
+    ) -> R {
+        // The semaphore is never closed. The permit is handed back with the
+        // instance, by the blocking task, so it outlives a cancelled caller.
+        self.0
+            .permits
+            .acquire()
+            .await
+            .expect("blocking pool closed")
+            .forget();
+        let pool = self.0.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut inner = pool
+                .lock_idle()
+                .pop()
+                .expect("a permit guarantees an idle instance");
+            // The instance goes back even if `f` panics.
+            let result =
+                std::panic::catch_unwind(AssertUnwindSafe(|| f(&mut inner)));
+            pool.lock_idle().push(inner);
+            pool.permits.add_permits(1);
+            result
+        })
+        .await;
+        match result {
+            Ok(Ok(result)) => result,
+            Ok(Err(panic)) => std::panic::resume_unwind(panic),
+            Err(err) if err.is_panic() => {
+                std::panic::resume_unwind(err.into_panic())
+            }
+            // Only happens while the runtime shuts down.
+            Err(err) => panic!("blocking call cancelled: {err}"),
+        }
+    }
+}
+
+impl<T> Pool<T> {
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, Vec<T>> {
+        self.idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
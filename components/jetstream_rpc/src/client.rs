@@ -1,22 +1,47 @@
-use crate::{error::Error, Frame, Protocol};
+use crate::{
+    compression::{self, CompressionState},
+    error::Error,
+    Frame, Protocol,
+};
 use futures::{
     stream::{SplitSink, SplitStream},
     Sink, Stream, StreamExt,
 };
-use jetstream_wireformat::WireFormat;
 
 use tokio_util::{
-    bytes::{self, Buf, BufMut},
+    bytes,
     codec::{Decoder, Encoder},
 };
 
+/// Frames requests and responses on the client side of a stream.
+///
+/// Clones share the negotiated compression, so a transport that reads and
+/// writes through separate codecs must clone one codec for both halves.
 pub struct ClientCodec<P>
 where
     P: Protocol,
 {
+    compression: CompressionState,
     _p: std::marker::PhantomData<P>,
 }
 
+impl<P: Protocol> ClientCodec<P> {
+    /// The compression and msize acknowledged in the last `Rversion`
+    /// decoded.
+    pub fn compression(&self) -> &CompressionState {
+        &self.compression
+    }
+}
+
+impl<P: Protocol> Clone for ClientCodec<P> {
+    fn clone(&self) -> Self {
+        Self {
+            compression: self.compression.clone(),
+            _p: std::marker::PhantomData,
+        }
+    }
+}
+
 impl<P: Protocol> Encoder<Frame<P::Request>> for ClientCodec<P> {
     type Error = Error;
 
@@ -25,7 +50,7 @@ impl<P: Protocol> Encoder<Frame<P::Request>> for ClientCodec<P> {
         item: Frame<P::Request>,
         dst: &mut bytes::BytesMut,
     ) -> Result<(), Self::Error> {
-        WireFormat::encode(&item, &mut dst.writer())?;
+        compression::encode_frame(&item, self.compression.get(), dst)?;
         Ok(())
     }
 }
@@ -38,22 +63,12 @@ impl<P: Protocol> Decoder for ClientCodec<P> {
         &mut self,
         src: &mut bytes::BytesMut,
     ) -> Result<Option<Self::Item>, Self::Error> {
-        // check to see if you have at least 4 bytes to figure out the size
-        if src.len() < 4 {
-            src.reserve(4);
-            return Ok(None);
-        }
-        let Some(mut bytz) = src.get(..4) else {
-            return Ok(None);
-        };
-
-        let byte_size: u32 = WireFormat::decode(&mut bytz)?;
-        if src.len() < byte_size as usize {
-            src.reserve(byte_size as usize);
-            return Ok(None);
+        let frame =
+            compression::decode_frame::<P::Response>(src, &self.compression)?;
+        if let Some(frame) = &frame {
+            self.compression.observe(frame);
         }
-        let frame = Frame::<P::Response>::decode(&mut src.reader())?;
-        Ok(Some(frame))
+        Ok(frame)
     }
 }
 
@@ -63,6 +78,7 @@ where
 {
     fn default() -> Self {
         Self {
+            compression: CompressionState::default(),
             _p: std::marker::PhantomData,
         }
     }
@@ -0,0 +1,569 @@
+//! Negotiated per-frame compression.
+//!
+//! Peers agree on at most one algorithm during version negotiation: the
+//! client offers `cap-zstd` and/or `cap-lz4` and the server acknowledges the
+//! one it prefers (see [`Capabilities::negotiate`]). From then on both
+//! codecs compress frames larger than [`THRESHOLD`] and mark them by setting
+//! the [`COMPRESSED`] bit of the size field:
+//!
+//! ```text
+//! size[4] | COMPRESSED  compressed(type[1] tag[2] msg)
+//! ```
+//!
+//! The codecs learn the outcome of the negotiation by watching the `Rversion`
+//! frame pass through them, so transports need no extra wiring beyond sharing
+//! one codec between their read and write halves. Compressed bodies start
+//! with the zstd or lz4 frame magic, so either algorithm can be decoded once
+//! one was negotiated. Peers that never acknowledge a compression capability
+//! never see the flag, and codecs reject flagged frames on such streams.
+//!
+//! Compressed frames larger than the negotiated msize are rejected, and may
+//! not decompress to more than the msize either. Uncompressed frames are not
+//! held to the msize: clients that predate compression negotiate one without
+//! ever keeping their requests under it.
+use std::{
+    io, mem,
+    sync::{
+        atomic::{AtomicU32, AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+use jetstream_wireformat::WireFormat;
+use tokio_util::bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{Capabilities, Frame, Framer, RawFrame, Rversion, RVERSION};
+
+/// Set on the size field of frames whose body is compressed.
+pub const COMPRESSED: u32 = 1 << 31;
+
+/// Frames at most this many bytes long are always sent uncompressed.
+pub const THRESHOLD: usize = 1024;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
+
+// r[impl jetstream.compression.algorithms]
+/// A frame compression algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    Zstd,
+    Lz4,
+}
+
+impl Compression {
+    /// The algorithm this build prefers among `capabilities`, if any.
+    pub fn select(capabilities: Capabilities) -> Option<Self> {
+        let available = capabilities & Capabilities::COMPRESSION;
+        if available.contains(Capabilities::ZSTD) {
+            Some(Compression::Zstd)
+        } else if available.contains(Capabilities::LZ4) {
+            Some(Compression::Lz4)
+        } else {
+            None
+        }
+    }
+
+    pub fn capability(self) -> Capabilities {
+        match self {
+            Compression::Zstd => Capabilities::ZSTD,
+            Compression::Lz4 => Capabilities::LZ4,
+        }
+    }
+
+    fn compress(self, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Zstd => zstd_compress(body),
+            Compression::Lz4 => lz4_compress(body),
+        }
+    }
+
+    /// Decompresses `body`, failing if it expands to more than `limit`
+    /// bytes, so a small frame cannot expand without bound.
+    fn decompress(body: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+        let out = match body.get(..4) {
+            Some(magic) if magic == ZSTD_MAGIC => zstd_decompress(body, limit),
+            Some(magic) if magic == LZ4_MAGIC => lz4_decompress(body, limit),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "compressed frame with unknown format",
+            )),
+        }?;
+        if out.len() > limit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("compressed frame expands beyond {limit} bytes"),
+            ));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(body: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::bulk::compress(body, ZSTD_LEVEL)
+}
+
+// Both decoders read one byte past `limit`, so `decompress` can tell a body
+// that fits from one that was cut off.
+#[cfg(feature = "zstd")]
+fn zstd_decompress(body: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut out = Vec::with_capacity((body.len() * 4).min(limit));
+    zstd::stream::read::Decoder::new(body)?
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_compress(_: &[u8]) -> io::Result<Vec<u8>> {
+    Err(unsupported(Compression::Zstd))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_: &[u8], _: usize) -> io::Result<Vec<u8>> {
+    Err(unsupported(Compression::Zstd))
+}
+
+#[cfg(feature = "lz4")]
+fn lz4_compress(body: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+    encoder.write_all(body)?;
+    encoder.finish().map_err(io::Error::other)
+}
+
+#[cfg(feature = "lz4")]
+fn lz4_decompress(body: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut out = Vec::with_capacity((body.len() * 4).min(limit));
+    lz4_flex::frame::FrameDecoder::new(body)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "lz4"))]
+fn lz4_compress(_: &[u8]) -> io::Result<Vec<u8>> {
+    Err(unsupported(Compression::Lz4))
+}
+
+#[cfg(not(feature = "lz4"))]
+fn lz4_decompress(_: &[u8], _: usize) -> io::Result<Vec<u8>> {
+    Err(unsupported(Compression::Lz4))
+}
+
+#[cfg(not(all(feature = "zstd", feature = "lz4")))]
+fn unsupported(algorithm: Compression) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{algorithm:?} compression is not enabled in this build"),
+    )
+}
+
+/// The algorithm a codec compresses outgoing frames with and the msize that
+/// bounds incoming frames, shared between clones of the codec.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionState(Arc<Negotiated>);
+
+#[derive(Debug, Default)]
+struct Negotiated {
+    algorithm: AtomicU8,
+    /// Zero until an msize is negotiated.
+    msize: AtomicU32,
+}
+
+impl CompressionState {
+    pub fn get(&self) -> Option<Compression> {
+        match self.0.algorithm.load(Ordering::Acquire) {
+            1 => Some(Compression::Zstd),
+            2 => Some(Compression::Lz4),
+            _ => None,
+        }
+    }
+
+    pub fn set(&self, compression: Option<Compression>) {
+        let value = match compression {
+            None => 0,
+            Some(Compression::Zstd) => 1,
+            Some(Compression::Lz4) => 2,
+        };
+        self.0.algorithm.store(value, Ordering::Release);
+    }
+
+    /// The largest frame, in bytes including the size field, the stream
+    /// accepts, once negotiated.
+    pub fn msize(&self) -> Option<u32> {
+        match self.0.msize.load(Ordering::Acquire) {
+            0 => None,
+            msize => Some(msize),
+        }
+    }
+
+    pub fn set_msize(&self, msize: Option<u32>) {
+        self.0.msize.store(msize.unwrap_or(0), Ordering::Release);
+    }
+
+    // r[impl jetstream.compression.negotiation]
+    /// Adopts the algorithm and msize acknowledged by `frame` if it is an
+    /// `Rversion`.
+    pub fn observe<T: Framer>(&self, frame: &Frame<T>) {
+        if frame.msg.message_type() != RVERSION {
+            return;
+        }
+        let mut buf = Vec::with_capacity(frame.msg.byte_size() as usize);
+        if frame.msg.encode(&mut buf).is_err() {
+            return;
+        }
+        if let Ok(rversion) = Rversion::decode(&mut buf.as_slice()) {
+            self.set(Compression::select(Capabilities::acknowledged(
+                &rversion.version,
+            )));
+            self.set_msize(Some(rversion.msize).filter(|&msize| msize > 0));
+        }
+    }
+}
+
+// r[impl jetstream.compression.frame]
+/// Encodes `item` into `dst`, compressing it if `compression` is set and the
+/// frame is larger than [`THRESHOLD`] and shrinks.
+pub(crate) fn encode_frame<T: Framer>(
+    item: &Frame<T>,
+    compression: Option<Compression>,
+    dst: &mut BytesMut,
+) -> io::Result<()> {
+    let start = dst.len();
+    if let Err(err) = item.encode(&mut dst.writer()) {
+        // Leave no partial frame behind, so the stream can carry on.
+        dst.truncate(start);
+        return Err(err);
+    }
+    let Some(compression) = compression else {
+        return Ok(());
+    };
+    let size = dst.len() - start;
+    if size <= THRESHOLD {
+        return Ok(());
+    }
+    let body = compression.compress(&dst[start + mem::size_of::<u32>()..])?;
+    let compressed_size = mem::size_of::<u32>() + body.len();
+    if compressed_size >= size {
+        return Ok(());
+    }
+    dst.truncate(start);
+    (compressed_size as u32 | COMPRESSED).encode(&mut dst.writer())?;
+    dst.extend_from_slice(&body);
+    Ok(())
+}
+
+/// Reads the size field of the next frame in `src`, checking compressed
+/// frames against the negotiated msize. Returns the frame's size and whether
+/// it is compressed.
+fn frame_size(
+    src: &[u8],
+    state: &CompressionState,
+) -> io::Result<Option<(usize, bool)>> {
+    if src.len() < mem::size_of::<u32>() {
+        return Ok(None);
+    }
+    let byte_size = u32::decode(&mut &src[..mem::size_of::<u32>()])?;
+    let size = (byte_size & !COMPRESSED) as usize;
+    if size < mem::size_of::<u32>() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("byte_size(= {size}) is less than 4 bytes"),
+        ));
+    }
+    let compressed = byte_size & COMPRESSED != 0;
+    if let (true, Some(msize)) = (compressed, state.msize()) {
+        if size > msize as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "compressed frame of {size} bytes exceeds the msize of \
+                     {msize}"
+                ),
+            ));
+        }
+    }
+    Ok(Some((size, compressed)))
+}
+
+// r[impl jetstream.compression.limits]
+/// Decompresses the body of `frame`, a whole frame flagged as compressed,
+/// and returns the body of the frame it encodes.
+fn decompress_frame(
+    frame: &[u8],
+    state: &CompressionState,
+) -> io::Result<Vec<u8>> {
+    let (Some(_), Some(msize)) = (state.get(), state.msize()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "compressed frame on a stream that did not negotiate compression",
+        ));
+    };
+    Compression::decompress(
+        &frame[mem::size_of::<u32>()..],
+        (msize as usize).saturating_sub(mem::size_of::<u32>()),
+    )
+}
+
+/// Decodes the next frame from `src`, decompressing it if it is flagged.
+pub(crate) fn decode_frame<T: Framer>(
+    src: &mut BytesMut,
+    state: &CompressionState,
+) -> io::Result<Option<Frame<T>>> {
+    let Some((size, compressed)) = frame_size(src, state)? else {
+        src.reserve(mem::size_of::<u32>());
+        return Ok(None);
+    };
+    if src.len() < size {
+        src.reserve(size - src.len());
+        return Ok(None);
+    }
+    if !compressed {
+        return Frame::<T>::decode(&mut src.reader()).map(Some);
+    }
+    let frame = src.split_to(size);
+    let body = decompress_frame(&frame, state)?;
+    let mut plain = Vec::with_capacity(mem::size_of::<u32>() + body.len());
+    ((mem::size_of::<u32>() + body.len()) as u32).encode(&mut plain)?;
+    plain.extend_from_slice(&body);
+    Frame::<T>::decode(&mut plain.as_slice()).map(Some)
+}
+
+// r[impl jetstream.rpc.raw-frame]
+/// Splits the next frame off `src` without decoding its message,
+/// decompressing it if it is flagged.
+pub(crate) fn decode_raw_frame(
+    src: &mut BytesMut,
+    state: &CompressionState,
+) -> io::Result<Option<RawFrame>> {
+    let Some((size, compressed)) = frame_size(src, state)? else {
+        src.reserve(mem::size_of::<u32>());
+        return Ok(None);
+    };
+    if src.len() < size {
+        src.reserve(size - src.len());
+        return Ok(None);
+    }
+    let frame = src.split_to(size).freeze();
+    let body = if compressed {
+        Bytes::from(decompress_frame(&frame, state)?)
+    } else {
+        frame.slice(mem::size_of::<u32>()..)
+    };
+    // type[1] tag[2]
+    if body.len() < 3 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "frame is shorter than its header",
+        ));
+    }
+    let tag = u16::from_le_bytes([body[1], body[2]]);
+    RawFrame::new(tag, body[0], body.slice(3..)).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use jetstream_wireformat::JetStreamWireFormat;
+
+    use super::*;
+    use crate::{
+        version::{Tversion, VersionFrame},
+        MAX_MSIZE, TVERSION,
+    };
+
+    fn frame(version: String) -> Frame<VersionFrame> {
+        Frame {
+            tag: 7,
+            msg: VersionFrame::Tversion(Tversion {
+                msize: 8192,
+                version,
+            }),
+        }
+    }
+
+    fn negotiated(msize: u32) -> CompressionState {
+        let state = CompressionState::default();
+        state.set(Compression::select(Capabilities::COMPRESSION));
+        state.set_msize(Some(msize));
+        state
+    }
+
+    #[test]
+    fn small_frames_stay_uncompressed() {
+        let mut dst = BytesMut::new();
+        let compression = Compression::select(Capabilities::COMPRESSION);
+        encode_frame(&frame("short".into()), compression, &mut dst).unwrap();
+        assert_eq!(u32::decode(&mut &dst[..4]).unwrap() & COMPRESSED, 0);
+        let decoded = decode_frame::<VersionFrame>(&mut dst, &negotiated(8192))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.tag, 7);
+    }
+
+    /// r[verify jetstream.rpc.raw-frame]
+    #[test]
+    fn raw_frames_decode_like_frames() {
+        #[derive(JetStreamWireFormat)]
+        struct TversionRef<'a> {
+            msize: u32,
+            version: &'a str,
+        }
+
+        let version = "abc".repeat(4096);
+        for compression in
+            [None, Compression::select(Capabilities::COMPRESSION)]
+        {
+            let mut dst = BytesMut::new();
+            encode_frame(&frame(version.clone()), compression, &mut dst)
+                .unwrap();
+            encode_frame(&frame(version.clone()), compression, &mut dst)
+                .unwrap();
+
+            let state = negotiated(MAX_MSIZE);
+            let raw = decode_raw_frame(&mut dst, &state).unwrap().unwrap();
+            assert_eq!((raw.tag, raw.message_type()), (7, TVERSION));
+            let view: TversionRef = raw.view().unwrap();
+            assert_eq!((view.msize, view.version), (8192, version.as_str()));
+            match raw.decode::<VersionFrame>().unwrap().msg {
+                VersionFrame::Tversion(tversion) => {
+                    assert_eq!(tversion.version, version)
+                }
+                other => panic!("unexpected frame {other:?}"),
+            }
+
+            // Each frame is split off whole.
+            assert!(decode_raw_frame(&mut dst, &state).unwrap().is_some());
+            assert!(dst.is_empty());
+        }
+    }
+
+    /// r[verify jetstream.compression.frame]
+    #[test]
+    fn large_frames_round_trip() {
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            if Compression::select(compression.capability()).is_none() {
+                continue;
+            }
+            let version = "abc".repeat(4096);
+            let mut dst = BytesMut::new();
+            encode_frame(&frame(version.clone()), Some(compression), &mut dst)
+                .unwrap();
+            let size = u32::decode(&mut &dst[..4]).unwrap();
+            assert_ne!(size & COMPRESSED, 0, "{compression:?}");
+            assert!(((size & !COMPRESSED) as usize) < version.len());
+
+            let mut partial = BytesMut::from(&dst[..dst.len() - 1]);
+            let state = negotiated(MAX_MSIZE);
+            assert!(decode_frame::<VersionFrame>(&mut partial, &state)
+                .unwrap()
+                .is_none());
+
+            let decoded = decode_frame::<VersionFrame>(&mut dst, &state)
+                .unwrap()
+                .unwrap();
+            assert!(dst.is_empty());
+            match decoded.msg {
+                VersionFrame::Tversion(tversion) => {
+                    assert_eq!(tversion.version, version)
+                }
+                other => panic!("unexpected frame {other:?}"),
+            }
+        }
+    }
+
+    /// r[verify jetstream.compression.limits]
+    #[test]
+    fn compressed_frames_need_negotiation() {
+        let Some(compression) = Compression::select(Capabilities::COMPRESSION)
+        else {
+            return;
+        };
+        let mut dst = BytesMut::new();
+        encode_frame(&frame("abc".repeat(4096)), Some(compression), &mut dst)
+            .unwrap();
+        let unnegotiated = CompressionState::default();
+        assert!(
+            decode_frame::<VersionFrame>(&mut dst.clone(), &unnegotiated)
+                .is_err()
+        );
+        assert!(decode_raw_frame(&mut dst, &unnegotiated).is_err());
+    }
+
+    /// r[verify jetstream.compression.limits]
+    #[test]
+    fn compressed_frames_are_bounded_by_msize() {
+        let version = "abc".repeat(4096);
+        let mut dst = BytesMut::new();
+        encode_frame(
+            &frame(version.clone()),
+            Compression::select(Capabilities::COMPRESSION),
+            &mut dst,
+        )
+        .unwrap();
+        // The frame fits the msize but its body does not.
+        let state = negotiated(4096);
+        assert!(
+            decode_frame::<VersionFrame>(&mut dst.clone(), &state).is_err()
+        );
+        assert!(decode_raw_frame(&mut dst.clone(), &state).is_err());
+
+        let state = negotiated(version.len() as u32 + 16);
+        assert!(decode_frame::<VersionFrame>(&mut dst.clone(), &state)
+            .unwrap()
+            .is_some());
+
+        // Oversized compressed frames are rejected before they are buffered.
+        let mut header = BytesMut::new();
+        ((MAX_MSIZE + 1) | COMPRESSED)
+            .encode(&mut (&mut header).writer())
+            .unwrap();
+        let state = negotiated(MAX_MSIZE);
+        assert!(
+            decode_frame::<VersionFrame>(&mut header.clone(), &state).is_err()
+        );
+        assert!(decode_raw_frame(&mut header, &state).is_err());
+    }
+
+    /// r[verify jetstream.compression.limits]
+    #[test]
+    fn uncompressed_frames_may_exceed_msize() {
+        // Clients that predate compression negotiate an msize and never
+        // keep their requests under it.
+        let version = "abc".repeat(4096);
+        let mut dst = BytesMut::new();
+        encode_frame(&frame(version.clone()), None, &mut dst).unwrap();
+        let state = negotiated(4096);
+        assert!(decode_frame::<VersionFrame>(&mut dst.clone(), &state)
+            .unwrap()
+            .is_some());
+        assert!(decode_raw_frame(&mut dst, &state).unwrap().is_some());
+    }
+
+    /// r[verify jetstream.compression.negotiation]
+    #[test]
+    fn observes_acknowledged_compression() {
+        let state = CompressionState::default();
+        let rversion = Frame {
+            tag: 0,
+            msg: VersionFrame::Rversion(Rversion {
+                msize: 8192,
+                version: Capabilities::COMPRESSION
+                    .negotiate(Capabilities::COMPRESSION)
+                    .acknowledge("rs.jetstream.proto/echo/1.0.0"),
+            }),
+        };
+        state.observe(&rversion);
+        assert_eq!(state.get(), Compression::select(Capabilities::COMPRESSION));
+        assert_eq!(state.msize(), Some(8192));
+
+        // Other frames leave the state alone.
+        state.observe(&frame("rs.jetstream.proto/echo/1.0.0".into()));
+        assert_eq!(state.get(), Compression::select(Capabilities::COMPRESSION));
+    }
+}
@@ -6,7 +6,13 @@ use std::net::SocketAddr;
 use std::ops::{Deref, DerefMut};
 #[cfg(tokio_unix)]
 use std::path::PathBuf;
-use std::{fmt::Display, net::IpAddr};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Display,
+    net::IpAddr,
+    sync::Arc,
+};
 
 use jetstream_wireformat::{JetStreamWireFormat, WireFormat};
 #[cfg(tokio_unix)]
@@ -16,10 +22,21 @@ use tokio_util::codec::Framed;
 #[cfg(any(feature = "iroh", feature = "x509"))]
 use url::Url;
 
+use crate::{
+    auth::{Authorizer, SharedAuthorizer},
+    compression::Compression,
+    push::Upstream,
+    Version,
+};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct Context {
     remote: Option<RemoteAddr>,
     peer: Option<Peer>,
+    authorizer: Option<SharedAuthorizer>,
+    compression: Option<Compression>,
+    msize: Option<u32>,
+    extensions: Extensions,
 }
 
 impl Display for Context {
@@ -63,6 +80,10 @@ impl From<NodeId> for Context {
         Context {
             remote: None,
             peer: Some(Peer::NodeId(value)),
+            authorizer: None,
+            compression: None,
+            msize: None,
+            extensions: Extensions::default(),
         }
     }
 }
@@ -332,7 +353,18 @@ impl TlsPeer {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, JetStreamWireFormat)]
-pub struct NodeId(String);
+pub struct NodeId(pub(crate) String);
+
+impl NodeId {
+    /// Node id of a peer identified by a raw public key, hex encoded.
+    pub fn from_public_key(key: &[u8]) -> Self {
+        NodeId(hex::encode(key))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
 
 #[cfg(feature = "iroh")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, JetStreamWireFormat)]
@@ -526,7 +558,14 @@ impl<U> Contextual for Framed<UnixStream, U> {
         } else {
             None
         };
-        Context { remote, peer }
+        Context {
+            remote,
+            peer,
+            authorizer: None,
+            compression: None,
+            msize: None,
+            extensions: Extensions::default(),
+        }
     }
 }
 
@@ -537,6 +576,10 @@ impl<U> Contextual for Framed<turmoil::net::TcpStream, U> {
         Context {
             remote: Some(RemoteAddr::IpAddr(addr.ip())),
             peer: None,
+            authorizer: None,
+            compression: None,
+            msize: None,
+            extensions: Extensions::default(),
         }
     }
 }
@@ -547,13 +590,24 @@ impl Contextual for worker::Request {
         Context {
             remote: None,
             peer: None,
+            authorizer: None,
+            compression: None,
+            msize: None,
+            extensions: Extensions::default(),
         }
     }
 }
 
 impl Context {
     pub fn new(remote: Option<RemoteAddr>, peer: Option<Peer>) -> Self {
-        Context { remote, peer }
+        Context {
+            remote,
+            peer,
+            authorizer: None,
+            compression: None,
+            msize: None,
+            extensions: Extensions::default(),
+        }
     }
 
     /// Get the remote address
@@ -565,4 +619,196 @@ impl Context {
     pub fn peer(&self) -> Option<&Peer> {
         self.peer.as_ref()
     }
+
+    /// Get the authorizer consulted before each call
+    pub fn authorizer(&self) -> Option<&dyn Authorizer> {
+        self.authorizer.as_ref().map(|a| a.0.as_ref())
+    }
+
+    /// Attach an authorizer to this context
+    pub fn with_authorizer(
+        self,
+        authorizer: impl Authorizer + 'static,
+    ) -> Self {
+        self.with_shared_authorizer(Arc::new(authorizer))
+    }
+
+    /// Attach a shared authorizer to this context
+    pub fn with_shared_authorizer(
+        mut self,
+        authorizer: Arc<dyn Authorizer>,
+    ) -> Self {
+        self.authorizer = Some(SharedAuthorizer(authorizer));
+        self
+    }
+
+    /// Get the frame compression negotiated for this stream
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Record the frame compression negotiated for this stream
+    pub fn with_compression(
+        mut self,
+        compression: Option<Compression>,
+    ) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Get the msize negotiated for this stream
+    pub fn msize(&self) -> Option<u32> {
+        self.msize
+    }
+
+    /// Record the msize negotiated for this stream
+    pub fn with_msize(mut self, msize: u32) -> Self {
+        self.msize = Some(msize);
+        self
+    }
+
+    /// Get the values attached by transports and middleware
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get the values attached by transports and middleware, mutably
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get the attached value of type `T`
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
+    /// Attach a value, replacing any previous value of the same type
+    pub fn with_extension<T: Send + Sync + 'static>(
+        mut self,
+        value: T,
+    ) -> Self {
+        self.extensions.insert(value);
+        self
+    }
+
+    /// Get the version negotiated for this stream
+    pub fn version(&self) -> Option<&Version> {
+        self.extension()
+    }
+
+    /// Get the name of the protocol the stream was routed to
+    pub fn protocol(&self) -> Option<&str> {
+        self.extension::<ProtocolName>().map(|name| name.0.as_str())
+    }
+
+    /// Get the id of the connection carrying this stream
+    pub fn connection_id(&self) -> Option<ConnectionId> {
+        self.extension().copied()
+    }
+
+    /// Get the id of this stream within its connection
+    pub fn stream_id(&self) -> Option<StreamId> {
+        self.extension().copied()
+    }
+
+    /// Get the opener of streams back to the client, if the transport
+    /// supports server-initiated streams
+    pub fn upstream(&self) -> Option<&Upstream> {
+        self.extension()
+    }
+}
+
+// r[impl jetstream.context.extensions]
+/// A map from type to value, for data that transports and middleware attach
+/// to a [`Context`] (an authenticated user, a tenant, ...) for handlers.
+///
+/// Values are shared behind an [`Arc`] and copied on write, so cloning an
+/// `Extensions` is a reference count increment. Two maps are equal only if
+/// they share the same storage.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: Option<Arc<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>,
+}
+
+impl Extensions {
+    /// Insert a value, returning the previous value of the same type.
+    pub fn insert<T: Send + Sync + 'static>(
+        &mut self,
+        value: T,
+    ) -> Option<Arc<T>> {
+        Arc::make_mut(self.map.get_or_insert_with(Default::default))
+            .insert(TypeId::of::<T>(), Arc::new(value))
+            .and_then(|previous| previous.downcast().ok())
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map.as_ref()?.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    /// Remove the value of type `T`, returning it.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<Arc<T>> {
+        let map = self.map.as_mut()?;
+        if !map.contains_key(&TypeId::of::<T>()) {
+            return None;
+        }
+        Arc::make_mut(map)
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl PartialEq for Extensions {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.map, &other.map) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
 }
+
+impl Eq for Extensions {}
+
+impl std::hash::Hash for Extensions {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.map
+            .as_ref()
+            .map(|map| Arc::as_ptr(map) as *const () as usize)
+            .hash(state);
+    }
+}
+
+// r[impl jetstream.context.builtin]
+/// Name of the protocol a stream was routed to, set by the
+/// [`Router`](crate::Router).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProtocolName(pub String);
+
+/// Identifies a connection among those accepted by the same server, set by
+/// QUIC transports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionId(pub u64);
+
+/// Identifies a stream within its connection, set by QUIC transports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamId(pub u64);
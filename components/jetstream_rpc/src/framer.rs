@@ -1,10 +1,62 @@
-use jetstream_wireformat::WireFormat;
+use jetstream_wireformat::{WireFormat, WireFormatRef};
 use std::io;
 use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Write;
 use std::mem;
 
+use tokio_util::bytes::{buf::Writer, BufMut, Bytes, BytesMut};
+
+use crate::{TraceContext, NOTAG, TTRACE, TVERSION};
+
+// r[impl jetstream.service.message-ids.extended]
+/// Message type of requests to services with `#[service(extended_ids)]`. The
+/// request is preceded by its `u16` message id.
+pub const TEXTENDED: u8 = 96;
+/// Message type of responses from services with `#[service(extended_ids)]`.
+pub const REXTENDED: u8 = TEXTENDED + 1;
+
+// r[impl jetstream.service.borrowed-args.client]
+/// A request encoded when it was made, so that its arguments could be
+/// borrowed from the caller rather than moved into a message.
+///
+/// The arguments are encoded once into a shared buffer, which the codec
+/// copies straight into the frame; clones share the buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoded {
+    message_type: u8,
+    bytes: Bytes,
+}
+
+impl Encoded {
+    /// Encodes a message of `message_type` with `encode`, which writes
+    /// everything after the frame header, about `size` bytes of it.
+    pub fn new(
+        message_type: u8,
+        size: u32,
+        encode: impl FnOnce(&mut Writer<BytesMut>) -> io::Result<()>,
+    ) -> io::Result<Self> {
+        let mut writer = BytesMut::with_capacity(size as usize).writer();
+        encode(&mut writer)?;
+        Ok(Self {
+            message_type,
+            bytes: writer.into_inner().freeze(),
+        })
+    }
+
+    pub fn message_type(&self) -> u8 {
+        self.message_type
+    }
+
+    pub fn byte_size(&self) -> u32 {
+        self.bytes.len() as u32
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Frame<T: Framer> {
     pub tag: u16,
@@ -20,12 +72,121 @@ impl<T: Framer + std::fmt::Debug> std::fmt::Debug for Frame<T> {
     }
 }
 
+impl<T: Framer> Frame<T> {
+    /// Whether the frame is a request to a `#[oneway]` method, which is
+    /// answered with nothing.
+    pub fn is_oneway(&self) -> bool {
+        self.tag == NOTAG && self.msg.message_type() != TVERSION
+    }
+}
+
 impl<T: Framer> From<(u16, T)> for Frame<T> {
     fn from((tag, msg): (u16, T)) -> Self {
         Self { tag, msg }
     }
 }
 
+// r[impl jetstream.rpc.raw-frame]
+/// A frame whose message is kept as the bytes it was framed with, so that
+/// handlers can inspect large requests through views that borrow from them
+/// rather than decoding owned messages.
+///
+/// Trace context envelopes and the message ids of
+/// `#[service(extended_ids)]` requests are unwrapped: [`message`](Self::message)
+/// and [`view`](Self::view) see the method's own message, and
+/// [`trace`](Self::trace) and [`message_id`](Self::message_id) what preceded
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame {
+    pub tag: u16,
+    message_type: u8,
+    /// The message as framed, envelope included.
+    body: Bytes,
+    trace: Option<TraceContext>,
+    message_id: u16,
+    /// The method's own message.
+    message: Bytes,
+}
+
+impl RawFrame {
+    /// Wraps the message of type `message_type` framed with `tag`,
+    /// unwrapping any trace context envelope and extended message id.
+    pub fn new(tag: u16, message_type: u8, body: Bytes) -> io::Result<Self> {
+        let mut message = body.clone();
+        let mut inner_type = message_type;
+        let mut trace = None;
+        if inner_type == TTRACE {
+            let mut reader = &message[..];
+            trace = Some(TraceContext::decode(&mut reader)?);
+            inner_type = u8::decode(&mut reader)?;
+            if inner_type == TTRACE {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "nested trace context envelope",
+                ));
+            }
+            message = message.slice(message.len() - reader.len()..);
+        }
+        let message_id = if inner_type == TEXTENDED {
+            let id = u16::decode(&mut &message[..])?;
+            message = message.slice(mem::size_of::<u16>()..);
+            id
+        } else {
+            inner_type as u16
+        };
+        Ok(Self {
+            tag,
+            message_type,
+            body,
+            trace,
+            message_id,
+            message,
+        })
+    }
+
+    /// The message type on the wire, before any envelope is unwrapped.
+    pub fn message_type(&self) -> u8 {
+        self.message_type
+    }
+
+    /// The message id of the method the request is for: the message type
+    /// inside any trace context envelope, or the extended message id.
+    pub fn message_id(&self) -> u16 {
+        self.message_id
+    }
+
+    /// The trace context the request was wrapped in, if any.
+    pub fn trace(&self) -> Option<&TraceContext> {
+        self.trace.as_ref()
+    }
+
+    /// The encoded message of the method, without the frame header, trace
+    /// context envelope or extended message id.
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    /// Whether the frame is a request to a `#[oneway]` method, which is
+    /// answered with nothing.
+    pub fn is_oneway(&self) -> bool {
+        self.tag == NOTAG && self.message_type != TVERSION
+    }
+
+    /// Decodes a view of the method's message that borrows from the frame.
+    /// Only the bytes the view reads need to be valid, so it can stop short
+    /// of the end of the message.
+    pub fn view<'a, V: WireFormatRef<'a>>(&'a self) -> io::Result<V> {
+        V::decode_ref(&mut &self.message[..])
+    }
+
+    /// Decodes the owned message, envelope included, as the frame would
+    /// have been decoded without opting into raw frames.
+    pub fn decode<T: Framer>(&self) -> io::Result<Frame<T>> {
+        let msg = T::decode(&mut &self.body[..], self.message_type)?;
+        Ok(Frame { tag: self.tag, msg })
+    }
+}
+
 impl<T: Framer> WireFormat for Frame<T> {
     fn byte_size(&self) -> u32 {
         let msg_size = self.msg.byte_size();
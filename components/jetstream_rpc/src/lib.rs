@@ -11,28 +11,43 @@
 
 extern crate tokio_util;
 mod any_server;
+pub mod auth;
+pub mod balance;
+#[cfg(native)]
+pub mod blocking;
 mod call;
 pub mod client;
+pub mod compression;
 mod constants;
 pub mod context;
 mod error;
 pub mod framer;
+pub mod lifecycle;
+pub mod mock;
 mod mux;
+pub mod name;
+pub mod push;
+pub mod retry;
 mod router;
+pub mod schema;
 pub mod server;
 mod tag;
+pub mod trace;
 mod version;
 pub use any_server::AnyServer;
 pub use call::*;
+pub use compression::Compression;
 pub use constants::*;
 pub use error::*;
 pub use jetstream_error::IntoError;
 use jetstream_wireformat::WireFormat;
 pub use mux::*;
+pub use retry::RetryPolicy;
 pub use router::*;
 use std::str::FromStr;
 pub use tag::*;
 pub use tokio_util::codec::{Decoder, Encoder, Framed};
+pub use trace::{TraceContext, TTRACE};
 pub use version::*;
 
 pub enum Encoding {
@@ -81,6 +96,26 @@ pub trait Protocol: Send + Sync {
     type Error: IntoError;
     const VERSION: &'static str;
     const NAME: &'static str;
+    /// Optional extensions this side understands and will acknowledge
+    /// during version negotiation.
+    const CAPABILITIES: Capabilities = Capabilities::NONE;
+    /// Digests of the protocol's methods, compared with the peer's during
+    /// version negotiation.
+    const METHODS: &'static [schema::MethodDigest] = &[];
+
+    // r[impl jetstream.service.generics.name]
+    /// The name clients ask the [`Router`] for. Instantiations of generic
+    /// services are named after their type arguments, so it is not always
+    /// [`NAME`](Self::NAME).
+    fn protocol_name() -> std::borrow::Cow<'static, str> {
+        std::borrow::Cow::Borrowed(Self::NAME)
+    }
+
+    /// The version string sent during version negotiation, carrying
+    /// [`protocol_name`](Self::protocol_name).
+    fn protocol_version() -> std::borrow::Cow<'static, str> {
+        std::borrow::Cow::Borrowed(Self::VERSION)
+    }
 }
 
 // const _: () = {
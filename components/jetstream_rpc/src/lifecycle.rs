@@ -0,0 +1,219 @@
+//! Methods added to, and deprecated in, later versions of a service.
+//!
+//! Peers negotiate the lower of their two versions, so during a rolling
+//! upgrade a client can talk to a server that predates some of its methods.
+//! Methods marked `#[since("1.3.0")]` are only called once a version at
+//! least that high was negotiated:
+//!
+//! ```ignore
+//! #[service]
+//! pub trait Storage {
+//!     async fn get(&mut self, key: String) -> Result<String>;
+//!     #[since("1.3.0")]
+//!     async fn scan(&mut self, prefix: String) -> Result<Vec<String>>;
+//!     #[deprecated(note = "use `get`")]
+//!     async fn fetch(&mut self, key: String) -> Result<String>;
+//! }
+//! ```
+//!
+//! The generated channel refuses calls to `scan` without sending them, and
+//! the generated server refuses them on streams that negotiated a lower
+//! version, both with [`UNIMPLEMENTED_BY_PEER`]. Transports that never
+//! negotiate, such as HTTP, have no version to refuse them by and serve them
+//! like any other method. The server logs calls to
+//! `#[deprecated]` methods and counts them in its protocol module's
+//! `DEPRECATED`.
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    RwLock,
+};
+
+use crate::{context::Context, Error, Version};
+
+/// Error code for calls to methods the negotiated version does not have.
+pub const UNIMPLEMENTED_BY_PEER: &str =
+    "jetstream_rpc::error::unimplemented_by_peer";
+
+// r[impl jetstream.service.since]
+/// A method first served in version `major.minor.patch` of its service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Since {
+    pub protocol: &'static str,
+    pub method: &'static str,
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Since {
+    /// Refuses the call if `negotiated` predates the method. Versions are
+    /// compared by major, minor and patch. Calls are let through when no
+    /// version was negotiated, or one that is not a JetStream version.
+    pub fn check(&self, negotiated: Option<&Version>) -> Result<(), Error> {
+        let Some(Version::JetStream { version, .. }) = negotiated else {
+            return Ok(());
+        };
+        if (version.major, version.minor, version.patch)
+            >= (self.major, self.minor, self.patch)
+        {
+            return Ok(());
+        }
+        Err(Error::with_code(
+            format!(
+                "method `{}` of protocol `{}` was added in {}.{}.{}, but \
+                 {}.{}.{} was negotiated",
+                self.method,
+                self.protocol,
+                self.major,
+                self.minor,
+                self.patch,
+                version.major,
+                version.minor,
+                version.patch
+            ),
+            UNIMPLEMENTED_BY_PEER,
+        ))
+    }
+
+    // r[impl jetstream.service.since.server]
+    /// Refuses the call if the version negotiated for the stream `ctx`
+    /// belongs to predates the method.
+    pub fn check_context(&self, ctx: &Context) -> Result<(), Error> {
+        self.check(ctx.version())
+    }
+}
+
+// r[impl jetstream.service.since.client]
+/// The version a channel negotiated.
+#[derive(Debug, Default)]
+pub struct NegotiatedVersion(RwLock<Option<Version>>);
+
+impl NegotiatedVersion {
+    pub fn get(&self) -> Option<Version> {
+        self.0.read().expect("negotiated version poisoned").clone()
+    }
+
+    pub fn set(&self, version: Option<Version>) {
+        *self.0.write().expect("negotiated version poisoned") = version;
+    }
+
+    /// Refuses a call to `since`'s method if the negotiated version
+    /// predates it.
+    pub fn check(&self, since: &Since) -> Result<(), Error> {
+        since.check(self.get().as_ref())
+    }
+}
+
+// r[impl jetstream.service.deprecated]
+/// A `#[deprecated]` method, and how often the server was called on it.
+#[derive(Debug)]
+pub struct Deprecation {
+    pub protocol: &'static str,
+    pub method: &'static str,
+    /// The `note` of its `#[deprecated]` attribute.
+    pub note: Option<&'static str>,
+    calls: AtomicU64,
+}
+
+impl Deprecation {
+    pub const fn new(
+        protocol: &'static str,
+        method: &'static str,
+        note: Option<&'static str>,
+    ) -> Self {
+        Self {
+            protocol,
+            method,
+            note,
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts a call. The first is logged as a warning and the others at
+    /// debug level, so clients calling it in a loop do not flood the log.
+    pub fn record(&self) {
+        let calls = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+        let note = self.note.unwrap_or_default();
+        if calls == 1 {
+            tracing::warn!(
+                protocol = self.protocol,
+                method = self.method,
+                note,
+                "deprecated method called"
+            );
+        } else {
+            tracing::debug!(
+                protocol = self.protocol,
+                method = self.method,
+                calls,
+                "deprecated method called"
+            );
+        }
+    }
+
+    /// How often the method was called since the process started.
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const SCAN: Since = Since {
+        protocol: "storage",
+        method: "scan",
+        major: 1,
+        minor: 3,
+        patch: 0,
+    };
+
+    fn version(version: &str) -> Version {
+        Version::from_str(&format!("rs.jetstream.proto/storage/{version}"))
+            .unwrap()
+    }
+
+    /// r[verify jetstream.service.since]
+    #[test]
+    fn refuses_methods_newer_than_the_negotiated_version() {
+        assert!(SCAN.check(Some(&version("1.3.0"))).is_ok());
+        assert!(SCAN.check(Some(&version("1.4.2+bfd7d20e"))).is_ok());
+        assert!(SCAN.check(Some(&version("2.0.0"))).is_ok());
+        let err = SCAN.check(Some(&version("1.2.9"))).unwrap_err();
+        assert_eq!(err.code(), Some(UNIMPLEMENTED_BY_PEER));
+        assert_eq!(
+            err.message(),
+            "method `scan` of protocol `storage` was added in 1.3.0, but \
+             1.2.9 was negotiated"
+        );
+
+        // Streams that never negotiated have no version to refuse by.
+        assert!(SCAN.check(None).is_ok());
+        assert!(SCAN.check(Some(&Version::V9P2000L)).is_ok());
+    }
+
+    /// r[verify jetstream.service.since.client]
+    #[test]
+    fn channels_check_the_version_they_negotiated() {
+        let negotiated = NegotiatedVersion::default();
+        assert!(negotiated.check(&SCAN).is_ok());
+        negotiated.set(Some(version("1.2.0")));
+        assert!(negotiated.check(&SCAN).is_err());
+        negotiated.set(Some(version("1.3.1")));
+        assert!(negotiated.check(&SCAN).is_ok());
+    }
+
+    /// r[verify jetstream.service.deprecated]
+    #[test]
+    fn counts_deprecated_calls() {
+        static FETCH: Deprecation =
+            Deprecation::new("storage", "fetch", Some("use `get`"));
+        assert_eq!(FETCH.calls(), 0);
+        FETCH.record();
+        FETCH.record();
+        assert_eq!(FETCH.calls(), 2);
+    }
+}
@@ -0,0 +1,398 @@
+//! Expectations behind the mocks `#[service(mock)]` generates.
+//!
+//! A service `Echo` gets a `MockEcho` that implements the trait. Each method
+//! has an `expect_{method}` builder and a `{method}_calls` counter:
+//!
+//! ```ignore
+//! let mock = MockEcho::new();
+//! mock.expect_ping()
+//!     .with_args("hello".to_string())
+//!     .returns("world".to_string())
+//!     .times(1);
+//! mock.expect_ping().fails(Error::new("unreachable"));
+//!
+//! // in-process
+//! assert_eq!(mock.clone().ping("hello".to_string()).await?, "world");
+//! // or behind the generated server, through any transport
+//! let router = Router::new().with_handler(PROTOCOL_NAME, mock.service());
+//!
+//! mock.verify();
+//! ```
+//!
+//! A call no expectation answers panics, unless it was served: a server task
+//! cannot panic at the test, so the call fails with [`UNEXPECTED_CALL`] and
+//! the mock panics when the test drops it, if `verify` did not report it
+//! first.
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::Error;
+
+/// Error code for served calls no expectation answered.
+pub const UNEXPECTED_CALL: &str = "jetstream_rpc::error::unexpected_mock_call";
+
+/// Return types whose success and failure values the mock can build.
+pub trait MockResult {
+    type Ok;
+    type Err;
+
+    fn from_ok(value: Self::Ok) -> Self;
+    fn from_err(err: Self::Err) -> Self;
+}
+
+impl<T, E> MockResult for Result<T, E> {
+    type Ok = T;
+    type Err = E;
+
+    fn from_ok(value: T) -> Self {
+        Ok(value)
+    }
+
+    fn from_err(err: E) -> Self {
+        Err(err)
+    }
+}
+
+type Matcher<A> = Box<dyn Fn(&A) -> bool + Send + Sync>;
+type Responder<A, R> = Box<dyn FnMut(A) -> R + Send>;
+
+struct Expected<A, R> {
+    matcher: Option<Matcher<A>>,
+    responder: Option<Arc<Mutex<Responder<A, R>>>>,
+    times: Option<usize>,
+    calls: usize,
+}
+
+struct State<A, R> {
+    expectations: Vec<Expected<A, R>>,
+    calls: usize,
+    /// Calls no expectation answered.
+    failures: Vec<String>,
+    /// Failures already reported by `verify` or on drop.
+    reported: usize,
+}
+
+// r[impl jetstream.mock.method]
+/// The expectations set on one method of a mock. Clones share them.
+///
+/// Calls are answered by the first expectation, in the order they were set,
+/// whose matcher accepts the arguments and whose `times` is not used up.
+/// Arguments are `()` for methods without any, the argument itself for
+/// methods with one, and a tuple otherwise. A `Context` argument is never
+/// part of them.
+pub struct MockMethod<A, R> {
+    name: &'static str,
+    state: Arc<Mutex<State<A, R>>>,
+}
+
+impl<A, R> Clone for MockMethod<A, R> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<A, R> std::fmt::Debug for MockMethod<A, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockMethod")
+            .field("name", &self.name)
+            .field("calls", &self.calls())
+            .finish()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A responder that panicked must not hide the expectations from
+    // `verify`.
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<A, R> MockMethod<A, R> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            state: Arc::new(Mutex::new(State {
+                expectations: Vec::new(),
+                calls: 0,
+                failures: Vec::new(),
+                reported: 0,
+            })),
+        }
+    }
+
+    /// Adds an expectation, configured through the returned builder.
+    pub fn expect(&self) -> Expectation<A, R> {
+        let mut state = lock(&self.state);
+        state.expectations.push(Expected {
+            matcher: None,
+            responder: None,
+            times: None,
+            calls: 0,
+        });
+        Expectation {
+            name: self.name,
+            state: self.state.clone(),
+            index: state.expectations.len() - 1,
+        }
+    }
+
+    // r[impl jetstream.mock.unexpected]
+    /// Answers a call. Panics if no expectation matches, or the matching one
+    /// has no return value.
+    pub fn call(&self, args: A) -> R {
+        match self.answer(args) {
+            Ok(result) => result,
+            Err(failure) => panic!("{}", failure),
+        }
+    }
+
+    // r[impl jetstream.mock.served]
+    /// Answers a call the generated server serves. Fails it with
+    /// [`UNEXPECTED_CALL`] where [`call`](Self::call) would panic.
+    pub fn serve(&self, args: A) -> R
+    where
+        R: MockResult,
+        R::Err: From<Error>,
+    {
+        self.answer(args).unwrap_or_else(|failure| {
+            R::from_err(Error::with_code(failure, UNEXPECTED_CALL).into())
+        })
+    }
+
+    fn answer(&self, args: A) -> Result<R, String> {
+        let responder = {
+            let mut state = lock(&self.state);
+            state.calls += 1;
+            let expected = state.expectations.iter_mut().find(|expected| {
+                expected.times.is_none_or(|times| expected.calls < times)
+                    && expected
+                        .matcher
+                        .as_ref()
+                        .is_none_or(|matches| matches(&args))
+            });
+            let responder = match expected {
+                None => Err("no expectation matches the call"),
+                Some(expected) => {
+                    expected.calls += 1;
+                    expected
+                        .responder
+                        .clone()
+                        .ok_or("the expectation has no return value")
+                }
+            };
+            match responder {
+                Ok(responder) => responder,
+                Err(failure) => {
+                    let failure = format!("{}: {}", self.name, failure);
+                    state.failures.push(failure.clone());
+                    return Err(failure);
+                }
+            }
+        };
+        // Responders run unlocked, so they may call the mock themselves.
+        let mut respond = lock(&responder);
+        Ok(respond(args))
+    }
+
+    /// The first call no expectation answered since the last report, if
+    /// any, marking them all reported.
+    pub fn take_failure(&self) -> Option<String> {
+        let mut state = lock(&self.state);
+        let failure = state.failures.get(state.reported).cloned();
+        state.reported = state.failures.len();
+        failure
+    }
+
+    /// Calls made so far, expected or not.
+    pub fn calls(&self) -> usize {
+        lock(&self.state).calls
+    }
+
+    // r[impl jetstream.mock.verify]
+    /// Checks that every expectation limited with `times` was called exactly
+    /// that often and that no call went unanswered.
+    pub fn verify(&self) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        if let Some(failure) = state.failures.first().cloned() {
+            state.reported = state.failures.len();
+            return Err(format!(
+                "{} call(s) went unanswered, the first: {}",
+                state.failures.len(),
+                failure
+            ));
+        }
+        for (index, expected) in state.expectations.iter().enumerate() {
+            match expected.times {
+                Some(times) if times != expected.calls => {
+                    return Err(format!(
+                        "{}: expectation #{} expected {} call(s), got {}",
+                        self.name, index, times, expected.calls
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// r[impl jetstream.mock.expectation]
+/// Builder for one expectation of a [`MockMethod`].
+pub struct Expectation<A, R> {
+    name: &'static str,
+    state: Arc<Mutex<State<A, R>>>,
+    index: usize,
+}
+
+impl<A, R> std::fmt::Debug for Expectation<A, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Expectation")
+            .field("name", &self.name)
+            .field("index", &self.index)
+            .field("calls", &self.calls())
+            .finish()
+    }
+}
+
+impl<A, R> Expectation<A, R> {
+    fn update(self, f: impl FnOnce(&mut Expected<A, R>)) -> Self {
+        f(&mut lock(&self.state).expectations[self.index]);
+        self
+    }
+
+    /// Only match calls whose arguments `matcher` accepts.
+    pub fn with(
+        self,
+        matcher: impl Fn(&A) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.update(|expected| expected.matcher = Some(Box::new(matcher)))
+    }
+
+    /// Only match calls with exactly these arguments.
+    pub fn with_args(self, args: A) -> Self
+    where
+        A: PartialEq + Send + Sync + 'static,
+    {
+        self.with(move |actual| *actual == args)
+    }
+
+    /// Answer matching calls with `respond`.
+    pub fn returning(
+        self,
+        respond: impl FnMut(A) -> R + Send + 'static,
+    ) -> Self {
+        self.update(|expected| {
+            expected.responder = Some(Arc::new(Mutex::new(Box::new(respond))))
+        })
+    }
+
+    /// Match at most `times` calls; [`MockMethod::verify`] fails unless it
+    /// matched exactly that many.
+    pub fn times(self, times: usize) -> Self {
+        self.update(|expected| expected.times = Some(times))
+    }
+
+    /// Calls this expectation answered.
+    pub fn calls(&self) -> usize {
+        lock(&self.state).expectations[self.index].calls
+    }
+}
+
+impl<A, R: MockResult> Expectation<A, R> {
+    /// Answer matching calls with `Ok(value)`.
+    pub fn returns(self, value: R::Ok) -> Self
+    where
+        R::Ok: Clone + Send + 'static,
+    {
+        self.returning(move |_| R::from_ok(value.clone()))
+    }
+
+    /// Answer matching calls with `Err(err)`.
+    pub fn fails(self, err: R::Err) -> Self
+    where
+        R::Err: Clone + Send + 'static,
+    {
+        self.returning(move |_| R::from_err(err.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Ping = MockMethod<String, Result<String, String>>;
+
+    /// r[verify jetstream.mock.expectation]
+    #[test]
+    fn expectations_match_in_order() {
+        let ping = Ping::new("Echo::ping");
+        let hello = ping
+            .expect()
+            .with_args("hello".to_string())
+            .returns("world".to_string())
+            .times(1);
+        ping.expect()
+            .with(|msg| msg.starts_with('!'))
+            .fails("boom".to_string());
+        ping.expect().returning(|msg| Ok(msg.to_uppercase()));
+
+        assert_eq!(ping.call("hello".to_string()), Ok("world".to_string()));
+        assert_eq!(ping.call("hello".to_string()), Ok("HELLO".to_string()));
+        assert_eq!(ping.call("!x".to_string()), Err("boom".to_string()));
+        assert_eq!(hello.calls(), 1);
+        assert_eq!(ping.calls(), 3);
+        ping.verify().unwrap();
+    }
+
+    /// r[verify jetstream.mock.verify]
+    /// r[verify jetstream.mock.unexpected]
+    #[test]
+    fn verify_reports_missing_and_unexpected_calls() {
+        let ping = Ping::new("Echo::ping");
+        ping.expect().returns("pong".to_string()).times(2);
+        ping.call(String::new()).unwrap();
+        assert!(ping.verify().unwrap_err().contains("expected 2 call(s)"));
+
+        ping.call(String::new()).unwrap();
+        ping.verify().unwrap();
+
+        let unexpected = ping.clone();
+        std::thread::spawn(move || unexpected.call(String::new()))
+            .join()
+            .unwrap_err();
+        assert!(ping
+            .verify()
+            .unwrap_err()
+            .contains("no expectation matches the call"));
+        assert_eq!(ping.take_failure(), None);
+    }
+
+    /// r[verify jetstream.mock.served]
+    #[test]
+    fn served_calls_fail_instead_of_panicking() {
+        type Add = MockMethod<u32, Result<u32, Error>>;
+        let add = Add::new("Calculator::add");
+        add.expect().with_args(1).returns(2);
+        let err = add.serve(3).unwrap_err();
+        assert_eq!(err.code(), Some(UNEXPECTED_CALL));
+        assert_eq!(
+            add.take_failure().as_deref(),
+            Some("Calculator::add: no expectation matches the call")
+        );
+        assert_eq!(add.take_failure(), None);
+    }
+
+    #[test]
+    fn responders_may_call_the_mock() {
+        let ping = Ping::new("Echo::ping");
+        let inner = ping.clone();
+        ping.expect()
+            .with_args("outer".to_string())
+            .returning(move |_| inner.call("inner".to_string()));
+        ping.expect().returns("pong".to_string());
+        assert_eq!(ping.call("outer".to_string()), Ok("pong".to_string()));
+    }
+}
@@ -1,4 +1,11 @@
-use std::{collections::BTreeMap, pin::Pin, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use futures::{Sink, Stream, StreamExt};
 use tokio::sync::{oneshot, Mutex};
@@ -6,8 +13,8 @@ use tokio::sync::{oneshot, Mutex};
 use jetstream_error::{Error, Result};
 
 use crate::{
-    client::ClientTransport, context::Context, Frame, Protocol, RpcCall,
-    TagPool,
+    client::ClientTransport, context::Context, retry::CONNECTION_LOST,
+    Capabilities, Frame, Protocol, RpcCall, TagPool, NOTAG,
 };
 
 pub type RxStream<P> = Pin<
@@ -33,11 +40,32 @@ pub type InFlight<P> = Arc<
     >,
 >;
 
+/// Tag pool that also counts the tags in use.
+struct Tags {
+    pool: TagPool,
+    outstanding: AtomicUsize,
+}
+
+impl Tags {
+    async fn acquire_tag(&self) -> u16 {
+        let tag = self.pool.acquire_tag().await;
+        self.outstanding.fetch_add(1, Ordering::AcqRel);
+        tag
+    }
+
+    async fn release_tag(&self, tag: u16) {
+        self.outstanding.fetch_sub(1, Ordering::AcqRel);
+        self.pool.release_tag(tag).await;
+    }
+}
+
 /// Client Mux
 pub struct Mux<P: Protocol> {
     send_queue: tokio::sync::mpsc::Sender<Frame<P::Request>>,
     in_flight: InFlight<P>,
-    tag_pool: Arc<TagPool>,
+    tag_pool: Arc<Tags>,
+    capabilities: AtomicU32,
+    closed: Arc<AtomicBool>,
 }
 
 impl<P: Protocol> Mux<P>
@@ -47,18 +75,22 @@ where
     async fn demux(
         mut rx: RxStream<P>,
         in_flight: InFlight<P>,
-        tag_pool: Arc<TagPool>,
+        tag_pool: Arc<Tags>,
+        closed: Arc<AtomicBool>,
     ) -> Result<()> {
         use futures::StreamExt;
-        while let Some(Ok(frame)) = rx.next().await {
-            let frame: Frame<P::Response> = frame;
+        let reason = loop {
+            let frame: Frame<P::Response> = match rx.next().await {
+                Some(Ok(frame)) => frame,
+                Some(Err(err)) => break err.to_string(),
+                None => break "connection closed".to_string(),
+            };
             let tag = frame.tag;
-            let result = in_flight
-                .lock()
-                .await
-                .remove(&frame.tag)
-                .unwrap()
-                .send(Ok(frame));
+            // Nothing waits for the answers to oneway requests.
+            let Some(tx) = in_flight.lock().await.remove(&tag) else {
+                continue;
+            };
+            let result = tx.send(Ok(frame));
             match result {
                 Ok(_) => {}
                 Err(_) => {
@@ -66,18 +98,40 @@ where
                 }
             };
             tag_pool.release_tag(tag).await;
-        }
+        };
 
+        // r[impl jetstream.retry.mux]
+        // Nothing will answer the calls still waiting, fail them so idempotent
+        // ones can be retried.
+        let mut in_flight = in_flight.lock().await;
+        closed.store(true, Ordering::Release);
+        for (tag, tx) in std::mem::take(&mut *in_flight) {
+            let _ =
+                tx.send(Err(Error::with_code(reason.clone(), CONNECTION_LOST)));
+            tag_pool.release_tag(tag).await;
+        }
         Ok(())
     }
 
     async fn mux(
         mut send_queue: tokio::sync::mpsc::Receiver<Frame<P::Request>>,
         mut tx_sink: TxSink<P>,
+        in_flight: InFlight<P>,
+        tag_pool: Arc<Tags>,
     ) -> Result<()> {
         while let Some(frame) = send_queue.recv().await {
             use futures::SinkExt;
-            tx_sink.send(frame).await?;
+            let tag = frame.tag;
+            if let Err(err) = tx_sink.send(frame).await {
+                if let Some(tx) = in_flight.lock().await.remove(&tag) {
+                    let _ = tx.send(Err(Error::with_code(
+                        err.to_string(),
+                        CONNECTION_LOST,
+                    )));
+                    tag_pool.release_tag(tag).await;
+                }
+                return Err(err);
+            }
         }
         Ok(())
     }
@@ -87,31 +141,111 @@ where
         let (tx, rx) = oneshot::channel();
         let in_flight = self.in_flight.clone();
         let send_queue = self.send_queue.clone();
+        let tag_pool = self.tag_pool.clone();
+        let closed = self.closed.clone();
 
         tokio::spawn(async move {
-            in_flight.lock().await.insert(tag, tx);
-            send_queue.send(Frame { tag, msg: request }).await.unwrap();
+            let lost = {
+                let mut pending = in_flight.lock().await;
+                if closed.load(Ordering::Acquire) {
+                    Some(tx)
+                } else {
+                    pending.insert(tag, tx);
+                    None
+                }
+            };
+            let lost = match lost {
+                Some(tx) => Some(tx),
+                None => {
+                    match send_queue.send(Frame { tag, msg: request }).await {
+                        Ok(()) => None,
+                        Err(_) => in_flight.lock().await.remove(&tag),
+                    }
+                }
+            };
+            if let Some(tx) = lost {
+                let _ = tx.send(Err(Error::with_code(
+                    "connection closed",
+                    CONNECTION_LOST,
+                )));
+                tag_pool.release_tag(tag).await;
+            }
         });
         RpcCall { tag, future: rx }
     }
 
+    // r[impl jetstream.rpc.oneway.mux]
+    /// Sends a request to a `#[oneway]` method. It takes no tag and nothing
+    /// waits for its response, so it returns once the request is queued.
+    pub async fn oneway(
+        &self,
+        _ctx: Context,
+        request: P::Request,
+    ) -> Result<()> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(Error::with_code("connection closed", CONNECTION_LOST));
+        }
+        self.send_queue
+            .send(Frame {
+                tag: NOTAG,
+                msg: request,
+            })
+            .await
+            .map_err(|_| Error::with_code("connection closed", CONNECTION_LOST))
+    }
+
+    /// Requests sent and still waiting for a response.
+    pub fn in_flight(&self) -> usize {
+        self.tag_pool.outstanding.load(Ordering::Acquire)
+    }
+
+    /// Whether the transport has gone away. Every call on a closed mux fails
+    /// with [`CONNECTION_LOST`].
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Capabilities both peers acknowledged during version negotiation.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::from_bits(self.capabilities.load(Ordering::Acquire))
+    }
+
+    /// Records the outcome of version negotiation.
+    pub fn set_capabilities(&self, capabilities: Capabilities) {
+        self.capabilities
+            .store(capabilities.bits(), Ordering::Release);
+    }
+
     pub fn new(
         max_concurrent_requests: u16,
         transport: Box<dyn ClientTransport<P>>,
     ) -> Self {
-        let tag_pool = Arc::new(TagPool::new(max_concurrent_requests));
+        let tag_pool = Arc::new(Tags {
+            pool: TagPool::new(max_concurrent_requests.min(NOTAG - 1)),
+            outstanding: AtomicUsize::new(0),
+        });
         let (send_queue, send_queue_rx) = tokio::sync::mpsc::channel(1024);
         let (tx, rx) = StreamExt::split(transport);
         let (tx, rx) = (Box::pin(tx), Box::pin(rx));
         let in_flight = Arc::new(Mutex::new(BTreeMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
         let pending = in_flight.clone();
         let tags = tag_pool.clone();
-        tokio::spawn(async move { Self::demux(rx, pending, tags).await });
-        tokio::spawn(async move { Self::mux(send_queue_rx, tx).await });
+        let demux_closed = closed.clone();
+        tokio::spawn(async move {
+            Self::demux(rx, pending, tags, demux_closed).await
+        });
+        let pending = in_flight.clone();
+        let tags = tag_pool.clone();
+        tokio::spawn(async move {
+            Self::mux(send_queue_rx, tx, pending, tags).await
+        });
         Self {
             in_flight,
             send_queue,
             tag_pool,
+            capabilities: AtomicU32::new(Capabilities::NONE.bits()),
+            closed,
         }
     }
 }
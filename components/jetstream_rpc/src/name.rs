@@ -0,0 +1,237 @@
+// r[impl jetstream.service.name]
+//! Protocol names.
+//!
+//! A protocol's name routes its connections on a [`Router`](crate::Router),
+//! is part of its version string, `rs.jetstream.proto/{name}/{version}`, and
+//! is its ALPN on iroh. `#[service]` names a service after the crate and
+//! module it is declared in, so `trait Storage` in two crates are two
+//! protocols, unless it is named with `#[service(name = "com.acme.storage")]`.
+//!
+//! Names are computed in constants, so the functions here are `const`.
+
+/// The longest name, as ALPN protocol ids are at most 255 bytes.
+pub const MAX_LEN: usize = 255;
+
+const PROTOCOL_SUFFIX: &[u8] = b"_protocol";
+
+// r[impl jetstream.service.name.valid]
+/// Whether `name` can name a protocol: 1 to [`MAX_LEN`] ASCII letters,
+/// digits, `.`, `_` and `-`, neither starting nor ending with `.`.
+pub const fn is_valid(name: &str) -> bool {
+    let name = name.as_bytes();
+    if name.is_empty() || name.len() > MAX_LEN {
+        return false;
+    }
+    if name[0] == b'.' || name[name.len() - 1] == b'.' {
+        return false;
+    }
+    let mut i = 0;
+    while i < name.len() {
+        match name[i] {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' | b'-' => {}
+            _ => return false,
+        }
+        i += 1;
+    }
+    true
+}
+
+/// `module_path` without the `_protocol` of the generated module.
+const fn trimmed_len(module_path: &[u8]) -> usize {
+    let len = module_path.len();
+    if len < PROTOCOL_SUFFIX.len() {
+        return len;
+    }
+    let start = len - PROTOCOL_SUFFIX.len();
+    let mut i = 0;
+    while i < PROTOCOL_SUFFIX.len() {
+        if module_path[start + i] != PROTOCOL_SUFFIX[i] {
+            return len;
+        }
+        i += 1;
+    }
+    start
+}
+
+/// Length of [`derived`] of `module_path`.
+pub const fn derived_len(module_path: &str) -> usize {
+    let path = module_path.as_bytes();
+    let end = trimmed_len(path);
+    let mut len = 0;
+    let mut i = 0;
+    while i < end {
+        if i + 1 < end && path[i] == b':' && path[i + 1] == b':' {
+            i += 2;
+        } else {
+            i += 1;
+        }
+        len += 1;
+    }
+    len
+}
+
+/// The name of the service whose protocol module is at `module_path`,
+/// which `N` must be the [`derived_len`] of: the protocol module of
+/// `trait Kv` in `my_crate::storage`, `my_crate::storage::kv_protocol`,
+/// names `my_crate.storage.kv`.
+pub const fn derived<const N: usize>(module_path: &str) -> [u8; N] {
+    let path = module_path.as_bytes();
+    let end = trimmed_len(path);
+    let mut name = [0; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < end {
+        if i + 1 < end && path[i] == b':' && path[i + 1] == b':' {
+            name[len] = b'.';
+            i += 2;
+        } else {
+            name[len] = path[i];
+            i += 1;
+        }
+        len += 1;
+    }
+    name
+}
+
+/// Length of [`concat`] of `parts`.
+pub const fn concat_len(parts: &[&str]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        len += parts[i].len();
+        i += 1;
+    }
+    len
+}
+
+/// `parts` one after another, which `N` must be the [`concat_len`] of.
+pub const fn concat<const N: usize>(parts: &[&str]) -> [u8; N] {
+    let mut out = [0; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let part = parts[i].as_bytes();
+        let mut j = 0;
+        while j < part.len() {
+            out[len] = part[j];
+            len += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `bytes` built by [`derived`] or [`concat`], as a string.
+pub const fn as_str(bytes: &'static [u8]) -> &'static str {
+    match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => panic!("protocol names and versions are UTF-8"),
+    }
+}
+
+// r[impl jetstream.service.generics.name]
+/// The name of the instantiation of the generic service `name` with type
+/// arguments described as `params`. Descriptions are mangled into the
+/// characters names allow: `<`, `(` and `[` open with `-`, `,` and `;`
+/// separate with `.`, and closing brackets become `_`, so
+/// `store` with `u64` and `Vec<u8>` is `store-u64.Vec-u8__`.
+///
+/// # Panics
+///
+/// If the mangled name is not [valid](is_valid), e.g. too long.
+pub fn instance(name: &str, params: &[String]) -> String {
+    let mut out = format!("{name}-");
+    for (index, param) in params.iter().enumerate() {
+        if index > 0 {
+            out.push('.');
+        }
+        out.extend(param.chars().map(|c| match c {
+            '<' | '(' | '[' => '-',
+            ',' | ';' => '.',
+            '>' | ')' | ']' => '_',
+            c => c,
+        }));
+    }
+    out.push('_');
+    assert!(
+        is_valid(&out),
+        "`{out}` is not a valid protocol name for `{name}` with type \
+         arguments {params:?}"
+    );
+    out
+}
+
+/// The name of the service whose protocol module is at `$module_path`, as
+/// a `&'static str` constant. See [`derived`].
+#[doc(hidden)]
+#[macro_export]
+macro_rules! derived_name {
+    ($module_path:expr) => {{
+        const PATH: &str = $module_path;
+        const BYTES: [u8; $crate::name::derived_len(PATH)] =
+            $crate::name::derived(PATH);
+        $crate::name::as_str(&BYTES)
+    }};
+}
+
+/// String constants one after another, as a `&'static str` constant. Unlike
+/// `concat!` it takes constants, not only literals.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! const_concat {
+    ($($part:expr),* $(,)?) => {{
+        const PARTS: &[&str] = &[$($part),*];
+        const BYTES: [u8; $crate::name::concat_len(PARTS)] =
+            $crate::name::concat(PARTS);
+        $crate::name::as_str(&BYTES)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// r[verify jetstream.service.name]
+    #[test]
+    fn names_services_after_their_module() {
+        const NAME: &str = derived_name!("my_crate::storage::kv_protocol");
+        assert_eq!(NAME, "my_crate.storage.kv");
+        const ROOT: &str = derived_name!("my_crate::echo_protocol");
+        assert_eq!(ROOT, "my_crate.echo");
+        const VERSION: &str =
+            const_concat!("rs.jetstream.proto/", ROOT, "/", "1.0.0");
+        assert_eq!(VERSION, "rs.jetstream.proto/my_crate.echo/1.0.0");
+    }
+
+    /// r[verify jetstream.service.name.valid]
+    #[test]
+    fn validates_names() {
+        assert!(is_valid("com.acme.storage"));
+        assert!(is_valid("my_crate.storage-v2"));
+        assert!(!is_valid(""));
+        assert!(!is_valid(".storage"));
+        assert!(!is_valid("storage."));
+        assert!(!is_valid("acme/storage"));
+        assert!(!is_valid("acme storage"));
+        assert!(!is_valid(&"a".repeat(MAX_LEN + 1)));
+    }
+
+    /// r[verify jetstream.service.generics.name]
+    #[test]
+    fn mangles_instance_names() {
+        let params = |params: &[&str]| {
+            params.iter().map(|p| p.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            instance("acme.store", &params(&["String", "u64"])),
+            "acme.store-String.u64_"
+        );
+        let nested = instance(
+            "acme.store",
+            &params(&["u64", "Vec<u8>", "(u8,[u16;4])"]),
+        );
+        assert_eq!(nested, "acme.store-u64.Vec-u8_.-u8.-u16.4___");
+        assert!(is_valid(&nested));
+    }
+}
@@ -0,0 +1,106 @@
+//! Streams a server opens back to its client.
+//!
+//! Calls are answered on the stream they came in on, so a server cannot
+//! deliver anything the client did not ask for. Transports that can open
+//! streams towards the client (QUIC, iroh, WebTransport) attach an
+//! [`Upstream`] to the [`Context`](crate::context::Context) of every stream
+//! they accept; clients accept those streams from their connection through
+//! [`AcceptUni`].
+use std::{fmt, io, sync::Arc};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// The sending half of a stream opened by the server.
+pub type SendHalf = Box<dyn AsyncWrite + Send + Sync + Unpin>;
+/// The receiving half of a stream opened by the server.
+pub type RecvHalf = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+// r[impl jetstream.push.open]
+/// Opens unidirectional streams to the peer of a connection.
+#[async_trait]
+pub trait OpenUni: Send + Sync {
+    async fn open_uni(&self) -> io::Result<SendHalf>;
+}
+
+// r[impl jetstream.push.accept]
+/// Accepts the unidirectional streams the peer of a connection opens.
+#[async_trait]
+pub trait AcceptUni: Send + Sync {
+    /// The next stream, or `None` once the connection is closed.
+    async fn accept_uni(&self) -> io::Result<Option<RecvHalf>>;
+}
+
+// r[impl jetstream.push.upstream]
+/// Opens streams back to the client of a connection, attached to the
+/// context of every stream accepted by transports that support it.
+#[derive(Clone)]
+pub struct Upstream(Arc<dyn OpenUni>);
+
+impl Upstream {
+    pub fn new(opener: impl OpenUni + 'static) -> Self {
+        Self(Arc::new(opener))
+    }
+
+    pub async fn open_uni(&self) -> io::Result<SendHalf> {
+        self.0.open_uni().await
+    }
+}
+
+impl fmt::Debug for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upstream").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "quinn")]
+#[async_trait]
+impl OpenUni for quinn::Connection {
+    async fn open_uni(&self) -> io::Result<SendHalf> {
+        let send = quinn::Connection::open_uni(self).await?;
+        Ok(Box::new(send))
+    }
+}
+
+#[cfg(feature = "quinn")]
+#[async_trait]
+impl AcceptUni for quinn::Connection {
+    async fn accept_uni(&self) -> io::Result<Option<RecvHalf>> {
+        match quinn::Connection::accept_uni(self).await {
+            Ok(recv) => Ok(Some(Box::new(recv))),
+            Err(
+                quinn::ConnectionError::ApplicationClosed(_)
+                | quinn::ConnectionError::LocallyClosed,
+            ) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(feature = "iroh")]
+#[async_trait]
+impl OpenUni for iroh::endpoint::Connection {
+    async fn open_uni(&self) -> io::Result<SendHalf> {
+        let send = iroh::endpoint::Connection::open_uni(self)
+            .await
+            .map_err(io::Error::other)?;
+        Ok(Box::new(send))
+    }
+}
+
+#[cfg(feature = "iroh")]
+#[async_trait]
+impl AcceptUni for iroh::endpoint::Connection {
+    async fn accept_uni(&self) -> io::Result<Option<RecvHalf>> {
+        use iroh::endpoint::ConnectionError;
+
+        match iroh::endpoint::Connection::accept_uni(self).await {
+            Ok(recv) => Ok(Some(Box::new(recv))),
+            Err(
+                ConnectionError::ApplicationClosed(_)
+                | ConnectionError::LocallyClosed,
+            ) => Ok(None),
+            Err(err) => Err(io::Error::other(err)),
+        }
+    }
+}
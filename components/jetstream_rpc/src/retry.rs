@@ -0,0 +1,139 @@
+//! Client-side retries for idempotent methods.
+//!
+//! Methods marked `#[idempotent]` in a `#[service]` trait are retried by the
+//! generated channel when a call fails with a retriable error code, following
+//! the channel's [`RetryPolicy`]. Other methods are never retried.
+//!
+//! Retriable codes are [`UNAVAILABLE`], returned by servers that are shutting
+//! down or overloaded, [`CONNECTION_LOST`], set by the [`Mux`](crate::Mux) on
+//! every pending call when its transport goes away, and [`DEADLINE_EXCEEDED`],
+//! for transports that reconnect underneath the channel and give up waiting
+//! for the new connection. A closed `Mux` never recovers, so a channel does
+//! not retry on it; a [`Balancer`](crate::balance::Balancer) retries on
+//! another endpoint instead.
+use std::time::Duration;
+
+use crate::Error;
+
+/// Error code for a server that cannot take the call right now.
+pub const UNAVAILABLE: &str = "jetstream_rpc::error::unavailable";
+/// Error code for calls whose connection went away before a response arrived.
+pub const CONNECTION_LOST: &str = "jetstream_rpc::error::connection_lost";
+/// Error code for calls that did not complete in time.
+pub const DEADLINE_EXCEEDED: &str = "jetstream_rpc::error::deadline_exceeded";
+
+// r[impl jetstream.retry.retriable]
+/// Whether an idempotent call that failed with `err` may be sent again.
+pub fn is_retriable(err: &Error) -> bool {
+    matches!(
+        err.code(),
+        Some(UNAVAILABLE) | Some(CONNECTION_LOST) | Some(DEADLINE_EXCEEDED)
+    )
+}
+
+// r[impl jetstream.retry.policy]
+/// How often, and how patiently, idempotent calls are retried.
+///
+/// The delay before retry `n` (counting from zero) is
+/// `initial_backoff * 2^n`, capped at `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+    /// Retries allowed per call, not counting the first attempt.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub const NONE: Self = Self {
+        max_retries: 0,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+    };
+
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_backoff(self, initial: Duration, max: Duration) -> Self {
+        Self {
+            initial_backoff: initial,
+            max_backoff: max,
+            ..self
+        }
+    }
+
+    /// Delay before retry `attempt`, counting from zero.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+
+    /// Decides whether retry `attempt` of a call that failed with `err` should
+    /// happen, sleeping for the backoff delay first if it should.
+    pub async fn backoff(&self, attempt: u32, err: &Error) -> bool {
+        if attempt >= self.max_retries || !is_retriable(err) {
+            return false;
+        }
+        tracing::debug!(attempt, error = %err, "retrying idempotent call");
+        tokio::time::sleep(self.delay(attempt)).await;
+        true
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_delay() {
+        let policy = RetryPolicy::new(5).with_backoff(
+            Duration::from_millis(100),
+            Duration::from_millis(500),
+        );
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(3), Duration::from_millis(500));
+        assert_eq!(policy.delay(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn retriable_codes() {
+        assert!(is_retriable(&Error::with_code("gone", CONNECTION_LOST)));
+        assert!(is_retriable(&Error::with_code("busy", UNAVAILABLE)));
+        assert!(is_retriable(&Error::with_code("slow", DEADLINE_EXCEEDED)));
+        assert!(!is_retriable(&Error::new("boom")));
+        assert!(!is_retriable(&Error::with_code(
+            "no",
+            crate::auth::PERMISSION_DENIED
+        )));
+    }
+
+    #[tokio::test]
+    async fn budget() {
+        let policy = RetryPolicy::new(2)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let err = Error::with_code("gone", CONNECTION_LOST);
+        assert!(policy.backoff(0, &err).await);
+        assert!(policy.backoff(1, &err).await);
+        assert!(!policy.backoff(2, &err).await);
+        assert!(!RetryPolicy::NONE.backoff(0, &err).await);
+        assert!(!policy.backoff(0, &Error::new("boom")).await);
+    }
+}
@@ -1,8 +1,11 @@
 use crate::{
-    context::Context,
+    auth::Authorizer,
+    compression::Compression,
+    context::{Context, ProtocolName},
+    schema::{DigestPolicy, Schema},
     server::{Server, ServerCodec},
     version::VersionFrame,
-    Error, Frame, Protocol, Rversion, Version,
+    Capabilities, Error, Frame, Protocol, Rversion, Version, MAX_MSIZE,
 };
 use async_trait::async_trait;
 use futures::SinkExt;
@@ -26,17 +29,37 @@ pub trait Handler: Send + Sync {
         reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
         writer: Box<dyn AsyncWrite + Send + Sync + Unpin>,
     ) -> Result<(), Error>;
+
+    /// Optional extensions the handler's protocol understands.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::NONE
+    }
+
+    /// The handler's version and method digests, compared with the
+    /// client's during version negotiation.
+    fn schema(&self) -> Option<Schema> {
+        None
+    }
+
+    /// The version to serve a client that asked for `client`.
+    fn version(&self, client: Version) -> Result<Version, Error> {
+        Ok(client)
+    }
 }
 
 #[derive(Clone)]
 pub struct Router {
     handlers: HashMap<String, Arc<Box<dyn Handler>>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    digest_policy: DigestPolicy,
 }
 
 impl std::fmt::Debug for Router {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Router")
             .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .field("authorizer", &self.authorizer.is_some())
+            .field("digest_policy", &self.digest_policy)
             .finish()
     }
 }
@@ -45,6 +68,8 @@ impl Router {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            authorizer: None,
+            digest_policy: DigestPolicy::default(),
         }
     }
 
@@ -60,6 +85,25 @@ impl Router {
             .insert(name.to_string(), Arc::new(Box::new(handler)));
         self
     }
+
+    // r[impl jetstream.auth.router]
+    /// Attach an authorizer to the context of every accepted stream.
+    /// Generated servers consult it before dispatching each call.
+    pub fn with_authorizer(
+        mut self,
+        authorizer: impl Authorizer + 'static,
+    ) -> Self {
+        self.authorizer = Some(Arc::new(authorizer));
+        self
+    }
+
+    // r[impl jetstream.schema.router]
+    /// Sets how clients built from a different definition of a service are
+    /// treated. Defaults to [`DigestPolicy::Warn`].
+    pub fn with_digest_policy(mut self, policy: DigestPolicy) -> Self {
+        self.digest_policy = policy;
+        self
+    }
 }
 
 impl Default for Router {
@@ -158,18 +202,68 @@ impl Router {
                             }
                         }
                     };
+                    let name = match &version {
+                        Version::JetStream { name, .. } => name.clone(),
+                        legacy => legacy.to_string(),
+                    };
+                    let schema = handler.schema();
+                    let mismatch =
+                        match self.digest_policy.check(schema.and_then(
+                            |schema| schema.compare(&name, &tversion.version),
+                        )) {
+                            Ok(mismatch) => mismatch,
+                            Err(err) => {
+                                reject(&mut framed_write, frame.tag).await?;
+                                return Err(err);
+                            }
+                        };
+                    // r[impl jetstream.version.negotiation.rversion]
+                    // Serve the lower of the two versions, and tell the
+                    // client which one that is.
+                    let version = match handler.version(version) {
+                        Ok(version) => version,
+                        Err(err) => {
+                            reject(&mut framed_write, frame.tag).await?;
+                            return Err(err);
+                        }
+                    };
+                    // r[impl jetstream.version.capabilities.router]
+                    let capabilities = Capabilities::offered(&tversion.version)
+                        .negotiate(handler.capabilities());
+                    let reply = match schema {
+                        Some(schema) => schema
+                            .acknowledge(&version.to_string(), capabilities),
+                        None => capabilities.acknowledge(&version.to_string()),
+                    };
+                    let msize = tversion.msize.min(MAX_MSIZE);
                     framed_write
                         .send(Frame {
                             tag: frame.tag,
                             msg: VersionFrame::Rversion(Rversion {
-                                msize: tversion.msize,
-                                version: version.to_string(),
+                                msize,
+                                version: reply,
                             }),
                         })
                         .await?;
 
                     let reader = framed_read.into_inner();
                     let writer = framed_write.into_inner();
+                    let ctx = match &self.authorizer {
+                        Some(authorizer) => {
+                            ctx.with_shared_authorizer(authorizer.clone())
+                        }
+                        None => ctx,
+                    };
+                    // r[impl jetstream.compression.router]
+                    let ctx = ctx
+                        .with_compression(Compression::select(capabilities))
+                        .with_msize(msize)
+                        .with_extension(ProtocolName(name))
+                        .with_extension(version);
+                    let ctx = match mismatch {
+                        Some(mismatch) => ctx.with_extension(mismatch),
+                        None => ctx,
+                    };
                     handler.handle(ctx, reader, writer).await?;
                 }
                 VersionFrame::Rversion(_) => {
@@ -184,6 +278,25 @@ impl Router {
     }
 }
 
+/// Answers a `Tversion` the router will not serve.
+async fn reject(
+    framed_write: &mut FramedWrite<
+        Box<dyn AsyncWrite + Send + Sync + Unpin>,
+        ServerCodec<VersionProtocol>,
+    >,
+    tag: u16,
+) -> Result<(), Error> {
+    framed_write
+        .send(Frame {
+            tag,
+            msg: VersionFrame::Rversion(Rversion {
+                msize: 0,
+                version: "unknown".to_string(),
+            }),
+        })
+        .await
+}
+
 #[async_trait]
 impl<T: Server + Clone + 'static> Handler for T {
     async fn handle(
@@ -194,8 +307,13 @@ impl<T: Server + Clone + 'static> Handler for T {
     ) -> Result<(), Error> {
         let server = self.clone();
         tokio::spawn(async move {
-            let mut reader = FramedRead::new(reader, ServerCodec::<T>::new());
-            let mut writer = FramedWrite::new(writer, ServerCodec::<T>::new());
+            // The router negotiated the version, so the codec cannot have
+            // seen the Rversion.
+            let codec = ServerCodec::<T>::new();
+            codec.compression().set(ctx.compression());
+            codec.compression().set_msize(ctx.msize());
+            let mut reader = FramedRead::new(reader, codec.clone());
+            let mut writer = FramedWrite::new(writer, codec);
 
             // Channel for sending responses back to the writer
             let (resp_tx, mut resp_rx) =
@@ -217,8 +335,11 @@ impl<T: Server + Clone + 'static> Handler for T {
                     Ok(req) => {
                         let mut handler = server.clone();
                         let resp_tx = resp_tx.clone();
+                        // r[impl jetstream.rpc.oneway.server]
+                        let oneway = req.is_oneway();
                         tokio::spawn(async move {
                             match handler.rpc(ctx, req).await {
+                                Ok(_) if oneway => {}
                                 Ok(resp) => {
                                     let _ = resp_tx.send(resp).await;
                                 }
@@ -244,4 +365,16 @@ impl<T: Server + Clone + 'static> Handler for T {
         });
         Ok(())
     }
+
+    fn capabilities(&self) -> Capabilities {
+        T::CAPABILITIES
+    }
+
+    fn schema(&self) -> Option<Schema> {
+        Some(Schema::of::<T>())
+    }
+
+    fn version(&self, client: Version) -> Result<Version, Error> {
+        T::version(client)
+    }
 }
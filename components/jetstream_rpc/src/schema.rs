@@ -0,0 +1,375 @@
+//! Detecting peers built from a different definition of the same service.
+//!
+//! `#[service]` hashes the trait into the build metadata of its version
+//! string (`rs.jetstream.proto/echo/1.0.0+bfd7d20e`) and hashes every
+//! method's request and response messages into a [`MethodDigest`]. Both
+//! sides send the method digests during version negotiation as one more
+//! build identifier:
+//!
+//! ```text
+//! Tversion: rs.jetstream.proto/echo/1.0.0+bfd7d20e.cap-tracecontext.methods-1a2b5e6f70a1b2c33c4d8e9fa0b1c2d3
+//! Rversion: rs.jetstream.proto/echo/1.0.0+0c9e8f7a.ack-tracecontext.methods-1a2b5e6f70a1b2c39f8e7d6c5b4a3928
+//! ```
+//!
+//! When the trait digests differ, the [`DigestPolicy`] decides whether
+//! negotiation fails, or only the methods whose digests differ are refused,
+//! so the compatible subset of the service can still be called.
+use std::{fmt, sync::RwLock};
+
+use crate::{context::Context, Capabilities, Error, Protocol};
+
+/// Error code for calls and negotiations refused because the peers disagree
+/// on the definition of a service.
+pub const SCHEMA_MISMATCH: &str = "jetstream_rpc::error::schema_mismatch";
+
+const METHODS_PREFIX: &str = "methods-";
+/// Hex characters of each method digest sent on the wire.
+pub const METHOD_DIGEST_LEN: usize = 16;
+
+/// The wire identity of one service method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MethodDigest {
+    pub name: &'static str,
+    /// Message id of the method's request.
+    pub id: u16,
+    /// Hash of the method's message type and request and response fields,
+    /// [`METHOD_DIGEST_LEN`] hex characters long.
+    pub digest: &'static str,
+}
+
+/// The version string and method digests of one side of a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Schema {
+    pub version: &'static str,
+    pub methods: &'static [MethodDigest],
+}
+
+impl Schema {
+    pub fn of<P: Protocol>() -> Self {
+        Self {
+            version: P::VERSION,
+            methods: P::METHODS,
+        }
+    }
+
+    // r[impl jetstream.schema.compare]
+    /// Compares this side with the version string a peer sent for
+    /// `protocol`. Peers that send no digest are never reported.
+    pub fn compare(&self, protocol: &str, remote: &str) -> Option<Mismatch> {
+        let local_digest = digest(self.version)?;
+        let remote_digest = digest(remote)?;
+        if local_digest == remote_digest {
+            return None;
+        }
+        // Method digests cover the message ids, so methods that were only
+        // reordered still match.
+        let methods = method_digests(remote).map(|remote| {
+            self.methods
+                .iter()
+                .filter(|method| !remote.contains(&method.digest))
+                .copied()
+                .collect()
+        });
+        Some(Mismatch {
+            protocol: protocol.to_string(),
+            local: local_digest.to_string(),
+            remote: remote_digest.to_string(),
+            methods,
+        })
+    }
+
+    /// The version string to answer a `Tversion` with: the `negotiated`
+    /// version carrying this side's digest and method digests, and the
+    /// acknowledged `capabilities`.
+    pub fn acknowledge(
+        &self,
+        negotiated: &str,
+        capabilities: Capabilities,
+    ) -> String {
+        let negotiated = Capabilities::strip(negotiated);
+        let head = negotiated.split_once('+').map_or(&*negotiated, |v| v.0);
+        let version = match digest(self.version) {
+            Some(digest) => format!("{}+{}", head, digest),
+            None => head.to_string(),
+        };
+        self.advertise(&capabilities.acknowledge(&version))
+    }
+
+    /// Appends the method digests to a version string.
+    pub fn advertise(&self, version: &str) -> String {
+        if self.methods.is_empty() {
+            return version.to_string();
+        }
+        let separator = if version.contains('+') { '.' } else { '+' };
+        let mut out = format!("{}{}{}", version, separator, METHODS_PREFIX);
+        for method in self.methods {
+            out.push_str(method.digest);
+        }
+        out
+    }
+}
+
+/// The service digest in the build metadata of a version string.
+pub fn digest(version: &str) -> Option<&str> {
+    let (_, build) = version.split_once('+')?;
+    build.split('.').find(|id| {
+        !id.starts_with(METHODS_PREFIX)
+            && !id.starts_with(Capabilities::OFFER_PREFIX)
+            && !id.starts_with(Capabilities::ACK_PREFIX)
+    })
+}
+
+/// The method digests in the build metadata of a version string, in method
+/// order.
+pub fn method_digests(version: &str) -> Option<Vec<&str>> {
+    let (_, build) = version.split_once('+')?;
+    let digests = build
+        .split('.')
+        .find_map(|id| id.strip_prefix(METHODS_PREFIX))?;
+    if !digests.is_ascii() || digests.len() % METHOD_DIGEST_LEN != 0 {
+        return None;
+    }
+    Some(
+        (0..digests.len())
+            .step_by(METHOD_DIGEST_LEN)
+            .map(|start| &digests[start..start + METHOD_DIGEST_LEN])
+            .collect(),
+    )
+}
+
+/// Whether a build identifier carries method digests.
+pub(crate) fn is_methods_identifier(id: &str) -> bool {
+    id.starts_with(METHODS_PREFIX)
+}
+
+/// Two peers disagree on the definition of a service.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mismatch {
+    pub protocol: String,
+    /// This side's service digest.
+    pub local: String,
+    /// The peer's service digest.
+    pub remote: String,
+    /// This side's methods whose digests differ from the peer's, or `None`
+    /// if the peer did not send method digests.
+    pub methods: Option<Vec<MethodDigest>>,
+}
+
+impl Mismatch {
+    // r[impl jetstream.schema.methods]
+    /// Refuses a call with request message id `id` if that method differs.
+    pub fn check_method(&self, id: u16) -> Result<(), Error> {
+        let Some(method) =
+            self.methods.iter().flatten().find(|method| method.id == id)
+        else {
+            return Ok(());
+        };
+        Err(Error::with_code(
+            format!(
+                "method `{}` of protocol `{}` differs from the peer's \
+                 definition (local digest {}, remote digest {})",
+                method.name, self.protocol, self.local, self.remote
+            ),
+            SCHEMA_MISMATCH,
+        ))
+    }
+}
+
+// r[impl jetstream.schema.server]
+/// Refuses a call with request message id `id` if that method differs from
+/// the peer's, according to the mismatch a `Router` attached to `ctx`.
+pub fn check_context(ctx: &Context, id: impl Into<u16>) -> Result<(), Error> {
+    match ctx.extension::<Mismatch>() {
+        Some(mismatch) => mismatch.check_method(id.into()),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "schema mismatch for protocol `{}`: local digest {}, remote \
+             digest {}",
+            self.protocol, self.local, self.remote
+        )?;
+        match &self.methods {
+            Some(methods) if methods.is_empty() => {
+                write!(f, "; no method differs")
+            }
+            Some(methods) => {
+                let names = methods.iter().map(|m| m.name).collect::<Vec<_>>();
+                write!(f, "; methods differ: {}", names.join(", "))
+            }
+            None => write!(f, "; peer sent no method digests"),
+        }
+    }
+}
+
+impl From<Mismatch> for Error {
+    fn from(mismatch: Mismatch) -> Self {
+        Error::with_code(mismatch.to_string(), SCHEMA_MISMATCH)
+    }
+}
+
+// r[impl jetstream.schema.policy]
+/// What to do when a peer's service digest differs from ours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DigestPolicy {
+    /// Fail version negotiation.
+    Strict,
+    /// Log a warning and refuse only the methods whose digests differ.
+    #[default]
+    Warn,
+    /// Do not compare digests.
+    Ignore,
+}
+
+impl DigestPolicy {
+    /// Applies the policy to the outcome of [`Schema::compare`], returning
+    /// the mismatch to enforce per method, if any.
+    pub fn check(
+        self,
+        mismatch: Option<Mismatch>,
+    ) -> Result<Option<Mismatch>, Error> {
+        match (self, mismatch) {
+            (DigestPolicy::Ignore, _) | (_, None) => Ok(None),
+            (DigestPolicy::Strict, Some(mismatch)) => Err(mismatch.into()),
+            (DigestPolicy::Warn, Some(mismatch)) => {
+                tracing::warn!("{}", mismatch);
+                Ok(Some(mismatch))
+            }
+        }
+    }
+}
+
+/// The mismatch a channel found while negotiating its version.
+#[derive(Debug, Default)]
+pub struct SchemaState(RwLock<Option<Mismatch>>);
+
+impl SchemaState {
+    pub fn get(&self) -> Option<Mismatch> {
+        self.0.read().expect("schema state poisoned").clone()
+    }
+
+    pub fn set(&self, mismatch: Option<Mismatch>) {
+        *self.0.write().expect("schema state poisoned") = mismatch;
+    }
+
+    pub fn check_method(&self, id: impl Into<u16>) -> Result<(), Error> {
+        match &*self.0.read().expect("schema state poisoned") {
+            Some(mismatch) => mismatch.check_method(id.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHODS: &[MethodDigest] = &[
+        MethodDigest {
+            name: "ping",
+            id: 102,
+            digest: "1a2b5e6f70a1b2c3",
+        },
+        MethodDigest {
+            name: "blob",
+            id: 104,
+            digest: "3c4d8e9fa0b1c2d3",
+        },
+    ];
+
+    const LOCAL: Schema = Schema {
+        version: "rs.jetstream.proto/echo/1.0.0+bfd7d20e",
+        methods: METHODS,
+    };
+
+    #[test]
+    fn advertise_round_trip() {
+        let offer =
+            LOCAL.advertise(&Capabilities::TRACE_CONTEXT.offer(LOCAL.version));
+        assert_eq!(
+            offer,
+            "rs.jetstream.proto/echo/1.0.0+bfd7d20e.cap-tracecontext.methods-1a2b5e6f70a1b2c33c4d8e9fa0b1c2d3"
+        );
+        assert_eq!(digest(&offer), Some("bfd7d20e"));
+        assert_eq!(
+            method_digests(&offer),
+            Some(vec!["1a2b5e6f70a1b2c3", "3c4d8e9fa0b1c2d3"])
+        );
+        assert_eq!(Capabilities::strip(&offer), LOCAL.version);
+        // Digests cut short are not method digests.
+        assert_eq!(method_digests("echo/1.0.0+bfd7d20e.methods-1a2b"), None);
+        assert_eq!(LOCAL.compare("echo", &offer), None);
+    }
+
+    #[test]
+    fn acknowledge_carries_local_digest() {
+        let ack = LOCAL.acknowledge(
+            "rs.jetstream.proto/echo/1.0.0+0c9e8f7a.cap-tracecontext.methods-1a2b5e6f70a1b2c3",
+            Capabilities::TRACE_CONTEXT,
+        );
+        assert_eq!(
+            ack,
+            "rs.jetstream.proto/echo/1.0.0+bfd7d20e.ack-tracecontext.methods-1a2b5e6f70a1b2c33c4d8e9fa0b1c2d3"
+        );
+    }
+
+    /// r[verify jetstream.schema.compare]
+    /// r[verify jetstream.schema.methods]
+    #[test]
+    fn reports_differing_methods() {
+        let mismatch = LOCAL
+            .compare(
+                "echo",
+                "rs.jetstream.proto/echo/1.0.0+0c9e8f7a.methods-1a2b5e6f70a1b2c39f8e7d6c5b4a3928",
+            )
+            .unwrap();
+        assert_eq!(mismatch.local, "bfd7d20e");
+        assert_eq!(mismatch.remote, "0c9e8f7a");
+        assert_eq!(mismatch.methods, Some(vec![METHODS[1]]));
+        assert_eq!(
+            mismatch.to_string(),
+            "schema mismatch for protocol `echo`: local digest bfd7d20e, \
+             remote digest 0c9e8f7a; methods differ: blob"
+        );
+        assert!(mismatch.check_method(102).is_ok());
+        let err = mismatch.check_method(104).unwrap_err();
+        assert_eq!(err.code(), Some(SCHEMA_MISMATCH));
+
+        // Without method digests every method is let through.
+        let mismatch = LOCAL
+            .compare("echo", "rs.jetstream.proto/echo/1.0.0+0c9e8f7a")
+            .unwrap();
+        assert_eq!(mismatch.methods, None);
+        assert!(mismatch.check_method(104).is_ok());
+
+        // Peers without a digest are never reported.
+        assert_eq!(
+            LOCAL.compare("echo", "rs.jetstream.proto/echo/1.0.0"),
+            None
+        );
+    }
+
+    /// r[verify jetstream.schema.policy]
+    #[test]
+    fn policies() {
+        let mismatch =
+            LOCAL.compare("echo", "rs.jetstream.proto/echo/1.0.0+0c9e8f7a");
+        assert_eq!(
+            DigestPolicy::Strict
+                .check(mismatch.clone())
+                .unwrap_err()
+                .code(),
+            Some(SCHEMA_MISMATCH)
+        );
+        assert_eq!(
+            DigestPolicy::Warn.check(mismatch.clone()).unwrap(),
+            mismatch
+        );
+        assert_eq!(DigestPolicy::Ignore.check(mismatch).unwrap(), None);
+        assert_eq!(DigestPolicy::Strict.check(None).unwrap(), None);
+    }
+}
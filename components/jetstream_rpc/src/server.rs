@@ -1,23 +1,44 @@
 use std::{pin::pin, str::FromStr};
 
 use crate::{
+    compression::{self, CompressionState},
     context::{Context, Contextual},
-    Error, Frame, Protocol, Version,
+    Error, Frame, Protocol, RawFrame, Version,
 };
 use futures::{Sink, Stream};
-use jetstream_wireformat::WireFormat;
 use tokio_util::{
-    bytes::{self, Buf, BufMut},
+    bytes,
     codec::{Decoder, Encoder},
 };
 
+/// Frames requests and responses on the server side of a stream.
+///
+/// Clones share the negotiated compression, so a transport that reads and
+/// writes through separate codecs must clone one codec for both halves.
 pub struct ServerCodec<P: Protocol> {
+    compression: CompressionState,
     _phantom: std::marker::PhantomData<P>,
 }
 
 impl<P: Protocol> ServerCodec<P> {
     pub fn new() -> Self {
         Self {
+            compression: CompressionState::default(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// The compression and msize acknowledged in the last `Rversion`
+    /// encoded, or set by whoever negotiated the version for this stream.
+    pub fn compression(&self) -> &CompressionState {
+        &self.compression
+    }
+}
+
+impl<P: Protocol> Clone for ServerCodec<P> {
+    fn clone(&self) -> Self {
+        Self {
+            compression: self.compression.clone(),
             _phantom: std::marker::PhantomData,
         }
     }
@@ -64,27 +85,86 @@ where
         &mut self,
         src: &mut bytes::BytesMut,
     ) -> Result<Option<Self::Item>, Self::Error> {
-        // check to see if you have at least 4 bytes to figure out the size
-        if src.len() < 4 {
-            src.reserve(4);
-            return Ok(None);
+        Ok(compression::decode_frame::<P::Request>(
+            src,
+            &self.compression,
+        )?)
+    }
+}
+
+impl<P> Encoder<Frame<P::Response>> for ServerCodec<P>
+where
+    P: Protocol,
+{
+    type Error = Error;
+
+    fn encode(
+        &mut self,
+        item: Frame<P::Response>,
+        dst: &mut bytes::BytesMut,
+    ) -> Result<(), Self::Error> {
+        compression::encode_frame(&item, self.compression.get(), dst)?;
+        // Compression starts with the frame after the acknowledging Rversion.
+        self.compression.observe(&item);
+        Ok(())
+    }
+}
+
+// r[impl jetstream.rpc.raw-frame]
+/// Frames requests as [`RawFrame`]s on the server side of a stream, for
+/// handlers that inspect them through borrowed views, and responses like
+/// [`ServerCodec`].
+pub struct RawCodec<P: Protocol> {
+    inner: ServerCodec<P>,
+}
+
+impl<P: Protocol> RawCodec<P> {
+    pub fn new() -> Self {
+        Self {
+            inner: ServerCodec::new(),
         }
-        let Some(mut bytz) = src.get(..4) else {
-            return Ok(None);
-        };
-
-        let byte_size: u32 = WireFormat::decode(&mut bytz)?;
-        if src.len() < byte_size as usize {
-            src.reserve(byte_size as usize);
-            return Ok(None);
+    }
+
+    /// The compression and msize acknowledged in the last `Rversion`
+    /// encoded, or set by whoever negotiated the version for this stream.
+    pub fn compression(&self) -> &CompressionState {
+        self.inner.compression()
+    }
+}
+
+impl<P: Protocol> Clone for RawCodec<P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
         }
+    }
+}
 
-        let frame = Frame::<P::Request>::decode(&mut src.reader())?;
-        Ok(Some(frame))
+impl<P: Protocol> Default for RawCodec<P> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl<P> Encoder<Frame<P::Response>> for ServerCodec<P>
+impl<P> Decoder for RawCodec<P>
+where
+    P: Protocol,
+{
+    type Error = Error;
+    type Item = RawFrame;
+
+    fn decode(
+        &mut self,
+        src: &mut bytes::BytesMut,
+    ) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(compression::decode_raw_frame(
+            src,
+            self.inner.compression(),
+        )?)
+    }
+}
+
+impl<P> Encoder<Frame<P::Response>> for RawCodec<P>
 where
     P: Protocol,
 {
@@ -95,8 +175,7 @@ where
         item: Frame<P::Response>,
         dst: &mut bytes::BytesMut,
     ) -> Result<(), Self::Error> {
-        item.encode(&mut dst.writer())?;
-        Ok(())
+        self.inner.encode(item, dst)
     }
 }
 
@@ -105,11 +184,12 @@ pub trait Server: Protocol + Send + Sync {
     /// Negotiate the protocol version to use.
     fn version(client_version: Version) -> jetstream_error::Result<Version> {
         // By default, accept any version that matches the major version of the server's protocol version.
+        let version = <Self as Protocol>::protocol_version();
         let server_version =
-            Version::from_str(Self::VERSION).unwrap_or_else(|_| {
+            Version::from_str(&version).unwrap_or_else(|_| {
                 panic!(
                     "Invalid version format for JetStream protocol: {}",
-                    Self::VERSION
+                    version
                 )
             });
         match (client_version, server_version) {
@@ -148,6 +228,13 @@ pub trait Server: Protocol + Send + Sync {
     ) -> Result<Frame<Self::Response>, Self::Error>;
 }
 
+// r[impl jetstream.rpc.oneway.server]
+/// Reports the failure of a `#[oneway]` call. There is no caller to return
+/// the error to, so it is only logged.
+pub fn oneway_failed(protocol: &str, err: &Error) {
+    tracing::warn!(protocol, error = %err, "oneway call failed");
+}
+
 pub async fn run<T, P>(p: &mut P, mut stream: T) -> Result<(), P::Error>
 where
     T: ServiceTransport<P>,
@@ -156,7 +243,11 @@ where
     use futures::{SinkExt, StreamExt};
     let mut a = pin!(p);
     while let Some(Ok(frame)) = stream.next().await {
-        stream.send(a.rpc(stream.context(), frame).await?).await?
+        let oneway = frame.is_oneway();
+        let response = a.rpc(stream.context(), frame).await?;
+        if !oneway {
+            stream.send(response).await?
+        }
     }
     Ok(())
 }
@@ -11,3 +11,8 @@ pub use channel::TagPool;
 pub use notify::TagPool;
 #[cfg(semaphor)]
 pub use semaphor::TagPool;
+
+// r[impl jetstream.rpc.oneway.notag]
+/// Tag of frames that expect no response: `Tversion` and requests to
+/// `#[oneway]` methods. Tag pools never hand it out.
+pub const NOTAG: u16 = u16::MAX;
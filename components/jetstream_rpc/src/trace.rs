@@ -0,0 +1,205 @@
+//! W3C trace context propagation.
+//!
+//! When both peers acknowledge [`Capabilities::TRACE_CONTEXT`](crate::Capabilities::TRACE_CONTEXT)
+//! during version negotiation, the client may wrap a request in a [`TTRACE`]
+//! envelope:
+//!
+//! ```text
+//! size[4] TTRACE tag[2] traceparent[s] tracestate[s] type[1] msg
+//! ```
+//!
+//! The server unwraps the envelope and makes its `rpc_server` span a child of
+//! the remote parent. Reading spans from, and attaching parents to, `tracing`
+//! spans requires the `opentelemetry` feature; without it the envelope is still
+//! understood but never produced.
+use jetstream_wireformat::JetStreamWireFormat;
+
+/// Message type of the trace context envelope.
+pub const TTRACE: u8 = 98;
+
+const TRACEPARENT_VERSION: &str = "00";
+
+// r[impl jetstream.trace.context]
+/// A W3C trace context, as carried by the `traceparent` and `tracestate`
+/// HTTP headers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, JetStreamWireFormat)]
+pub struct TraceContext {
+    /// `{version}-{trace-id}-{parent-id}-{trace-flags}`
+    pub traceparent: String,
+    /// Vendor specific `key=value` list, possibly empty.
+    pub tracestate: String,
+}
+
+impl TraceContext {
+    pub fn new(
+        trace_id: [u8; 16],
+        parent_id: [u8; 8],
+        flags: u8,
+        tracestate: impl Into<String>,
+    ) -> Self {
+        Self {
+            traceparent: format!(
+                "{}-{}-{}-{:02x}",
+                TRACEPARENT_VERSION,
+                hex::encode(trace_id),
+                hex::encode(parent_id),
+                flags
+            ),
+            tracestate: tracestate.into(),
+        }
+    }
+
+    /// Splits `traceparent` into trace id, parent span id and trace flags.
+    /// Returns `None` if the header is malformed or uses the all-zero ids the
+    /// specification marks as invalid.
+    pub fn parts(&self) -> Option<([u8; 16], [u8; 8], u8)> {
+        let mut fields = self.traceparent.split('-');
+        let version = fields.next()?;
+        let trace_id = fields.next()?;
+        let parent_id = fields.next()?;
+        let flags = fields.next()?;
+        if version != TRACEPARENT_VERSION || fields.next().is_some() {
+            return None;
+        }
+
+        let mut trace = [0u8; 16];
+        hex::decode_to_slice(trace_id, &mut trace).ok()?;
+        let mut parent = [0u8; 8];
+        hex::decode_to_slice(parent_id, &mut parent).ok()?;
+        let mut flag = [0u8; 1];
+        hex::decode_to_slice(flags, &mut flag).ok()?;
+
+        if trace == [0; 16] || parent == [0; 8] {
+            return None;
+        }
+        Some((trace, parent, flag[0]))
+    }
+
+    /// Captures the OpenTelemetry context of the current `tracing` span.
+    #[cfg(feature = "opentelemetry")]
+    pub fn current() -> Option<Self> {
+        use opentelemetry::trace::TraceContextExt;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let cx = tracing::Span::current().context();
+        let span = cx.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return None;
+        }
+        Some(Self::new(
+            span_context.trace_id().to_bytes(),
+            span_context.span_id().to_bytes(),
+            span_context.trace_flags().to_u8(),
+            span_context.trace_state().header(),
+        ))
+    }
+
+    /// Captures the OpenTelemetry context of the current `tracing` span.
+    #[cfg(not(feature = "opentelemetry"))]
+    pub fn current() -> Option<Self> {
+        None
+    }
+
+    /// Makes `span` a child of the remote span this context describes.
+    #[cfg(feature = "opentelemetry")]
+    pub fn set_parent(&self, span: &tracing::Span) {
+        use opentelemetry::trace::{
+            SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId,
+            TraceState,
+        };
+        use std::str::FromStr;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let Some((trace_id, parent_id, flags)) = self.parts() else {
+            tracing::debug!(traceparent = %self.traceparent, "ignoring malformed traceparent");
+            return;
+        };
+        let span_context = SpanContext::new(
+            TraceId::from_bytes(trace_id),
+            SpanId::from_bytes(parent_id),
+            TraceFlags::new(flags),
+            true,
+            TraceState::from_str(&self.tracestate).unwrap_or_default(),
+        );
+        let cx = opentelemetry::Context::new()
+            .with_remote_span_context(span_context);
+        if let Err(err) = span.set_parent(cx) {
+            tracing::debug!("failed to set remote parent: {}", err);
+        }
+    }
+
+    /// Makes `span` a child of the remote span this context describes.
+    #[cfg(not(feature = "opentelemetry"))]
+    pub fn set_parent(&self, _span: &tracing::Span) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jetstream_wireformat::WireFormat;
+
+    #[test]
+    fn traceparent_round_trip() {
+        let trace_id = [0x4b; 16];
+        let parent_id = [0xf0; 8];
+        let context = TraceContext::new(trace_id, parent_id, 1, "vendor=1");
+        assert_eq!(
+            context.traceparent,
+            "00-4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b-f0f0f0f0f0f0f0f0-01"
+        );
+        assert_eq!(context.parts(), Some((trace_id, parent_id, 1)));
+
+        let mut buf = Vec::new();
+        context.encode(&mut buf).unwrap();
+        let decoded = TraceContext::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(decoded, context);
+    }
+
+    #[test]
+    fn invalid_traceparent() {
+        for traceparent in [
+            "",
+            "00-4b4b-f0f0-01",
+            "01-4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b-f0f0f0f0f0f0f0f0-01",
+            "00-00000000000000000000000000000000-f0f0f0f0f0f0f0f0-01",
+            "00-4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b-0000000000000000-01",
+            "00-4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b-f0f0f0f0f0f0f0f0-01-ff",
+        ] {
+            let context = TraceContext {
+                traceparent: traceparent.to_string(),
+                tracestate: String::new(),
+            };
+            assert_eq!(context.parts(), None, "{traceparent}");
+        }
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    #[test]
+    fn propagates_between_spans() {
+        use opentelemetry::trace::{TraceContextExt, TracerProvider};
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let provider =
+            opentelemetry_sdk::trace::SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_opentelemetry::layer().with_tracer(provider.tracer("test")),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let client = tracing::info_span!("rpc_client");
+            let context = client.in_scope(TraceContext::current).unwrap();
+
+            let server = tracing::info_span!("rpc_server");
+            context.set_parent(&server);
+
+            let client_cx = client.context();
+            let server_cx = server.context();
+            assert_eq!(
+                client_cx.span().span_context().trace_id(),
+                server_cx.span().span_context().trace_id()
+            );
+        });
+    }
+}
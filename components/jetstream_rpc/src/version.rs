@@ -1,12 +1,22 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::Display,
+    ops::{BitAnd, BitOr},
+    str::FromStr,
+};
 
 use jetstream_wireformat::{JetStreamWireFormat, WireFormat};
 
-use crate::Framer;
+use crate::{compression::Compression, schema, Framer};
 
 pub const TVERSION: u8 = 100;
 pub const RVERSION: u8 = TVERSION + 1;
 
+// r[impl jetstream.compression.limits]
+/// The largest msize a server acknowledges. Clients asking for more are
+/// granted this much, which also bounds what a compressed frame may expand
+/// to.
+pub const MAX_MSIZE: u32 = 16 * 1024 * 1024;
+
 /// version -- negotiate protocol version
 ///
 /// ```text
@@ -90,7 +100,7 @@ impl Framer for VersionFrame {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Version {
     V9P2000L,
     V9P2000,
@@ -145,7 +155,7 @@ impl FromStr for Version {
                     ));
                 }
                 let name = parts[1].to_string();
-                let version_str = parts[2];
+                let version_str = &Capabilities::strip(parts[2]);
                 let version = semver::Version::parse(version_str).map_err(|e| {
                     format!("Invalid semver version in JetStream version: {}: {}", version_str, e)
                 })?;
@@ -156,6 +166,177 @@ impl FromStr for Version {
     }
 }
 
+// r[impl jetstream.version.capabilities]
+/// Optional protocol extensions negotiated in the `Tversion`/`Rversion`
+/// exchange.
+///
+/// Capabilities travel as extra semver build identifiers on the version
+/// string, so peers that predate them still parse the version. The client
+/// offers `cap-{name}` and the server acknowledges with `ack-{name}`; an old
+/// server either echoes the offer or replies with its own version, neither of
+/// which counts as an acknowledgement.
+///
+/// ```text
+/// Tversion: rs.jetstream.proto/echo/1.0.0+bfd7d20e.cap-tracecontext
+/// Rversion: rs.jetstream.proto/echo/1.0.0+bfd7d20e.ack-tracecontext
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// No optional extensions.
+    pub const NONE: Self = Self(0);
+    /// Requests may be wrapped in a [`TTRACE`](crate::TTRACE) envelope
+    /// carrying the caller's W3C trace context.
+    pub const TRACE_CONTEXT: Self = Self(1 << 0);
+    /// Frame bodies may be compressed with zstd.
+    pub const ZSTD: Self = Self(1 << 1);
+    /// Frame bodies may be compressed with lz4.
+    pub const LZ4: Self = Self(1 << 2);
+
+    /// The compression algorithms compiled into this build.
+    pub const COMPRESSION: Self = Self(
+        if cfg!(feature = "zstd") {
+            Self::ZSTD.0
+        } else {
+            0
+        } | if cfg!(feature = "lz4") {
+            Self::LZ4.0
+        } else {
+            0
+        },
+    );
+
+    const NAMES: &'static [(Capabilities, &'static str)] = &[
+        (Self::TRACE_CONTEXT, "tracecontext"),
+        (Self::ZSTD, "zstd"),
+        (Self::LZ4, "lz4"),
+    ];
+
+    pub(crate) const OFFER_PREFIX: &'static str = "cap-";
+    pub(crate) const ACK_PREFIX: &'static str = "ack-";
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// The capabilities a server acknowledges when a client offers `self`
+    /// and it supports `supported`: their intersection, keeping only the
+    /// preferred compression algorithm if several are shared.
+    pub fn negotiate(self, supported: Self) -> Self {
+        let shared = self & supported;
+        let compression = Self::ZSTD.union(Self::LZ4);
+        let uncompressed = Self(shared.0 & !compression.0);
+        match Compression::select(shared) {
+            Some(algorithm) => uncompressed | algorithm.capability(),
+            None => uncompressed,
+        }
+    }
+
+    /// Appends the capabilities a client offers to a version string.
+    pub fn offer(self, version: &str) -> String {
+        self.append(version, Self::OFFER_PREFIX)
+    }
+
+    /// Appends the capabilities a server acknowledges to a version string.
+    pub fn acknowledge(self, version: &str) -> String {
+        self.append(version, Self::ACK_PREFIX)
+    }
+
+    /// Capabilities offered in a `Tversion` version string.
+    pub fn offered(version: &str) -> Self {
+        Self::collect(version, Self::OFFER_PREFIX)
+    }
+
+    /// Capabilities acknowledged in an `Rversion` version string.
+    pub fn acknowledged(version: &str) -> Self {
+        Self::collect(version, Self::ACK_PREFIX)
+    }
+
+    /// Removes every capability identifier, and the method digests, from a
+    /// version string.
+    pub fn strip(version: &str) -> String {
+        let Some((head, build)) = version.split_once('+') else {
+            return version.to_string();
+        };
+        let build = build
+            .split('.')
+            .filter(|id| {
+                !id.starts_with(Self::OFFER_PREFIX)
+                    && !id.starts_with(Self::ACK_PREFIX)
+                    && !schema::is_methods_identifier(id)
+            })
+            .collect::<Vec<_>>();
+        if build.is_empty() {
+            head.to_string()
+        } else {
+            format!("{}+{}", head, build.join("."))
+        }
+    }
+
+    fn append(self, version: &str, prefix: &str) -> String {
+        let mut out = version.to_string();
+        let mut separator = if version.contains('+') { '.' } else { '+' };
+        for (capability, name) in Self::NAMES {
+            if self.contains(*capability) {
+                out.push(separator);
+                out.push_str(prefix);
+                out.push_str(name);
+                separator = '.';
+            }
+        }
+        out
+    }
+
+    fn collect(version: &str, prefix: &str) -> Self {
+        let Some((_, build)) = version.split_once('+') else {
+            return Self::NONE;
+        };
+        build
+            .split('.')
+            .filter_map(|id| id.strip_prefix(prefix))
+            .filter_map(|name| {
+                Self::NAMES
+                    .iter()
+                    .find(|(_, known)| *known == name)
+                    .map(|(capability, _)| *capability)
+            })
+            .fold(Self::NONE, |acc, capability| acc | capability)
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Capabilities {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,4 +356,71 @@ mod tests {
 
         assert!(Version::from_str("invalid").is_err());
     }
+
+    #[test]
+    fn capabilities_round_trip() {
+        let base = "rs.jetstream.proto/echo/1.0.0+bfd7d20e";
+        let offer = Capabilities::TRACE_CONTEXT.offer(base);
+        assert_eq!(
+            offer,
+            "rs.jetstream.proto/echo/1.0.0+bfd7d20e.cap-tracecontext"
+        );
+        assert_eq!(Capabilities::offered(&offer), Capabilities::TRACE_CONTEXT);
+        assert_eq!(Capabilities::acknowledged(&offer), Capabilities::NONE);
+        assert_eq!(Capabilities::strip(&offer), base);
+
+        let ack = Capabilities::TRACE_CONTEXT.acknowledge(base);
+        assert_eq!(
+            Capabilities::acknowledged(&ack),
+            Capabilities::TRACE_CONTEXT
+        );
+        assert_eq!(Capabilities::offered(&ack), Capabilities::NONE);
+
+        // Without a digest the capabilities start the build metadata.
+        assert_eq!(
+            Capabilities::TRACE_CONTEXT.offer("rs.jetstream.proto/echo/1.0.0"),
+            "rs.jetstream.proto/echo/1.0.0+cap-tracecontext"
+        );
+        assert_eq!(
+            Capabilities::strip(
+                "rs.jetstream.proto/echo/1.0.0+cap-tracecontext"
+            ),
+            "rs.jetstream.proto/echo/1.0.0"
+        );
+    }
+
+    #[test]
+    fn negotiate_picks_one_compression() {
+        let offered = Capabilities::TRACE_CONTEXT
+            | Capabilities::ZSTD
+            | Capabilities::LZ4;
+        assert_eq!(
+            offered.negotiate(Capabilities::TRACE_CONTEXT),
+            Capabilities::TRACE_CONTEXT
+        );
+        let negotiated = offered.negotiate(
+            Capabilities::TRACE_CONTEXT.union(Capabilities::COMPRESSION),
+        );
+        assert!(negotiated.contains(Capabilities::TRACE_CONTEXT));
+        assert_eq!(
+            Compression::select(negotiated).map(Compression::capability),
+            Compression::select(Capabilities::COMPRESSION)
+                .map(Compression::capability)
+        );
+        assert!(
+            !negotiated.contains(Capabilities::ZSTD.union(Capabilities::LZ4))
+        );
+    }
+
+    #[test]
+    fn version_ignores_capabilities() {
+        assert_eq!(
+            Version::from_str(
+                "rs.jetstream.proto/echo/1.0.0+bfd7d20e.cap-tracecontext"
+            )
+            .unwrap(),
+            Version::from_str("rs.jetstream.proto/echo/1.0.0+bfd7d20e")
+                .unwrap()
+        );
+    }
 }
@@ -0,0 +1,223 @@
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! `WireFormat` impls for types from other crates, each behind a cargo
+//! feature named after the crate:
+//!
+//! - `uuid`: `Uuid` is its 16 bytes.
+//! - `chrono`: `DateTime<Utc>` is milliseconds since the Unix epoch as a
+//!   `u64`, like `SystemTime`.
+//! - `time`: `OffsetDateTime` is milliseconds since the Unix epoch as a
+//!   `u64`, and decodes in UTC.
+//! - `semver`: `Version` is its string form.
+//! - `indexmap`: `IndexMap` and `IndexSet` encode like `HashMap` and
+//!   `HashSet`, a `u16` count followed by the entries, in order.
+// r[impl jetstream.wireformat.ecosystem]
+#[cfg(any(feature = "chrono", feature = "time"))]
+use std::io::ErrorKind;
+use std::io::{self, Read, Write};
+#[cfg(feature = "indexmap")]
+use std::{hash::Hash, mem};
+
+use crate::{Describe, WireFormat};
+
+#[cfg(feature = "uuid")]
+impl WireFormat for uuid::Uuid {
+    fn byte_size(&self) -> u32 {
+        16
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut buf = [0u8; 16];
+        reader.read_exact(&mut buf)?;
+        Ok(uuid::Uuid::from_bytes(buf))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl Describe for uuid::Uuid {
+    fn describe() -> String {
+        String::from("Uuid")
+    }
+}
+
+/// Converts a timestamp in milliseconds since the Unix epoch to the `u64`
+/// it is sent as, rejecting timestamps before the epoch like `SystemTime`.
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn unix_millis(millis: i128) -> io::Result<u64> {
+    u64::try_from(millis).map_err(|_| {
+        io::Error::new(ErrorKind::InvalidData, "timestamp out of range")
+    })
+}
+
+#[cfg(feature = "chrono")]
+impl WireFormat for chrono::DateTime<chrono::Utc> {
+    fn byte_size(&self) -> u32 {
+        8
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        unix_millis(self.timestamp_millis() as i128)?.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let millis = u64::decode(reader)?;
+        i64::try_from(millis)
+            .ok()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .ok_or_else(|| {
+                io::Error::new(ErrorKind::InvalidData, "timestamp overflow")
+            })
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Describe for chrono::DateTime<chrono::Utc> {
+    fn describe() -> String {
+        String::from("DateTime<Utc>")
+    }
+}
+
+#[cfg(feature = "time")]
+impl WireFormat for time::OffsetDateTime {
+    fn byte_size(&self) -> u32 {
+        8
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        unix_millis(self.unix_timestamp_nanos() / 1_000_000)?.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let millis = u64::decode(reader)?;
+        time::OffsetDateTime::from_unix_timestamp_nanos(
+            millis as i128 * 1_000_000,
+        )
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(feature = "time")]
+impl Describe for time::OffsetDateTime {
+    fn describe() -> String {
+        String::from("OffsetDateTime")
+    }
+}
+
+#[cfg(feature = "semver")]
+impl WireFormat for semver::Version {
+    fn byte_size(&self) -> u32 {
+        self.to_string().byte_size()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.to_string().encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let string = String::decode(reader)?;
+        semver::Version::parse(&string)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(feature = "semver")]
+impl Describe for semver::Version {
+    fn describe() -> String {
+        String::from("Version")
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V> WireFormat for indexmap::IndexMap<K, V>
+where
+    K: WireFormat + Send + Sync + Eq + Hash,
+    V: WireFormat + Send + Sync,
+{
+    fn byte_size(&self) -> u32 {
+        mem::size_of::<u16>() as u32
+            + self
+                .iter()
+                .map(|(k, v)| k.byte_size() + v.byte_size())
+                .sum::<u32>()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "too many elements in map",
+            ));
+        }
+        (self.len() as u16).encode(writer)?;
+        for (k, v) in self {
+            k.encode(writer)?;
+            v.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len: u16 = WireFormat::decode(reader)?;
+        let mut map = indexmap::IndexMap::with_capacity(len as usize);
+        for _ in 0..len {
+            let k = K::decode(reader)?;
+            let v = V::decode(reader)?;
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T> WireFormat for indexmap::IndexSet<T>
+where
+    T: WireFormat + Send + Sync + Eq + Hash,
+{
+    fn byte_size(&self) -> u32 {
+        mem::size_of::<u16>() as u32
+            + self.iter().map(|elem| elem.byte_size()).sum::<u32>()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "too many elements in set",
+            ));
+        }
+        (self.len() as u16).encode(writer)?;
+        for elem in self {
+            elem.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len: u16 = WireFormat::decode(reader)?;
+        let mut set = indexmap::IndexSet::with_capacity(len as usize);
+        for _ in 0..len {
+            set.insert(T::decode(reader)?);
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: Describe, V: Describe> Describe for indexmap::IndexMap<K, V> {
+    fn describe() -> String {
+        format!("IndexMap<{},{}>", K::describe(), V::describe())
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T: Describe> Describe for indexmap::IndexSet<T> {
+    fn describe() -> String {
+        format!("IndexSet<{}>", T::describe())
+    }
+}
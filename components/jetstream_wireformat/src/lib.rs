@@ -7,6 +7,8 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #[cfg(feature = "std")]
 use std::collections::{BTreeMap, BinaryHeap};
+#[cfg(target_arch = "wasm32")]
+use std::rc::Rc;
 // Copyright (c) 2024, Sevki <s@sevki.io>
 // Copyright 2018 The ChromiumOS Authors
 // Use of this source code is governed by a BSD-style license that can be
@@ -19,8 +21,9 @@ use std::{
     io::{self, ErrorKind, Read, Write},
     marker::PhantomData,
     mem,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, Range},
     string::String,
+    sync::Arc,
     vec::Vec,
 };
 
@@ -29,8 +32,27 @@ use hashbrown::{HashMap, HashSet};
 pub use jetstream_macros::JetStreamWireFormat;
 use zerocopy::LittleEndian;
 
+#[cfg(any(
+    feature = "uuid",
+    feature = "chrono",
+    feature = "time",
+    feature = "semver",
+    feature = "indexmap"
+))]
+mod ecosystem;
+pub mod long;
+#[cfg(feature = "serde")]
+pub mod serde_bridge;
+pub mod varint;
 pub mod wire_format_extensions;
 
+#[cfg(feature = "std")]
+pub use long::LongMap;
+pub use long::{LongString, LongVec};
+#[cfg(feature = "serde")]
+pub use serde_bridge::Serde;
+pub use varint::Varint;
+
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
 
@@ -400,6 +422,132 @@ impl<T: WireFormat> WireFormat for Vec<T> {
     }
 }
 
+/// Borrowed values that encode exactly like an owned type, so they can be
+/// sent without first being copied into one: `str` encodes like `String`,
+/// `[T]` like `Vec<T>`, and every `WireFormat` type like itself.
+///
+/// Methods taking `&str`, `&[T]` or `&T` in a `#[service]` trait encode
+/// their arguments through this.
+///
+/// Like the owned types, `str` and `[T]` encode their length as a `u16`, so
+/// they fail to encode past `u16::MAX` bytes or elements. Arguments that
+/// can be larger should be `Data`, which encodes its length as a `u32`.
+pub trait BorrowedWireFormat {
+    /// Returns the number of bytes necessary to fully encode `self`.
+    fn borrowed_byte_size(&self) -> u32;
+
+    /// Encodes `self` into `writer`.
+    fn encode_borrowed<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+impl<T: WireFormat> BorrowedWireFormat for T {
+    fn borrowed_byte_size(&self) -> u32 {
+        WireFormat::byte_size(self)
+    }
+
+    fn encode_borrowed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        WireFormat::encode(self, writer)
+    }
+}
+
+impl BorrowedWireFormat for str {
+    fn borrowed_byte_size(&self) -> u32 {
+        (mem::size_of::<u16>() + self.len()) as u32
+    }
+
+    fn encode_borrowed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "string is too long",
+            ));
+        }
+
+        (self.len() as u16).encode(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+}
+
+impl<T: WireFormat> BorrowedWireFormat for [T] {
+    fn borrowed_byte_size(&self) -> u32 {
+        mem::size_of::<u16>() as u32
+            + self.iter().map(|elem| elem.byte_size()).sum::<u32>()
+    }
+
+    fn encode_borrowed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.len() > u16::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "too many elements in vector",
+            ));
+        }
+
+        (self.len() as u16).encode(writer)?;
+        for elem in self {
+            elem.encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+// r[impl jetstream.wireformat.borrowed]
+/// A type that can be decoded from a byte slice while borrowing from it, so
+/// that `&str` and `&[u8]` fields point into the message rather than being
+/// copied out of it: `&'a str` decodes like `String`, `&'a [u8]` like
+/// `Vec<u8>`, and every `WireFormat` type like itself.
+///
+/// Structs with a lifetime parameter derive this and `BorrowedWireFormat`
+/// instead of `WireFormat`:
+///
+/// ```ignore
+/// #[derive(JetStreamWireFormat)]
+/// pub struct Put<'a> {
+///     pub key: &'a str,
+///     pub value: &'a [u8],
+/// }
+///
+/// let put = Put::decode_ref(&mut bytes.as_slice())?;
+/// ```
+pub trait WireFormatRef<'a>: Sized {
+    /// Decodes `Self` from the front of `buf`, advancing it past the bytes
+    /// read.
+    fn decode_ref(buf: &mut &'a [u8]) -> io::Result<Self>;
+}
+
+impl<'a, T: WireFormat> WireFormatRef<'a> for T {
+    fn decode_ref(buf: &mut &'a [u8]) -> io::Result<Self> {
+        T::decode(buf)
+    }
+}
+
+impl<'a> WireFormatRef<'a> for &'a str {
+    fn decode_ref(buf: &mut &'a [u8]) -> io::Result<Self> {
+        let bytes = <&[u8]>::decode_ref(buf)?;
+        std::str::from_utf8(bytes)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+impl<'a> WireFormatRef<'a> for &'a [u8] {
+    fn decode_ref(buf: &mut &'a [u8]) -> io::Result<Self> {
+        let len: u16 = WireFormat::decode(buf)?;
+        if buf.len() < len as usize {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "unexpected end of slice: want: {} bytes, got: {} bytes",
+                    len,
+                    buf.len()
+                ),
+            ));
+        }
+        let (bytes, rest) = buf.split_at(len as usize);
+        *buf = rest;
+        Ok(bytes)
+    }
+}
+
 /// A type that encodes an arbitrary number of bytes of data.  Typically used for Rread
 /// Twrite messages.  This differs from a `Vec<u8>` in that it encodes the number of bytes
 /// using a `u32` instead of a `u16`.
@@ -411,7 +559,7 @@ pub struct Data(pub Vec<u8>);
 // The maximum length of a data buffer that we support.  In practice the server's max message
 // size should prevent us from reading too much data so this check is mainly to ensure a
 // malicious client cannot trick us into allocating massive amounts of memory.
-const MAX_DATA_LENGTH: u32 = 32 * 1024 * 1024;
+pub(crate) const MAX_DATA_LENGTH: u32 = 32 * 1024 * 1024;
 
 impl fmt::Debug for Data {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -637,7 +785,7 @@ impl<T: WireFormat + Send + Sync + Eq + Hash> WireFormat for HashSet<T> {
         Self: Sized,
     {
         if self.len() > u16::MAX as usize {
-            return Err(io::Error::new(io::ErrorKind::Other, "Set too large"));
+            return Err(io::Error::other("Set too large"));
         }
         (self.len() as u16).encode(writer)?;
         for v in self.iter() {
@@ -904,3 +1052,159 @@ impl WireFormat for url::Url {
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 }
+
+/// A stable description of a wire type, the same in every crate and on
+/// every compiler, unlike [`std::any::type_name`].
+///
+/// Generic `#[service]` traits name each instantiation after the
+/// descriptions of its type arguments, so `Store<u64, String>` and
+/// `Store<String, Data>` can be served side by side.
+/// `#[derive(JetStreamWireFormat)]` describes a type by its name and the
+/// descriptions of its type parameters.
+pub trait Describe {
+    /// Describes `Self`, e.g. `Vec<u8>` or `Point`.
+    fn describe() -> String;
+}
+
+macro_rules! describe_as {
+    ($($Ty:ty => $name:literal),+ $(,)?) => {
+        $(
+            impl Describe for $Ty {
+                fn describe() -> String {
+                    String::from($name)
+                }
+            }
+        )+
+    };
+}
+
+describe_as! {
+    u8 => "u8",
+    u16 => "u16",
+    u32 => "u32",
+    u64 => "u64",
+    u128 => "u128",
+    usize => "usize",
+    i16 => "i16",
+    i32 => "i32",
+    i64 => "i64",
+    i128 => "i128",
+    isize => "isize",
+    f32 => "f32",
+    f64 => "f64",
+    bool => "bool",
+    () => "()",
+    String => "String",
+    P9String => "P9String",
+    Data => "Data",
+    LongString => "LongString",
+    url::Url => "Url",
+    std::net::Ipv4Addr => "Ipv4Addr",
+    std::net::Ipv6Addr => "Ipv6Addr",
+    std::net::IpAddr => "IpAddr",
+    std::net::SocketAddrV4 => "SocketAddrV4",
+    std::net::SocketAddrV6 => "SocketAddrV6",
+    std::net::SocketAddr => "SocketAddr",
+    std::time::SystemTime => "SystemTime",
+    std::time::Duration => "Duration",
+    std::path::PathBuf => "PathBuf",
+    bytes::Bytes => "Bytes",
+    char => "char",
+    std::num::NonZeroU8 => "NonZeroU8",
+    std::num::NonZeroU16 => "NonZeroU16",
+    std::num::NonZeroU32 => "NonZeroU32",
+    std::num::NonZeroU64 => "NonZeroU64",
+    std::num::NonZeroU128 => "NonZeroU128",
+    std::num::NonZeroUsize => "NonZeroUsize",
+    std::num::NonZeroI16 => "NonZeroI16",
+    std::num::NonZeroI32 => "NonZeroI32",
+    std::num::NonZeroI64 => "NonZeroI64",
+    std::num::NonZeroI128 => "NonZeroI128",
+    std::num::NonZeroIsize => "NonZeroIsize",
+}
+
+macro_rules! describe_generic {
+    ($($Ty:ident<$($param:ident),+>),+ $(,)?) => {
+        $(
+            impl<$($param: Describe),+> Describe for $Ty<$($param),+> {
+                fn describe() -> String {
+                    let params: &[String] = &[$($param::describe()),+];
+                    format!("{}<{}>", stringify!($Ty), params.join(","))
+                }
+            }
+        )+
+    };
+}
+
+describe_generic! {
+    Vec<T>,
+    Option<T>,
+    Box<T>,
+    PhantomData<T>,
+    VecDeque<T>,
+    BTreeSet<T>,
+    HashSet<T>,
+    HashMap<K, V>,
+    LongVec<T>,
+    Varint<T>,
+    Arc<T>,
+    Result<T, E>,
+    Range<T>,
+}
+
+#[cfg(feature = "std")]
+describe_generic! {
+    BTreeMap<K, V>,
+    BinaryHeap<T>,
+    LongMap<K, V>,
+}
+
+#[cfg(target_arch = "wasm32")]
+describe_generic! {
+    Rc<T>,
+}
+
+impl<T: Describe, const N: usize> Describe for [T; N] {
+    fn describe() -> String {
+        format!("[{};{}]", T::describe(), N)
+    }
+}
+
+impl<T> Describe for std::borrow::Cow<'_, T>
+where
+    T: ToOwned + ?Sized,
+    T::Owned: Describe,
+{
+    fn describe() -> String {
+        format!("Cow<{}>", T::Owned::describe())
+    }
+}
+
+impl<A: Describe> Describe for (A,) {
+    fn describe() -> String {
+        format!("({},)", A::describe())
+    }
+}
+
+macro_rules! tuple_describe_impl {
+    ($( $name:ident ),+) => {
+        impl<$( $name: Describe ),+> Describe for ( $( $name ),+ ) {
+            fn describe() -> String {
+                let elems: &[String] = &[$( $name::describe() ),+];
+                format!("({})", elems.join(","))
+            }
+        }
+    };
+}
+
+tuple_describe_impl!(A, B);
+tuple_describe_impl!(A, B, C);
+tuple_describe_impl!(A, B, C, D);
+tuple_describe_impl!(A, B, C, D, E);
+tuple_describe_impl!(A, B, C, D, E, F);
+tuple_describe_impl!(A, B, C, D, E, F, G);
+tuple_describe_impl!(A, B, C, D, E, F, G, H);
+tuple_describe_impl!(A, B, C, D, E, F, G, H, I);
+tuple_describe_impl!(A, B, C, D, E, F, G, H, I, J);
+tuple_describe_impl!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_describe_impl!(A, B, C, D, E, F, G, H, I, J, K, L);
@@ -0,0 +1,362 @@
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Strings, vectors and maps with `u32` length prefixes.
+//!
+//! `String`, `Vec<T>` and the maps are prefixed with a `u16` length, so they
+//! fail to encode past 65535 bytes or elements. Fields that can grow larger
+//! are either wrapped in [`LongString`], [`LongVec`] or [`LongMap`], or keep
+//! their type and are marked `#[jetstream(len = u32)]`:
+//!
+//! ```ignore
+//! #[derive(JetStreamWireFormat)]
+//! pub struct Page {
+//!     #[jetstream(len = u32)]
+//!     pub body: String,
+//!     pub links: LongVec<String>,
+//! }
+//! ```
+//!
+//! Both encode the same bytes. Lengths above 32 MiB are rejected on decode,
+//! like those of [`Data`](crate::Data).
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+use std::{
+    fmt,
+    hash::Hash,
+    io::{self, ErrorKind, Read, Write},
+    mem,
+    ops::{Deref, DerefMut},
+};
+
+use bytes::Bytes;
+use hashbrown::HashMap;
+
+use crate::{WireFormat, MAX_DATA_LENGTH};
+
+// Decoding preallocates at most this many elements, so a forged length
+// cannot allocate more than the peer actually sends.
+const MAX_PREALLOCATED: usize = 4096;
+
+/// A string, vector or map, whose length can be prefixed with something
+/// other than a `u16`.
+pub trait LengthPrefixed: Sized {
+    /// What is prefixed, for error messages: `string`, `vector` or `map`.
+    const KIND: &'static str;
+
+    /// The number of bytes or elements the prefix counts.
+    fn prefix_len(&self) -> usize;
+
+    /// Returns the number of bytes necessary to encode the contents,
+    /// without the prefix.
+    fn contents_byte_size(&self) -> u32;
+
+    /// Encodes the contents into `writer`, without the prefix.
+    fn encode_contents<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Decodes contents of `len` bytes or elements from `reader`.
+    fn decode_contents<R: Read>(len: usize, reader: &mut R)
+        -> io::Result<Self>;
+}
+
+// r[impl jetstream.wireformat.long]
+// The `byte_size`, `encode` and `decode` of fields marked
+// `#[jetstream(len = u32)]`.
+
+/// Returns the number of bytes necessary to encode `value` with a `u32`
+/// length prefix.
+pub fn byte_size<T: LengthPrefixed>(value: &T) -> u32 {
+    mem::size_of::<u32>() as u32 + value.contents_byte_size()
+}
+
+/// Encodes `value` into `writer` with a `u32` length prefix.
+pub fn encode<T: LengthPrefixed, W: Write>(
+    value: &T,
+    writer: &mut W,
+) -> io::Result<()> {
+    let len = u32::try_from(value.prefix_len()).map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is too long", T::KIND),
+        )
+    })?;
+    len.encode(writer)?;
+    value.encode_contents(writer)
+}
+
+/// Decodes a `T` with a `u32` length prefix from `reader`.
+pub fn decode<T: LengthPrefixed, R: Read>(reader: &mut R) -> io::Result<T> {
+    let len: u32 = WireFormat::decode(reader)?;
+    T::decode_contents(checked_len::<T>(len as u64)?, reader)
+}
+
+/// Rejects decoded lengths above 32 MiB.
+pub(crate) fn checked_len<T: LengthPrefixed>(len: u64) -> io::Result<usize> {
+    if len > MAX_DATA_LENGTH as u64 {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{} length ({len}) is too large", T::KIND),
+        ));
+    }
+    Ok(len as usize)
+}
+
+impl LengthPrefixed for String {
+    const KIND: &'static str = "string";
+
+    fn prefix_len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents_byte_size(&self) -> u32 {
+        self.len() as u32
+    }
+
+    fn encode_contents<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.as_bytes())
+    }
+
+    fn decode_contents<R: Read>(
+        len: usize,
+        reader: &mut R,
+    ) -> io::Result<Self> {
+        let mut result = String::with_capacity(len.min(MAX_PREALLOCATED));
+        reader.take(len as u64).read_to_string(&mut result)?;
+        if result.len() != len {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "unexpected end of string: want: {} bytes, got: {} bytes",
+                    len,
+                    result.len()
+                ),
+            ));
+        }
+        Ok(result)
+    }
+}
+
+impl<T: WireFormat> LengthPrefixed for Vec<T> {
+    const KIND: &'static str = "vector";
+
+    fn prefix_len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents_byte_size(&self) -> u32 {
+        self.iter().map(|elem| elem.byte_size()).sum::<u32>()
+    }
+
+    fn encode_contents<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for elem in self {
+            elem.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn decode_contents<R: Read>(
+        len: usize,
+        reader: &mut R,
+    ) -> io::Result<Self> {
+        let mut result = Vec::with_capacity(len.min(MAX_PREALLOCATED));
+        for _ in 0..len {
+            result.push(WireFormat::decode(reader)?);
+        }
+        Ok(result)
+    }
+}
+
+impl LengthPrefixed for Bytes {
+    const KIND: &'static str = "byte string";
+
+    fn prefix_len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents_byte_size(&self) -> u32 {
+        self.len() as u32
+    }
+
+    fn encode_contents<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self)
+    }
+
+    fn decode_contents<R: Read>(
+        len: usize,
+        reader: &mut R,
+    ) -> io::Result<Self> {
+        let mut buf = vec![0; len];
+        reader.read_exact(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
+}
+
+impl<K, V> LengthPrefixed for HashMap<K, V>
+where
+    K: WireFormat + Send + Sync + Eq + Hash,
+    V: WireFormat + Send + Sync,
+{
+    const KIND: &'static str = "map";
+
+    fn prefix_len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents_byte_size(&self) -> u32 {
+        self.iter()
+            .fold(0, |acc, (k, v)| acc + k.byte_size() + v.byte_size())
+    }
+
+    fn encode_contents<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (k, v) in self {
+            k.encode(writer)?;
+            v.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn decode_contents<R: Read>(
+        len: usize,
+        reader: &mut R,
+    ) -> io::Result<Self> {
+        let mut map = HashMap::with_capacity(len.min(MAX_PREALLOCATED));
+        for _ in 0..len {
+            let k = K::decode(reader)?;
+            let v = V::decode(reader)?;
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V> LengthPrefixed for BTreeMap<K, V>
+where
+    K: WireFormat + Send + Sync + Ord,
+    V: WireFormat + Send + Sync,
+{
+    const KIND: &'static str = "map";
+
+    fn prefix_len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents_byte_size(&self) -> u32 {
+        self.iter()
+            .fold(0, |acc, (k, v)| acc + k.byte_size() + v.byte_size())
+    }
+
+    fn encode_contents<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (k, v) in self {
+            k.encode(writer)?;
+            v.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn decode_contents<R: Read>(
+        len: usize,
+        reader: &mut R,
+    ) -> io::Result<Self> {
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let k = K::decode(reader)?;
+            let v = V::decode(reader)?;
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+}
+
+macro_rules! long_wrapper {
+    ($(#[$attr:meta])* $Long:ident$(<$($param:ident),+>)?($Inner:ty) $(where $($bounds:tt)+)?) => {
+        $(#[$attr])*
+        #[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[repr(transparent)]
+        pub struct $Long$(<$($param),+>)?(pub $Inner);
+
+        impl$(<$($param),+>)? fmt::Debug for $Long$(<$($param),+>)?
+        where
+            $Inner: fmt::Debug,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl$(<$($param),+>)? Deref for $Long$(<$($param),+>)? {
+            type Target = $Inner;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl$(<$($param),+>)? DerefMut for $Long$(<$($param),+>)? {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
+        }
+
+        impl$(<$($param),+>)? From<$Inner> for $Long$(<$($param),+>)? {
+            fn from(inner: $Inner) -> Self {
+                Self(inner)
+            }
+        }
+
+        impl$(<$($param),+>)? WireFormat for $Long$(<$($param),+>)?
+        $(where $($bounds)+)?
+        {
+            fn byte_size(&self) -> u32 {
+                byte_size(&self.0)
+            }
+
+            fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                encode(&self.0, writer)
+            }
+
+            fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                decode(reader).map(Self)
+            }
+        }
+    };
+}
+
+long_wrapper! {
+    /// A `String` encoded with a `u32` length prefix.
+    LongString(String)
+}
+
+long_wrapper! {
+    /// A `Vec<T>` encoded with a `u32` element count.
+    LongVec<T>(Vec<T>) where T: WireFormat
+}
+
+#[cfg(feature = "std")]
+long_wrapper! {
+    /// A `BTreeMap<K, V>` encoded with a `u32` entry count.
+    LongMap<K, V>(BTreeMap<K, V>)
+    where
+        K: WireFormat + Send + Sync + Ord,
+        V: WireFormat + Send + Sync
+}
+
+impl From<&str> for LongString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<T> FromIterator<T> for LongVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord, V> FromIterator<(K, V)> for LongMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
@@ -0,0 +1,883 @@
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A serde `Serializer` and `Deserializer` for the wire format.
+//!
+//! Types that only implement `serde::Serialize` and `serde::Deserialize`
+//! encode to the same bytes as the equivalent `#[derive(JetStreamWireFormat)]`
+//! type: struct fields in order, enums as a `u8` variant index followed by
+//! their fields, options as a `u8` tag, and sequences, strings and maps with a
+//! `u16` length prefix. Wrapping such a type in [`Serde`] makes it a
+//! [`WireFormat`], so it can be used as a `#[service]` argument:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! pub struct Point {
+//!     pub x: i32,
+//!     pub y: i32,
+//! }
+//!
+//! #[service]
+//! pub trait Canvas {
+//!     async fn draw(&mut self, point: Serde<Point>) -> Result<()>;
+//! }
+//! ```
+//!
+//! The format is not self-describing, so `#[serde(flatten)]`, untagged
+//! enums, `skip_serializing_if` and other attributes that need
+//! `deserialize_any` are rejected. Types with their own serde impls are
+//! encoded as those impls describe them, which may differ from their
+//! [`WireFormat`] impl.
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    ops::{Deref, DerefMut},
+};
+
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor},
+    ser::{self, Serialize},
+};
+
+use crate::{BorrowedWireFormat, WireFormat};
+
+// r[impl jetstream.wireformat.serde]
+
+/// An error encoding or decoding through serde.
+#[derive(Debug)]
+pub struct Error(io::Error);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(io::Error::new(ErrorKind::InvalidData, msg.to_string()))
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(io::Error::new(ErrorKind::InvalidData, msg.to_string()))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        err.0
+    }
+}
+
+/// Returns the number of bytes `value` is encoded in.
+pub fn byte_size<T: Serialize + ?Sized>(value: &T) -> io::Result<u32> {
+    let mut counter = Serializer::new(ByteCounter(0));
+    value.serialize(&mut counter)?;
+    u32::try_from(counter.into_inner().0).map_err(|_| {
+        io::Error::new(ErrorKind::InvalidInput, "value too large to encode")
+    })
+}
+
+/// Encodes `value` into `writer`.
+pub fn to_writer<T: Serialize + ?Sized, W: Write>(
+    value: &T,
+    writer: &mut W,
+) -> io::Result<()> {
+    value.serialize(&mut Serializer::new(writer))?;
+    Ok(())
+}
+
+/// Decodes a `T` from `reader`.
+pub fn from_reader<T: DeserializeOwned, R: Read>(
+    reader: &mut R,
+) -> io::Result<T> {
+    Ok(T::deserialize(&mut Deserializer::new(reader))?)
+}
+
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A serde `Serializer` writing the wire format.
+pub struct Serializer<W> {
+    writer: W,
+}
+
+impl<W: Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Serializer { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn length(&mut self, len: usize) -> Result<(), Error> {
+        let len = u16::try_from(len).map_err(|_| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "too many elements in vector",
+            )
+        })?;
+        Ok(len.encode(&mut self.writer)?)
+    }
+
+    fn variant(&mut self, index: u32) -> Result<(), Error> {
+        let index = u8::try_from(index).map_err(|_| {
+            io::Error::new(ErrorKind::InvalidInput, "too many enum variants")
+        })?;
+        Ok(index.encode(&mut self.writer)?)
+    }
+}
+
+impl<W: Write> ser::Serializer for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        Ok((v as u8).encode(&mut self.writer)?)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        Ok(v.encode(&mut self.writer)?)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        Ok(v.encode_borrowed(&mut self.writer)?)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.length(v.len())?;
+        Ok(self.writer.write_all(v)?)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(0u8.encode(&mut self.writer)?)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<(), Error> {
+        1u8.encode(&mut self.writer)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        self.variant(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.variant(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, Error> {
+        let len = len.ok_or_else(|| {
+            <Error as ser::Error>::custom("sequences must have a known length")
+        })?;
+        self.length(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, Error> {
+        let len = len.ok_or_else(|| {
+            <Error as ser::Error>::custom("maps must have a known length")
+        })?;
+        self.length(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeMap for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), Error> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    // Fields are positional, so a skipped field would shift every field
+    // after it.
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        Err(ser::Error::custom(format_args!("can't skip field `{key}`")))
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        Err(ser::Error::custom(format_args!("can't skip field `{key}`")))
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A serde `Deserializer` reading the wire format.
+pub struct Deserializer<R> {
+    reader: R,
+}
+
+impl<R: Read> Deserializer<R> {
+    pub fn new(reader: R) -> Self {
+        Deserializer { reader }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn decode<T: WireFormat>(&mut self) -> Result<T, Error> {
+        Ok(T::decode(&mut self.reader)?)
+    }
+}
+
+fn not_self_describing() -> Error {
+    de::Error::custom("the wire format is not self-describing")
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_bool(self.decode()?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i8(self.decode::<u8>()? as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i16(self.decode()?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i32(self.decode()?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i64(self.decode()?)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i128(self.decode()?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u8(self.decode()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u16(self.decode()?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u32(self.decode()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u64(self.decode()?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u128(self.decode()?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_f32(self.decode()?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_f64(self.decode()?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_char(self.decode()?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_string(self.decode()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.decode::<Vec<u8>>()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.decode::<u8>()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            tag => Err(de::Error::custom(format_args!(
+                "Invalid Option tag: {tag}"
+            ))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let len = self.decode::<u16>()? as usize;
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let len = self.decode::<u16>()? as usize;
+        visitor.visit_map(Access { de: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access {
+            de: self,
+            len: fields.len(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(
+        self,
+        _: V,
+    ) -> Result<V::Value, Error> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        _: V,
+    ) -> Result<V::Value, Error> {
+        Err(not_self_describing())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// The elements of a sequence, tuple, struct or map with `len` entries.
+struct Access<'a, R> {
+    de: &'a mut Deserializer<R>,
+    len: usize,
+}
+
+impl<'de, R: Read> de::SeqAccess<'de> for Access<'_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for Access<'_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, R: Read> de::EnumAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), Error> {
+        let index = u32::from(self.decode::<u8>()?);
+        let variant = seed
+            .deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+/// A serde type encoded in the wire format.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct Serde<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Debug for Serde<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T> Deref for Serde<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Serde<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Serde<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Send> WireFormat for Serde<T> {
+    // `byte_size` can't fail, so values that fail to encode or don't fit
+    // a `u32` saturate to `u32::MAX`, which no frame can hold, rather than
+    // passing for a smaller message; `encode` reports the error.
+    fn byte_size(&self) -> u32 {
+        byte_size(&self.0).unwrap_or(u32::MAX)
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        to_writer(&self.0, writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        from_reader(reader).map(Self)
+    }
+}
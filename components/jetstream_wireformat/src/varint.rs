@@ -0,0 +1,213 @@
+// Copyright (c) 2024, Sevki <s@sevki.io>
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Variable-length integers.
+//!
+//! Integers are encoded as unsigned LEB128: seven bits per byte, least
+//! significant group first, with the high bit set on every byte but the
+//! last. Signed integers are zigzag encoded first, so small negative values
+//! stay small. Fields are either wrapped in [`Varint`], or keep their type
+//! and are marked `#[jetstream(varint)]`; strings, vectors and maps marked
+//! `#[jetstream(len = varint)]` prefix their length with a varint:
+//!
+//! ```ignore
+//! #[derive(JetStreamWireFormat)]
+//! pub struct Sample {
+//!     #[jetstream(varint)]
+//!     pub id: u64,
+//!     pub delta: Varint<i32>,
+//!     #[jetstream(len = varint)]
+//!     pub values: Vec<u8>,
+//! }
+//! ```
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    ops::{Deref, DerefMut},
+};
+
+use crate::WireFormat;
+
+/// An integer that can be encoded as a varint.
+pub trait VarInt: Copy {
+    /// The unsigned value encoded: the integer itself if it is unsigned,
+    /// else its zigzag encoding.
+    fn to_varint(self) -> u128;
+
+    /// The integer `value` encodes, if it fits.
+    fn from_varint(value: u128) -> Option<Self>;
+}
+
+macro_rules! unsigned_varint_impl {
+    ($($Ty:ty),+) => {
+        $(
+            impl VarInt for $Ty {
+                fn to_varint(self) -> u128 {
+                    self as u128
+                }
+
+                fn from_varint(value: u128) -> Option<Self> {
+                    Self::try_from(value).ok()
+                }
+            }
+        )+
+    };
+}
+
+// Zigzag maps 0, -1, 1, -2, ... to 0, 1, 2, 3, ...; the mapping is the same
+// at every width, so it is done on `i128`.
+macro_rules! signed_varint_impl {
+    ($($Ty:ty),+) => {
+        $(
+            impl VarInt for $Ty {
+                fn to_varint(self) -> u128 {
+                    let value = self as i128;
+                    ((value << 1) ^ (value >> 127)) as u128
+                }
+
+                fn from_varint(value: u128) -> Option<Self> {
+                    let value = (value >> 1) as i128 ^ -((value & 1) as i128);
+                    Self::try_from(value).ok()
+                }
+            }
+        )+
+    };
+}
+
+unsigned_varint_impl!(u16, u32, u64, u128, usize);
+signed_varint_impl!(i16, i32, i64, i128, isize);
+
+// r[impl jetstream.wireformat.varint]
+// The `byte_size`, `encode` and `decode` of fields marked
+// `#[jetstream(varint)]`.
+
+/// Returns the number of bytes `value` is encoded in, from 1 to 19.
+pub fn byte_size<T: VarInt>(value: &T) -> u32 {
+    let bits = 128 - value.to_varint().leading_zeros();
+    bits.div_ceil(7).max(1)
+}
+
+/// Encodes `value` into `writer` as a varint.
+pub fn encode<T: VarInt, W: Write>(
+    value: &T,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut value = value.to_varint();
+    let mut buf = [0u8; 19];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buf[..len])
+}
+
+/// Decodes a varint `T` from `reader`. Values that do not fit `T` are
+/// rejected.
+pub fn decode<T: VarInt, R: Read>(reader: &mut R) -> io::Result<T> {
+    let mut value = 0u128;
+    let mut shift = 0;
+    loop {
+        let byte: u8 = WireFormat::decode(reader)?;
+        let group = (byte & 0x7f) as u128;
+        if shift >= 128 || (shift > 121 && group >> (128 - shift) != 0) {
+            return Err(overflow::<T>());
+        }
+        value |= group << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    T::from_varint(value).ok_or_else(overflow::<T>)
+}
+
+fn overflow<T>() -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("varint overflows {}", std::any::type_name::<T>()),
+    )
+}
+
+/// The `byte_size`, `encode` and `decode` of strings, vectors and maps
+/// marked `#[jetstream(len = varint)]`.
+pub mod len {
+    use std::io::{self, Read, Write};
+
+    use crate::long::{checked_len, LengthPrefixed};
+
+    /// Returns the number of bytes necessary to encode `value` with a varint
+    /// length prefix.
+    pub fn byte_size<T: LengthPrefixed>(value: &T) -> u32 {
+        super::byte_size(&value.prefix_len()) + value.contents_byte_size()
+    }
+
+    /// Encodes `value` into `writer` with a varint length prefix.
+    pub fn encode<T: LengthPrefixed, W: Write>(
+        value: &T,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        super::encode(&value.prefix_len(), writer)?;
+        value.encode_contents(writer)
+    }
+
+    /// Decodes a `T` with a varint length prefix from `reader`. Lengths
+    /// above 32 MiB are rejected.
+    pub fn decode<T: LengthPrefixed, R: Read>(reader: &mut R) -> io::Result<T> {
+        let len: u64 = super::decode(reader)?;
+        T::decode_contents(checked_len::<T>(len)?, reader)
+    }
+}
+
+/// An integer encoded as a varint.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct Varint<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Debug for Varint<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T> Deref for Varint<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Varint<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Varint<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: VarInt + Send> WireFormat for Varint<T> {
+    fn byte_size(&self) -> u32 {
+        byte_size(&self.0)
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode(&self.0, writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        decode(reader).map(Self)
+    }
+}
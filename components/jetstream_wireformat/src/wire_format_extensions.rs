@@ -5,7 +5,17 @@ use core::net::{
     IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6,
 };
 use std::{
-    io::{self},
+    borrow::Cow,
+    io::{self, Read, Write},
+    mem,
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroIsize,
+        NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+        NonZeroUsize,
+    },
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 
@@ -13,7 +23,7 @@ use bytes::Bytes;
 
 pub extern crate bytes;
 
-use super::WireFormat;
+use super::{BorrowedWireFormat, WireFormat};
 
 pub trait AsyncWireFormat: std::marker::Sized {
     fn encode_async<W: AsyncWireFormat + Unpin + Send>(
@@ -314,3 +324,268 @@ impl WireFormat for SystemTime {
             })
     }
 }
+
+// r[impl jetstream.wireformat.std]
+// A duration is its whole seconds as a u64 followed by the nanoseconds
+// within the second as a u32.
+impl WireFormat for Duration {
+    fn byte_size(&self) -> u32 {
+        12
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_secs().encode(writer)?;
+        self.subsec_nanos().encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let secs = u64::decode(reader)?;
+        let nanos = u32::decode(reader)?;
+        if nanos >= 1_000_000_000 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid duration nanoseconds: {nanos}"),
+            ));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+// Shared ownership is not visible on the wire: an `Arc<T>` encodes like `T`.
+impl<T: WireFormat + Sync> WireFormat for Arc<T> {
+    fn byte_size(&self) -> u32 {
+        (**self).byte_size()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (**self).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        T::decode(reader).map(Arc::new)
+    }
+}
+
+// `Rc<T>` is never `Send`, so it only implements the WebAssembly
+// `WireFormat`, which doesn't require it.
+#[cfg(target_arch = "wasm32")]
+impl<T: WireFormat> WireFormat for std::rc::Rc<T> {
+    fn byte_size(&self) -> u32 {
+        (**self).byte_size()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (**self).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        T::decode(reader).map(std::rc::Rc::new)
+    }
+}
+
+// A `Cow` encodes like its owned form, so `Cow<'_, str>` encodes like
+// `String` and `Cow<'_, [T]>` like `Vec<T>`. It always decodes owned.
+impl<T> WireFormat for Cow<'_, T>
+where
+    T: ToOwned + BorrowedWireFormat + Sync + ?Sized,
+    T::Owned: WireFormat,
+{
+    fn byte_size(&self) -> u32 {
+        (**self).borrowed_byte_size()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (**self).encode_borrowed(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        T::Owned::decode(reader).map(Cow::Owned)
+    }
+}
+
+// Arrays have a fixed length, so unlike `Vec<T>` they are not prefixed
+// with one: `[T; N]` is just its `N` elements.
+impl<T: WireFormat, const N: usize> WireFormat for [T; N] {
+    fn byte_size(&self) -> u32 {
+        self.iter().map(|elem| elem.byte_size()).sum()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for elem in self {
+            elem.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let elems = (0..N)
+            .map(|_| T::decode(reader))
+            .collect::<io::Result<Vec<T>>>()?;
+        elems.try_into().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "array length mismatch")
+        })
+    }
+}
+
+// A char is its Unicode scalar value as a u32.
+impl WireFormat for char {
+    fn byte_size(&self) -> u32 {
+        mem::size_of::<u32>() as u32
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u32).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let value = u32::decode(reader)?;
+        char::from_u32(value).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid char: {value:#x}"),
+            )
+        })
+    }
+}
+
+// A result is a u8 tag, 0 for `Ok` and 1 for `Err`, followed by the value,
+// like an enum with those two variants.
+impl<T: WireFormat, E: WireFormat> WireFormat for Result<T, E> {
+    fn byte_size(&self) -> u32 {
+        1 + match self {
+            Ok(value) => value.byte_size(),
+            Err(err) => err.byte_size(),
+        }
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Ok(value) => {
+                0u8.encode(writer)?;
+                value.encode(writer)
+            }
+            Err(err) => {
+                1u8.encode(writer)?;
+                err.encode(writer)
+            }
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let tag = u8::decode(reader)?;
+        match tag {
+            0 => Ok(Ok(T::decode(reader)?)),
+            1 => Ok(Err(E::decode(reader)?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid Result tag: {}", tag),
+            )),
+        }
+    }
+}
+
+macro_rules! non_zero_wire_format_impl {
+    ($($NonZero:ty => $Ty:ty),+ $(,)?) => {
+        $(
+            impl WireFormat for $NonZero {
+                fn byte_size(&self) -> u32 {
+                    mem::size_of::<$Ty>() as u32
+                }
+
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    self.get().encode(writer)
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    <$NonZero>::new(<$Ty>::decode(reader)?).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            concat!("zero is not a valid ", stringify!($NonZero)),
+                        )
+                    })
+                }
+            }
+        )+
+    };
+}
+
+// Non-zero integers encode like their integer, and reject zero on decode.
+non_zero_wire_format_impl! {
+    NonZeroU8 => u8,
+    NonZeroU16 => u16,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64,
+    NonZeroU128 => u128,
+    NonZeroUsize => usize,
+    NonZeroI16 => i16,
+    NonZeroI32 => i32,
+    NonZeroI64 => i64,
+    NonZeroI128 => i128,
+    NonZeroIsize => isize,
+}
+
+// Paths are sent as UTF-8 strings, so paths that aren't valid UTF-8 fail to
+// encode.
+impl WireFormat for PathBuf {
+    fn byte_size(&self) -> u32 {
+        (mem::size_of::<u16>() + self.as_os_str().len()) as u32
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.to_str()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "path is not valid UTF-8",
+                )
+            })?
+            .encode_borrowed(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        String::decode(reader).map(PathBuf::from)
+    }
+}
+
+// `Bytes` encodes like `Vec<u8>`: a u16 length followed by the bytes.
+impl WireFormat for Bytes {
+    fn byte_size(&self) -> u32 {
+        (mem::size_of::<u16>() + self.len()) as u32
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "too many bytes",
+            ));
+        }
+        (self.len() as u16).encode(writer)?;
+        writer.write_all(self)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = u16::decode(reader)?;
+        let mut buf = vec![0; len as usize];
+        reader.read_exact(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
+}
+
+// A range is its start followed by its end.
+impl<T: WireFormat> WireFormat for Range<T> {
+    fn byte_size(&self) -> u32 {
+        self.start.byte_size() + self.end.byte_size()
+    }
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.start.encode(writer)?;
+        self.end.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let start = T::decode(reader)?;
+        let end = T::decode(reader)?;
+        Ok(start..end)
+    }
+}
@@ -167,8 +167,8 @@ export function rmessageDecode(reader: BinaryReader, type: number): RmessageFram
   return new RmessageFramer(rmessageFramer.decode(reader, type));
 }
 
-export const PROTOCOL_NAME = 'rs.jetstream.proto/echohttp';
-export const PROTOCOL_VERSION = 'rs.jetstream.proto/echohttp/15.0.0+bfd7d20e';
+export const PROTOCOL_NAME = 'rs.jetstream.proto/http.echohttp';
+export const PROTOCOL_VERSION = 'rs.jetstream.proto/http.echohttp/15.0.0+bfd7d20e';
 
 export class EchoHttpClient {
   private mux: Mux<TmessageFramer, RmessageFramer>;
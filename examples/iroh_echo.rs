@@ -52,6 +52,7 @@ async fn main() {
         .unwrap();
 
     let ec = SquareChannel::new(10, Box::new(transport));
+    ec.negotiate_version(u32::MAX).await.unwrap();
     let mut futures = FuturesUnordered::new();
     for i in 0..1000 {
         futures.push(ec.square(Context::default(), i));
@@ -6,3 +6,8 @@ export const TVERSION = 100;
 export const RVERSION = 101;
 export const MESSAGE_ID_START = 102;
 export const RJETSTREAMERROR = 5;
+/**
+ * r[impl jetstream.rpc.oneway.notag]
+ * Tag of frames that expect no response: Tversion and oneway requests.
+ */
+export const NOTAG = 0xffff;
@@ -8,3 +8,4 @@ export * from "./protocol.js";
 export * from "./server-codec.js";
 export * from "./server-loop.js";
 export * from "./version.js";
+export * from "./retry.js";
@@ -1,9 +1,19 @@
 /**
  * r[impl jetstream.rpc.ts.mux]
  */
+import { JetStreamError } from "@sevki/jetstream-wireformat";
 import type { Frame, Framer } from "./frame.js";
 import type { Transport } from "./transport.js";
 import { TagPool } from "./tag-pool.js";
+import { CONNECTION_LOST } from "./retry.js";
+import { NOTAG } from "./constants.js";
+
+function connectionLost(reason: string): JetStreamError {
+  return new JetStreamError(
+    { message: reason, code: CONNECTION_LOST, help: null, url: null },
+    { internTable: [], frames: [] },
+  );
+}
 
 export class Mux<TReq extends Framer, TRes extends Framer> {
   private tagPool: TagPool;
@@ -13,27 +23,43 @@ export class Mux<TReq extends Framer, TRes extends Framer> {
   > = new Map();
   private transport: Transport<TReq, TRes>;
   private running = false;
+  private closed: string | null = null;
 
   constructor(transport: Transport<TReq, TRes>, maxConcurrent: number = 256) {
     this.transport = transport;
-    this.tagPool = new TagPool(maxConcurrent);
+    this.tagPool = new TagPool(Math.min(maxConcurrent, NOTAG - 1));
   }
 
   async start(): Promise<void> {
     this.running = true;
     (async () => {
-      for await (const frame of this.transport.receive()) {
-        const pending = this.pending.get(frame.tag);
-        if (pending) {
-          this.pending.delete(frame.tag);
-          this.tagPool.release(frame.tag);
-          pending.resolve(frame);
+      let reason = "connection closed";
+      try {
+        for await (const frame of this.transport.receive()) {
+          const pending = this.pending.get(frame.tag);
+          if (pending) {
+            this.pending.delete(frame.tag);
+            this.tagPool.release(frame.tag);
+            pending.resolve(frame);
+          }
         }
+      } catch (err) {
+        reason = String(err);
+      }
+      // r[impl jetstream.retry.mux]
+      // Nothing will answer the calls still waiting, fail them so idempotent
+      // ones can be retried.
+      this.closed = reason;
+      for (const [tag, pending] of this.pending) {
+        this.tagPool.release(tag);
+        pending.reject(connectionLost(reason));
       }
+      this.pending.clear();
     })();
   }
 
   async rpc(msg: TReq): Promise<Frame<TRes>> {
+    if (this.closed !== null) throw connectionLost(this.closed);
     const tag = this.tagPool.acquire();
     if (tag === null) throw new Error("no tags available");
 
@@ -42,11 +68,25 @@ export class Mux<TReq extends Framer, TRes extends Framer> {
       this.transport.send({ tag, msg }).catch((err) => {
         this.pending.delete(tag);
         this.tagPool.release(tag);
-        reject(err);
+        reject(connectionLost(String(err)));
       });
     });
   }
 
+  /**
+   * r[impl jetstream.rpc.oneway.mux]
+   * Send a request to a oneway method. It takes no tag and nothing waits
+   * for its response.
+   */
+  async oneway(msg: TReq): Promise<void> {
+    if (this.closed !== null) throw connectionLost(this.closed);
+    try {
+      await this.transport.send({ tag: NOTAG, msg });
+    } catch (err) {
+      throw connectionLost(String(err));
+    }
+  }
+
   async close(): Promise<void> {
     this.running = false;
     await this.transport.close();
@@ -0,0 +1,73 @@
+/**
+ * r[verify jetstream.rpc.ts.retry]
+ */
+import { describe, test, expect } from "vitest";
+import { JetStreamError } from "@sevki/jetstream-wireformat";
+import {
+  CONNECTION_LOST,
+  NO_RETRY,
+  retryDelay,
+  withRetry,
+} from "./retry.js";
+import type { RetryPolicy } from "./retry.js";
+
+const fast: RetryPolicy = { maxRetries: 2, initialBackoffMs: 1, maxBackoffMs: 1 };
+
+function error(code: string | null): JetStreamError {
+  return new JetStreamError(
+    { message: "failed", code, help: null, url: null },
+    { internTable: [], frames: [] },
+  );
+}
+
+describe("withRetry", () => {
+  test("retries retriable errors", async () => {
+    let calls = 0;
+    const result = await withRetry(fast, async () => {
+      if (calls++ < 2) throw error(CONNECTION_LOST);
+      return "ok";
+    });
+    expect(result).toBe("ok");
+    expect(calls).toBe(3);
+  });
+
+  test("stops at the retry budget", async () => {
+    let calls = 0;
+    await expect(
+      withRetry(fast, async () => {
+        calls++;
+        throw error(CONNECTION_LOST);
+      }),
+    ).rejects.toBeInstanceOf(JetStreamError);
+    expect(calls).toBe(3);
+  });
+
+  test("does not retry other errors", async () => {
+    let calls = 0;
+    await expect(
+      withRetry(fast, async () => {
+        calls++;
+        throw error(null);
+      }),
+    ).rejects.toBeInstanceOf(JetStreamError);
+    expect(calls).toBe(1);
+  });
+
+  test("NO_RETRY makes a single attempt", async () => {
+    let calls = 0;
+    await expect(
+      withRetry(NO_RETRY, async () => {
+        calls++;
+        throw error(CONNECTION_LOST);
+      }),
+    ).rejects.toBeInstanceOf(JetStreamError);
+    expect(calls).toBe(1);
+  });
+
+  test("backoff doubles up to the cap", () => {
+    const policy = { maxRetries: 5, initialBackoffMs: 100, maxBackoffMs: 500 };
+    expect(retryDelay(policy, 0)).toBe(100);
+    expect(retryDelay(policy, 1)).toBe(200);
+    expect(retryDelay(policy, 3)).toBe(500);
+  });
+});
@@ -0,0 +1,68 @@
+/**
+ * r[impl jetstream.rpc.ts.retry]
+ *
+ * Client-side retries for methods marked `#[idempotent]`, mirroring
+ * `jetstream_rpc::retry` on the Rust side.
+ */
+import { JetStreamError } from "@sevki/jetstream-wireformat";
+
+export const UNAVAILABLE = "jetstream_rpc::error::unavailable";
+export const CONNECTION_LOST = "jetstream_rpc::error::connection_lost";
+export const DEADLINE_EXCEEDED = "jetstream_rpc::error::deadline_exceeded";
+
+const RETRIABLE = new Set([UNAVAILABLE, CONNECTION_LOST, DEADLINE_EXCEEDED]);
+
+export interface RetryPolicy {
+  /** Retries allowed per call, not counting the first attempt. */
+  maxRetries: number;
+  initialBackoffMs: number;
+  maxBackoffMs: number;
+}
+
+export const DEFAULT_RETRY_POLICY: RetryPolicy = {
+  maxRetries: 3,
+  initialBackoffMs: 50,
+  maxBackoffMs: 2000,
+};
+
+export const NO_RETRY: RetryPolicy = {
+  maxRetries: 0,
+  initialBackoffMs: 0,
+  maxBackoffMs: 0,
+};
+
+/** Whether an idempotent call that failed with `err` may be sent again. */
+export function isRetriable(err: unknown): boolean {
+  return (
+    err instanceof JetStreamError &&
+    err.inner.code !== null &&
+    RETRIABLE.has(err.inner.code)
+  );
+}
+
+/** Delay before retry `attempt`, counting from zero. */
+export function retryDelay(policy: RetryPolicy, attempt: number): number {
+  return Math.min(
+    policy.initialBackoffMs * 2 ** attempt,
+    policy.maxBackoffMs,
+  );
+}
+
+/** Runs `call`, retrying retriable failures as allowed by `policy`. */
+export async function withRetry<T>(
+  policy: RetryPolicy,
+  call: () => Promise<T>,
+): Promise<T> {
+  for (let attempt = 0; ; attempt++) {
+    try {
+      return await call();
+    } catch (err) {
+      if (attempt >= policy.maxRetries || !isRetriable(err)) {
+        throw err;
+      }
+      await new Promise((resolve) =>
+        setTimeout(resolve, retryDelay(policy, attempt)),
+      );
+    }
+  }
+}
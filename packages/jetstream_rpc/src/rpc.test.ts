@@ -3,7 +3,9 @@
  * r[verify jetstream.rpc.ts.frame]
  * r[verify jetstream.rpc.ts.framer]
  * r[verify jetstream.rpc.ts.mux]
+ * r[verify jetstream.retry.mux]
  * r[verify jetstream.rpc.ts.protocol]
+ * r[verify jetstream.rpc.oneway.mux]
  */
 import { describe, test, expect } from "vitest";
 import {
@@ -18,7 +20,8 @@ import { frameCodec } from "./frame.js";
 import type { Framer, FramerDecode, Frame } from "./frame.js";
 import type { Transport } from "./transport.js";
 import { Mux } from "./mux.js";
-import { MESSAGE_ID_START, RJETSTREAMERROR } from "./constants.js";
+import { MESSAGE_ID_START, NOTAG, RJETSTREAMERROR } from "./constants.js";
+import { CONNECTION_LOST } from "./retry.js";
 import type { Protocol } from "./protocol.js";
 
 /** A simple Framer that carries a single u32 payload. */
@@ -279,6 +282,39 @@ describe("Mux", () => {
 
     await mux.close();
   });
+
+  test("oneway sends with NOTAG and takes no tag", async () => {
+    const transport = mockTransport();
+    const mux = new Mux<SimpleMsg, SimpleMsg>(transport, 1);
+    await mux.start();
+
+    await mux.oneway(new SimpleMsg(1, 7));
+    expect(transport.sent[0].tag).toBe(NOTAG);
+
+    // The only tag is still free, and the echoed NOTAG frame is ignored.
+    const result = await mux.rpc(new SimpleMsg(1, 42));
+    expect(result.msg.value).toBe(42);
+
+    await mux.close();
+  });
+
+  test("pending rpcs fail when the connection is lost", async () => {
+    const transport = mockTransport();
+    // Swallow requests so they stay pending.
+    transport.send = async () => {};
+    const mux = new Mux<SimpleMsg, SimpleMsg>(transport, 16);
+    await mux.start();
+
+    const pending = mux.rpc(new SimpleMsg(1, 1));
+    await transport.close();
+
+    await expect(pending).rejects.toMatchObject({
+      inner: { code: CONNECTION_LOST },
+    });
+    await expect(mux.rpc(new SimpleMsg(1, 2))).rejects.toMatchObject({
+      inner: { code: CONNECTION_LOST },
+    });
+  });
 });
 
 describe("constants", () => {
@@ -2,6 +2,7 @@
  * r[impl jetstream.rpc.ts.server-loop]
  */
 import type { ServerCodec } from './server-codec.js';
+import { NOTAG } from './constants.js';
 
 export type DispatchFn<TReq, TRes> = (
   frame: { tag: number; msg: TReq },
@@ -26,6 +27,9 @@ export async function serverLoop<TReq, TRes>(
       // Dispatch concurrently — each incoming frame is handed off immediately
       const p = dispatch(frame)
         .then((response) => {
+          // r[impl jetstream.rpc.oneway.server]
+          // Nobody waits for the response of a oneway call.
+          if (frame.tag === NOTAG) return;
           const bytes = codec.encodeResponse(response);
           return writer.write(bytes);
         })
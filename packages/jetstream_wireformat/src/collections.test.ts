@@ -2,8 +2,15 @@ import { describe, test, expect } from 'vitest';
 import { BinaryReader } from './binary-reader.js';
 import { BinaryWriter } from './binary-writer.js';
 import { u16Codec, u32Codec } from './primitives.js';
-import { stringCodec } from './string.js';
-import { vecCodec, dataCodec, mapCodec, setCodec } from './collections.js';
+import { stringCodec, longStringCodec } from './string.js';
+import {
+  vecCodec,
+  longVecCodec,
+  dataCodec,
+  mapCodec,
+  longMapCodec,
+  setCodec,
+} from './collections.js';
 import type { WireFormat } from './primitives.js';
 
 function roundTrip<T>(codec: WireFormat<T>, value: T): T {
@@ -143,3 +150,43 @@ describe('set', () => {
     expect(result.size).toBe(3);
   });
 });
+
+/**
+ * r[verify jetstream.wireformat.ts.long]
+ * r[verify jetstream.wireformat.long]
+ */
+describe('long', () => {
+  test('round-trip string longer than u16 max', () => {
+    const value = 'x'.repeat(0x10000);
+    expect(roundTrip(longStringCodec, value)).toBe(value);
+  });
+
+  test('encoding format: u32 length + bytes', () => {
+    const writer = new BinaryWriter();
+    longStringCodec.encode('hi', writer);
+    expect(writer.toUint8Array()).toEqual(new Uint8Array([0x02, 0x00, 0x00, 0x00, 0x68, 0x69]));
+  });
+
+  test('round-trip vector longer than u16 max', () => {
+    const value = Array.from({ length: 70000 }, (_, i) => i % 0x10000);
+    expect(roundTrip(longVecCodec(u16Codec), value)).toEqual(value);
+  });
+
+  test('round-trip map with u32 count', () => {
+    const codec = longMapCodec(stringCodec, u32Codec, (a, b) => (a < b ? -1 : a > b ? 1 : 0));
+    const value = new Map([['b', 2], ['a', 1]]);
+    const writer = new BinaryWriter();
+    codec.encode(value, writer);
+    const bytes = writer.toUint8Array();
+    expect(new BinaryReader(bytes).readU32()).toBe(2);
+    expect(roundTrip(codec, value)).toEqual(value);
+  });
+
+  test('rejects lengths above 32 MB', () => {
+    const writer = new BinaryWriter();
+    writer.writeU32(32 * 1024 * 1024 + 1);
+    const bytes = writer.toUint8Array();
+    expect(() => longVecCodec(u16Codec).decode(new BinaryReader(bytes))).toThrow(/too large/);
+    expect(() => longStringCodec.decode(new BinaryReader(bytes))).toThrow(/too large/);
+  });
+});
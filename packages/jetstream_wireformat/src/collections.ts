@@ -7,6 +7,9 @@
  * r[impl jetstream.wireformat.ts.map]
  * r[impl jetstream.wireformat.set]
  * r[impl jetstream.wireformat.ts.set]
+ * r[impl jetstream.wireformat.long]
+ * r[impl jetstream.wireformat.ts.long]
+ * r[impl jetstream.wireformat.ts.std]
  */
 
 import { BinaryReader } from './binary-reader.js';
@@ -48,6 +51,70 @@ export function vecCodec<T>(elementCodec: WireFormat<T>): WireFormat<T[]> {
   };
 }
 
+/**
+ * Creates a codec for a Rust `[T; N]`: exactly `length` elements, with no
+ * count prefix.
+ */
+export function arrayCodec<T>(elementCodec: WireFormat<T>, length: number): WireFormat<T[]> {
+  return {
+    byteSize(value: T[]): number {
+      let size = 0;
+      for (const elem of value) {
+        size += elementCodec.byteSize(elem);
+      }
+      return size;
+    },
+    encode(value: T[], writer: BinaryWriter): void {
+      if (value.length !== length) {
+        throw new Error(`array has ${value.length} elements, expected ${length}`);
+      }
+      for (const elem of value) {
+        elementCodec.encode(elem, writer);
+      }
+    },
+    decode(reader: BinaryReader): T[] {
+      const result: T[] = [];
+      for (let i = 0; i < length; i++) {
+        result.push(elementCodec.decode(reader));
+      }
+      return result;
+    },
+  };
+}
+
+/**
+ * Creates a Vec/Array codec with u32 count prefix.
+ * Counts above 32 MB are rejected on decode.
+ */
+export function longVecCodec<T>(elementCodec: WireFormat<T>): WireFormat<T[]> {
+  return {
+    byteSize(value: T[]): number {
+      let size = 4; // u32 count
+      for (const elem of value) {
+        size += elementCodec.byteSize(elem);
+      }
+      return size;
+    },
+    encode(value: T[], writer: BinaryWriter): void {
+      writer.writeU32(value.length);
+      for (const elem of value) {
+        elementCodec.encode(elem, writer);
+      }
+    },
+    decode(reader: BinaryReader): T[] {
+      const len = reader.readU32();
+      if (len > MAX_DATA_LENGTH) {
+        throw new Error(`vector length (${len}) is too large, max is ${MAX_DATA_LENGTH}`);
+      }
+      const result: T[] = [];
+      for (let i = 0; i < len; i++) {
+        result.push(elementCodec.decode(reader));
+      }
+      return result;
+    },
+  };
+}
+
 /**
  * Data codec: u32 length prefix followed by raw bytes.
  * Max 32 MB (33,554,432 bytes).
@@ -112,6 +179,122 @@ export function mapCodec<K, V>(
   };
 }
 
+/**
+ * Creates a Map codec with u16 count prefix for a Rust `IndexMap`.
+ * Entries are encoded in insertion order.
+ * Max 65,535 entries.
+ */
+export function indexMapCodec<K, V>(
+  keyCodec: WireFormat<K>,
+  valueCodec: WireFormat<V>,
+): WireFormat<Map<K, V>> {
+  return {
+    byteSize(value: Map<K, V>): number {
+      let size = 2; // u16 count
+      for (const [k, v] of value) {
+        size += keyCodec.byteSize(k) + valueCodec.byteSize(v);
+      }
+      return size;
+    },
+    encode(value: Map<K, V>, writer: BinaryWriter): void {
+      if (value.size > 0xFFFF) {
+        throw new Error(`map too large: ${value.size} exceeds u16 max (65535)`);
+      }
+      writer.writeU16(value.size);
+      for (const [k, v] of value) {
+        keyCodec.encode(k, writer);
+        valueCodec.encode(v, writer);
+      }
+    },
+    decode(reader: BinaryReader): Map<K, V> {
+      const len = reader.readU16();
+      const map = new Map<K, V>();
+      for (let i = 0; i < len; i++) {
+        const key = keyCodec.decode(reader);
+        const value = valueCodec.decode(reader);
+        map.set(key, value);
+      }
+      return map;
+    },
+  };
+}
+
+/**
+ * Creates a Set codec with u16 count prefix for a Rust `IndexSet`.
+ * Elements are encoded in insertion order.
+ * Max 65,535 elements.
+ */
+export function indexSetCodec<T>(elementCodec: WireFormat<T>): WireFormat<Set<T>> {
+  return {
+    byteSize(value: Set<T>): number {
+      let size = 2; // u16 count
+      for (const elem of value) {
+        size += elementCodec.byteSize(elem);
+      }
+      return size;
+    },
+    encode(value: Set<T>, writer: BinaryWriter): void {
+      if (value.size > 0xFFFF) {
+        throw new Error(`set too large: ${value.size} exceeds u16 max (65535)`);
+      }
+      writer.writeU16(value.size);
+      for (const elem of value) {
+        elementCodec.encode(elem, writer);
+      }
+    },
+    decode(reader: BinaryReader): Set<T> {
+      const len = reader.readU16();
+      const set = new Set<T>();
+      for (let i = 0; i < len; i++) {
+        set.add(elementCodec.decode(reader));
+      }
+      return set;
+    },
+  };
+}
+
+/**
+ * Creates a Map codec with u32 count prefix.
+ * Entries are encoded in sorted key order to match Rust BTreeMap.
+ * Counts above 32 MB are rejected on decode.
+ */
+export function longMapCodec<K, V>(
+  keyCodec: WireFormat<K>,
+  valueCodec: WireFormat<V>,
+  compareKeys: (a: K, b: K) => number,
+): WireFormat<Map<K, V>> {
+  return {
+    byteSize(value: Map<K, V>): number {
+      let size = 4; // u32 count
+      for (const [k, v] of value) {
+        size += keyCodec.byteSize(k) + valueCodec.byteSize(v);
+      }
+      return size;
+    },
+    encode(value: Map<K, V>, writer: BinaryWriter): void {
+      writer.writeU32(value.size);
+      const entries = [...value.entries()].sort((a, b) => compareKeys(a[0], b[0]));
+      for (const [k, v] of entries) {
+        keyCodec.encode(k, writer);
+        valueCodec.encode(v, writer);
+      }
+    },
+    decode(reader: BinaryReader): Map<K, V> {
+      const len = reader.readU32();
+      if (len > MAX_DATA_LENGTH) {
+        throw new Error(`map length (${len}) is too large, max is ${MAX_DATA_LENGTH}`);
+      }
+      const map = new Map<K, V>();
+      for (let i = 0; i < len; i++) {
+        const key = keyCodec.decode(reader);
+        const value = valueCodec.decode(reader);
+        map.set(key, value);
+      }
+      return map;
+    },
+  };
+}
+
 /**
  * Creates a Set codec with u16 count prefix.
  * Elements are encoded in sorted order to match Rust BTreeSet.
@@ -3,6 +3,7 @@
  * r[impl jetstream.wireformat.ts.struct]
  * r[impl jetstream.wireformat.enum]
  * r[impl jetstream.wireformat.ts.enum]
+ * r[impl jetstream.wireformat.ts.std]
  */
 
 import { BinaryReader } from './binary-reader.js';
@@ -96,3 +97,71 @@ export function enumCodec<T>(variants: VariantCodec<T>[]): WireFormat<T> {
     },
   };
 }
+
+/**
+ * A Rust `Result<T, E>`.
+ */
+export type Result<T, E> = { ok: true; value: T } | { ok: false; error: E };
+
+/**
+ * Creates a Result codec: a u8 tag, 0 for `Ok` and 1 for `Err`, followed by
+ * the value.
+ */
+export function resultCodec<T, E>(
+  okCodec: WireFormat<T>,
+  errCodec: WireFormat<E>,
+): WireFormat<Result<T, E>> {
+  return {
+    byteSize(value: Result<T, E>): number {
+      return 1 + (value.ok ? okCodec.byteSize(value.value) : errCodec.byteSize(value.error));
+    },
+    encode(value: Result<T, E>, writer: BinaryWriter): void {
+      if (value.ok) {
+        writer.writeU8(0);
+        okCodec.encode(value.value, writer);
+      } else {
+        writer.writeU8(1);
+        errCodec.encode(value.error, writer);
+      }
+    },
+    decode(reader: BinaryReader): Result<T, E> {
+      const tag = reader.readU8();
+      switch (tag) {
+        case 0:
+          return { ok: true, value: okCodec.decode(reader) };
+        case 1:
+          return { ok: false, error: errCodec.decode(reader) };
+        default:
+          throw new Error(`invalid Result tag: ${tag}`);
+      }
+    },
+  };
+}
+
+/**
+ * A Rust `Range<T>`.
+ */
+export interface Range<T> {
+  start: T;
+  end: T;
+}
+
+/**
+ * Creates a Range codec: the start followed by the end.
+ */
+export function rangeCodec<T>(codec: WireFormat<T>): WireFormat<Range<T>> {
+  return {
+    byteSize(value: Range<T>): number {
+      return codec.byteSize(value.start) + codec.byteSize(value.end);
+    },
+    encode(value: Range<T>, writer: BinaryWriter): void {
+      codec.encode(value.start, writer);
+      codec.encode(value.end, writer);
+    },
+    decode(reader: BinaryReader): Range<T> {
+      const start = codec.decode(reader);
+      const end = codec.decode(reader);
+      return { start, end };
+    },
+  };
+}
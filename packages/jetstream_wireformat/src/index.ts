@@ -21,11 +21,34 @@ export {
   boolCodec,
   unitCodec,
 } from './primitives.js';
-export { stringCodec } from './string.js';
-export { vecCodec, dataCodec, mapCodec, setCodec } from './collections.js';
+export { stringCodec, longStringCodec, charCodec } from './string.js';
+export {
+  vecCodec,
+  arrayCodec,
+  longVecCodec,
+  dataCodec,
+  mapCodec,
+  indexMapCodec,
+  indexSetCodec,
+  longMapCodec,
+  setCodec,
+} from './collections.js';
+export {
+  varintU16Codec,
+  varintU32Codec,
+  varintU64Codec,
+  varintU128Codec,
+  varintI16Codec,
+  varintI32Codec,
+  varintI64Codec,
+  varintI128Codec,
+  varintStringCodec,
+  varintVecCodec,
+  varintMapCodec,
+} from './varint.js';
 export { optionCodec } from './option.js';
-export { structCodec, enumCodec } from './composite.js';
-export type { FieldCodec, VariantCodec } from './composite.js';
+export { structCodec, enumCodec, resultCodec, rangeCodec } from './composite.js';
+export type { FieldCodec, VariantCodec, Result, Range } from './composite.js';
 export {
   ipv4Codec,
   ipv6Codec,
@@ -35,7 +58,9 @@ export {
   socketAddrCodec,
 } from './network.js';
 export type { IPv4, IPv6, IpAddr, SocketAddrV4, SocketAddrV6, SocketAddr } from './network.js';
-export { systemTimeCodec } from './time.js';
+export { systemTimeCodec, durationCodec } from './time.js';
+export type { Duration } from './time.js';
+export { uuidCodec } from './uuid.js';
 export {
   Level,
   levelCodec,
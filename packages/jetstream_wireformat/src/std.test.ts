@@ -0,0 +1,92 @@
+import { describe, test, expect } from 'vitest';
+import { BinaryReader } from './binary-reader.js';
+import { BinaryWriter } from './binary-writer.js';
+import { u8Codec, u16Codec, u32Codec } from './primitives.js';
+import { stringCodec, charCodec } from './string.js';
+import { arrayCodec, indexMapCodec, indexSetCodec } from './collections.js';
+import { resultCodec, rangeCodec } from './composite.js';
+import { durationCodec } from './time.js';
+import { uuidCodec } from './uuid.js';
+import type { WireFormat } from './primitives.js';
+
+function encode<T>(codec: WireFormat<T>, value: T): Uint8Array {
+  const writer = new BinaryWriter();
+  codec.encode(value, writer);
+  const bytes = writer.toUint8Array();
+  expect(bytes.byteLength).toBe(codec.byteSize(value));
+  return bytes;
+}
+
+function roundTrip<T>(codec: WireFormat<T>, value: T): T {
+  return codec.decode(new BinaryReader(encode(codec, value)));
+}
+
+/**
+ * r[verify jetstream.wireformat.ts.std]
+ * r[verify jetstream.wireformat.std]
+ */
+describe('std types', () => {
+  test('char is its code point as a u32', () => {
+    expect([...encode(charCodec, 'A')]).toEqual([0x41, 0, 0, 0]);
+    expect(roundTrip(charCodec, '😀')).toBe('😀');
+    expect(() => encode(charCodec, 'ab')).toThrow();
+    const surrogate = new Uint8Array([0x00, 0xd8, 0, 0]);
+    expect(() => charCodec.decode(new BinaryReader(surrogate))).toThrow();
+  });
+
+  test('arrays have no length prefix', () => {
+    const codec = arrayCodec(u16Codec, 2);
+    expect([...encode(codec, [1, 2])]).toEqual([1, 0, 2, 0]);
+    expect(roundTrip(codec, [7, 8])).toEqual([7, 8]);
+    expect(() => encode(codec, [1])).toThrow();
+  });
+
+  test('results are tagged 0 for ok and 1 for err', () => {
+    const codec = resultCodec(u8Codec, stringCodec);
+    expect([...encode(codec, { ok: true, value: 5 })]).toEqual([0, 5]);
+    expect(roundTrip(codec, { ok: false, error: 'nope' })).toEqual({ ok: false, error: 'nope' });
+    expect(() => codec.decode(new BinaryReader(new Uint8Array([2])))).toThrow();
+  });
+
+  test('ranges are start then end', () => {
+    const codec = rangeCodec(u32Codec);
+    expect([...encode(codec, { start: 1, end: 2 })]).toEqual([1, 0, 0, 0, 2, 0, 0, 0]);
+    expect(roundTrip(codec, { start: 3, end: 9 })).toEqual({ start: 3, end: 9 });
+  });
+
+  test('durations are u64 seconds and u32 nanoseconds', () => {
+    const bytes = encode(durationCodec, { secs: 1n, nanos: 2 });
+    expect([...bytes]).toEqual([1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0]);
+    expect(roundTrip(durationCodec, { secs: 30n, nanos: 500 })).toEqual({ secs: 30n, nanos: 500 });
+    expect(() => encode(durationCodec, { secs: 0n, nanos: 1_000_000_000 })).toThrow();
+  });
+
+  test('index maps keep insertion order', () => {
+    const codec = indexMapCodec(u8Codec, u8Codec);
+    const map = new Map([[3, 0], [1, 0], [2, 0]]);
+    expect([...encode(codec, map)]).toEqual([3, 0, 3, 0, 1, 0, 2, 0]);
+    expect([...roundTrip(codec, map).keys()]).toEqual([3, 1, 2]);
+  });
+
+  test('index sets keep insertion order', () => {
+    const codec = indexSetCodec(u8Codec);
+    const set = new Set([3, 1, 2]);
+    expect([...encode(codec, set)]).toEqual([3, 0, 3, 1, 2]);
+    expect([...roundTrip(codec, set)]).toEqual([3, 1, 2]);
+  });
+});
+
+/**
+ * r[verify jetstream.wireformat.ts.std]
+ * r[verify jetstream.wireformat.ecosystem]
+ */
+describe('uuid', () => {
+  test('is its 16 bytes', () => {
+    const id = '67e55044-10b1-426f-9247-bb680e5fe0c8';
+    const bytes = encode(uuidCodec, id);
+    expect(bytes[0]).toBe(0x67);
+    expect(bytes[15]).toBe(0xc8);
+    expect(roundTrip(uuidCodec, id)).toBe(id);
+    expect(() => encode(uuidCodec, 'not-a-uuid')).toThrow();
+  });
+});
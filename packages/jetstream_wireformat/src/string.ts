@@ -1,6 +1,9 @@
 /**
  * r[impl jetstream.wireformat.string]
  * r[impl jetstream.wireformat.ts.string]
+ * r[impl jetstream.wireformat.long]
+ * r[impl jetstream.wireformat.ts.long]
+ * r[impl jetstream.wireformat.ts.std]
  */
 
 import { BinaryReader } from './binary-reader.js';
@@ -10,6 +13,8 @@ import type { WireFormat } from './primitives.js';
 const textEncoder = new TextEncoder();
 const textDecoder = new TextDecoder('utf-8', { fatal: true });
 
+const MAX_LONG_LENGTH = 32 * 1024 * 1024; // 32 MB
+
 /**
  * String codec: u16 length prefix followed by UTF-8 bytes.
  * Maximum string length is 65,535 bytes (u16::MAX).
@@ -32,3 +37,50 @@ export const stringCodec: WireFormat<string> = {
     return textDecoder.decode(bytes);
   },
 };
+
+/**
+ * Long string codec: u32 length prefix followed by UTF-8 bytes.
+ * Lengths above 32 MB are rejected on decode.
+ */
+export const longStringCodec: WireFormat<string> = {
+  byteSize(value: string): number {
+    return 4 + textEncoder.encode(value).byteLength;
+  },
+  encode(value: string, writer: BinaryWriter): void {
+    const bytes = textEncoder.encode(value);
+    writer.writeU32(bytes.byteLength);
+    writer.writeBytes(bytes);
+  },
+  decode(reader: BinaryReader): string {
+    const len = reader.readU32();
+    if (len > MAX_LONG_LENGTH) {
+      throw new Error(`string length (${len}) is too large, max is ${MAX_LONG_LENGTH}`);
+    }
+    const bytes = reader.readBytes(len);
+    return textDecoder.decode(bytes);
+  },
+};
+
+/**
+ * Char codec: the Unicode scalar value as a u32.
+ * Represented as a string holding a single code point.
+ */
+export const charCodec: WireFormat<string> = {
+  byteSize(_value: string): number {
+    return 4;
+  },
+  encode(value: string, writer: BinaryWriter): void {
+    const codePoint = value.codePointAt(0);
+    if (codePoint === undefined || String.fromCodePoint(codePoint) !== value) {
+      throw new Error(`not a single char: ${JSON.stringify(value)}`);
+    }
+    writer.writeU32(codePoint);
+  },
+  decode(reader: BinaryReader): string {
+    const codePoint = reader.readU32();
+    if (codePoint > 0x10FFFF || (codePoint >= 0xD800 && codePoint <= 0xDFFF)) {
+      throw new Error(`invalid char: 0x${codePoint.toString(16)}`);
+    }
+    return String.fromCodePoint(codePoint);
+  },
+};
@@ -1,6 +1,7 @@
 /**
  * r[impl jetstream.wireformat.systime]
  * r[impl jetstream.wireformat.ts.systime]
+ * r[impl jetstream.wireformat.ts.std]
  */
 
 import { BinaryReader } from './binary-reader.js';
@@ -29,3 +30,35 @@ export const systemTimeCodec: WireFormat<Date> = {
     return new Date(millisNum);
   },
 };
+
+/**
+ * A Rust `Duration`: whole seconds and the nanoseconds within the second.
+ */
+export interface Duration {
+  secs: bigint;
+  nanos: number;
+}
+
+/**
+ * Duration codec: u64 seconds followed by u32 nanoseconds (LE).
+ */
+export const durationCodec: WireFormat<Duration> = {
+  byteSize(_value: Duration): number {
+    return 12;
+  },
+  encode(value: Duration, writer: BinaryWriter): void {
+    if (value.nanos < 0 || value.nanos >= 1_000_000_000) {
+      throw new Error(`invalid duration nanoseconds: ${value.nanos}`);
+    }
+    writer.writeU64(value.secs);
+    writer.writeU32(value.nanos);
+  },
+  decode(reader: BinaryReader): Duration {
+    const secs = reader.readU64();
+    const nanos = reader.readU32();
+    if (nanos >= 1_000_000_000) {
+      throw new Error(`invalid duration nanoseconds: ${nanos}`);
+    }
+    return { secs, nanos };
+  },
+};
@@ -0,0 +1,35 @@
+/**
+ * r[impl jetstream.wireformat.ecosystem]
+ * r[impl jetstream.wireformat.ts.std]
+ */
+
+import { BinaryReader } from './binary-reader.js';
+import { BinaryWriter } from './binary-writer.js';
+import type { WireFormat } from './primitives.js';
+
+const UUID_PATTERN = /^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$/i;
+
+/**
+ * Uuid codec: the 16 bytes of a Rust `uuid::Uuid`.
+ * Represented as its hyphenated lowercase string.
+ */
+export const uuidCodec: WireFormat<string> = {
+  byteSize(_value: string): number {
+    return 16;
+  },
+  encode(value: string, writer: BinaryWriter): void {
+    if (!UUID_PATTERN.test(value)) {
+      throw new Error(`invalid uuid: ${value}`);
+    }
+    const hex = value.replace(/-/g, '');
+    const bytes = new Uint8Array(16);
+    for (let i = 0; i < 16; i++) {
+      bytes[i] = parseInt(hex.slice(i * 2, i * 2 + 2), 16);
+    }
+    writer.writeBytes(bytes);
+  },
+  decode(reader: BinaryReader): string {
+    const hex = [...reader.readBytes(16)].map((b) => b.toString(16).padStart(2, '0')).join('');
+    return `${hex.slice(0, 8)}-${hex.slice(8, 12)}-${hex.slice(12, 16)}-${hex.slice(16, 20)}-${hex.slice(20)}`;
+  },
+};
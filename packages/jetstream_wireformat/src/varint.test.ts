@@ -0,0 +1,65 @@
+import { describe, test, expect } from 'vitest';
+import { BinaryReader } from './binary-reader.js';
+import { BinaryWriter } from './binary-writer.js';
+import { u8Codec, u32Codec } from './primitives.js';
+import { stringCodec } from './string.js';
+import {
+  varintU32Codec,
+  varintU64Codec,
+  varintU128Codec,
+  varintI32Codec,
+  varintI64Codec,
+  varintStringCodec,
+  varintVecCodec,
+  varintMapCodec,
+} from './varint.js';
+import type { WireFormat } from './primitives.js';
+
+function encode<T>(codec: WireFormat<T>, value: T): Uint8Array {
+  const writer = new BinaryWriter();
+  codec.encode(value, writer);
+  const bytes = writer.toUint8Array();
+  expect(bytes.byteLength).toBe(codec.byteSize(value));
+  return bytes;
+}
+
+function roundTrip<T>(codec: WireFormat<T>, value: T): T {
+  return codec.decode(new BinaryReader(encode(codec, value)));
+}
+
+/**
+ * r[verify jetstream.wireformat.ts.varint]
+ * r[verify jetstream.wireformat.varint]
+ */
+describe('varint', () => {
+  test('unsigned LEB128', () => {
+    expect(encode(varintU32Codec, 0)).toEqual(new Uint8Array([0x00]));
+    expect(encode(varintU32Codec, 127)).toEqual(new Uint8Array([0x7f]));
+    expect(encode(varintU32Codec, 300)).toEqual(new Uint8Array([0xac, 0x02]));
+    expect(encode(varintU64Codec, 0xffffffffffffffffn).byteLength).toBe(10);
+    expect(encode(varintU128Codec, (1n << 128n) - 1n).byteLength).toBe(19);
+  });
+
+  test('zigzag', () => {
+    expect(encode(varintI32Codec, 0)).toEqual(new Uint8Array([0x00]));
+    expect(encode(varintI32Codec, -1)).toEqual(new Uint8Array([0x01]));
+    expect(encode(varintI32Codec, 1)).toEqual(new Uint8Array([0x02]));
+    expect(encode(varintI32Codec, -2)).toEqual(new Uint8Array([0x03]));
+    expect(roundTrip(varintI32Codec, -2147483648)).toBe(-2147483648);
+    expect(roundTrip(varintI64Codec, -(1n << 63n))).toBe(-(1n << 63n));
+  });
+
+  test('rejects values that overflow', () => {
+    const reader = new BinaryReader(new Uint8Array([0x80, 0x80, 0x80, 0x80, 0x10]));
+    expect(() => varintU32Codec.decode(reader)).toThrow('varint overflows u32');
+  });
+
+  test('length prefixes', () => {
+    expect(encode(varintStringCodec, 'abc')).toEqual(new Uint8Array([0x03, 0x61, 0x62, 0x63]));
+    const vec = varintVecCodec(u8Codec);
+    expect(roundTrip(vec, Array.from({ length: 200 }, (_, i) => i))).toHaveLength(200);
+    const map = varintMapCodec(stringCodec, u32Codec, (a, b) => (a < b ? -1 : a > b ? 1 : 0));
+    const value = new Map([['b', 2], ['a', 1]]);
+    expect(roundTrip(map, value)).toEqual(value);
+  });
+});
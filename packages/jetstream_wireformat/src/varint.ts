@@ -0,0 +1,206 @@
+/**
+ * r[impl jetstream.wireformat.varint]
+ * r[impl jetstream.wireformat.ts.varint]
+ *
+ * Variable-length integers: unsigned LEB128, with signed integers zigzag
+ * encoded first. Strings, arrays and maps marked `#[jetstream(len = varint)]`
+ * prefix their length with a varint.
+ */
+
+import { BinaryReader } from './binary-reader.js';
+import { BinaryWriter } from './binary-writer.js';
+import type { WireFormat } from './primitives.js';
+
+const textEncoder = new TextEncoder();
+const textDecoder = new TextDecoder('utf-8', { fatal: true });
+
+const MAX_DATA_LENGTH = 32 * 1024 * 1024; // 32 MB
+
+function varintByteSize(value: bigint): number {
+  let size = 1;
+  while (value >= 0x80n) {
+    value >>= 7n;
+    size++;
+  }
+  return size;
+}
+
+function writeVarint(value: bigint, writer: BinaryWriter): void {
+  while (value >= 0x80n) {
+    writer.writeU8(Number(value & 0x7fn) | 0x80);
+    value >>= 7n;
+  }
+  writer.writeU8(Number(value));
+}
+
+function readVarint(reader: BinaryReader, bits: number, name: string): bigint {
+  let value = 0n;
+  let shift = 0n;
+  for (;;) {
+    const byte = reader.readU8();
+    value |= BigInt(byte & 0x7f) << shift;
+    if (value >> BigInt(bits) !== 0n) {
+      throw new Error(`varint overflows ${name}`);
+    }
+    if ((byte & 0x80) === 0) {
+      return value;
+    }
+    shift += 7n;
+    if (shift >= BigInt(bits)) {
+      throw new Error(`varint overflows ${name}`);
+    }
+  }
+}
+
+function zigzag(value: bigint, bits: number): bigint {
+  return BigInt.asUintN(bits, (value << 1n) ^ (value >> BigInt(bits - 1)));
+}
+
+function unzigzag(value: bigint): bigint {
+  return (value >> 1n) ^ -(value & 1n);
+}
+
+function unsignedBigintCodec(bits: number, name: string): WireFormat<bigint> {
+  return {
+    byteSize(value: bigint): number {
+      return varintByteSize(value);
+    },
+    encode(value: bigint, writer: BinaryWriter): void {
+      writeVarint(value, writer);
+    },
+    decode(reader: BinaryReader): bigint {
+      return readVarint(reader, bits, name);
+    },
+  };
+}
+
+function signedBigintCodec(bits: number, name: string): WireFormat<bigint> {
+  return {
+    byteSize(value: bigint): number {
+      return varintByteSize(zigzag(value, bits));
+    },
+    encode(value: bigint, writer: BinaryWriter): void {
+      writeVarint(zigzag(value, bits), writer);
+    },
+    decode(reader: BinaryReader): bigint {
+      return unzigzag(readVarint(reader, bits, name));
+    },
+  };
+}
+
+function numberCodec(codec: WireFormat<bigint>): WireFormat<number> {
+  return {
+    byteSize(value: number): number {
+      return codec.byteSize(BigInt(value));
+    },
+    encode(value: number, writer: BinaryWriter): void {
+      codec.encode(BigInt(value), writer);
+    },
+    decode(reader: BinaryReader): number {
+      return Number(codec.decode(reader));
+    },
+  };
+}
+
+export const varintU16Codec: WireFormat<number> = numberCodec(unsignedBigintCodec(16, 'u16'));
+export const varintU32Codec: WireFormat<number> = numberCodec(unsignedBigintCodec(32, 'u32'));
+export const varintU64Codec: WireFormat<bigint> = unsignedBigintCodec(64, 'u64');
+export const varintU128Codec: WireFormat<bigint> = unsignedBigintCodec(128, 'u128');
+export const varintI16Codec: WireFormat<number> = numberCodec(signedBigintCodec(16, 'i16'));
+export const varintI32Codec: WireFormat<number> = numberCodec(signedBigintCodec(32, 'i32'));
+export const varintI64Codec: WireFormat<bigint> = signedBigintCodec(64, 'i64');
+export const varintI128Codec: WireFormat<bigint> = signedBigintCodec(128, 'i128');
+
+function decodeVarintLength(reader: BinaryReader, kind: string): number {
+  const len = varintU64Codec.decode(reader);
+  if (len > BigInt(MAX_DATA_LENGTH)) {
+    throw new Error(`${kind} length (${len}) is too large, max is ${MAX_DATA_LENGTH}`);
+  }
+  return Number(len);
+}
+
+/**
+ * Varint string codec: varint length prefix followed by UTF-8 bytes.
+ * Lengths above 32 MB are rejected on decode.
+ */
+export const varintStringCodec: WireFormat<string> = {
+  byteSize(value: string): number {
+    const len = textEncoder.encode(value).byteLength;
+    return varintU32Codec.byteSize(len) + len;
+  },
+  encode(value: string, writer: BinaryWriter): void {
+    const bytes = textEncoder.encode(value);
+    varintU32Codec.encode(bytes.byteLength, writer);
+    writer.writeBytes(bytes);
+  },
+  decode(reader: BinaryReader): string {
+    const len = decodeVarintLength(reader, 'string');
+    return textDecoder.decode(reader.readBytes(len));
+  },
+};
+
+/**
+ * Varint vector codec: varint count prefix followed by elements.
+ */
+export function varintVecCodec<T>(elementCodec: WireFormat<T>): WireFormat<T[]> {
+  return {
+    byteSize(value: T[]): number {
+      let size = varintU32Codec.byteSize(value.length);
+      for (const elem of value) {
+        size += elementCodec.byteSize(elem);
+      }
+      return size;
+    },
+    encode(value: T[], writer: BinaryWriter): void {
+      varintU32Codec.encode(value.length, writer);
+      for (const elem of value) {
+        elementCodec.encode(elem, writer);
+      }
+    },
+    decode(reader: BinaryReader): T[] {
+      const len = decodeVarintLength(reader, 'vector');
+      const result: T[] = [];
+      for (let i = 0; i < len; i++) {
+        result.push(elementCodec.decode(reader));
+      }
+      return result;
+    },
+  };
+}
+
+/**
+ * Varint map codec: varint count prefix followed by entries sorted by key.
+ */
+export function varintMapCodec<K, V>(
+  keyCodec: WireFormat<K>,
+  valueCodec: WireFormat<V>,
+  compareKeys: (a: K, b: K) => number,
+): WireFormat<Map<K, V>> {
+  return {
+    byteSize(value: Map<K, V>): number {
+      let size = varintU32Codec.byteSize(value.size);
+      for (const [k, v] of value) {
+        size += keyCodec.byteSize(k) + valueCodec.byteSize(v);
+      }
+      return size;
+    },
+    encode(value: Map<K, V>, writer: BinaryWriter): void {
+      varintU32Codec.encode(value.size, writer);
+      const entries = [...value.entries()].sort((a, b) => compareKeys(a[0], b[0]));
+      for (const [k, v] of entries) {
+        keyCodec.encode(k, writer);
+        valueCodec.encode(v, writer);
+      }
+    },
+    decode(reader: BinaryReader): Map<K, V> {
+      const len = decodeVarintLength(reader, 'map');
+      const map = new Map<K, V>();
+      for (let i = 0; i < len; i++) {
+        const key = keyCodec.decode(reader);
+        const value = valueCodec.decode(reader);
+        map.set(key, value);
+      }
+      return map;
+    },
+  };
+}
@@ -24,14 +24,17 @@ pub mod prelude {
     pub use jetstream_macros::{service, JetStreamWireFormat};
     pub use jetstream_rpc::{
         client, client::ClientTransport, context::Context, server,
-        server::Server, Error, Frame, Framed, Framer, Message, Mux, Protocol,
-        RpcCall, Rversion, TagPool, Tversion, Version, RJETSTREAMERROR,
-        RVERSION, TVERSION,
+        server::Server, Capabilities, Error, Frame, Framed, Framer, Message,
+        Mux, Protocol, RetryPolicy, RpcCall, Rversion, TagPool, TraceContext,
+        Tversion, Version, NOTAG, REXTENDED, RJETSTREAMERROR, RVERSION,
+        TEXTENDED, TTRACE, TVERSION,
     };
-    pub use jetstream_wireformat::{Data, WireFormat};
+    pub use jetstream_wireformat::{Data, Describe, WireFormat};
     pub use lazy_static::*;
     pub use trait_variant::make;
 
+    #[cfg(feature = "serde")]
+    pub use jetstream_wireformat::Serde;
     #[cfg(feature = "tracing")]
     pub extern crate tracing;
     #[cfg(feature = "tracing")]
@@ -52,6 +55,18 @@ pub mod quic {
     pub use jetstream_quic::*;
 }
 
+#[cfg(feature = "noise")]
+pub mod noise {
+    extern crate jetstream_noise;
+    pub use jetstream_noise::*;
+}
+
+#[cfg(feature = "pubsub")]
+pub mod pubsub {
+    extern crate jetstream_pubsub;
+    pub use jetstream_pubsub::*;
+}
+
 #[cfg(feature = "http")]
 pub mod http {
     extern crate jetstream_http;
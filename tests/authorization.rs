@@ -0,0 +1,91 @@
+use jetstream::prelude::*;
+use jetstream_rpc::{
+    auth::{Policy, Principal, PERMISSION_DENIED},
+    client::ClientCodec,
+    Router,
+};
+use vault_protocol::{Rmessage, Tmessage, Twipe, VaultChannel, VaultService};
+
+#[service]
+pub trait Vault {
+    async fn read(&mut self) -> Result<String>;
+    #[authorize("admin")]
+    async fn wipe(&mut self) -> Result<()>;
+}
+
+#[derive(Clone)]
+struct VaultImpl;
+
+impl Vault for VaultImpl {
+    async fn read(&mut self) -> Result<String> {
+        Ok("secret".to_string())
+    }
+
+    async fn wipe(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn connect(router: Router) -> VaultChannel {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+            .expect("accept failed");
+    });
+    let framed = Framed::new(client, ClientCodec::<VaultChannel>::default());
+    VaultChannel::new(10, Box::new(framed))
+}
+
+/// r[verify jetstream.auth.router]
+/// r[verify jetstream.auth.policy]
+#[tokio::test]
+async fn router_enforces_policy() {
+    let router = Router::new()
+        .with_handler(
+            vault_protocol::PROTOCOL_NAME,
+            VaultService { inner: VaultImpl },
+        )
+        .with_authorizer(Policy::new().allow(
+            Principal::Any,
+            vault_protocol::PROTOCOL_NAME,
+            "read",
+        ));
+    let mut chan = connect(router);
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(chan.read().await.unwrap(), "secret");
+    let err = chan.wipe().await.unwrap_err();
+    assert_eq!(err.code(), Some(PERMISSION_DENIED));
+}
+
+/// r[verify jetstream.auth.enforce]
+/// r[verify jetstream.auth.macro]
+#[tokio::test]
+async fn annotated_methods_fail_closed() {
+    let mut serv = VaultService { inner: VaultImpl };
+    let frame = Frame {
+        tag: 1,
+        msg: Tmessage::Wipe(Twipe {}),
+    };
+    let rframe = serv.rpc(Context::default(), frame).await.unwrap();
+    match rframe.msg {
+        Rmessage::Error(err) => assert_eq!(err.code(), Some(PERMISSION_DENIED)),
+        other => panic!("expected permission denied, got {:?}", other),
+    }
+
+    // An authorizer that grants the admin role lets the call through.
+    let ctx = Context::default().with_authorizer(
+        |_: &Context, request: &jetstream_rpc::auth::AuthRequest<'_>| {
+            request.roles.unwrap_or_default().contains(&"admin")
+        },
+    );
+    let frame = Frame {
+        tag: 2,
+        msg: Tmessage::Wipe(Twipe {}),
+    };
+    let rframe = serv.rpc(ctx, frame).await.unwrap();
+    assert!(matches!(rframe.msg, Rmessage::Wipe(_)));
+}
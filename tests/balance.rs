@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{
+    balance::{Balancer, Strategy},
+    client::ClientCodec,
+    retry::UNAVAILABLE,
+    Router,
+};
+use replica_protocol::{ReplicaChannel, ReplicaService};
+use tokio::sync::Notify;
+
+#[service]
+pub trait Replica {
+    async fn whoami(&mut self) -> Result<String>;
+    async fn hold(&mut self) -> Result<()>;
+}
+
+#[derive(Clone)]
+struct ReplicaImpl {
+    name: &'static str,
+    calls: Arc<AtomicU32>,
+    down: Arc<AtomicBool>,
+    gate: Arc<Notify>,
+}
+
+impl ReplicaImpl {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            calls: Arc::new(AtomicU32::new(0)),
+            down: Arc::new(AtomicBool::new(false)),
+            gate: Arc::new(Notify::new()),
+        }
+    }
+}
+
+impl Replica for ReplicaImpl {
+    async fn whoami(&mut self) -> Result<String> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err(Error::with_code("draining", UNAVAILABLE));
+        }
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(self.name.to_string())
+    }
+
+    async fn hold(&mut self) -> Result<()> {
+        self.gate.notified().await;
+        Ok(())
+    }
+}
+
+async fn connect(replica: ReplicaImpl) -> Result<ReplicaChannel> {
+    let router = Router::new().with_handler(
+        replica_protocol::PROTOCOL_NAME,
+        ReplicaService { inner: replica },
+    );
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        let _ = router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await;
+    });
+    let framed = Framed::new(client, ClientCodec::<ReplicaChannel>::default());
+    let chan = ReplicaChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await?;
+    Ok(chan)
+}
+
+fn replicas() -> HashMap<&'static str, ReplicaImpl> {
+    ["a", "b"]
+        .into_iter()
+        .map(|name| (name, ReplicaImpl::new(name)))
+        .collect()
+}
+
+async fn balancer(
+    replicas: &HashMap<&'static str, ReplicaImpl>,
+    strategy: Strategy,
+) -> Balancer<ReplicaChannel, &'static str> {
+    let replicas = replicas.clone();
+    Balancer::builder(vec!["a", "b"], move |endpoint: &'static str| {
+        connect(replicas[endpoint].clone())
+    })
+    .strategy(strategy)
+    .max_failures(1)
+    .health_check(|mut chan: ReplicaChannel| async move {
+        chan.whoami().await?;
+        Ok(chan)
+    })
+    .build()
+    .await
+    .unwrap()
+}
+
+/// r[verify jetstream.balance.balancer]
+/// r[verify jetstream.balance.service]
+/// r[verify jetstream.balance.strategy]
+#[tokio::test]
+async fn round_robin_spreads_calls() {
+    let replicas = replicas();
+    let mut balancer = balancer(&replicas, Strategy::RoundRobin).await;
+    for _ in 0..10 {
+        balancer.whoami().await.unwrap();
+    }
+    // The health check made one call on each replica.
+    assert_eq!(replicas["a"].calls.load(Ordering::SeqCst), 6);
+    assert_eq!(replicas["b"].calls.load(Ordering::SeqCst), 6);
+}
+
+/// r[verify jetstream.balance.strategy]
+#[tokio::test]
+async fn least_outstanding_avoids_busy_endpoints() {
+    let replicas = replicas();
+    let balancer = balancer(&replicas, Strategy::LeastOutstanding).await;
+
+    let mut holder = balancer.clone();
+    let held = tokio::spawn(async move { holder.hold().await });
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+    let mut balancer = balancer;
+    let mut seen = Vec::new();
+    for _ in 0..4 {
+        seen.push(balancer.whoami().await.unwrap());
+    }
+    seen.dedup();
+    assert_eq!(seen.len(), 1, "all calls should avoid the busy replica");
+    let busy = if seen[0] == "a" { "b" } else { "a" };
+
+    replicas[busy].gate.notify_one();
+    held.await.unwrap().unwrap();
+}
+
+/// r[verify jetstream.balance.eject]
+/// r[verify jetstream.balance.refresh]
+#[tokio::test]
+async fn failing_endpoints_are_ejected_and_readded() {
+    let replicas = replicas();
+    let mut balancer = balancer(&replicas, Strategy::RoundRobin).await;
+
+    replicas["b"].down.store(true, Ordering::SeqCst);
+    let mut failures = 0;
+    for _ in 0..4 {
+        if balancer.whoami().await.is_err() {
+            failures += 1;
+        }
+    }
+    assert_eq!(failures, 1);
+    assert_eq!(balancer.endpoints(), vec![("a", true), ("b", false)]);
+
+    // Still down: the health check keeps it out.
+    balancer.refresh().await.unwrap();
+    assert_eq!(balancer.endpoints(), vec![("a", true), ("b", false)]);
+
+    replicas["b"].down.store(false, Ordering::SeqCst);
+    balancer.refresh().await.unwrap();
+    assert_eq!(balancer.endpoints(), vec![("a", true), ("b", true)]);
+}
+
+#[tokio::test]
+async fn no_healthy_endpoints_is_unavailable() {
+    let mut balancer =
+        Balancer::builder(vec!["nowhere"], |_: &'static str| async {
+            Err::<ReplicaChannel, _>(Error::new("refused"))
+        })
+        .build()
+        .await
+        .unwrap();
+    let err = balancer.whoami().await.unwrap_err();
+    assert_eq!(err.code(), Some(UNAVAILABLE));
+}
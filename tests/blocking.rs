@@ -0,0 +1,162 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{
+    blocking::{Blocking, BlockingRuntime},
+    client::ClientCodec,
+    retry::DEADLINE_EXCEEDED,
+    Router,
+};
+
+#[service(blocking)]
+pub trait Counter {
+    async fn add(&mut self, amount: u64) -> Result<u64>;
+    async fn protocol(&mut self, ctx: Context) -> Result<String>;
+    async fn sleep(&mut self, millis: u64) -> Result<()>;
+}
+
+use counter_protocol::{
+    BlockingCounter, CounterBlockingChannel, CounterChannel, CounterService,
+};
+
+/// A synchronous backend: no `async` anywhere.
+#[derive(Default)]
+struct Tally(u64);
+
+impl BlockingCounter for Tally {
+    fn add(&mut self, amount: u64) -> Result<u64> {
+        self.0 += amount;
+        Ok(self.0)
+    }
+
+    fn protocol(&mut self, ctx: Context) -> Result<String> {
+        Ok(ctx.protocol().unwrap_or("none").to_string())
+    }
+
+    fn sleep(&mut self, millis: u64) -> Result<()> {
+        std::thread::sleep(Duration::from_millis(millis));
+        Ok(())
+    }
+}
+
+fn connect(runtime: &BlockingRuntime) -> CounterChannel {
+    runtime.block_on(async {
+        let router = Router::new().with_handler(
+            counter_protocol::PROTOCOL_NAME,
+            CounterService {
+                inner: Blocking::new(Tally::default()),
+            },
+        );
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(server);
+            router
+                .accept(Context::default(), Box::new(reader), Box::new(writer))
+                .await
+        });
+        let framed =
+            Framed::new(client, ClientCodec::<CounterChannel>::default());
+        CounterChannel::new(10, Box::new(framed))
+    })
+}
+
+/// r[verify jetstream.blocking.macro]
+/// r[verify jetstream.blocking.runtime]
+/// r[verify jetstream.blocking.server]
+#[test]
+fn blocking_client_calls_blocking_server() {
+    let runtime = BlockingRuntime::new().unwrap();
+    let chan = connect(&runtime);
+    let mut counter = CounterBlockingChannel::new(runtime, chan);
+    counter.negotiate_version(8192).unwrap();
+
+    assert_eq!(counter.add(2).unwrap(), 2);
+    assert_eq!(counter.add(3).unwrap(), 5);
+    assert_eq!(
+        counter.protocol(Context::default()).unwrap(),
+        counter_protocol::PROTOCOL_NAME
+    );
+}
+
+/// r[verify jetstream.blocking.runtime]
+#[test]
+fn blocking_client_borrows_a_runtime_handle() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let borrowed = BlockingRuntime::from(runtime.handle().clone());
+    let chan = connect(&borrowed);
+    let mut counter = CounterBlockingChannel::new(borrowed, chan);
+    counter.negotiate_version(8192).unwrap();
+
+    assert_eq!(counter.add(7).unwrap(), 7);
+}
+
+/// r[verify jetstream.blocking.timeout]
+#[test]
+fn blocking_calls_time_out() {
+    let runtime = BlockingRuntime::new().unwrap();
+    let chan = connect(&runtime);
+    let mut counter = CounterBlockingChannel::new(runtime, chan)
+        .with_timeout(Duration::from_millis(50));
+    counter.negotiate_version(8192).unwrap();
+
+    let err = counter.sleep(1000).unwrap_err();
+    assert_eq!(err.code(), Some(DEADLINE_EXCEEDED));
+}
+
+/// r[verify jetstream.blocking.runtime]
+#[test]
+#[should_panic(expected = "multi-threaded runtime")]
+fn blocking_runtime_refuses_current_thread_handles() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let _ = BlockingRuntime::from(runtime.handle().clone());
+}
+
+/// Counts the calls inside [`Blocking::run`] at once, keeping the peak.
+#[derive(Default)]
+struct Overlap {
+    inside: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl Overlap {
+    /// Stay inside the call until `target` calls overlapped, or a second
+    /// passed.
+    fn enter(&self, target: usize) {
+        let now = self.inside.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while self.peak.load(Ordering::SeqCst) < target
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        self.inside.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// r[verify jetstream.blocking.server]
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_pools_serve_a_call_per_instance() {
+    let overlap = Arc::new(Overlap::default());
+    let pool = Blocking::pool([Tally::default(), Tally::default()]);
+    let (a, b) = (overlap.clone(), overlap.clone());
+    tokio::join!(pool.run(move |_| a.enter(2)), pool.run(move |_| b.enter(2)));
+    assert_eq!(overlap.peak.load(Ordering::SeqCst), 2);
+
+    let overlap = Arc::new(Overlap::default());
+    let single = Blocking::new(Tally::default());
+    let (a, b) = (overlap.clone(), overlap.clone());
+    tokio::join!(
+        single.run(move |_| a.enter(2)),
+        single.run(move |_| b.enter(2))
+    );
+    assert_eq!(overlap.peak.load(Ordering::SeqCst), 1);
+}
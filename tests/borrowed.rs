@@ -0,0 +1,348 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, Router};
+
+#[derive(Debug, Clone, PartialEq, JetStreamWireFormat)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[service(mock, blocking, uses(super::Point))]
+pub trait Store {
+    async fn put(&mut self, key: &str, value: &[u8]) -> Result<u32>;
+    #[idempotent]
+    async fn get(&mut self, key: &str) -> Result<Vec<u8>>;
+    #[oneway]
+    async fn touch(&mut self, key: &str) -> Result<()>;
+    async fn mark(
+        &mut self,
+        ctx: Context,
+        at: &Point,
+        labels: &[String],
+        weight: u8,
+    ) -> Result<String>;
+}
+
+// A view of `store_protocol::Tput` that borrows the key and value from the
+// frame.
+#[derive(Debug, JetStreamWireFormat)]
+pub struct PutView<'a> {
+    key: &'a str,
+    value: &'a [u8],
+}
+
+#[service(extended_ids)]
+pub trait Log {
+    async fn append(&mut self, line: &str) -> Result<u64>;
+}
+
+#[derive(Clone, Default)]
+struct MemoryStore {
+    values: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl Store for MemoryStore {
+    async fn put(&mut self, key: &str, value: &[u8]) -> Result<u32> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_vec());
+        Ok(value.len() as u32)
+    }
+
+    async fn get(&mut self, key: &str) -> Result<Vec<u8>> {
+        self.values
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| Error::new(format!("no {key}")))
+    }
+
+    async fn touch(&mut self, key: &str) -> Result<()> {
+        self.values
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default();
+        Ok(())
+    }
+
+    async fn mark(
+        &mut self,
+        _ctx: Context,
+        at: &Point,
+        labels: &[String],
+        weight: u8,
+    ) -> Result<String> {
+        Ok(format!(
+            "{},{} {} x{}",
+            at.x,
+            at.y,
+            labels.join("+"),
+            weight
+        ))
+    }
+}
+
+#[derive(Clone, Default)]
+struct Lines(Arc<Mutex<Vec<String>>>);
+
+impl Log for Lines {
+    async fn append(&mut self, line: &str) -> Result<u64> {
+        let mut lines = self.0.lock().unwrap();
+        lines.push(line.to_string());
+        Ok(lines.len() as u64)
+    }
+}
+
+fn serve(router: Router) -> tokio::io::DuplexStream {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    client
+}
+
+/// r[verify jetstream.service.borrowed-args]
+/// r[verify jetstream.service.borrowed-args.client]
+/// r[verify jetstream.service.borrowed-args.server]
+#[tokio::test]
+async fn borrowed_arguments_round_trip() {
+    use store_protocol::{StoreChannel, StoreService};
+
+    let store = MemoryStore::default();
+    let client = serve(Router::new().with_handler(
+        store_protocol::PROTOCOL_NAME,
+        StoreService {
+            inner: store.clone(),
+        },
+    ));
+    let framed = Framed::new(client, ClientCodec::<StoreChannel>::default());
+    let mut chan = StoreChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    let buffer = vec![7u8; 4096];
+    assert_eq!(chan.put("blob", &buffer).await.unwrap(), 4096);
+    assert_eq!(chan.get("blob").await.unwrap(), buffer);
+    assert!(chan.get("missing").await.is_err());
+
+    chan.touch("empty").await.unwrap();
+    assert_eq!(chan.get("empty").await.unwrap(), Vec::<u8>::new());
+
+    let labels = ["a".to_string(), "b".to_string()];
+    let marked = chan
+        .mark(Context::default(), &Point { x: 1, y: -2 }, &labels, 3)
+        .await
+        .unwrap();
+    assert_eq!(marked, "1,-2 a+b x3");
+    assert_eq!(store.values.lock().unwrap().len(), 2);
+}
+
+/// r[verify jetstream.service.borrowed-args.client]
+#[tokio::test]
+async fn borrowed_arguments_with_extended_ids() {
+    use log_protocol::{LogChannel, LogService};
+
+    let lines = Lines::default();
+    let client = serve(Router::new().with_handler(
+        log_protocol::PROTOCOL_NAME,
+        LogService {
+            inner: lines.clone(),
+        },
+    ));
+    let framed = Framed::new(client, ClientCodec::<LogChannel>::default());
+    let mut chan = LogChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(chan.append("one").await.unwrap(), 1);
+    assert_eq!(chan.append("two").await.unwrap(), 2);
+    assert_eq!(*lines.0.lock().unwrap(), ["one", "two"]);
+}
+
+/// r[verify jetstream.service.borrowed-args.client]
+#[test]
+fn encoded_requests_match_owned_ones() {
+    use jetstream_rpc::Encoded;
+    use jetstream_wireformat::BorrowedWireFormat;
+    use store_protocol::{Tmessage, Tput, TPUT};
+
+    let owned = Tmessage::Put(Tput {
+        key: "k".to_string(),
+        value: vec![1, 2, 3],
+    });
+    let value: &[u8] = &[1, 2, 3];
+    let encoded = Tmessage::Encoded(
+        Encoded::new(TPUT, 0, |writer| {
+            "k".encode_borrowed(writer)?;
+            value.encode_borrowed(writer)
+        })
+        .unwrap(),
+    );
+
+    let mut expected = Vec::new();
+    Framer::encode(&owned, &mut expected).unwrap();
+    let mut actual = Vec::new();
+    Framer::encode(&encoded, &mut actual).unwrap();
+    assert_eq!(actual, expected);
+    assert_eq!(Framer::byte_size(&encoded), Framer::byte_size(&owned));
+    assert_eq!(encoded.message_type(), owned.message_type());
+}
+
+/// r[verify jetstream.service.borrowed-args]
+#[tokio::test]
+async fn mocks_see_borrowed_arguments_owned() {
+    let mock = store_protocol::MockStore::new();
+    mock.expect_put()
+        .with_args(("k".to_string(), vec![1, 2]))
+        .returns(2);
+
+    let mut store = mock.clone();
+    assert_eq!(store.put("k", &[1, 2]).await.unwrap(), 2);
+    mock.verify();
+}
+
+/// r[verify jetstream.rpc.raw-frame]
+/// r[verify jetstream.wireformat.borrowed]
+#[tokio::test]
+async fn raw_frames_hand_handlers_borrowed_views() {
+    use futures::{SinkExt, StreamExt};
+    use jetstream_rpc::server::RawCodec;
+    use store_protocol::{
+        Rmessage, Rput, StoreChannel, StoreService, Tmessage, Tput, TPUT,
+    };
+
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut client =
+        Framed::new(client, ClientCodec::<StoreChannel>::default());
+    let mut server =
+        Framed::new(server, RawCodec::<StoreService<MemoryStore>>::default());
+
+    let value = vec![7u8; 4096];
+    client
+        .send(Frame {
+            tag: 1,
+            msg: Tmessage::Put(Tput {
+                key: "blob".to_string(),
+                value: value.clone(),
+            }),
+        })
+        .await
+        .unwrap();
+
+    let frame = server.next().await.unwrap().unwrap();
+    assert_eq!(frame.message_type(), TPUT);
+    let view: PutView = frame.view().unwrap();
+    assert_eq!(view.key, "blob");
+    assert_eq!(view.value, value.as_slice());
+    assert!(frame
+        .message()
+        .as_ptr_range()
+        .contains(&view.value.as_ptr()));
+    let size = view.value.len() as u32;
+
+    server
+        .send(Frame {
+            tag: frame.tag,
+            msg: Rmessage::Put(Rput(size)),
+        })
+        .await
+        .unwrap();
+    match client.next().await.unwrap().unwrap() {
+        Frame {
+            tag: 1,
+            msg: Rmessage::Put(Rput(4096)),
+        } => {}
+        other => panic!("unexpected frame {other:?}"),
+    }
+}
+
+/// r[verify jetstream.rpc.raw-frame]
+#[tokio::test]
+async fn raw_frames_unwrap_envelopes() {
+    use futures::{SinkExt, StreamExt};
+    use jetstream_rpc::{server::RawCodec, TEXTENDED};
+    use log_protocol::{LogChannel, LogService, Tappend, TAPPEND};
+    use store_protocol::{StoreChannel, StoreService, Tmessage, Tput, TPUT};
+
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut client =
+        Framed::new(client, ClientCodec::<StoreChannel>::default());
+    let mut server =
+        Framed::new(server, RawCodec::<StoreService<MemoryStore>>::default());
+    let trace = TraceContext::new([0x4b; 16], [0xf0; 8], 1, "");
+    let put = Tmessage::Put(Tput {
+        key: "blob".to_string(),
+        value: vec![7; 16],
+    });
+    client
+        .send(Frame {
+            tag: 1,
+            msg: Tmessage::Traced(trace.clone(), Box::new(put)),
+        })
+        .await
+        .unwrap();
+
+    let frame = server.next().await.unwrap().unwrap();
+    assert_eq!(frame.message_type(), TTRACE);
+    assert_eq!(frame.message_id(), TPUT as u16);
+    assert_eq!(frame.trace(), Some(&trace));
+    let view: PutView = frame.view().unwrap();
+    assert_eq!((view.key, view.value), ("blob", &[7u8; 16][..]));
+    assert!(matches!(
+        frame.decode::<Tmessage>().unwrap().msg,
+        Tmessage::Traced(..)
+    ));
+
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut client = Framed::new(client, ClientCodec::<LogChannel>::default());
+    let mut server =
+        Framed::new(server, RawCodec::<LogService<Lines>>::default());
+    client
+        .send(Frame {
+            tag: 2,
+            msg: log_protocol::Tmessage::Append(Tappend {
+                line: "one".to_string(),
+            }),
+        })
+        .await
+        .unwrap();
+
+    let frame = server.next().await.unwrap().unwrap();
+    assert_eq!(frame.message_type(), TEXTENDED);
+    assert_eq!(frame.message_id(), TAPPEND);
+    assert_eq!(frame.trace(), None);
+    assert_eq!(frame.view::<&str>().unwrap(), "one");
+}
+
+/// r[verify jetstream.service.borrowed-args]
+#[test]
+fn borrowed_slices_keep_the_u16_length() {
+    use jetstream_rpc::Encoded;
+    use jetstream_wireformat::BorrowedWireFormat;
+    use store_protocol::TPUT;
+
+    let value = vec![0u8; u16::MAX as usize + 1];
+    let err = Encoded::new(TPUT, 0, |writer| {
+        "k".encode_borrowed(writer)?;
+        value[..].encode_borrowed(writer)
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    let encoded = Encoded::new(TPUT, 0, |writer| {
+        "k".encode_borrowed(writer)?;
+        value[..u16::MAX as usize].encode_borrowed(writer)
+    })
+    .unwrap();
+    let clone = encoded.clone();
+    assert_eq!(clone.as_bytes().as_ptr(), encoded.as_bytes().as_ptr());
+}
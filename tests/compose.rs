@@ -0,0 +1,139 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, Protocol, Router};
+
+#[service]
+pub trait Health {
+    #[idempotent]
+    async fn status(&mut self) -> Result<String>;
+}
+
+#[service]
+pub trait Echo {
+    async fn echo(&mut self, message: String) -> Result<String>;
+}
+
+#[service]
+pub trait Admin: Health + Echo {
+    async fn restarts(&mut self) -> Result<u64>;
+    async fn restart(&mut self) -> Result<()>;
+}
+
+#[service]
+pub trait Ops: Admin + Send + Sync {
+    async fn drain(&mut self, seconds: u32) -> Result<String>;
+}
+
+use admin_protocol::{AdminChannel, AdminService};
+use ops_protocol::{OpsChannel, OpsService};
+
+#[derive(Clone, Default)]
+struct Node {
+    restarts: Arc<AtomicU64>,
+}
+
+impl Health for Node {
+    async fn status(&mut self) -> Result<String> {
+        Ok("ok".to_string())
+    }
+}
+
+impl Echo for Node {
+    async fn echo(&mut self, message: String) -> Result<String> {
+        Ok(message)
+    }
+}
+
+impl Admin for Node {
+    async fn restarts(&mut self) -> Result<u64> {
+        Ok(self.restarts.load(Ordering::SeqCst))
+    }
+
+    async fn restart(&mut self) -> Result<()> {
+        self.restarts.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Ops for Node {
+    async fn drain(&mut self, seconds: u32) -> Result<String> {
+        Ok(format!("draining for {seconds}s"))
+    }
+}
+
+fn serve(router: Router) -> tokio::io::DuplexStream {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    client
+}
+
+async fn check<T: Health + Echo>(mut node: T) {
+    assert_eq!(node.status().await.unwrap(), "ok");
+    assert_eq!(node.echo("hi".to_string()).await.unwrap(), "hi");
+}
+
+/// r[verify jetstream.service.compose]
+#[tokio::test]
+async fn one_protocol_serves_every_supertrait() {
+    let client = serve(Router::new().with_handler(
+        admin_protocol::PROTOCOL_NAME,
+        AdminService {
+            inner: Node::default(),
+        },
+    ));
+    let framed = Framed::new(client, ClientCodec::<AdminChannel>::default());
+    let mut chan = AdminChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(chan.status().await.unwrap(), "ok");
+    assert_eq!(chan.echo("hello".to_string()).await.unwrap(), "hello");
+    chan.restart().await.unwrap();
+    assert_eq!(chan.restarts().await.unwrap(), 1);
+    assert_eq!(AdminChannel::METHODS.len(), 4);
+    check(chan).await;
+}
+
+/// r[verify jetstream.service.compose]
+#[tokio::test]
+async fn compositions_nest() {
+    let client = serve(Router::new().with_handler(
+        ops_protocol::PROTOCOL_NAME,
+        OpsService {
+            inner: Node::default(),
+        },
+    ));
+    let framed = Framed::new(client, ClientCodec::<OpsChannel>::default());
+    let mut chan = OpsChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    chan.restart().await.unwrap();
+    assert_eq!(chan.restarts().await.unwrap(), 1);
+    assert_eq!(chan.drain(5).await.unwrap(), "draining for 5s");
+    assert_eq!(OpsChannel::METHODS.len(), 5);
+    check(chan).await;
+}
+
+/// r[verify jetstream.service.compose.ids]
+#[test]
+fn inherited_ids_do_not_depend_on_other_traits() {
+    let id = |methods: &[jetstream_rpc::schema::MethodDigest], name| {
+        methods.iter().find(|m| m.name == name).unwrap().id
+    };
+    // `Ops` inherits `Admin`'s methods, which sit after others in its
+    // messages, under the same ids.
+    for name in ["status", "echo", "restarts", "restart"] {
+        assert_eq!(
+            id(AdminChannel::METHODS, name),
+            id(OpsChannel::METHODS, name),
+        );
+    }
+}
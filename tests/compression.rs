@@ -0,0 +1,260 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context as TaskContext, Poll},
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{
+    client::ClientCodec,
+    compression::{CompressionState, COMPRESSED},
+    Compression, Handler, Router,
+};
+use logs_protocol::{LogsChannel, LogsService};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+#[service]
+pub trait Logs {
+    async fn tail(&mut self, lines: u16) -> Result<Vec<String>>;
+    async fn append(&mut self, line: String) -> Result<u32>;
+}
+
+#[derive(Clone)]
+struct LogsImpl;
+
+const LINE: &str =
+    "2026-10-18T12:00:00Z INFO request handled path=/v1/files status=200";
+
+impl Logs for LogsImpl {
+    async fn tail(&mut self, lines: u16) -> Result<Vec<String>> {
+        Ok(vec![LINE.to_string(); lines as usize])
+    }
+
+    async fn append(&mut self, line: String) -> Result<u32> {
+        Ok(line.len() as u32)
+    }
+}
+
+/// Counts the bytes the server writes and reads.
+struct Counting<T> {
+    inner: T,
+    bytes: Arc<AtomicUsize>,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Counting<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.bytes
+            .fetch_add(buf.filled().len() - before, Ordering::SeqCst);
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Counting<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            self.bytes.fetch_add(n, Ordering::SeqCst);
+        }
+        poll
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// A handler that predates compression.
+struct Legacy(LogsService<LogsImpl>);
+
+#[jetstream::prelude::async_trait]
+impl Handler for Legacy {
+    async fn handle(
+        &self,
+        ctx: Context,
+        reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+        writer: Box<dyn AsyncWrite + Send + Sync + Unpin>,
+    ) -> std::result::Result<(), Error> {
+        self.0.handle(ctx, reader, writer).await
+    }
+}
+
+struct Wire {
+    read: Arc<AtomicUsize>,
+    written: Arc<AtomicUsize>,
+}
+
+fn serve(router: Router, server: tokio::io::DuplexStream) -> Wire {
+    let wire = Wire {
+        read: Arc::new(AtomicUsize::new(0)),
+        written: Arc::new(AtomicUsize::new(0)),
+    };
+    let (reader, writer) = tokio::io::split(server);
+    let reader = Counting {
+        inner: reader,
+        bytes: wire.read.clone(),
+    };
+    let writer = Counting {
+        inner: writer,
+        bytes: wire.written.clone(),
+    };
+    tokio::spawn(async move {
+        let _ = router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await;
+    });
+    wire
+}
+
+async fn connect(router: Router) -> (LogsChannel, CompressionState, Wire) {
+    let (client, server) = tokio::io::duplex(1024 * 1024);
+    let wire = serve(router, server);
+    let codec = ClientCodec::<LogsChannel>::default();
+    let compression = codec.compression().clone();
+    let chan = LogsChannel::new(10, Box::new(Framed::new(client, codec)));
+    chan.negotiate_version(u32::MAX).await.unwrap();
+    (chan, compression, wire)
+}
+
+fn router() -> Router {
+    Router::new().with_handler(
+        logs_protocol::PROTOCOL_NAME,
+        LogsService { inner: LogsImpl },
+    )
+}
+
+/// r[verify jetstream.compression.negotiation]
+/// r[verify jetstream.compression.router]
+#[tokio::test]
+async fn large_frames_are_compressed_both_ways() {
+    let (mut chan, compression, wire) = connect(router()).await;
+    assert_eq!(
+        compression.get(),
+        Compression::select(Capabilities::COMPRESSION)
+    );
+    assert!(compression.get().is_some());
+
+    let before = wire.written.load(Ordering::SeqCst);
+    let lines = chan.tail(500).await.unwrap();
+    assert_eq!(lines.len(), 500);
+    assert!(lines.iter().all(|line| line == LINE));
+    let sent = wire.written.load(Ordering::SeqCst) - before;
+    assert!(sent < 500 * LINE.len() / 4, "response took {sent} bytes");
+
+    let before = wire.read.load(Ordering::SeqCst);
+    let line = LINE.repeat(200);
+    assert_eq!(chan.append(line.clone()).await.unwrap(), line.len() as u32);
+    let received = wire.read.load(Ordering::SeqCst) - before;
+    assert!(received < line.len() / 4, "request took {received} bytes");
+}
+
+/// r[verify jetstream.compression.interop]
+#[tokio::test]
+async fn old_servers_get_uncompressed_frames() {
+    let router = Router::new().with_handler(
+        logs_protocol::PROTOCOL_NAME,
+        Legacy(LogsService { inner: LogsImpl }),
+    );
+    let (mut chan, compression, wire) = connect(router).await;
+    assert_eq!(compression.get(), None);
+
+    let before = wire.read.load(Ordering::SeqCst);
+    let line = LINE.repeat(200);
+    assert_eq!(chan.append(line.clone()).await.unwrap(), line.len() as u32);
+    assert!(wire.read.load(Ordering::SeqCst) - before > line.len());
+    assert_eq!(chan.tail(500).await.unwrap().len(), 500);
+}
+
+/// r[verify jetstream.compression.interop]
+#[tokio::test]
+async fn old_clients_get_uncompressed_frames() {
+    use logs_protocol::{Tmessage, Ttail};
+
+    let (mut client, server) = tokio::io::duplex(1024 * 1024);
+    serve(router(), server);
+
+    let tversion = Frame {
+        tag: 0,
+        msg: Tmessage::Version(Tversion {
+            msize: u32::MAX,
+            version: logs_protocol::PROTOCOL_VERSION.to_string(),
+        }),
+    };
+    let tail = Frame {
+        tag: 1,
+        msg: Tmessage::Tail(Ttail { lines: 500 }),
+    };
+    for request in [tversion, tail] {
+        let mut buf = Vec::new();
+        request.encode(&mut buf).unwrap();
+        client.write_all(&buf).await.unwrap();
+
+        let size = client.read_u32_le().await.unwrap();
+        assert_eq!(size & COMPRESSED, 0);
+        let mut frame = vec![0; size as usize - 4];
+        client.read_exact(&mut frame).await.unwrap();
+    }
+}
+
+/// r[verify jetstream.compression.limits]
+/// r[verify jetstream.compression.interop]
+#[tokio::test]
+async fn uncompressed_requests_may_exceed_the_msize() {
+    use futures::{SinkExt, StreamExt};
+    use logs_protocol::{Rmessage, Tappend, Tmessage};
+
+    // TypeScript and Swift clients negotiate 64 KiB, and send larger
+    // requests without compressing them.
+    let (client, server) = tokio::io::duplex(1024 * 1024);
+    serve(router(), server);
+    let mut framed = Framed::new(client, ClientCodec::<LogsChannel>::default());
+    let tversion = Frame {
+        tag: 0xFFFF,
+        msg: Tmessage::Version(Tversion {
+            msize: 65536,
+            version: logs_protocol::PROTOCOL_VERSION.to_string(),
+        }),
+    };
+    framed.send(tversion).await.unwrap();
+    let Rmessage::Version(rversion) = framed.next().await.unwrap().unwrap().msg
+    else {
+        panic!("expected Rversion");
+    };
+    assert_eq!(rversion.msize, 65536);
+
+    let line = "x".repeat(u16::MAX as usize);
+    let append = Frame {
+        tag: 1,
+        msg: Tmessage::Append(Tappend { line: line.clone() }),
+    };
+    assert!(append.byte_size() > rversion.msize);
+    framed.send(append).await.unwrap();
+    match framed.next().await.unwrap().unwrap().msg {
+        Rmessage::Append(appended) => {
+            assert_eq!(appended.0, line.len() as u32)
+        }
+        other => panic!("expected Rappend, got {other:?}"),
+    }
+}
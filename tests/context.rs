@@ -0,0 +1,85 @@
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, Router};
+use tenants_protocol::{TenantsChannel, TenantsService};
+
+#[service]
+pub trait Tenants {
+    async fn whoami(&mut self, ctx: Context) -> Result<String>;
+    async fn routed(&mut self, ctx: Context) -> Result<String>;
+}
+
+/// What an authenticating middleware would attach for the handler.
+struct Tenant(&'static str);
+
+#[derive(Clone)]
+struct TenantsImpl;
+
+impl Tenants for TenantsImpl {
+    async fn whoami(&mut self, ctx: Context) -> Result<String> {
+        Ok(ctx
+            .extension::<Tenant>()
+            .map_or("anonymous", |tenant| tenant.0)
+            .to_string())
+    }
+
+    async fn routed(&mut self, ctx: Context) -> Result<String> {
+        let protocol = ctx.protocol().unwrap_or_default();
+        let version = ctx.version().map(|v| v.to_string()).unwrap_or_default();
+        Ok(format!("{protocol} {version}"))
+    }
+}
+
+fn connect(ctx: Context) -> TenantsChannel {
+    let router = Router::new().with_handler(
+        tenants_protocol::PROTOCOL_NAME,
+        TenantsService { inner: TenantsImpl },
+    );
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(ctx, Box::new(reader), Box::new(writer))
+            .await
+            .expect("accept failed");
+    });
+    let framed = Framed::new(client, ClientCodec::<TenantsChannel>::default());
+    TenantsChannel::new(10, Box::new(framed))
+}
+
+/// r[verify jetstream.context.extensions]
+#[tokio::test]
+async fn handler_sees_extensions() {
+    let mut chan = connect(Context::default().with_extension(Tenant("acme")));
+    chan.negotiate_version(8192).await.unwrap();
+    assert_eq!(chan.whoami(Context::default()).await.unwrap(), "acme");
+
+    let mut chan = connect(Context::default());
+    chan.negotiate_version(8192).await.unwrap();
+    assert_eq!(chan.whoami(Context::default()).await.unwrap(), "anonymous");
+}
+
+/// r[verify jetstream.context.builtin]
+#[tokio::test]
+async fn router_records_version() {
+    let mut chan = connect(Context::default());
+    chan.negotiate_version(8192).await.unwrap();
+    assert_eq!(
+        chan.routed(Context::default()).await.unwrap(),
+        format!(
+            "{} {}",
+            tenants_protocol::PROTOCOL_NAME,
+            tenants_protocol::PROTOCOL_VERSION
+        )
+    );
+}
+
+#[test]
+fn extensions_copy_on_write() {
+    let ctx = Context::default().with_extension(Tenant("acme"));
+    let mut clone = ctx.clone();
+    assert_eq!(clone, ctx);
+    assert_eq!(clone.extensions_mut().remove::<Tenant>().unwrap().0, "acme");
+    assert_ne!(clone, ctx);
+    assert!(clone.extension::<Tenant>().is_none());
+    assert_eq!(ctx.extension::<Tenant>().unwrap().0, "acme");
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, Protocol, Router};
+
+#[service]
+pub trait Store<K: Clone + Ord, V: Clone> {
+    async fn put(&mut self, key: K, value: V) -> Result<Option<V>>;
+    #[idempotent]
+    async fn get(&mut self, key: K) -> Result<Option<V>>;
+    async fn keys(&mut self) -> Result<Vec<K>>;
+}
+
+#[service]
+pub trait Catalog {
+    type Id: WireFormat;
+    async fn name(&mut self, id: Self::Id) -> Result<String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, JetStreamWireFormat)]
+pub struct Sku(u32);
+
+use catalog_protocol::{CatalogChannel, CatalogService};
+use store_protocol::{StoreChannel, StoreService};
+
+#[derive(Debug)]
+struct Memory<K, V>(Arc<Mutex<BTreeMap<K, V>>>);
+
+impl<K, V> Clone for Memory<K, V> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K, V> Default for Memory<K, V> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<K, V> Store<K, V> for Memory<K, V>
+where
+    K: Clone + Ord + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    async fn put(&mut self, key: K, value: V) -> Result<Option<V>> {
+        Ok(self.0.lock().unwrap().insert(key, value))
+    }
+
+    async fn get(&mut self, key: K) -> Result<Option<V>> {
+        Ok(self.0.lock().unwrap().get(&key).cloned())
+    }
+
+    async fn keys(&mut self) -> Result<Vec<K>> {
+        Ok(self.0.lock().unwrap().keys().cloned().collect())
+    }
+}
+
+#[derive(Clone)]
+struct Skus;
+
+impl Catalog for Skus {
+    type Id = Sku;
+
+    async fn name(&mut self, id: Sku) -> Result<String> {
+        Ok(format!("sku-{}", id.0))
+    }
+}
+
+fn serve(router: Router) -> tokio::io::DuplexStream {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    client
+}
+
+type Counts = StoreService<Memory<String, u64>, String, u64>;
+type Blobs = StoreService<Memory<u64, Vec<u8>>, u64, Vec<u8>>;
+
+fn router() -> Router {
+    Router::new()
+        .with_handler(&Counts::protocol_name(), Counts::new(Memory::default()))
+        .with_handler(&Blobs::protocol_name(), Blobs::new(Memory::default()))
+}
+
+/// r[verify jetstream.service.generics]
+/// r[verify jetstream.service.generics.name]
+#[tokio::test]
+async fn instantiations_share_a_router() {
+    let router = router();
+    let framed = Framed::new(
+        serve(router.clone()),
+        ClientCodec::<StoreChannel<String, u64>>::default(),
+    );
+    let mut counts = StoreChannel::new(10, Box::new(framed));
+    let framed = Framed::new(
+        serve(router),
+        ClientCodec::<StoreChannel<u64, Vec<u8>>>::default(),
+    );
+    let mut blobs = StoreChannel::new(10, Box::new(framed));
+    counts.negotiate_version(8192).await.unwrap();
+    blobs.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(counts.put("a".to_string(), 1).await.unwrap(), None);
+    assert_eq!(counts.put("a".to_string(), 2).await.unwrap(), Some(1));
+    assert_eq!(blobs.put(7, vec![1, 2, 3]).await.unwrap(), None);
+
+    assert_eq!(counts.get("a".to_string()).await.unwrap(), Some(2));
+    assert_eq!(blobs.get(7).await.unwrap(), Some(vec![1, 2, 3]));
+    assert_eq!(counts.keys().await.unwrap(), ["a"]);
+    assert_eq!(blobs.keys().await.unwrap(), [7]);
+}
+
+/// r[verify jetstream.service.generics.name]
+#[tokio::test]
+async fn instantiations_are_named_after_their_arguments() {
+    assert_eq!(Counts::protocol_name(), "generics.store-String.u64_");
+    assert_eq!(
+        StoreChannel::<u64, Vec<u8>>::protocol_name(),
+        Blobs::protocol_name()
+    );
+    assert!(StoreChannel::<u64, Vec<u8>>::protocol_version()
+        .starts_with("rs.jetstream.proto/generics.store-u64.Vec-u8__/"));
+    assert_eq!(
+        CatalogChannel::<Sku>::protocol_name(),
+        "generics.catalog-Sku_"
+    );
+
+    let framed = Framed::new(
+        serve(router()),
+        ClientCodec::<StoreChannel<u64, String>>::default(),
+    );
+    let unserved = StoreChannel::new(10, Box::new(framed));
+    assert!(unserved.negotiate_version(8192).await.is_err());
+}
+
+/// r[verify jetstream.service.generics]
+#[tokio::test]
+async fn associated_types_are_message_parameters() {
+    let router = Router::new().with_handler(
+        &CatalogService::<Skus>::protocol_name(),
+        CatalogService { inner: Skus },
+    );
+    let framed = Framed::new(
+        serve(router),
+        ClientCodec::<CatalogChannel<Sku>>::default(),
+    );
+    let mut catalog = CatalogChannel::new(10, Box::new(framed));
+    catalog.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(catalog.name(Sku(42)).await.unwrap(), "sku-42");
+}
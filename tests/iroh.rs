@@ -69,6 +69,7 @@ async fn test_iroh_echo_service() {
     let transport = IrohTransport::<EchoChannel>::from(conn.open_bi().await.unwrap());
 
     let ec = EchoChannel::new(10, Box::new(transport));
+    ec.negotiate_version(u32::MAX).await.unwrap();
     let mut futures = FuturesUnordered::new();
     for _ in 0..10 {
         futures.push(ec.ping());
@@ -0,0 +1,24 @@
+//! Services for the tests of this crate to compose from another crate,
+//! which `#[service]` can only reach through their exported `methods!`.
+
+use jetstream::prelude::*;
+
+pub mod ops {
+    use super::*;
+
+    #[service]
+    pub trait Health {
+        #[idempotent]
+        async fn status(&mut self) -> Result<String>;
+    }
+
+    #[service]
+    pub trait Admin: crate::ops::Health {
+        async fn restarts(&mut self) -> Result<u64>;
+    }
+}
+
+#[service]
+pub trait Echo {
+    async fn echo(&mut self, message: String) -> Result<String>;
+}
@@ -0,0 +1,77 @@
+use jetstream::prelude::*;
+use jetstream_compose::{
+    ops::{Admin, Health},
+    Echo,
+};
+use jetstream_rpc::{client::ClientCodec, Router};
+
+#[service]
+pub trait Ops: jetstream_compose::ops::Admin + jetstream_compose::Echo {
+    async fn drain(&mut self, seconds: u32) -> Result<String>;
+}
+
+use ops_protocol::{OpsChannel, OpsService};
+
+#[derive(Clone, Default)]
+struct Node;
+
+impl Health for Node {
+    async fn status(&mut self) -> Result<String> {
+        Ok("ok".to_string())
+    }
+}
+
+impl Admin for Node {
+    async fn restarts(&mut self) -> Result<u64> {
+        Ok(3)
+    }
+}
+
+impl Echo for Node {
+    async fn echo(&mut self, message: String) -> Result<String> {
+        Ok(message)
+    }
+}
+
+impl Ops for Node {
+    async fn drain(&mut self, seconds: u32) -> Result<String> {
+        Ok(format!("draining for {seconds}s"))
+    }
+}
+
+/// r[verify jetstream.service.compose]
+#[tokio::test]
+async fn services_compose_across_crates() {
+    let router = Router::new()
+        .with_handler(ops_protocol::PROTOCOL_NAME, OpsService { inner: Node });
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    let framed = Framed::new(client, ClientCodec::<OpsChannel>::default());
+    let mut chan = OpsChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(chan.status().await.unwrap(), "ok");
+    assert_eq!(chan.restarts().await.unwrap(), 3);
+    assert_eq!(chan.echo("hi".to_string()).await.unwrap(), "hi");
+    assert_eq!(chan.drain(5).await.unwrap(), "draining for 5s");
+    assert_eq!(OpsChannel::METHODS.len(), 4);
+}
+
+/// r[verify jetstream.service.compose.ids]
+#[test]
+fn inherited_ids_match_across_crates() {
+    use jetstream_compose::ops::admin_protocol::AdminChannel;
+
+    for method in AdminChannel::METHODS {
+        let inherited = OpsChannel::METHODS
+            .iter()
+            .find(|m| m.name == method.name)
+            .unwrap();
+        assert_eq!(inherited.id, method.id);
+    }
+}
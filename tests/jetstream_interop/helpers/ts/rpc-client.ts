@@ -0,0 +1,66 @@
+// r[impl jetstream.interop.ts.rpc]
+// TypeScript RPC client: negotiates the default msize with the Rust server on
+// stdin/stdout, then sends an `append` request larger than that msize and
+// checks the reply. Exits non-zero if the request is not served.
+
+import { Readable, Writable } from 'node:stream';
+import {
+  BinaryReader,
+  BinaryWriter,
+  stringCodec,
+  u8Codec,
+  u16Codec,
+  u32Codec,
+} from '@sevki/jetstream-wireformat';
+import { MESSAGE_ID_START, negotiateVersion } from '@sevki/jetstream-rpc';
+
+const TAPPEND = MESSAGE_ID_START;
+const RAPPEND = MESSAGE_ID_START + 1;
+
+const readable = Readable.toWeb(process.stdin) as ReadableStream<Uint8Array>;
+const writable = Writable.toWeb(process.stdout) as WritableStream<Uint8Array>;
+
+const negotiated = await negotiateVersion(readable, writable, process.argv[2]);
+
+// Send Tappend: [size:u32][type:u8][tag:u16][line:string]
+const line = 'x'.repeat(0xffff);
+const size = 4 + 1 + 2 + stringCodec.byteSize(line);
+if (size <= negotiated.msize) {
+  throw new Error(`request of ${size} bytes fits the msize of ${negotiated.msize}`);
+}
+const bw = new BinaryWriter();
+u32Codec.encode(size, bw);
+u8Codec.encode(TAPPEND, bw);
+u16Codec.encode(1, bw);
+stringCodec.encode(line, bw);
+const writer = writable.getWriter();
+await writer.write(bw.toUint8Array());
+writer.releaseLock();
+
+// Read Rappend
+const reader = readable.getReader();
+let buffer = new Uint8Array(0);
+while (
+  buffer.length < 4 ||
+  buffer.length < new DataView(buffer.buffer, buffer.byteOffset).getUint32(0, true)
+) {
+  const { done, value } = await reader.read();
+  if (done) throw new Error('stream closed before the response was received');
+  const next = new Uint8Array(buffer.length + value.length);
+  next.set(buffer);
+  next.set(value, buffer.length);
+  buffer = next;
+}
+
+const br = new BinaryReader(buffer);
+u32Codec.decode(br); // consume size
+const type = u8Codec.decode(br);
+u16Codec.decode(br); // consume tag
+if (type !== RAPPEND) {
+  throw new Error(`expected Rappend (type ${RAPPEND}), got type ${type}`);
+}
+const appended = u32Codec.decode(br);
+if (appended !== line.length) {
+  throw new Error(`server appended ${appended} bytes, expected ${line.length}`);
+}
+process.exit(0);
@@ -0,0 +1,71 @@
+// r[verify jetstream.interop.ts.rpc]
+// r[verify jetstream.compression.interop]
+
+use std::{path::Path, process::Stdio, time::Duration};
+
+use jetstream::prelude::*;
+use jetstream_rpc::Router;
+use logs_protocol::LogsService;
+use tokio::process::Command;
+
+#[service(name = "interop.logs")]
+pub trait Logs {
+    async fn append(&mut self, line: String) -> Result<u32>;
+}
+
+#[derive(Clone)]
+struct LogsImpl;
+
+impl Logs for LogsImpl {
+    async fn append(&mut self, line: String) -> Result<u32> {
+        Ok(line.len() as u32)
+    }
+}
+
+fn ts_client_path() -> String {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    format!("{manifest_dir}/helpers/ts/rpc-client.ts")
+}
+
+fn tsx_bin() -> String {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    format!("{manifest_dir}/helpers/ts/node_modules/.bin/tsx")
+}
+
+/// TypeScript clients negotiate a 64 KiB msize but never keep their requests
+/// under it, so the server must serve larger ones.
+#[tokio::test]
+async fn ts_requests_larger_than_the_msize_are_served() {
+    let tsx = tsx_bin();
+    if !Path::new(&tsx).exists() {
+        eprintln!("SKIP: tsx not found at {tsx} — run `pnpm install` first");
+        return;
+    }
+    let mut child = Command::new(&tsx)
+        .arg(ts_client_path())
+        .arg(logs_protocol::PROTOCOL_VERSION)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to spawn tsx client");
+    let reader = child.stdout.take().unwrap();
+    let writer = child.stdin.take().unwrap();
+
+    let router = Router::new().with_handler(
+        logs_protocol::PROTOCOL_NAME,
+        LogsService { inner: LogsImpl },
+    );
+    tokio::spawn(async move {
+        let _ = router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await;
+    });
+
+    let status = tokio::time::timeout(Duration::from_secs(60), child.wait())
+        .await
+        .expect("tsx client timed out")
+        .unwrap();
+    assert!(status.success(), "tsx client failed: {status}");
+}
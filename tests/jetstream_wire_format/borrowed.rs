@@ -0,0 +1,101 @@
+//! Structs that borrow from the buffer they are decoded from.
+
+use std::io::ErrorKind;
+
+use jetstream_wireformat::{
+    BorrowedWireFormat, JetStreamWireFormat, WireFormat, WireFormatRef,
+};
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct Put {
+    pub id: u64,
+    pub key: String,
+    pub value: Vec<u8>,
+    pub meta: Meta,
+}
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct Meta {
+    pub owner: String,
+    pub flags: u32,
+}
+
+#[derive(Debug, PartialEq, JetStreamWireFormat)]
+pub struct PutRef<'a> {
+    pub id: u64,
+    pub key: &'a str,
+    pub value: &'a [u8],
+    pub meta: MetaRef<'a>,
+}
+
+#[derive(Debug, PartialEq, JetStreamWireFormat)]
+pub struct MetaRef<'a> {
+    pub owner: &'a str,
+    pub flags: u32,
+}
+
+#[derive(Debug, PartialEq, JetStreamWireFormat)]
+pub struct Pair<'a, T>(pub &'a str, pub T);
+
+fn put() -> Put {
+    Put {
+        id: 7,
+        key: "key".to_string(),
+        value: vec![1, 2, 3],
+        meta: Meta {
+            owner: "owner".to_string(),
+            flags: 3,
+        },
+    }
+}
+
+/// r[verify jetstream.wireformat.borrowed]
+#[test]
+fn decodes_borrowing_from_the_buffer() {
+    let mut buf = Vec::new();
+    put().encode(&mut buf).unwrap();
+
+    let mut reader = buf.as_slice();
+    let view = PutRef::decode_ref(&mut reader).unwrap();
+    assert!(reader.is_empty());
+    assert_eq!(view.id, 7);
+    assert_eq!(view.key, "key");
+    assert_eq!(view.value, [1, 2, 3]);
+    assert_eq!(view.meta.owner, "owner");
+    assert!(buf.as_ptr_range().contains(&view.key.as_ptr()));
+    assert!(buf.as_ptr_range().contains(&view.value.as_ptr()));
+}
+
+/// r[verify jetstream.wireformat.borrowed]
+#[test]
+fn encodes_like_the_owned_struct() {
+    let view = PutRef {
+        id: 7,
+        key: "key",
+        value: &[1, 2, 3],
+        meta: MetaRef {
+            owner: "owner",
+            flags: 3,
+        },
+    };
+    let mut buf = Vec::new();
+    view.encode_borrowed(&mut buf).unwrap();
+    assert_eq!(buf.len() as u32, view.borrowed_byte_size());
+    assert_eq!(Put::decode(&mut buf.as_slice()).unwrap(), put());
+}
+
+#[test]
+fn generic_tuple_structs() {
+    let mut buf = Vec::new();
+    Pair("one", 1u16).encode_borrowed(&mut buf).unwrap();
+    let pair = Pair::<u16>::decode_ref(&mut buf.as_slice()).unwrap();
+    assert_eq!(pair, Pair("one", 1));
+}
+
+#[test]
+fn rejects_truncated_and_invalid_strings() {
+    let err = <&str>::decode_ref(&mut [5, 0, b'a'].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    let err = <&str>::decode_ref(&mut [1, 0, 0xff].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
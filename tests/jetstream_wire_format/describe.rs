@@ -0,0 +1,38 @@
+//! `Describe` names types stably, for naming generic service instantiations.
+
+use std::collections::BTreeSet;
+
+use jetstream_wireformat::{Describe, JetStreamWireFormat};
+
+#[derive(Debug, JetStreamWireFormat)]
+pub struct Pair<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+#[derive(Debug, JetStreamWireFormat)]
+pub enum Shape {
+    Dot,
+    Line(u32),
+}
+
+/// r[verify jetstream.wireformat.describe]
+#[test]
+fn library_types_are_described_by_name() {
+    assert_eq!(u64::describe(), "u64");
+    assert_eq!(String::describe(), "String");
+    assert_eq!(Vec::<Option<u8>>::describe(), "Vec<Option<u8>>");
+    assert_eq!(BTreeSet::<String>::describe(), "BTreeSet<String>");
+    assert_eq!(<(u8, (), i32)>::describe(), "(u8,(),i32)");
+}
+
+/// r[verify jetstream.wireformat.describe]
+#[test]
+fn derived_types_describe_their_parameters() {
+    assert_eq!(Shape::describe(), "Shape");
+    assert_eq!(Pair::<u8, Shape>::describe(), "Pair<u8,Shape>");
+    assert_eq!(
+        Pair::<Vec<String>, Pair<u8, u8>>::describe(),
+        "Pair<Vec<String>,Pair<u8,u8>>"
+    );
+}
@@ -0,0 +1,90 @@
+//! Types from other crates, behind `jetstream_wireformat` features.
+
+use std::time::{Duration, SystemTime};
+
+use indexmap::{IndexMap, IndexSet};
+use jetstream_wireformat::{Describe, JetStreamWireFormat, WireFormat};
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct Release {
+    pub id: uuid::Uuid,
+    pub version: semver::Version,
+    pub published: chrono::DateTime<chrono::Utc>,
+    pub built: time::OffsetDateTime,
+    pub artifacts: IndexMap<String, u64>,
+    pub tags: IndexSet<String>,
+}
+
+fn encoded<T: WireFormat>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.encode(&mut buf).unwrap();
+    assert_eq!(buf.len() as u32, value.byte_size());
+    buf
+}
+
+fn round_trip<T: WireFormat + PartialEq + std::fmt::Debug>(value: T) {
+    let buf = encoded(&value);
+    assert_eq!(T::decode(&mut buf.as_slice()).unwrap(), value);
+}
+
+/// r[verify jetstream.wireformat.ecosystem]
+#[test]
+fn ecosystem_types_round_trip_in_derived_structs() {
+    round_trip(Release {
+        id: uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8),
+        version: semver::Version::parse("1.2.3-beta.1+build.5").unwrap(),
+        published: chrono::DateTime::from_timestamp_millis(1_700_000_000_123)
+            .unwrap(),
+        built: time::OffsetDateTime::from_unix_timestamp(1_700_000_000)
+            .unwrap(),
+        artifacts: [("linux".to_string(), 42), ("darwin".to_string(), 7)]
+            .into_iter()
+            .collect(),
+        tags: ["stable".to_string(), "lts".to_string()]
+            .into_iter()
+            .collect(),
+    });
+}
+
+/// r[verify jetstream.wireformat.ecosystem]
+#[test]
+fn timestamps_encode_like_system_time() {
+    let millis = 1_700_000_000_123u64;
+    let system = SystemTime::UNIX_EPOCH + Duration::from_millis(millis);
+    let chrono =
+        chrono::DateTime::from_timestamp_millis(millis as i64).unwrap();
+    let time = time::OffsetDateTime::from_unix_timestamp_nanos(
+        millis as i128 * 1_000_000,
+    )
+    .unwrap();
+    assert_eq!(encoded(&chrono), encoded(&system));
+    assert_eq!(encoded(&time), encoded(&system));
+
+    let before_epoch = chrono::DateTime::from_timestamp_millis(-1).unwrap();
+    assert!(before_epoch.encode(&mut Vec::new()).is_err());
+}
+
+/// r[verify jetstream.wireformat.ecosystem]
+#[test]
+fn index_maps_keep_insertion_order() {
+    let map: IndexMap<u8, u8> = [(3, 0), (1, 0), (2, 0)].into_iter().collect();
+    assert_eq!(encoded(&map), [3, 0, 3, 0, 1, 0, 2, 0]);
+    let decoded =
+        IndexMap::<u8, u8>::decode(&mut encoded(&map).as_slice()).unwrap();
+    assert_eq!(decoded.keys().copied().collect::<Vec<_>>(), [3, 1, 2]);
+}
+
+/// r[verify jetstream.wireformat.ecosystem]
+#[test]
+fn uuids_and_versions_encode_like_their_representation() {
+    let id = uuid::Uuid::from_u128(1);
+    assert_eq!(encoded(&id), id.as_bytes());
+    let version = semver::Version::new(1, 0, 0);
+    assert_eq!(encoded(&version), encoded(&"1.0.0".to_string()));
+    assert!(semver::Version::decode(
+        &mut encoded(&"one".to_string()).as_slice()
+    )
+    .is_err());
+    assert_eq!(uuid::Uuid::describe(), "Uuid");
+    assert_eq!(IndexMap::<String, u8>::describe(), "IndexMap<String,u8>");
+}
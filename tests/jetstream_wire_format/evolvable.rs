@@ -0,0 +1,131 @@
+//! Structs marked `#[jetstream(evolvable)]` decode across versions.
+
+use jetstream_wireformat::{JetStreamWireFormat, WireFormat};
+
+mod v1 {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+    #[jetstream(evolvable)]
+    pub struct User {
+        pub id: u64,
+        pub name: String,
+    }
+}
+
+mod v2 {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+    #[jetstream(evolvable)]
+    pub struct User {
+        pub id: u64,
+        pub name: String,
+        #[jetstream(since = 2, default)]
+        pub email: Option<String>,
+        #[jetstream(since = 2, default)]
+        pub age: u8,
+    }
+
+    #[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+    pub struct Roster {
+        pub users: Vec<User>,
+        pub trailer: u32,
+    }
+}
+
+mod v1_roster {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+    pub struct Roster {
+        pub users: Vec<v1::User>,
+        pub trailer: u32,
+    }
+}
+
+fn convert<A: WireFormat, B: WireFormat>(value: &A) -> std::io::Result<B> {
+    let mut buf = Vec::new();
+    value.encode(&mut buf)?;
+    assert_eq!(buf.len() as u32, value.byte_size());
+    B::decode(&mut buf.as_slice())
+}
+
+/// r[verify jetstream.wireformat.evolvable]
+#[test]
+fn old_peer_to_new_peer_fills_defaults() {
+    let old = v1::User {
+        id: 7,
+        name: "ada".into(),
+    };
+    let new: v2::User = convert(&old).unwrap();
+    assert_eq!(
+        new,
+        v2::User {
+            id: 7,
+            name: "ada".into(),
+            email: None,
+            age: 0,
+        }
+    );
+}
+
+/// r[verify jetstream.wireformat.evolvable]
+#[test]
+fn new_peer_to_old_peer_skips_unknown_fields() {
+    let new = v2::Roster {
+        users: vec![
+            v2::User {
+                id: 1,
+                name: "ada".into(),
+                email: Some("ada@example.com".into()),
+                age: 36,
+            },
+            v2::User {
+                id: 2,
+                name: "grace".into(),
+                email: None,
+                age: 85,
+            },
+        ],
+        trailer: 0xdead_beef,
+    };
+    let old: v1_roster::Roster = convert(&new).unwrap();
+    assert_eq!(old.trailer, 0xdead_beef);
+    assert_eq!(
+        old.users,
+        vec![
+            v1::User {
+                id: 1,
+                name: "ada".into(),
+            },
+            v1::User {
+                id: 2,
+                name: "grace".into(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn same_version_round_trip() {
+    let user = v2::User {
+        id: 3,
+        name: "linus".into(),
+        email: Some("linus@example.com".into()),
+        age: 54,
+    };
+    assert_eq!(convert::<_, v2::User>(&user).unwrap(), user);
+}
+
+#[test]
+fn truncated_body_is_an_error() {
+    let user = v1::User {
+        id: 7,
+        name: "ada".into(),
+    };
+    let mut buf = Vec::new();
+    user.encode(&mut buf).unwrap();
+    buf.truncate(buf.len() - 1);
+    assert!(v1::User::decode(&mut buf.as_slice()).is_err());
+}
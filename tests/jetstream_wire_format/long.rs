@@ -0,0 +1,85 @@
+//! Strings, vectors and maps with `u32` length prefixes.
+
+use std::collections::BTreeMap;
+
+use jetstream_wireformat::{
+    JetStreamWireFormat, LongMap, LongString, LongVec, WireFormat,
+};
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct Page {
+    #[jetstream(len = u32)]
+    pub body: String,
+    #[jetstream(len = u32)]
+    pub links: Vec<String>,
+    #[jetstream(len = u32)]
+    pub headers: BTreeMap<String, String>,
+    pub title: String,
+}
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct WrappedPage {
+    pub body: LongString,
+    pub links: LongVec<String>,
+    pub headers: LongMap<String, String>,
+    pub title: String,
+}
+
+fn round_trip<T: WireFormat>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.encode(&mut buf).unwrap();
+    assert_eq!(buf.len() as u32, value.byte_size());
+    buf
+}
+
+fn page() -> Page {
+    Page {
+        body: "x".repeat(u16::MAX as usize + 1),
+        links: (0..70_000).map(|i| i.to_string()).collect(),
+        headers: [("host".to_string(), "example.com".to_string())].into(),
+        title: "large".to_string(),
+    }
+}
+
+/// r[verify jetstream.wireformat.long]
+#[test]
+fn fields_marked_len_u32_encode_past_u16_lengths() {
+    let page = page();
+    let buf = round_trip(&page);
+    assert_eq!(&buf[..4], &(u16::MAX as u32 + 1).to_le_bytes());
+    assert_eq!(Page::decode(&mut buf.as_slice()).unwrap(), page);
+
+    // Without the attribute the same string fails to encode.
+    let err = page.body.encode(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.to_string(), "string is too long");
+}
+
+/// r[verify jetstream.wireformat.long]
+#[test]
+fn wrappers_encode_like_fields_marked_len_u32() {
+    let page = page();
+    let wrapped = WrappedPage {
+        body: page.body.clone().into(),
+        links: page.links.clone().into(),
+        headers: page.headers.clone().into(),
+        title: page.title.clone(),
+    };
+    let buf = round_trip(&wrapped);
+    assert_eq!(buf, round_trip(&page));
+    assert_eq!(WrappedPage::decode(&mut buf.as_slice()).unwrap(), wrapped);
+}
+
+/// r[verify jetstream.wireformat.long]
+#[test]
+fn rejects_lengths_above_32_mib() {
+    let mut buf = Vec::new();
+    (32 * 1024 * 1024 + 1u32).encode(&mut buf).unwrap();
+    let err = LongVec::<u8>::decode(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let mut buf = Vec::new();
+    10u32.encode(&mut buf).unwrap();
+    buf.extend_from_slice(b"short");
+    let err = LongString::decode(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
@@ -1,6 +1,14 @@
+pub mod borrowed;
+pub mod describe;
+pub mod ecosystem;
+pub mod evolvable;
 pub mod generics_and_options;
+pub mod long;
 pub mod prost_tests;
+pub mod serde_bridge;
+pub mod std_types;
 pub mod systemtime_tests;
+pub mod varint;
 
 #[cfg(target_arch = "wasm32")]
 pub mod wasm_tests;
@@ -0,0 +1,171 @@
+//! Serde types encoded through `jetstream_wireformat::serde_bridge`.
+
+use std::collections::BTreeMap;
+
+use jetstream_wireformat::{
+    serde_bridge, JetStreamWireFormat, Serde, WireFormat,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct DerivedPoint {
+    pub x: i32,
+    pub y: u64,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SerdePoint {
+    pub x: i32,
+    pub y: u64,
+}
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub enum DerivedShape {
+    Empty,
+    Circle(DerivedPoint, f64),
+    Polygon {
+        name: String,
+        points: Vec<DerivedPoint>,
+        closed: bool,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum SerdeShape {
+    Empty,
+    Circle(SerdePoint, f64),
+    Polygon {
+        name: String,
+        points: Vec<SerdePoint>,
+        closed: bool,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct DerivedLayer {
+    pub shapes: Vec<DerivedShape>,
+    pub label: Option<String>,
+    pub opacity: Option<f32>,
+    pub tags: BTreeMap<String, u16>,
+    pub origin: (u8, i64),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SerdeLayer {
+    pub shapes: Vec<SerdeShape>,
+    pub label: Option<String>,
+    pub opacity: Option<f32>,
+    pub tags: BTreeMap<String, u16>,
+    pub origin: (u8, i64),
+}
+
+fn encoded<T: WireFormat>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.encode(&mut buf).unwrap();
+    assert_eq!(buf.len() as u32, value.byte_size());
+    buf
+}
+
+fn layers() -> (DerivedLayer, SerdeLayer) {
+    let derived = DerivedLayer {
+        shapes: vec![
+            DerivedShape::Empty,
+            DerivedShape::Circle(DerivedPoint { x: -1, y: 2 }, 0.5),
+            DerivedShape::Polygon {
+                name: "triangle".to_string(),
+                points: vec![
+                    DerivedPoint { x: 0, y: 0 },
+                    DerivedPoint { x: 3, y: 4 },
+                ],
+                closed: true,
+            },
+        ],
+        label: Some("background".to_string()),
+        opacity: None,
+        tags: [("z".to_string(), 1), ("a".to_string(), 2)]
+            .into_iter()
+            .collect(),
+        origin: (7, -9),
+    };
+    let serde = SerdeLayer {
+        shapes: vec![
+            SerdeShape::Empty,
+            SerdeShape::Circle(SerdePoint { x: -1, y: 2 }, 0.5),
+            SerdeShape::Polygon {
+                name: "triangle".to_string(),
+                points: vec![
+                    SerdePoint { x: 0, y: 0 },
+                    SerdePoint { x: 3, y: 4 },
+                ],
+                closed: true,
+            },
+        ],
+        label: Some("background".to_string()),
+        opacity: None,
+        tags: [("z".to_string(), 1), ("a".to_string(), 2)]
+            .into_iter()
+            .collect(),
+        origin: (7, -9),
+    };
+    (derived, serde)
+}
+
+/// r[verify jetstream.wireformat.serde]
+#[test]
+fn serde_types_encode_like_derived_types() {
+    let (derived, serde) = layers();
+    let bytes = encoded(&derived);
+    assert_eq!(encoded(&Serde(serde.clone())), bytes);
+
+    let decoded = Serde::<SerdeLayer>::decode(&mut bytes.as_slice()).unwrap();
+    assert_eq!(decoded.0, serde);
+    let decoded =
+        DerivedLayer::decode(&mut encoded(&Serde(serde)).as_slice()).unwrap();
+    assert_eq!(decoded, derived);
+}
+
+/// r[verify jetstream.wireformat.serde]
+#[test]
+fn serde_primitives_encode_like_wire_format() {
+    assert_eq!(encoded(&Serde('A')), encoded(&'A'));
+    assert_eq!(encoded(&Serde(true)), [1]);
+    assert_eq!(encoded(&Serde(-2i8)), [0xfe]);
+    assert_eq!(encoded(&Serde(u128::MAX)), encoded(&u128::MAX));
+    assert_eq!(encoded(&Serde(vec![1u8, 2])), encoded(&vec![1u8, 2]));
+    assert_eq!(encoded(&Serde(())), Vec::<u8>::new());
+    assert_eq!(
+        serde_bridge::byte_size(&Some("hi".to_string())).unwrap(),
+        Some("hi".to_string()).byte_size()
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sparse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// r[verify jetstream.wireformat.serde]
+#[test]
+fn serde_rejects_what_the_wire_format_cannot_express() {
+    assert!(Serde(Sparse { note: None })
+        .encode(&mut Vec::new())
+        .is_err());
+    assert_eq!(Serde(Sparse { note: None }).byte_size(), u32::MAX);
+    assert!(Serde(vec![0u8; 65536]).encode(&mut Vec::new()).is_err());
+
+    let invalid_variant = [9u8];
+    assert!(serde_bridge::from_reader::<SerdeShape, _>(
+        &mut &invalid_variant[..]
+    )
+    .is_err());
+    let invalid_option = [2u8];
+    assert!(serde_bridge::from_reader::<Option<u8>, _>(
+        &mut &invalid_option[..]
+    )
+    .is_err());
+    let truncated = [3u8, 0, 1];
+    assert!(
+        serde_bridge::from_reader::<Vec<u8>, _>(&mut &truncated[..]).is_err()
+    );
+}
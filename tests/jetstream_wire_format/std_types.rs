@@ -0,0 +1,115 @@
+//! Standard library types that encode without `#[jetstream(with(...))]`.
+
+use std::{
+    borrow::Cow,
+    num::{NonZeroU32, NonZeroU8},
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use jetstream_wireformat::{
+    wire_format_extensions::bytes::Bytes, Describe, JetStreamWireFormat,
+    WireFormat,
+};
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct Job {
+    pub id: NonZeroU32,
+    pub timeout: Duration,
+    pub digest: [u8; 4],
+    pub path: PathBuf,
+    pub label: Cow<'static, str>,
+    pub config: Arc<String>,
+    pub payload: Bytes,
+    pub lines: Range<u64>,
+    pub grade: char,
+    pub outcome: Result<u32, String>,
+}
+
+fn encoded<T: WireFormat>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.encode(&mut buf).unwrap();
+    assert_eq!(buf.len() as u32, value.byte_size());
+    buf
+}
+
+fn round_trip<T: WireFormat + PartialEq + std::fmt::Debug>(value: T) {
+    let buf = encoded(&value);
+    assert_eq!(T::decode(&mut buf.as_slice()).unwrap(), value);
+}
+
+/// r[verify jetstream.wireformat.std]
+#[test]
+fn std_types_round_trip_in_derived_structs() {
+    round_trip(Job {
+        id: NonZeroU32::new(7).unwrap(),
+        timeout: Duration::new(30, 500),
+        digest: [0xde, 0xad, 0xbe, 0xef],
+        path: PathBuf::from("/var/run/job.sock"),
+        label: Cow::Borrowed("nightly"),
+        config: Arc::new("verbose".to_string()),
+        payload: Bytes::from_static(b"\x00\x01\x02"),
+        lines: 10..20,
+        grade: 'λ',
+        outcome: Err("timed out".to_string()),
+    });
+}
+
+/// r[verify jetstream.wireformat.std]
+#[test]
+fn std_types_encode_like_their_representation() {
+    assert_eq!(
+        encoded(&Duration::new(1, 2)),
+        [1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0]
+    );
+    assert_eq!(encoded(&[1u16, 2]), [1, 0, 2, 0]);
+    assert_eq!(encoded(&'A'), [0x41, 0, 0, 0]);
+    assert_eq!(encoded(&Ok::<u8, u8>(5)), [0, 5]);
+    assert_eq!(encoded(&Err::<u8, u8>(5)), [1, 5]);
+    assert_eq!(encoded(&(3u8..9)), [3, 9]);
+    assert_eq!(encoded(&NonZeroU8::new(4).unwrap()), [4]);
+    assert_eq!(encoded(&Arc::new(1u8)), encoded(&1u8));
+    assert_eq!(
+        encoded(&Cow::<str>::Borrowed("hi")),
+        encoded(&"hi".to_string())
+    );
+    assert_eq!(
+        encoded(&Cow::<[u8]>::Borrowed(&[1, 2])),
+        encoded(&vec![1u8, 2])
+    );
+    assert_eq!(encoded(&PathBuf::from("a/b")), encoded(&"a/b".to_string()));
+    assert_eq!(
+        encoded(&Bytes::from_static(b"abc")),
+        encoded(&b"abc".to_vec())
+    );
+}
+
+/// r[verify jetstream.wireformat.std]
+#[test]
+fn std_types_reject_invalid_values() {
+    let zero = 0u32.to_le_bytes();
+    assert!(NonZeroU32::decode(&mut zero.as_slice()).is_err());
+
+    let surrogate = 0xd800u32.to_le_bytes();
+    assert!(char::decode(&mut surrogate.as_slice()).is_err());
+
+    let mut nanos = encoded(&0u64);
+    nanos.extend_from_slice(&1_000_000_000u32.to_le_bytes());
+    assert!(Duration::decode(&mut nanos.as_slice()).is_err());
+
+    assert!(Result::<u8, u8>::decode(&mut [2, 0].as_slice()).is_err());
+    assert!(<[u8; 4]>::decode(&mut [1, 2, 3].as_slice()).is_err());
+}
+
+/// r[verify jetstream.wireformat.describe]
+#[test]
+fn std_types_are_described_by_name() {
+    assert_eq!(Duration::describe(), "Duration");
+    assert_eq!(<[u8; 16]>::describe(), "[u8;16]");
+    assert_eq!(Result::<u8, String>::describe(), "Result<u8,String>");
+    assert_eq!(Cow::<str>::describe(), "Cow<String>");
+    assert_eq!(Arc::<NonZeroU32>::describe(), "Arc<NonZeroU32>");
+    assert_eq!(Range::<u64>::describe(), "Range<u64>");
+}
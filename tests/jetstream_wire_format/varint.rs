@@ -0,0 +1,83 @@
+//! Integers and lengths encoded as LEB128 varints.
+
+use jetstream_wireformat::{JetStreamWireFormat, Varint, WireFormat};
+
+#[derive(Debug, PartialEq, Clone, JetStreamWireFormat)]
+pub struct Sample {
+    #[jetstream(varint)]
+    pub id: u64,
+    #[jetstream(varint)]
+    pub delta: i32,
+    #[jetstream(len = varint)]
+    pub name: String,
+    #[jetstream(len = varint)]
+    pub values: Vec<Varint<i64>>,
+}
+
+fn encoded<T: WireFormat>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.encode(&mut buf).unwrap();
+    assert_eq!(buf.len() as u32, value.byte_size());
+    buf
+}
+
+fn round_trip<T: WireFormat + PartialEq + std::fmt::Debug>(value: T) {
+    let buf = encoded(&value);
+    assert_eq!(T::decode(&mut buf.as_slice()).unwrap(), value);
+}
+
+/// r[verify jetstream.wireformat.varint]
+#[test]
+fn encodes_unsigned_leb128() {
+    assert_eq!(encoded(&Varint(0u64)), [0x00]);
+    assert_eq!(encoded(&Varint(127u64)), [0x7f]);
+    assert_eq!(encoded(&Varint(300u64)), [0xac, 0x02]);
+    assert_eq!(encoded(&Varint(u64::MAX)).len(), 10);
+    assert_eq!(encoded(&Varint(u128::MAX)).len(), 19);
+    round_trip(Varint(u16::MAX));
+    round_trip(Varint(u64::MAX));
+    round_trip(Varint(u128::MAX));
+}
+
+/// r[verify jetstream.wireformat.varint]
+#[test]
+fn zigzag_encodes_signed_integers() {
+    assert_eq!(encoded(&Varint(0i32)), [0x00]);
+    assert_eq!(encoded(&Varint(-1i32)), [0x01]);
+    assert_eq!(encoded(&Varint(1i32)), [0x02]);
+    assert_eq!(encoded(&Varint(-64i64)), [0x7f]);
+    assert_eq!(encoded(&Varint(64i64)), [0x80, 0x01]);
+    round_trip(Varint(i16::MIN));
+    round_trip(Varint(i64::MIN));
+    round_trip(Varint(i64::MAX));
+    round_trip(Varint(i128::MIN));
+}
+
+/// r[verify jetstream.wireformat.varint]
+#[test]
+fn rejects_values_that_overflow() {
+    let err =
+        Varint::<u16>::decode(&mut [0x80, 0x80, 0x04].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let err = Varint::<u128>::decode(&mut [0x80; 20].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let err = Varint::<u64>::decode(&mut [0x80].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+/// r[verify jetstream.wireformat.varint]
+#[test]
+fn fields_marked_varint_are_compact() {
+    let sample = Sample {
+        id: 1,
+        delta: -2,
+        name: "abc".to_string(),
+        values: vec![Varint(-1), Varint(1000)],
+    };
+    let buf = encoded(&sample);
+    assert_eq!(
+        buf,
+        [0x01, 0x03, 0x03, b'a', b'b', b'c', 0x02, 0x01, 0xd0, 0x0f]
+    );
+    round_trip(sample);
+}
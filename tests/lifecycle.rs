@@ -0,0 +1,257 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{
+    client::ClientCodec, lifecycle::UNIMPLEMENTED_BY_PEER, Router,
+};
+
+#[service(name = "storage")]
+pub trait Storage {
+    async fn get(&mut self, key: String) -> Result<String>;
+    #[since("16.0.0")]
+    async fn scan(&mut self, prefix: String) -> Result<Vec<String>>;
+    #[since("99.0.0")]
+    async fn compact(&mut self) -> Result<()>;
+    #[deprecated(note = "use `get`")]
+    async fn fetch(&mut self, key: String) -> Result<String>;
+}
+
+pub mod old {
+    use jetstream::prelude::*;
+
+    /// `Storage` as built by a client that does not know `compact` is newer
+    /// than the version it negotiates.
+    #[service(name = "storage")]
+    pub trait Storage {
+        async fn get(&mut self, key: String) -> Result<String>;
+        async fn scan(&mut self, prefix: String) -> Result<Vec<String>>;
+        async fn compact(&mut self) -> Result<()>;
+        async fn fetch(&mut self, key: String) -> Result<String>;
+    }
+}
+
+use storage_protocol::{StorageChannel, StorageService};
+
+#[derive(Clone, Default)]
+struct Memory {
+    compactions: Arc<AtomicU64>,
+}
+
+impl Storage for Memory {
+    async fn get(&mut self, key: String) -> Result<String> {
+        Ok(format!("value of {key}"))
+    }
+
+    async fn scan(&mut self, prefix: String) -> Result<Vec<String>> {
+        Ok(vec![format!("{prefix}a"), format!("{prefix}b")])
+    }
+
+    async fn compact(&mut self) -> Result<()> {
+        self.compactions.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn fetch(&mut self, key: String) -> Result<String> {
+        self.get(key).await
+    }
+}
+
+fn serve(memory: Memory) -> tokio::io::DuplexStream {
+    let router = Router::new().with_handler(
+        storage_protocol::PROTOCOL_NAME,
+        StorageService { inner: memory },
+    );
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    client
+}
+
+/// r[verify jetstream.service.since]
+/// r[verify jetstream.service.since.client]
+#[tokio::test]
+async fn channels_refuse_methods_newer_than_the_negotiated_version() {
+    let memory = Memory::default();
+    let framed = Framed::new(
+        serve(memory.clone()),
+        ClientCodec::<StorageChannel>::default(),
+    );
+    let mut chan = StorageChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+    assert!(matches!(
+        chan.negotiated_version(),
+        Some(Version::JetStream { version, .. }) if version.major == 16
+    ));
+
+    assert_eq!(chan.scan("k".to_string()).await.unwrap(), ["ka", "kb"]);
+    let err = chan.compact().await.unwrap_err();
+    assert_eq!(err.code(), Some(UNIMPLEMENTED_BY_PEER));
+    assert!(err.message().contains("was added in 99.0.0"));
+    assert_eq!(memory.compactions.load(Ordering::SeqCst), 0);
+}
+
+/// r[verify jetstream.service.since.server]
+#[tokio::test]
+async fn servers_refuse_methods_newer_than_the_negotiated_version() {
+    let memory = Memory::default();
+    let framed = Framed::new(
+        serve(memory.clone()),
+        ClientCodec::<old::storage_protocol::StorageChannel>::default(),
+    );
+    let mut chan =
+        old::storage_protocol::StorageChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(
+        old::Storage::get(&mut chan, "k".to_string()).await.unwrap(),
+        "value of k"
+    );
+    let err = old::Storage::compact(&mut chan).await.unwrap_err();
+    assert_eq!(err.code(), Some(UNIMPLEMENTED_BY_PEER));
+    assert_eq!(memory.compactions.load(Ordering::SeqCst), 0);
+}
+
+/// r[verify jetstream.service.deprecated]
+#[tokio::test]
+#[allow(deprecated)]
+async fn servers_count_calls_to_deprecated_methods() {
+    let framed = Framed::new(
+        serve(Memory::default()),
+        ClientCodec::<StorageChannel>::default(),
+    );
+    let mut chan = StorageChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    let fetch = &storage_protocol::DEPRECATED[0];
+    assert_eq!((fetch.method, fetch.note), ("fetch", Some("use `get`")));
+    let before = fetch.calls();
+    chan.fetch("k".to_string()).await.unwrap();
+    chan.fetch("k".to_string()).await.unwrap();
+    assert_eq!(fetch.calls(), before + 2);
+}
+
+/// Negotiates `offer` on a bare stream, then asks the server for `req`.
+async fn call_as(
+    offer: &str,
+    req: storage_protocol::Tmessage,
+) -> (String, storage_protocol::Rmessage) {
+    use futures::{SinkExt, StreamExt};
+
+    let mut framed = Framed::new(
+        serve(Memory::default()),
+        ClientCodec::<StorageChannel>::default(),
+    );
+    framed
+        .send(Frame::from((
+            0,
+            storage_protocol::Tmessage::Version(Tversion {
+                msize: 8192,
+                version: format!("rs.jetstream.proto/storage/{offer}"),
+            }),
+        )))
+        .await
+        .unwrap();
+    let acked = match framed.next().await.unwrap().unwrap().msg {
+        storage_protocol::Rmessage::Version(rversion) => rversion.version,
+        other => panic!("expected Rversion, got {other:?}"),
+    };
+    framed.send(Frame::from((1, req))).await.unwrap();
+    (acked, framed.next().await.unwrap().unwrap().msg)
+}
+
+/// r[verify jetstream.version.negotiation.rversion]
+/// r[verify jetstream.service.since.server]
+#[tokio::test]
+async fn routers_serve_the_lower_of_both_versions() {
+    let server = env!("CARGO_PKG_VERSION");
+
+    // A newer client gets the server's version, and the server does not
+    // serve methods newer than it.
+    let (acked, resp) = call_as(
+        "99.0.0",
+        storage_protocol::Tmessage::Compact(storage_protocol::Tcompact {}),
+    )
+    .await;
+    assert!(
+        acked.starts_with(&format!("rs.jetstream.proto/storage/{server}+")),
+        "{acked}"
+    );
+    let storage_protocol::Rmessage::Error(err) = resp else {
+        panic!("compact was served: {resp:?}");
+    };
+    assert_eq!(err.code(), Some(UNIMPLEMENTED_BY_PEER));
+
+    // An older client keeps its own version.
+    let (acked, resp) = call_as(
+        "15.0.0",
+        storage_protocol::Tmessage::Scan(storage_protocol::Tscan {
+            prefix: "k".to_string(),
+        }),
+    )
+    .await;
+    assert!(
+        acked.starts_with("rs.jetstream.proto/storage/15.0.0+"),
+        "{acked}"
+    );
+    let storage_protocol::Rmessage::Error(err) = resp else {
+        panic!("scan was served: {resp:?}");
+    };
+    assert!(
+        err.message().contains("but 15.0.0 was negotiated"),
+        "{}",
+        err.message()
+    );
+}
+
+/// Sends `req` to `ProtocolService`, which never negotiates a version.
+async fn call_over_http(
+    memory: Memory,
+    req: storage_protocol::Tmessage,
+) -> storage_protocol::Rmessage {
+    use tower::ServiceExt;
+
+    let mut body = vec![];
+    Frame::from((1, req)).encode(&mut body).unwrap();
+    let response =
+        jetstream_http::ProtocolService::new(StorageService { inner: memory })
+            .oneshot(
+                axum::http::Request::post("/")
+                    .header(axum::http::header::CONTENT_LENGTH, body.len())
+                    .body(axum::body::Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    Frame::<storage_protocol::Rmessage>::decode(&mut std::io::Cursor::new(
+        bytes,
+    ))
+    .unwrap()
+    .msg
+}
+
+/// r[verify jetstream.service.since]
+/// r[verify jetstream.service.since.server]
+#[tokio::test]
+async fn servers_without_a_negotiated_version_serve_every_method() {
+    let memory = Memory::default();
+    let resp = call_over_http(
+        memory.clone(),
+        storage_protocol::Tmessage::Compact(storage_protocol::Tcompact {}),
+    )
+    .await;
+    assert!(
+        matches!(resp, storage_protocol::Rmessage::Compact(_)),
+        "{resp:?}"
+    );
+    assert_eq!(memory.compactions.load(Ordering::SeqCst), 1);
+}
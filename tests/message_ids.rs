@@ -0,0 +1,147 @@
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, schema::SCHEMA_MISMATCH, Router};
+
+mod v1 {
+    use jetstream::prelude::*;
+
+    #[service(name = "shop")]
+    pub trait Shop {
+        #[id = 120]
+        async fn price(&mut self, item: String) -> Result<u32>;
+        #[id = 130]
+        async fn stock(&mut self, item: String) -> Result<u32>;
+    }
+
+    #[derive(Clone)]
+    pub struct ShopImpl;
+
+    impl Shop for ShopImpl {
+        async fn price(&mut self, _item: String) -> Result<u32> {
+            Ok(250)
+        }
+
+        async fn stock(&mut self, _item: String) -> Result<u32> {
+            Ok(7)
+        }
+    }
+}
+
+mod v2 {
+    use jetstream::prelude::*;
+
+    /// Reordered, with a method inserted in front.
+    #[service(name = "shop")]
+    pub trait Shop {
+        #[id = 140]
+        async fn restock(&mut self, item: String, count: u32) -> Result<()>;
+        #[id = 130]
+        async fn stock(&mut self, item: String) -> Result<u32>;
+        #[id = 120]
+        async fn price(&mut self, item: String) -> Result<u32>;
+    }
+}
+
+mod extended {
+    use jetstream::prelude::*;
+
+    #[service(hashed_ids, extended_ids)]
+    pub trait Inventory {
+        async fn count(&mut self, item: String) -> Result<u32>;
+        #[id = 40000]
+        async fn rename(&mut self, from: String, to: String) -> Result<String>;
+    }
+
+    #[derive(Clone)]
+    pub struct InventoryImpl;
+
+    impl Inventory for InventoryImpl {
+        async fn count(&mut self, item: String) -> Result<u32> {
+            Ok(item.len() as u32)
+        }
+
+        async fn rename(&mut self, from: String, to: String) -> Result<String> {
+            Ok(format!("{from} -> {to}"))
+        }
+    }
+}
+
+fn serve<T: jetstream_rpc::Handler + 'static>(
+    name: &'static str,
+    handler: T,
+) -> tokio::io::DuplexStream {
+    let router = Router::new().with_handler(name, handler);
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    client
+}
+
+/// r[verify jetstream.service.message-ids]
+#[tokio::test]
+async fn explicit_ids_survive_reordering() {
+    use v2::Shop;
+
+    let client = serve(
+        v1::shop_protocol::PROTOCOL_NAME,
+        v1::shop_protocol::ShopService {
+            inner: v1::ShopImpl,
+        },
+    );
+    let framed = Framed::new(
+        client,
+        ClientCodec::<v2::shop_protocol::ShopChannel>::default(),
+    );
+    let mut chan = v2::shop_protocol::ShopChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    let mismatch = chan.schema_mismatch().expect("digests differ");
+    let methods = mismatch.methods.unwrap();
+    assert_eq!(
+        methods.iter().map(|m| m.name).collect::<Vec<_>>(),
+        ["restock"]
+    );
+
+    assert_eq!(chan.price("tea".to_string()).await.unwrap(), 250);
+    assert_eq!(chan.stock("tea".to_string()).await.unwrap(), 7);
+    let err = chan.restock("tea".to_string(), 3).await.unwrap_err();
+    assert_eq!(err.code(), Some(SCHEMA_MISMATCH));
+}
+
+/// r[verify jetstream.service.message-ids.extended]
+#[tokio::test]
+async fn extended_ids_round_trip() {
+    use extended::Inventory;
+
+    assert_eq!(extended::inventory_protocol::TRENAME, 40000);
+    assert_eq!(extended::inventory_protocol::RRENAME, 40001);
+
+    let client = serve(
+        extended::inventory_protocol::PROTOCOL_NAME,
+        extended::inventory_protocol::InventoryService {
+            inner: extended::InventoryImpl,
+        },
+    );
+    let framed = Framed::new(
+        client,
+        ClientCodec::<extended::inventory_protocol::InventoryChannel>::default(
+        ),
+    );
+    let mut chan = extended::inventory_protocol::InventoryChannel::new(
+        10,
+        Box::new(framed),
+    );
+    chan.negotiate_version(8192).await.unwrap();
+    assert!(chan.schema_mismatch().is_none());
+
+    assert_eq!(chan.count("teapot".to_string()).await.unwrap(), 6);
+    assert_eq!(
+        chan.rename("tea".to_string(), "chai".to_string())
+            .await
+            .unwrap(),
+        "tea -> chai"
+    );
+}
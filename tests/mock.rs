@@ -0,0 +1,111 @@
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, Router};
+
+#[service(mock)]
+pub trait Calculator {
+    async fn add(&mut self, a: u32, b: u32) -> Result<u32>;
+    async fn name(&mut self, ctx: Context) -> Result<String>;
+    async fn reset(&mut self) -> Result<()>;
+}
+
+use calculator_protocol::{CalculatorChannel, MockCalculator};
+
+/// r[verify jetstream.mock.macro]
+/// r[verify jetstream.mock.expectation]
+#[tokio::test]
+async fn mocks_answer_from_expectations() {
+    let mock = MockCalculator::new();
+    mock.expect_add().with_args((2, 2)).returns(5).times(1);
+    mock.expect_add().returning(|(a, b)| Ok(a + b));
+    mock.expect_name().returns("calc".to_string());
+    mock.expect_reset()
+        .fails(Error::with_code("read only", "calc::read_only"));
+
+    let mut calc = mock.clone();
+    assert_eq!(calc.add(2, 2).await.unwrap(), 5);
+    assert_eq!(calc.add(2, 2).await.unwrap(), 4);
+    assert_eq!(calc.name(Context::default()).await.unwrap(), "calc");
+    let err = calc.reset().await.unwrap_err();
+    assert_eq!(err.code(), Some("calc::read_only"));
+
+    assert_eq!(mock.add_calls(), 2);
+    assert_eq!(mock.reset_calls(), 1);
+    mock.verify();
+}
+
+/// r[verify jetstream.mock.verify]
+#[test]
+#[should_panic(expected = "MockCalculator::add: expectation #0 expected 2")]
+fn verify_panics_on_unmet_expectations() {
+    let mock = MockCalculator::new();
+    mock.expect_add().returns(0).times(2);
+    mock.verify();
+}
+
+/// r[verify jetstream.mock.service]
+#[tokio::test]
+async fn mocks_serve_through_the_generated_server() {
+    let mock = MockCalculator::new();
+    mock.expect_add().with(|(a, _)| *a > 10).returns(100);
+    mock.expect_add().returning(|(a, b)| Ok(a * b));
+    mock.expect_reset().fails(Error::new("not now"));
+
+    let router = Router::new()
+        .with_handler(calculator_protocol::PROTOCOL_NAME, mock.service());
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    let framed =
+        Framed::new(client, ClientCodec::<CalculatorChannel>::default());
+    let mut chan = CalculatorChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(chan.add(3, 4).await.unwrap(), 12);
+    assert_eq!(chan.add(11, 4).await.unwrap(), 100);
+    let err = chan.reset().await.unwrap_err();
+    assert!(err.to_string().contains("not now"), "{err}");
+
+    assert_eq!(mock.add_calls(), 2);
+    assert_eq!(mock.name_calls(), 0);
+    mock.verify();
+}
+
+/// r[verify jetstream.mock.served]
+#[tokio::test]
+async fn served_mocks_fail_unexpected_calls() {
+    let mock = MockCalculator::new();
+    mock.expect_add().with_args((1, 1)).returns(2);
+
+    let router = Router::new()
+        .with_handler(calculator_protocol::PROTOCOL_NAME, mock.service());
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    let framed =
+        Framed::new(client, ClientCodec::<CalculatorChannel>::default());
+    let mut chan = CalculatorChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    let err = chan.add(2, 2).await.unwrap_err();
+    assert_eq!(err.code(), Some(jetstream_rpc::mock::UNEXPECTED_CALL));
+    // The stream outlives the failed call.
+    assert_eq!(chan.add(1, 1).await.unwrap(), 2);
+
+    let unreported = std::panic::catch_unwind(|| drop(mock.clone()))
+        .expect_err("dropping the mock reports the failure");
+    let message = unreported.downcast_ref::<String>().unwrap();
+    assert!(
+        message.contains("no expectation matches the call"),
+        "{message}"
+    );
+    // Reported once on drop, but `verify` still fails.
+    assert!(std::panic::catch_unwind(|| mock.verify()).is_err());
+}
@@ -0,0 +1,117 @@
+use futures::{SinkExt, StreamExt};
+use jetstream::prelude::*;
+use jetstream_rpc::{balance::Backend, client::ClientCodec, Router};
+use tokio::sync::mpsc;
+
+#[service]
+pub trait Telemetry {
+    #[oneway]
+    async fn record(&mut self, event: String) -> Result<()>;
+    async fn flush(&mut self) -> Result<u32>;
+}
+
+#[derive(Clone)]
+struct TelemetryImpl {
+    events: mpsc::UnboundedSender<String>,
+}
+
+impl Telemetry for TelemetryImpl {
+    async fn record(&mut self, event: String) -> Result<()> {
+        if event == "bad" {
+            return Err(Error::new("bad event"));
+        }
+        let _ = self.events.send(event);
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<u32> {
+        Ok(42)
+    }
+}
+
+fn serve() -> (tokio::io::DuplexStream, mpsc::UnboundedReceiver<String>) {
+    let (events, received) = mpsc::unbounded_channel();
+    let handler = telemetry_protocol::TelemetryService {
+        inner: TelemetryImpl { events },
+    };
+    let router =
+        Router::new().with_handler(telemetry_protocol::PROTOCOL_NAME, handler);
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    (client, received)
+}
+
+/// r[verify jetstream.rpc.oneway.client]
+#[tokio::test]
+async fn oneway_calls_take_no_tag() {
+    use telemetry_protocol::TelemetryChannel;
+
+    let (client, mut received) = serve();
+    let framed =
+        Framed::new(client, ClientCodec::<TelemetryChannel>::default());
+    let mut chan = TelemetryChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    chan.record("started".to_string()).await.unwrap();
+    chan.record("bad".to_string()).await.unwrap();
+    chan.record("stopped".to_string()).await.unwrap();
+    assert_eq!(chan.in_flight(), 0);
+
+    assert_eq!(received.recv().await.unwrap(), "started");
+    assert_eq!(received.recv().await.unwrap(), "stopped");
+    assert_eq!(chan.flush().await.unwrap(), 42);
+}
+
+/// r[verify jetstream.rpc.oneway.server]
+#[tokio::test]
+async fn server_does_not_answer_oneway_calls() {
+    use telemetry_protocol::{
+        Rmessage, TelemetryChannel, Tflush, Tmessage, Trecord,
+    };
+
+    let (client, mut received) = serve();
+    let mut framed =
+        Framed::new(client, ClientCodec::<TelemetryChannel>::default());
+    framed
+        .send(Frame {
+            tag: NOTAG,
+            msg: Tmessage::Version(Tversion {
+                msize: 8192,
+                version: telemetry_protocol::PROTOCOL_VERSION.to_string(),
+            }),
+        })
+        .await
+        .unwrap();
+    let rversion = framed.next().await.unwrap().unwrap();
+    assert_eq!(rversion.tag, NOTAG);
+    assert!(matches!(rversion.msg, Rmessage::Version(_)));
+
+    for event in ["bad", "started"] {
+        framed
+            .send(Frame {
+                tag: NOTAG,
+                msg: Tmessage::Record(Trecord {
+                    event: event.to_string(),
+                }),
+            })
+            .await
+            .unwrap();
+    }
+    assert_eq!(received.recv().await.unwrap(), "started");
+
+    framed
+        .send(Frame {
+            tag: 1,
+            msg: Tmessage::Flush(Tflush {}),
+        })
+        .await
+        .unwrap();
+    let rframe = framed.next().await.unwrap().unwrap();
+    assert_eq!(rframe.tag, 1);
+    assert!(matches!(rframe.msg, Rmessage::Flush(_)));
+}
@@ -0,0 +1,103 @@
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, Protocol, Router};
+
+pub mod a {
+    use jetstream::prelude::*;
+
+    #[service]
+    pub trait Storage {
+        async fn get(&mut self) -> Result<String>;
+    }
+}
+
+pub mod b {
+    use jetstream::prelude::*;
+
+    #[service]
+    pub trait Storage {
+        async fn get(&mut self) -> Result<String>;
+    }
+}
+
+#[service(name = "com.acme.storage")]
+pub trait Acme {
+    async fn get(&mut self) -> Result<String>;
+}
+
+#[derive(Clone)]
+struct Named(&'static str);
+
+impl a::Storage for Named {
+    async fn get(&mut self) -> Result<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+impl b::Storage for Named {
+    async fn get(&mut self) -> Result<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+fn serve(router: Router) -> tokio::io::DuplexStream {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    client
+}
+
+/// r[verify jetstream.service.name]
+#[test]
+fn names_are_explicit_or_derived_from_the_module() {
+    assert_eq!(acme_protocol::PROTOCOL_NAME, "com.acme.storage");
+    assert!(acme_protocol::PROTOCOL_VERSION
+        .starts_with("rs.jetstream.proto/com.acme.storage/"));
+    assert_eq!(
+        a::storage_protocol::PROTOCOL_NAME,
+        "protocol_names.a.storage"
+    );
+    assert_eq!(
+        b::storage_protocol::PROTOCOL_NAME,
+        "protocol_names.b.storage"
+    );
+    assert_eq!(
+        <a::storage_protocol::StorageChannel as Protocol>::NAME,
+        a::storage_protocol::PROTOCOL_NAME
+    );
+}
+
+/// r[verify jetstream.service.name]
+#[tokio::test]
+async fn same_named_traits_share_a_router() {
+    let router = Router::new()
+        .with_handler(
+            a::storage_protocol::PROTOCOL_NAME,
+            a::storage_protocol::StorageService { inner: Named("a") },
+        )
+        .with_handler(
+            b::storage_protocol::PROTOCOL_NAME,
+            b::storage_protocol::StorageService { inner: Named("b") },
+        );
+
+    let framed = Framed::new(
+        serve(router.clone()),
+        ClientCodec::<a::storage_protocol::StorageChannel>::default(),
+    );
+    let mut first =
+        a::storage_protocol::StorageChannel::new(10, Box::new(framed));
+    first.negotiate_version(8192).await.unwrap();
+    let framed = Framed::new(
+        serve(router),
+        ClientCodec::<b::storage_protocol::StorageChannel>::default(),
+    );
+    let mut second =
+        b::storage_protocol::StorageChannel::new(10, Box::new(framed));
+    second.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(a::Storage::get(&mut first).await.unwrap(), "a");
+    assert_eq!(b::Storage::get(&mut second).await.unwrap(), "b");
+}
@@ -1,11 +1,11 @@
 #![cfg(feature = "quic")]
-use std::{net::SocketAddr, path::Path, sync::Arc};
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
 
 use echo_protocol::EchoChannel;
 use jetstream::prelude::*;
 use jetstream_macros::service;
 use jetstream_quic::{
-    Client, QuicRouter, QuicRouterHandler, QuicTransport, Server,
+    Client, QuicRouter, QuicRouterHandler, QuicTransport, Server, StreamPool,
 };
 
 use rustls::pki_types::{CertificateDer, PrivateKeyDer};
@@ -13,6 +13,8 @@ use rustls::pki_types::{CertificateDer, PrivateKeyDer};
 #[service]
 pub trait Echo {
     async fn ping(&mut self) -> Result<String>;
+    async fn blob(&mut self, size: u32) -> Result<String>;
+    async fn slow(&mut self, millis: u64) -> Result<String>;
 }
 
 #[derive(Clone)]
@@ -24,6 +26,15 @@ impl Echo for EchoImpl {
         eprintln!("Pong sent");
         Ok("pong".to_string())
     }
+
+    async fn blob(&mut self, size: u32) -> Result<String> {
+        Ok("x".repeat(size as usize))
+    }
+
+    async fn slow(&mut self, millis: u64) -> Result<String> {
+        tokio::time::sleep(std::time::Duration::from_millis(millis)).await;
+        Ok("slow".to_string())
+    }
 }
 
 pub static CA_CERT_PEM: &str =
@@ -91,9 +102,8 @@ async fn server(
     Ok(())
 }
 
-async fn client(
-    addr: SocketAddr,
-) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn quic_client(
+) -> std::result::Result<Client, Box<dyn std::error::Error + Send + Sync>> {
     // Wait for server to start
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
@@ -111,7 +121,13 @@ async fn client(
         bind_addr,
     )?;
 
-    let connection = client.connect(addr, "localhost").await?;
+    Ok(client)
+}
+
+async fn client(
+    addr: SocketAddr,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let connection = quic_client().await?.connect(addr, "localhost").await?;
 
     let (send, recv) = connection.open_bi().await?;
     let transport: QuicTransport<EchoChannel> = (send, recv).into();
@@ -142,3 +158,126 @@ async fn echo() {
       _ = client(addr) => {},
     }
 }
+
+async fn pooled_client(
+    addr: SocketAddr,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let connection = quic_client().await?.connect(addr, "localhost").await?;
+    let chan = StreamPool::new(connection)
+        .streams(3)
+        .balancer(|transport: QuicTransport<EchoChannel>| async move {
+            let chan = EchoChannel::new(10, Box::new(transport));
+            chan.negotiate_version(u32::MAX).await?;
+            Ok(chan)
+        })
+        .build()
+        .await?;
+    assert_eq!(
+        chan.endpoints(),
+        vec![(0, true), (1, true), (2, true)],
+        "every stream should be negotiated"
+    );
+
+    let mut blob_chan = chan.clone();
+    let blob = tokio::spawn(async move { blob_chan.blob(60_000).await });
+    let pings = (0..20).map(|_| {
+        let mut chan = chan.clone();
+        async move { chan.ping().await }
+    });
+    for pong in futures::future::join_all(pings).await {
+        assert_eq!(pong?, "pong");
+    }
+    assert_eq!(blob.await??.len(), 60_000);
+    Ok(())
+}
+
+/// r[verify jetstream.quic.streams]
+/// r[verify jetstream.quic.streams.limit]
+#[tokio::test]
+async fn echo_over_stream_pool() {
+    rustls::crypto::ring::default_provider()
+        .install_default()
+        .ok();
+
+    let addr: SocketAddr = "127.0.0.1:4436".parse().unwrap();
+    tokio::select! {
+      _ = server(addr) => {},
+      result = pooled_client(addr) => result.unwrap(),
+    }
+}
+
+/// Starts a slow call, then checks that a fast call issued while it is
+/// outstanding completes long before it.
+async fn fast_call_overtakes<F, Fut>(
+    call: F,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    F: Fn(u64) -> Fut,
+    Fut: std::future::Future<Output = Result<String>> + Send + 'static,
+{
+    let slow = tokio::spawn(call(5_000));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let fast = tokio::time::timeout(Duration::from_secs(1), call(0))
+        .await
+        .expect("fast call was held up by the slow one")?;
+    assert_eq!(fast, "slow");
+    assert!(!slow.is_finished(), "slow call finished early");
+    slow.abort();
+    let _ = slow.await;
+    Ok(())
+}
+
+async fn head_of_line_client(
+    addr: SocketAddr,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let connection = quic_client().await?.connect(addr, "localhost").await?;
+
+    let pooled = StreamPool::new(connection.clone())
+        .streams(2)
+        .balancer(|transport: QuicTransport<EchoChannel>| async move {
+            let chan = EchoChannel::new(10, Box::new(transport));
+            chan.negotiate_version(u32::MAX).await?;
+            Ok(chan)
+        })
+        .build()
+        .await?;
+    fast_call_overtakes(|millis| {
+        let mut chan = pooled.clone();
+        async move { chan.slow(millis).await }
+    })
+    .await?;
+
+    let per_call = StreamPool::new(connection).max_streams(2).per_call(
+        |transport: QuicTransport<EchoChannel>| async move {
+            let chan = EchoChannel::new(1, Box::new(transport));
+            chan.negotiate_version(u32::MAX).await?;
+            Ok(chan)
+        },
+    );
+    fast_call_overtakes(|millis| {
+        let calls = per_call.clone();
+        async move {
+            calls
+                .call(|mut chan| async move { chan.slow(millis).await })
+                .await
+        }
+    })
+    .await?;
+    assert_eq!(per_call.in_flight(), 0);
+    Ok(())
+}
+
+/// r[verify jetstream.quic.streams]
+/// r[verify jetstream.quic.streams.per-call]
+#[tokio::test]
+async fn slow_call_does_not_block_fast_call() {
+    rustls::crypto::ring::default_provider()
+        .install_default()
+        .ok();
+
+    let addr: SocketAddr = "127.0.0.1:4437".parse().unwrap();
+    tokio::select! {
+      _ = server(addr) => {},
+      result = head_of_line_client(addr) => result.unwrap(),
+    }
+}
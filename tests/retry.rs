@@ -0,0 +1,139 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, retry::UNAVAILABLE, Router};
+use ledger_protocol::{LedgerChannel, LedgerService};
+
+#[service]
+pub trait Ledger {
+    #[idempotent]
+    async fn balance(&mut self, account: String) -> Result<u64>;
+    async fn deposit(&mut self, account: String, amount: u64) -> Result<u64>;
+}
+
+/// Fails the first `failures` calls with `unavailable`.
+#[derive(Clone)]
+struct FlakyLedger {
+    calls: Arc<AtomicU32>,
+    failures: u32,
+}
+
+impl FlakyLedger {
+    fn check(&self) -> Result<()> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+            return Err(Error::with_code("try again", UNAVAILABLE));
+        }
+        Ok(())
+    }
+}
+
+impl Ledger for FlakyLedger {
+    async fn balance(&mut self, account: String) -> Result<u64> {
+        self.check()?;
+        Ok(account.len() as u64)
+    }
+
+    async fn deposit(&mut self, _account: String, amount: u64) -> Result<u64> {
+        self.check()?;
+        Ok(amount)
+    }
+}
+
+async fn connect(failures: u32) -> (LedgerChannel, Arc<AtomicU32>) {
+    let calls = Arc::new(AtomicU32::new(0));
+    let ledger = FlakyLedger {
+        calls: calls.clone(),
+        failures,
+    };
+    let router = Router::new().with_handler(
+        ledger_protocol::PROTOCOL_NAME,
+        LedgerService { inner: ledger },
+    );
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+            .expect("accept failed");
+    });
+    let framed = Framed::new(client, ClientCodec::<LedgerChannel>::default());
+    let chan = LedgerChannel::new(10, Box::new(framed)).with_retry_policy(
+        RetryPolicy::new(3)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(5)),
+    );
+    chan.negotiate_version(8192).await.unwrap();
+    (chan, calls)
+}
+
+/// r[verify jetstream.retry.idempotent]
+/// r[verify jetstream.retry.channel]
+#[tokio::test]
+async fn idempotent_methods_are_retried() {
+    let (mut chan, calls) = connect(2).await;
+    assert_eq!(chan.balance("alice".to_string()).await.unwrap(), 5);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+/// r[verify jetstream.retry.policy]
+#[tokio::test]
+async fn retries_stop_at_budget() {
+    let (mut chan, calls) = connect(10).await;
+    let err = chan.balance("alice".to_string()).await.unwrap_err();
+    assert_eq!(err.code(), Some(UNAVAILABLE));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn other_methods_are_not_retried() {
+    let (mut chan, calls) = connect(1).await;
+    let err = chan.deposit("alice".to_string(), 10).await.unwrap_err();
+    assert_eq!(err.code(), Some(UNAVAILABLE));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+/// r[verify jetstream.retry.mux]
+#[tokio::test]
+async fn pending_calls_fail_when_connection_is_lost() {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let framed = Framed::new(client, ClientCodec::<LedgerChannel>::default());
+    let mut chan = LedgerChannel::new(10, Box::new(framed))
+        .with_retry_policy(RetryPolicy::NONE);
+    let call = tokio::spawn(async move { chan.balance("alice".into()).await });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    drop(server);
+
+    let err = tokio::time::timeout(Duration::from_secs(5), call)
+        .await
+        .expect("call hung after the connection was lost")
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.code(), Some(jetstream_rpc::retry::CONNECTION_LOST));
+}
+
+/// r[verify jetstream.retry.mux]
+#[tokio::test]
+async fn closed_mux_is_not_retried() {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let framed = Framed::new(client, ClientCodec::<LedgerChannel>::default());
+    let mut chan = LedgerChannel::new(10, Box::new(framed)).with_retry_policy(
+        RetryPolicy::new(3)
+            .with_backoff(Duration::from_secs(10), Duration::from_secs(10)),
+    );
+    drop(server);
+
+    let err = tokio::time::timeout(
+        Duration::from_secs(5),
+        chan.balance("alice".into()),
+    )
+    .await
+    .expect("retried a call on a closed mux")
+    .unwrap_err();
+    assert_eq!(err.code(), Some(jetstream_rpc::retry::CONNECTION_LOST));
+}
@@ -0,0 +1,144 @@
+use jetstream::prelude::*;
+use jetstream_rpc::{
+    client::ClientCodec,
+    schema::{DigestPolicy, SCHEMA_MISMATCH},
+    Router,
+};
+
+mod v1 {
+    use jetstream::prelude::*;
+
+    #[service(name = "shop")]
+    pub trait Shop {
+        async fn price(&mut self, item: String) -> Result<u32>;
+        async fn stock(&mut self, item: String) -> Result<u32>;
+    }
+
+    #[derive(Clone)]
+    pub struct ShopImpl;
+
+    impl Shop for ShopImpl {
+        async fn price(&mut self, _item: String) -> Result<u32> {
+            Ok(250)
+        }
+
+        async fn stock(&mut self, _item: String) -> Result<u32> {
+            Ok(7)
+        }
+    }
+}
+
+mod v2 {
+    use jetstream::prelude::*;
+
+    /// `stock` now reports per warehouse.
+    #[service(name = "shop")]
+    pub trait Shop {
+        async fn price(&mut self, item: String) -> Result<u32>;
+        async fn stock(&mut self, item: String) -> Result<Vec<u32>>;
+    }
+}
+
+fn router() -> Router {
+    Router::new().with_handler(
+        v1::shop_protocol::PROTOCOL_NAME,
+        v1::shop_protocol::ShopService {
+            inner: v1::ShopImpl,
+        },
+    )
+}
+
+fn connect(
+    router: Router,
+) -> (
+    v2::shop_protocol::ShopChannel,
+    tokio::task::JoinHandle<std::result::Result<(), Error>>,
+) {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let accept = tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    let framed = Framed::new(
+        client,
+        ClientCodec::<v2::shop_protocol::ShopChannel>::default(),
+    );
+    (
+        v2::shop_protocol::ShopChannel::new(10, Box::new(framed)),
+        accept,
+    )
+}
+
+/// r[verify jetstream.schema.client]
+/// r[verify jetstream.schema.method-digest]
+#[tokio::test]
+async fn compatible_methods_still_work() {
+    use v2::Shop;
+
+    let (mut chan, _) = connect(router());
+    chan.negotiate_version(8192).await.unwrap();
+
+    let mismatch = chan.schema_mismatch().expect("digests differ");
+    assert_eq!(mismatch.protocol, "shop");
+    let methods = mismatch.methods.unwrap();
+    assert_eq!(
+        methods.iter().map(|m| m.name).collect::<Vec<_>>(),
+        ["stock"]
+    );
+
+    assert_eq!(chan.price("tea".to_string()).await.unwrap(), 250);
+    let err = chan.stock("tea".to_string()).await.unwrap_err();
+    assert_eq!(err.code(), Some(SCHEMA_MISMATCH));
+}
+
+/// r[verify jetstream.schema.router]
+#[tokio::test]
+async fn strict_router_rejects_mismatch() {
+    let (chan, accept) =
+        connect(router().with_digest_policy(DigestPolicy::Strict));
+    assert!(chan.negotiate_version(8192).await.is_err());
+
+    let err = accept.await.unwrap().unwrap_err();
+    assert_eq!(err.code(), Some(SCHEMA_MISMATCH));
+    let message = err.to_string();
+    assert!(message.contains("`shop`"), "{message}");
+    assert!(message.contains("methods differ: stock"), "{message}");
+}
+
+#[tokio::test]
+async fn strict_client_rejects_mismatch() {
+    let (chan, _) = connect(router());
+    let chan = chan.with_digest_policy(DigestPolicy::Strict);
+    let err = chan.negotiate_version(8192).await.unwrap_err();
+    assert_eq!(err.code(), Some(SCHEMA_MISMATCH));
+}
+
+#[tokio::test]
+async fn ignore_skips_the_check() {
+    let (chan, _) = connect(router().with_digest_policy(DigestPolicy::Ignore));
+    let chan = chan.with_digest_policy(DigestPolicy::Ignore);
+    chan.negotiate_version(8192).await.unwrap();
+    assert!(chan.schema_mismatch().is_none());
+}
+
+/// r[verify jetstream.schema.server]
+#[tokio::test]
+async fn router_refuses_methods_that_differ() {
+    use v2::Shop;
+
+    // The client does not check, so only the server stops `stock`.
+    let (chan, _) = connect(router());
+    let mut chan = chan.with_digest_policy(DigestPolicy::Ignore);
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(chan.price("tea".to_string()).await.unwrap(), 250);
+    let err = chan.stock("tea".to_string()).await.unwrap_err();
+    assert_eq!(err.code(), Some(SCHEMA_MISMATCH));
+    assert!(
+        err.message().contains("method `stock`"),
+        "{}",
+        err.message()
+    );
+}
@@ -0,0 +1,99 @@
+use jetstream::prelude::*;
+use jetstream_rpc::{client::ClientCodec, Router};
+use jetstream_wireformat::Serde;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Circle { radius: u32 },
+    Square(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, JetStreamWireFormat)]
+pub enum WireShape {
+    Circle { radius: u32 },
+    Square(u32),
+}
+
+#[service(uses(super::Shape, jetstream_wireformat::Serde))]
+pub trait Canvas {
+    async fn area(&mut self, shape: Serde<Shape>) -> Result<u64>;
+    async fn grow(
+        &mut self,
+        shapes: Vec<Serde<Shape>>,
+    ) -> Result<Serde<Vec<Shape>>>;
+}
+
+use canvas_protocol::CanvasChannel;
+
+#[derive(Clone)]
+struct Squares;
+
+impl Canvas for Squares {
+    async fn area(&mut self, shape: Serde<Shape>) -> Result<u64> {
+        Ok(match *shape {
+            Shape::Circle { radius } => 3 * radius as u64 * radius as u64,
+            Shape::Square(side) => side as u64 * side as u64,
+        })
+    }
+
+    async fn grow(
+        &mut self,
+        shapes: Vec<Serde<Shape>>,
+    ) -> Result<Serde<Vec<Shape>>> {
+        Ok(Serde(
+            shapes
+                .into_iter()
+                .map(|shape| match shape.0 {
+                    Shape::Circle { radius } => {
+                        Shape::Circle { radius: radius * 2 }
+                    }
+                    Shape::Square(side) => Shape::Square(side * 2),
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// r[verify jetstream.wireformat.serde]
+#[tokio::test]
+async fn serde_types_are_service_arguments() {
+    let router = Router::new().with_handler(
+        canvas_protocol::PROTOCOL_NAME,
+        canvas_protocol::CanvasService { inner: Squares },
+    );
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        router
+            .accept(Context::default(), Box::new(reader), Box::new(writer))
+            .await
+    });
+    let framed = Framed::new(client, ClientCodec::<CanvasChannel>::default());
+    let mut chan = CanvasChannel::new(10, Box::new(framed));
+    chan.negotiate_version(8192).await.unwrap();
+
+    assert_eq!(chan.area(Serde(Shape::Square(3))).await.unwrap(), 9);
+    let grown = chan
+        .grow(vec![
+            Serde(Shape::Circle { radius: 1 }),
+            Serde(Shape::Square(2)),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(grown.0, [Shape::Circle { radius: 2 }, Shape::Square(4)]);
+}
+
+/// r[verify jetstream.wireformat.serde]
+#[test]
+fn serde_arguments_match_the_derived_layout() {
+    let mut serde = Vec::new();
+    Serde(Shape::Circle { radius: 5 })
+        .encode(&mut serde)
+        .unwrap();
+    let mut derived = Vec::new();
+    WireShape::Circle { radius: 5 }
+        .encode(&mut derived)
+        .unwrap();
+    assert_eq!(serde, derived);
+}
@@ -130,3 +130,77 @@ fn error_propagation_e2e() -> turmoil::Result {
 fn e2e_error_propagation() {
     error_propagation_e2e().expect("error propagation e2e failed");
 }
+
+/// r[verify jetstream.version.capabilities]
+/// The generated server acknowledges trace context propagation when the
+/// client offers it.
+fn trace_context_negotiation() -> turmoil::Result {
+    let mut sim = Builder::new().build();
+
+    sim.host("server", || async {
+        let listener = bind().await?;
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let servercodec: jetstream::prelude::server::ServerCodec<
+                echo_protocol::EchoService<EchoImpl>,
+            > = Default::default();
+            let framed =
+                Framed::with_capacity(stream, servercodec, 1024 * 1024 * 10);
+            let mut serv = echo_protocol::EchoService { inner: EchoImpl {} };
+            run(&mut serv, framed).await.expect("server run failed");
+        }
+    });
+
+    sim.client("client", async {
+        let stream = TcpStream::connect(("server", PORT)).await?;
+        let client_codec: ClientCodec<EchoChannel> = Default::default();
+        let framed = Framed::new(stream, client_codec);
+        let mut chan = EchoChannel::new(10, Box::new(framed));
+
+        let rversion = chan.negotiate_version(8192).await?;
+        assert!(Capabilities::acknowledged(&rversion.version)
+            .contains(Capabilities::TRACE_CONTEXT));
+        chan.ping().await.expect("ping failed");
+        Ok(())
+    });
+
+    sim.run()
+}
+
+#[test]
+fn e2e_trace_context_negotiation() {
+    trace_context_negotiation().expect("trace context negotiation failed");
+}
+
+/// r[verify jetstream.trace.envelope]
+/// A request wrapped in a trace context envelope survives the wire and is
+/// dispatched like the bare request.
+#[tokio::test]
+async fn traced_request_is_dispatched() {
+    use echo_protocol::{Rmessage, Tmessage, Tping};
+
+    let trace = TraceContext::new([0x4b; 16], [0xf0; 8], 1, "");
+    let frame = Frame {
+        tag: 7,
+        msg: Tmessage::Traced(
+            trace.clone(),
+            Box::new(Tmessage::Ping(Tping {})),
+        ),
+    };
+    let mut buf = Vec::new();
+    frame.encode(&mut buf).unwrap();
+    let frame: Frame<Tmessage> =
+        WireFormat::decode(&mut buf.as_slice()).unwrap();
+    match &frame.msg {
+        Tmessage::Traced(decoded, msg) => {
+            assert_eq!(decoded, &trace);
+            assert!(matches!(**msg, Tmessage::Ping(_)));
+        }
+        other => panic!("expected traced frame, got {:?}", other),
+    }
+
+    let mut serv = echo_protocol::EchoService { inner: EchoImpl {} };
+    let rframe = serv.rpc(Context::default(), frame).await.unwrap();
+    assert_eq!(rframe.tag, 7);
+    assert!(matches!(rframe.msg, Rmessage::Ping(_)));
+}